@@ -1,17 +1,33 @@
 import { z } from "zod/v4";
 
-// WebSocket connection to the Stop It daemon
-const DAEMON_WS_URL = "ws://127.0.0.1:8765";
+// WebSocket connection to the Stop It daemon. The daemon moves to one of the
+// following ports when 8765 is taken (`[server] fallback_ports`), so cycle
+// through them while it can't be reached.
+const DAEMON_PORT = 8765;
+const DAEMON_PORT_RANGE = 10;
+let portOffset = 0;
 
 // Track last seen state to avoid sending duplicate messages
 let lastUrl = "";
 let lastTitle = "";
+// Tab and window of the last update, so closing them stops tracking at once
+let lastTabId: number | null = null;
+let lastWindowId: number | null = null;
 
 // WebSocket connection
 let ws: WebSocket | null = null;
 let reconnectInterval: number | null = null;
 const RECONNECT_DELAY = 5000; // 5 seconds
 
+// Heartbeats tell the daemon whether the browser has focus and the user is
+// around, all it goes by when running without a focus backend
+const HEARTBEAT_INTERVAL = 15000; // 15 seconds
+const IDLE_DETECTION_SECONDS = 60;
+
+// Token of this browser profile (`[server.tokens]` in the daemon config),
+// entered on the options page
+const TOKEN_KEY = "daemonToken";
+
 // Message types
 type TabUpdateMessage = {
   type: "tab_update";
@@ -21,6 +37,35 @@ type TabUpdateMessage = {
   timestamp: number;
 };
 
+type TabClosedMessage = {
+  type: "tab_closed";
+  domain: string | null;
+};
+
+type WindowClosedMessage = {
+  type: "window_closed";
+  domain: string | null;
+  browser_exiting: boolean;
+};
+
+type HeartbeatMessage = {
+  type: "heartbeat";
+  focused: boolean;
+  idle: boolean;
+};
+
+type BreakAckMessage = {
+  type: "break_ack";
+  id: number;
+};
+
+type ClientMessage =
+  | TabUpdateMessage
+  | TabClosedMessage
+  | WindowClosedMessage
+  | HeartbeatMessage
+  | BreakAckMessage;
+
 // Zod schema for daemon response validation
 const NativeResponseSchema = z.object({
   success: z.boolean(),
@@ -29,6 +74,179 @@ const NativeResponseSchema = z.object({
 
 //type NativeResponse = z.infer<typeof NativeResponseSchema>;
 
+// Zod schema for the timer state the daemon broadcasts every second
+const BudgetStatusSchema = z.object({
+  target: z.string(),
+  used_today: z.number(),
+  daily_allowance: z.number().nullable(),
+  used_week: z.number(),
+  weekly_limit: z.number().nullable(),
+  remaining: z.number().nullable(),
+  off_day: z.boolean(),
+});
+
+const TimerStateSchema = z.object({
+  type: z.literal("timer_state"),
+  mode: z.string(),
+  remaining_seconds: z.number(),
+  duration_seconds: z.number(),
+  pomodoros_today: z.number(),
+  daily_goal: z.number(),
+  current_domain: z.string().nullable(),
+  current_category: z.string().nullable(),
+  category_source: z.enum(["client", "server", "builtin"]).nullable(),
+  block: z.string().nullable(),
+  budgets: z.array(BudgetStatusSchema),
+});
+
+type TimerState = z.infer<typeof TimerStateSchema>;
+
+// Compact badge the daemon sends when it changes (about once a minute)
+const BadgeSchema = z.object({
+  type: z.literal("badge"),
+  text: z.string(),
+  color: z.string(),
+});
+
+// Latest timer state received from the daemon
+let timerState: TimerState | null = null;
+
+// Recap of the work session that just ended, sent when the break starts
+const SessionSummarySchema = z.object({
+  type: z.literal("session_summary"),
+  duration_seconds: z.number(),
+  top_domains: z.array(z.object({ domain: z.string(), seconds: z.number() })),
+  focus_percent: z.number(),
+  idle_seconds: z.number(),
+  pomodoros_today: z.number(),
+  daily_goal: z.number(),
+});
+
+type SessionSummary = z.infer<typeof SessionSummarySchema>;
+
+const RECAP_NOTIFICATION_ID = "stop-it-session-recap";
+
+// Breaks with `[breaks] overlay`: pause media and grey out every page until
+// the daemon ends the break, confirming each command with a `break_ack`
+const BreakStartSchema = z.object({
+  type: z.literal("break_start"),
+  id: z.number(),
+  ends_at: z.string(),
+});
+
+const BreakEndSchema = z.object({
+  type: z.literal("break_end"),
+  id: z.number(),
+});
+
+const OVERLAY_ID = "stop-it-break-overlay";
+
+// End of the break in progress, null when there is none
+let breakEndsAt: string | null = null;
+
+/**
+ * Injected into pages: pause media and cover the page with a banner
+ * @param overlayId - Id of the banner element, so it is added only once
+ * @param endsAt - When the break is planned to end (RFC 3339)
+ */
+function showBreakOverlay(overlayId: string, endsAt: string): void {
+  for (const media of document.querySelectorAll<HTMLMediaElement>(
+    "video, audio",
+  )) {
+    media.pause();
+  }
+  if (document.getElementById(overlayId)) {
+    return;
+  }
+  const end = new Date(endsAt).toLocaleTimeString([], {
+    hour: "2-digit",
+    minute: "2-digit",
+  });
+  const overlay = document.createElement("div");
+  overlay.id = overlayId;
+  overlay.style.cssText =
+    "position:fixed;inset:0;z-index:2147483647;display:flex;" +
+    "align-items:center;justify-content:center;text-align:center;" +
+    "background:rgba(40,40,40,0.9);color:#fff;font:24px sans-serif;";
+  overlay.textContent = `☕ Break in progress until ${end}. Step away from the screen.`;
+  document.documentElement.appendChild(overlay);
+}
+
+/**
+ * Injected into pages: remove the banner of `showBreakOverlay`
+ * @param overlayId - Id of the banner element
+ */
+function hideBreakOverlay(overlayId: string): void {
+  document.getElementById(overlayId)?.remove();
+}
+
+/**
+ * Show or hide the break banner in one tab; internal pages can't be scripted
+ * @param tabId - The tab to update
+ * @returns Promise<void>
+ */
+async function updateOverlay(tabId: number): Promise<void> {
+  try {
+    if (breakEndsAt) {
+      await chrome.scripting.executeScript({
+        target: { tabId },
+        func: showBreakOverlay,
+        args: [OVERLAY_ID, breakEndsAt],
+      });
+    } else {
+      await chrome.scripting.executeScript({
+        target: { tabId },
+        func: hideBreakOverlay,
+        args: [OVERLAY_ID],
+      });
+    }
+  } catch {
+    // chrome:// pages, the web store and tabs that are still loading
+  }
+}
+
+/**
+ * Apply a `break_start` or `break_end` to every open tab, then confirm it
+ * @param id - Id of the daemon's command
+ * @param endsAt - End of the break for `break_start`, null for `break_end`
+ * @returns Promise<void>
+ */
+async function handleBreak(id: number, endsAt: string | null): Promise<void> {
+  breakEndsAt = endsAt;
+  const tabs = await chrome.tabs.query({});
+  await Promise.all(
+    tabs.flatMap((tab) => (tab.id === undefined ? [] : [updateOverlay(tab.id)])),
+  );
+  const message: BreakAckMessage = { type: "break_ack", id };
+  sendMessage(message);
+}
+
+/**
+ * Show the recap card for a finished work session during the break
+ * @param summary - Session summary pushed by the daemon
+ */
+function showSessionRecap(summary: SessionSummary): void {
+  const minutes = Math.round(summary.duration_seconds / 60);
+  const goal =
+    summary.daily_goal > 0
+      ? `${summary.pomodoros_today}/${summary.daily_goal}`
+      : `${summary.pomodoros_today}`;
+  const idle =
+    summary.idle_seconds >= 60
+      ? `, idle ${Math.round(summary.idle_seconds / 60)} min`
+      : "";
+  void chrome.notifications.create(RECAP_NOTIFICATION_ID, {
+    type: "list",
+    iconUrl: "leaf128.png",
+    title: `Break time! ${minutes} min session, ${summary.focus_percent}% focused`,
+    message: `Pomodoros today: ${goal}${idle}`,
+    items: summary.top_domains.map((total) => ({
+      title: total.domain,
+      message: `${Math.round(total.seconds / 60)} min`,
+    })),
+  });
+}
+
 /**
  * Extract domain from URL
  * @param url - The full URL string
@@ -70,6 +288,8 @@ async function checkActiveTab(): Promise<void> {
       if (tab.url !== lastUrl || tab.title !== lastTitle) {
         lastUrl = tab.url;
         lastTitle = tab.title;
+        lastTabId = tab.id ?? null;
+        lastWindowId = tab.windowId;
 
         const domain = extractDomain(tab.url);
 
@@ -89,26 +309,71 @@ async function checkActiveTab(): Promise<void> {
   }
 }
 
+/**
+ * Tell the daemon whether a browser window has focus and whether the user
+ * is idle (or the screen locked)
+ * @returns Promise<void>
+ */
+async function sendHeartbeat(): Promise<void> {
+  try {
+    const [lastFocused, state] = await Promise.all([
+      chrome.windows.getLastFocused(),
+      chrome.idle.queryState(IDLE_DETECTION_SECONDS),
+    ]);
+    const message: HeartbeatMessage = {
+      type: "heartbeat",
+      focused: lastFocused.focused,
+      idle: state !== "active",
+    };
+    sendMessage(message);
+  } catch (error) {
+    console.error("Error sending heartbeat:", error);
+  }
+}
+
+/**
+ * Read the profile token saved on the options page
+ * @returns The token, or an empty string when none is set
+ */
+async function loadToken(): Promise<string> {
+  const stored = await chrome.storage.local.get(TOKEN_KEY);
+  const token: unknown = stored[TOKEN_KEY];
+  return typeof token === "string" ? token : "";
+}
+
 /**
  * Connect to the WebSocket daemon
- * @return void
+ * @return Promise<void>
  */
-function connectWebSocket(): void {
-  if (ws && ws.readyState === WebSocket.OPEN) {
+async function connectWebSocket(): Promise<void> {
+  if (
+    ws &&
+    (ws.readyState === WebSocket.OPEN || ws.readyState === WebSocket.CONNECTING)
+  ) {
     return; // Already connected
   }
 
-  console.log("Connecting to Stop It daemon...");
-  ws = new WebSocket(DAEMON_WS_URL);
+  const address = `ws://127.0.0.1:${DAEMON_PORT + portOffset}`;
+  const token = await loadToken();
+  const url = token
+    ? `${address}/?token=${encodeURIComponent(token)}`
+    : address;
+  console.log(`Connecting to Stop It daemon at ${address}...`);
+  ws = new WebSocket(url);
+  let opened = false;
 
   ws.onopen = () => {
+    opened = true;
     console.log("Connected to Stop It daemon");
     if (reconnectInterval) {
       clearInterval(reconnectInterval);
       reconnectInterval = null;
     }
-    // Send current tab on connection (fire-and-forget)
+    // Send current tab on connection (fire-and-forget), even when unchanged
+    // since a restarted daemon doesn't know it
+    forgetLastTab();
     void checkActiveTab();
+    void sendHeartbeat();
   };
 
   ws.onmessage = (event: MessageEvent<unknown>) => {
@@ -119,6 +384,35 @@ function connectWebSocket(): void {
         return;
       }
       const data = JSON.parse(rawData) as unknown;
+      const breakStart = BreakStartSchema.safeParse(data);
+      if (breakStart.success) {
+        void handleBreak(breakStart.data.id, breakStart.data.ends_at);
+        return;
+      }
+      const breakEnd = BreakEndSchema.safeParse(data);
+      if (breakEnd.success) {
+        void handleBreak(breakEnd.data.id, null);
+        return;
+      }
+      const badge = BadgeSchema.safeParse(data);
+      if (badge.success) {
+        void chrome.action.setBadgeText({ text: badge.data.text });
+        void chrome.action.setBadgeBackgroundColor({ color: badge.data.color });
+        return;
+      }
+      const summary = SessionSummarySchema.safeParse(data);
+      if (summary.success) {
+        showSessionRecap(summary.data);
+        return;
+      }
+      const state = TimerStateSchema.safeParse(data);
+      if (state.success) {
+        if (timerState?.mode !== state.data.mode) {
+          console.log("Pomodoro mode:", state.data.mode);
+        }
+        timerState = state.data;
+        return;
+      }
       const response = NativeResponseSchema.parse(data);
       console.log("Daemon response:", response);
     } catch (error) {
@@ -136,12 +430,16 @@ function connectWebSocket(): void {
 
   ws.onclose = () => {
     console.log("Disconnected from Stop It daemon");
+    void chrome.action.setBadgeText({ text: "" });
     ws = null;
+    if (!opened) {
+      portOffset = (portOffset + 1) % DAEMON_PORT_RANGE;
+    }
     // Auto-reconnect
     if (!reconnectInterval) {
       reconnectInterval = setInterval(() => {
         console.log("Attempting to reconnect...");
-        connectWebSocket();
+        void connectWebSocket();
       }, RECONNECT_DELAY);
     }
   };
@@ -152,7 +450,7 @@ function connectWebSocket(): void {
  * @param message - The message to send
  * @returns void
  */
-function sendMessage(message: TabUpdateMessage): void {
+function sendMessage(message: ClientMessage): void {
   if (!ws || ws.readyState !== WebSocket.OPEN) {
     console.warn("WebSocket not connected. Message not sent.");
     return;
@@ -182,6 +480,62 @@ chrome.tabs.onUpdated.addListener((_tabId, changeInfo, tab) => {
   }
 });
 
+/*
+ * Pages loaded during a break get the banner too
+ */
+chrome.tabs.onUpdated.addListener((tabId, changeInfo) => {
+  if (breakEndsAt && changeInfo.status === "complete") {
+    void updateOverlay(tabId);
+  }
+});
+
+/**
+ * Forget the last reported tab, so the next update is sent even for the same URL
+ */
+function forgetLastTab(): void {
+  lastUrl = "";
+  lastTitle = "";
+  lastTabId = null;
+  lastWindowId = null;
+}
+
+/*
+ * Listen for tab closes: when the tracked tab goes away, tell the daemon right
+ * away instead of letting it credit the site until the next update.
+ * Tabs closed along with their window are reported by windows.onRemoved.
+ */
+chrome.tabs.onRemoved.addListener((tabId, removeInfo) => {
+  if (tabId !== lastTabId || removeInfo.isWindowClosing) {
+    return;
+  }
+  const message: TabClosedMessage = {
+    type: "tab_closed",
+    domain: extractDomain(lastUrl),
+  };
+  forgetLastTab();
+  sendMessage(message);
+});
+
+/*
+ * Listen for window closes; the last window closing means the browser is exiting
+ */
+chrome.windows.onRemoved.addListener((windowId) => {
+  if (windowId !== lastWindowId) {
+    return;
+  }
+  // The domain lets the daemon ignore the close if another tab was reported meanwhile
+  const domain = extractDomain(lastUrl);
+  forgetLastTab();
+  void chrome.windows.getAll().then((windows) => {
+    const message: WindowClosedMessage = {
+      type: "window_closed",
+      domain,
+      browser_exiting: windows.length === 0,
+    };
+    sendMessage(message);
+  });
+});
+
 /*
  * Listen for window focus changes
  * When the user switches windows, check the active tab in the new window
@@ -196,12 +550,31 @@ chrome.windows.onFocusChanged.addListener((windowId) => {
   if (windowId !== chrome.windows.WINDOW_ID_NONE) {
     void checkActiveTab();
   }
+  void sendHeartbeat();
+});
+
+/*
+ * Report going idle or coming back right away rather than with the next heartbeat
+ */
+chrome.idle.setDetectionInterval(IDLE_DETECTION_SECONDS);
+chrome.idle.onStateChanged.addListener(() => {
+  void sendHeartbeat();
+});
+
+/*
+ * Reconnect with the new token when it is changed on the options page
+ */
+chrome.storage.onChanged.addListener((changes, areaName) => {
+  if (areaName === "local" && TOKEN_KEY in changes) {
+    ws?.close(); // onclose schedules the reconnect
+  }
 });
 
 // Connect to daemon on startup
-connectWebSocket();
+void connectWebSocket();
 
 // Polling fallback (every 2 seconds) to catch cases where events might be missed
 setInterval(checkActiveTab, 2000);
+setInterval(() => void sendHeartbeat(), HEARTBEAT_INTERVAL);
 
 console.log("Stop It extension loaded and monitoring tabs");
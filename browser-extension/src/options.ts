@@ -0,0 +1,36 @@
+// Options page: stores the token this browser profile presents to the daemon
+const TOKEN_KEY = "daemonToken";
+
+const tokenInput = document.getElementById("token") as HTMLInputElement;
+const saveButton = document.getElementById("save") as HTMLButtonElement;
+const status = document.getElementById("status") as HTMLSpanElement;
+
+/**
+ * Fill the form with the saved token
+ * @returns Promise<void>
+ */
+async function restoreOptions(): Promise<void> {
+  const stored = await chrome.storage.local.get(TOKEN_KEY);
+  const token: unknown = stored[TOKEN_KEY];
+  tokenInput.value = typeof token === "string" ? token : "";
+}
+
+/**
+ * Save the token; the background worker reconnects with it
+ * @returns Promise<void>
+ */
+async function saveOptions(): Promise<void> {
+  await chrome.storage.local.set({ [TOKEN_KEY]: tokenInput.value.trim() });
+  status.textContent = " Saved";
+  setTimeout(() => {
+    status.textContent = "";
+  }, 1500);
+}
+
+saveButton.addEventListener("click", () => {
+  void saveOptions();
+});
+
+void restoreOptions();
+
+export {};
@@ -0,0 +1,115 @@
+//! Compiles the built-in databases in `data/` into perfect-hash maps:
+//! `data/sites.txt` for `stats::sites` and `data/services.txt` for
+//! `window::services`, so they cost no parsing at startup.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SITES: &str = "data/sites.txt";
+const SERVICES: &str = "data/services.txt";
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set"));
+    build_sites(&out_dir.join("sites.rs"));
+    build_services(&out_dir.join("services.rs"));
+}
+
+/// Lines of a data file without comments and blank lines, with line numbers
+fn data_lines(path: &str) -> Vec<(usize, String)> {
+    println!("cargo:rerun-if-changed={}", path);
+    let content = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let line = line.split('#').next().unwrap_or("").trim();
+            (!line.is_empty()).then(|| (number + 1, line.to_string()))
+        })
+        .collect()
+}
+
+fn phf_map(entries: &HashMap<String, String>) -> String {
+    let mut map = phf_codegen::Map::new();
+    for (key, value) in entries {
+        map.entry(key.as_str(), &format!("{:?}", value));
+    }
+    map.build().to_string()
+}
+
+/// `<domain> <category> [aliases...]`
+fn build_sites(out: &Path) {
+    let mut categories: HashMap<String, String> = HashMap::new();
+    let mut aliases: HashMap<String, String> = HashMap::new();
+    for (number, line) in data_lines(SITES) {
+        let mut fields = line.split_whitespace();
+        let (Some(domain), Some(category)) = (fields.next(), fields.next()) else {
+            panic!(
+                "{}:{}: expected `<domain> <category> [aliases...]`",
+                SITES, number
+            );
+        };
+        if categories
+            .insert(domain.to_string(), category.to_string())
+            .is_some()
+        {
+            panic!("{}:{}: {} is listed twice", SITES, number, domain);
+        }
+        for alias in fields {
+            if aliases
+                .insert(alias.to_string(), domain.to_string())
+                .is_some()
+            {
+                panic!("{}:{}: alias {} is listed twice", SITES, number, alias);
+            }
+        }
+    }
+    if let Some(alias) = aliases.keys().find(|alias| categories.contains_key(*alias)) {
+        panic!("{}: {} is both a site and an alias", SITES, alias);
+    }
+
+    fs::write(
+        out,
+        format!(
+            "/// Built-in category per site\n\
+             static CATEGORIES: phf::Map<&'static str, &'static str> = {};\n\n\
+             /// Other hosts of a site, mapped to the site\n\
+             static ALIASES: phf::Map<&'static str, &'static str> = {};\n",
+            phf_map(&categories),
+            phf_map(&aliases)
+        ),
+    )
+    .expect("OUT_DIR is writable");
+}
+
+/// `<domain> | <name> | <name> ...`, names matched in lowercase
+fn build_services(out: &Path) {
+    let mut names: HashMap<String, String> = HashMap::new();
+    for (number, line) in data_lines(SERVICES) {
+        let mut fields = line.split('|').map(str::trim);
+        let domain = fields.next().unwrap_or("");
+        let fields: Vec<&str> = fields.filter(|name| !name.is_empty()).collect();
+        if domain.is_empty() || fields.is_empty() {
+            panic!("{}:{}: expected `<domain> | <name> ...`", SERVICES, number);
+        }
+        for name in fields {
+            if let Some(other) = names.insert(name.to_lowercase(), domain.to_string()) {
+                panic!(
+                    "{}:{}: {:?} is already the name of {}",
+                    SERVICES, number, name, other
+                );
+            }
+        }
+    }
+
+    fs::write(
+        out,
+        format!(
+            "/// Lowercase service name -> domain\n\
+             static SERVICES: phf::Map<&'static str, &'static str> = {};\n",
+            phf_map(&names)
+        ),
+    )
+    .expect("OUT_DIR is writable");
+}
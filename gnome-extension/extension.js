@@ -0,0 +1,44 @@
+// Exposes the focused window on the session bus, since GNOME Shell offers no
+// way for other processes to read it under Wayland.
+//
+//   gdbus call --session --dest org.gnome.Shell \
+//     --object-path /org/gnome/Shell/Extensions/StopIt \
+//     --method org.gnome.Shell.Extensions.StopIt.FocusedWindow
+//
+// answers `('firefox', 'GitHub — Mozilla Firefox')`, or `('', '')` when no
+// window has focus.
+
+import Gio from 'gi://Gio';
+import { Extension } from 'resource:///org/gnome/shell/extensions/extension.js';
+
+const INTERFACE = `
+<node>
+  <interface name="org.gnome.Shell.Extensions.StopIt">
+    <method name="FocusedWindow">
+      <arg type="s" direction="out" name="class"/>
+      <arg type="s" direction="out" name="title"/>
+    </method>
+  </interface>
+</node>`;
+
+export default class StopItExtension extends Extension {
+    enable() {
+        this._dbus = Gio.DBusExportedObject.wrapJSObject(INTERFACE, this);
+        this._dbus.export(Gio.DBus.session, '/org/gnome/Shell/Extensions/StopIt');
+    }
+
+    disable() {
+        this._dbus.unexport();
+        this._dbus = null;
+    }
+
+    FocusedWindow() {
+        const window = global.display.focus_window;
+        if (!window) {
+            return ['', ''];
+        }
+        // Native Wayland windows may only have an app ID
+        const wmClass = window.get_wm_class() ?? window.get_gtk_application_id() ?? '';
+        return [wmClass, window.get_title() ?? ''];
+    }
+}
@@ -40,12 +40,13 @@ Description=Stop It - Activity Tracker & Pomodoro Timer Daemon
 After=network.target
 
 [Service]
-Type=simple
-ExecStart=$BINARY_PATH --daemon
+Type=notify
+NotifyAccess=main
+ExecStart=$BINARY_PATH daemon
+WatchdogSec=30
 Restart=on-failure
 RestartSec=5
-Environment="DISPLAY=:0"
-Environment="WAYLAND_DISPLAY=wayland-1"
+PassEnvironment=WAYLAND_DISPLAY DISPLAY HYPRLAND_INSTANCE_SIGNATURE SWAYSOCK XDG_CURRENT_DESKTOP KDE_FULL_SESSION
 
 # Logging
 StandardOutput=journal
@@ -57,6 +58,14 @@ EOF
 
 echo "✅ Service file created at: $SERVICE_FILE"
 
+# The daemon names its socket after the session, as the CLI does, and detects
+# the focus backend from it
+for variable in WAYLAND_DISPLAY DISPLAY HYPRLAND_INSTANCE_SIGNATURE SWAYSOCK XDG_CURRENT_DESKTOP KDE_FULL_SESSION; do
+    if [ -n "${!variable:-}" ]; then
+        systemctl --user import-environment "$variable"
+    fi
+done
+
 # Reload systemd user daemon
 echo "🔄 Reloading systemd user daemon..."
 systemctl --user daemon-reload
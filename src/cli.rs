@@ -0,0 +1,184 @@
+use crate::commands::categorize::CategorizeArgs;
+use crate::commands::config::ConfigArgs;
+#[cfg(feature = "debug-bundle")]
+use crate::commands::debug_bundle::DebugBundleArgs;
+use crate::commands::export::ExportArgs;
+use crate::commands::gen_binds::GenBindsArgs;
+use crate::commands::init::InitArgs;
+use crate::commands::inspect::InspectArgs;
+use crate::commands::laps::LapsArgs;
+use crate::commands::merge_db::MergeDbArgs;
+use crate::commands::plan::PlanArgs;
+use crate::commands::record::RecordArgs;
+use crate::commands::schema::SchemaArgs;
+use crate::commands::service::{InstallServiceArgs, ServiceArgs};
+#[cfg(feature = "share")]
+use crate::commands::share::ShareArgs;
+use crate::commands::stats::StatsArgs;
+use crate::commands::status::StatusArgs;
+use crate::commands::timer::{ModeArgs, SnoozeArgs};
+use crate::commands::token::TokenArgs;
+#[cfg(feature = "websocket")]
+use crate::commands::top::TopArgs;
+use crate::commands::triage::TriageArgs;
+use clap::{Args, Parser, Subcommand};
+use stop_it::config::settings::{self, parse_duration};
+use stop_it::window::backend::Backend;
+
+/// Browser activity monitor and Pomodoro timer. Without a command the daemon
+/// runs in the foreground.
+#[derive(Debug, Parser)]
+#[command(name = "stop_it", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Same as `stop_it daemon`, for service files installed before it existed
+    #[arg(long, hide = true)]
+    pub daemon: bool,
+
+    #[arg(long, hide = true)]
+    pub harden: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the daemon in this terminal; `stop_it daemonize` moves it to the background
+    Run(DaemonArgs),
+    /// Run the daemon in the background, as the systemd service does
+    Daemon(DaemonArgs),
+    /// Show the running timer and remaining budgets
+    Status(StatusArgs),
+    /// Stop the timer and tracking until `stop_it resume`
+    Pause,
+    /// Continue a paused session with the time it had left
+    Resume,
+    /// End the current session or break now and move on to the next
+    Skip,
+    /// Drop the current phase and start a new cycle with a work session
+    Reset,
+    /// Put the break off and keep working a few more minutes
+    Snooze(SnoozeArgs),
+    /// End the break and start the next work session, needed with `--manual`
+    Start,
+    /// Work without breaks (continuous) or go back to Pomodoro cycles,
+    /// without restarting the daemon
+    Mode(ModeArgs),
+    /// Show stored statistics (works while the daemon is running)
+    Stats(StatsArgs),
+    /// Write time per domain or the Pomodoro history as CSV or JSON
+    Export(ExportArgs),
+    /// Render a summary card to post in a team channel, as SVG or PNG
+    #[cfg(feature = "share")]
+    Share(ShareArgs),
+    /// Combine two SQLite databases into a new one, reporting records they
+    /// disagree on
+    MergeDb(MergeDbArgs),
+    /// List today's finished work sessions and breaks, with their focus
+    Laps(LapsArgs),
+    /// Live view of today's domains
+    #[cfg(feature = "websocket")]
+    Top(TopArgs),
+    /// Categorize the sites that took time without a category, as rules in the config
+    Triage(TriageArgs),
+    /// Set the category of a domain: saves the rule, re-tags today's activity
+    /// and updates connected clients
+    Categorize(CategorizeArgs),
+    /// Print keybindings for the control commands
+    GenBinds(GenBindsArgs),
+    /// Check the configuration file
+    Config(ConfigArgs),
+    /// Write a config interactively and install the systemd service
+    Init(InitArgs),
+    /// Plan work blocks for a day, or show the plan
+    Plan(PlanArgs),
+    /// Print the systemd user unit
+    Service(ServiceArgs),
+    /// Write the systemd user unit and start the daemon with it
+    InstallService(InstallServiceArgs),
+    /// Check the config, focus backend, running daemon and browser extension
+    Doctor,
+    /// Follow the running daemon's log until Ctrl+C
+    Attach,
+    /// Move a daemon running in a terminal to the background
+    Daemonize,
+    /// Record what the running daemon sees and decides, for bug reports
+    Record(RecordArgs),
+    /// Show the latest domain switches, browser messages and errors the
+    /// running daemon kept
+    Inspect(InspectArgs),
+    /// Collect the config without secrets, recent logs, environment
+    /// detection and the daemon's latest events into an anonymized
+    /// `.tar.gz` for bug reports
+    #[cfg(feature = "debug-bundle")]
+    DebugBundle(DebugBundleArgs),
+    /// Generate a WebSocket token for a browser profile
+    Token(TokenArgs),
+    /// Print the JSON Schemas of the protocol messages, for client developers
+    Schema(SchemaArgs),
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct DaemonArgs {
+    /// Make the data and runtime directories private and refuse a config
+    /// other users can write
+    #[arg(long)]
+    pub harden: bool,
+
+    /// Write rate-limited JSON events to stdout, one per line, for process
+    /// supervisors; the log moves to stderr
+    #[arg(long)]
+    pub machine: bool,
+
+    /// Work session length, e.g. 50m, over `[pomodoro] work`
+    #[arg(long, value_name = "DURATION", value_parser = parse_session_length)]
+    pub work: Option<i64>,
+
+    /// Break length, e.g. 10m, over `[pomodoro] break`
+    #[arg(long = "break", value_name = "DURATION", value_parser = parse_session_length)]
+    pub break_: Option<i64>,
+
+    /// Where the focused window comes from (hyprland, sway, gnome, kde, x11,
+    /// windows or none), over the one detected from the environment
+    #[arg(long, value_name = "BACKEND")]
+    pub backend: Option<Backend>,
+
+    /// Wait for `stop_it start` after a break instead of starting the next
+    /// work session on time (`[pomodoro] manual`)
+    #[arg(long)]
+    pub manual: bool,
+
+    /// Run from the browser extension alone: no focus backend, focus and
+    /// idle time come from its heartbeats (`[extension] only`)
+    #[arg(long, conflicts_with = "backend")]
+    pub browser_only: bool,
+
+    /// Play no `[sounds]` when sessions start
+    #[arg(long)]
+    pub mute: bool,
+}
+
+impl DaemonArgs {
+    /// Apply `--work`, `--break`, `--manual`, `--browser-only` and `--mute`
+    /// over the config
+    pub fn apply(&self, config: &mut settings::Config) {
+        if let Some(work) = self.work {
+            config.pomodoro.work = work;
+        }
+        if let Some(break_) = self.break_ {
+            config.pomodoro.break_ = break_;
+        }
+        config.pomodoro.manual |= self.manual;
+        config.extension.only |= self.browser_only;
+        config.sounds.mute |= self.mute;
+    }
+}
+
+/// Seconds in "50m", "1h" or plain minutes, longer than zero
+fn parse_session_length(value: &str) -> Result<i64, String> {
+    match parse_duration(value) {
+        Some(seconds) if seconds > 0 => Ok(seconds),
+        Some(_) => Err("must be longer than zero".to_string()),
+        None => Err("expected e.g. \"50m\", \"1h\" or minutes".to_string()),
+    }
+}
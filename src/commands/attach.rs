@@ -0,0 +1,19 @@
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+/// `stop_it attach`: follow the running daemon's log in this terminal until
+/// Ctrl+C, without stopping it
+pub async fn run_attach() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = ControlClient::connect(&socket_path()).await?;
+    if let ServerMessage::Ack { message } = client.request(&ClientMessage::Attach).await? {
+        println!("🍅 {}", message);
+    }
+    while let Some(message) = client.recv().await? {
+        if let ServerMessage::LogLine { line } = message {
+            println!("{}", line);
+        }
+    }
+    println!("Daemon stopped");
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::commands::util::{edit_config, format_duration};
+use clap::Args;
+use stop_it::config::edit::set_category_rule;
+use stop_it::config::settings::Config;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::stats::calendar::Calendar;
+use stop_it::stats::categories::CategoryRules;
+use stop_it::storage::backend::open_storage;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+#[derive(Debug, Args)]
+pub struct CategorizeArgs {
+    /// Domain to re-tag, subdomains included, e.g. youtube.com
+    domain: String,
+    /// Its category from now on, e.g. entertainment
+    category: String,
+}
+
+/// `stop_it categorize <domain> <category>`: save the `[categories]` rule
+/// and re-tag today's activity, through the running daemon when there is
+/// one so its clients see the change at once
+pub async fn run_categorize(args: CategorizeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let domain = args.domain.trim().to_lowercase();
+    let category = args.category.trim().to_string();
+    if domain.is_empty() || category.is_empty() {
+        return Err("Both a domain and a category are needed".into());
+    }
+    let path = edit_config(|original| set_category_rule(original, &domain, &category))
+        .map_err(|e| format!("Rule not saved: {}", e))?;
+    println!(
+        "✅ \"{}\" = \"{}\" saved to {}",
+        domain,
+        category,
+        path.display()
+    );
+
+    let request = ClientMessage::Categorize {
+        domain: domain.clone(),
+        category,
+    };
+    let Ok(mut client) = ControlClient::connect(&socket_path()).await else {
+        // No daemon running: re-tag the stored activity directly
+        let config = Config::load()?;
+        let today = Calendar::new(&config.day).today();
+        let mut storage = open_storage(config.storage.url.as_deref())?;
+        let mut store = storage.load(&today)?;
+        let changed = CategoryRules::new(&config.categories).reapply(&mut store, &today, &domain);
+        for segment in &changed {
+            storage.save_segment(segment)?;
+        }
+        let seconds: i64 = changed.iter().map(|segment| segment.seconds).sum();
+        println!("🏷 {} of today re-tagged", format_duration(seconds));
+        return Ok(());
+    };
+    match client.request(&request).await? {
+        ServerMessage::Ack { message } => println!("🏷 {}", message),
+        other => return Err(format!("Unexpected answer from the daemon: {:?}", other).into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+use stop_it::config::paths;
+use stop_it::config::settings::Config;
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Validate the config without starting the daemon
+    Check {
+        /// Config file to check instead of the default one
+        path: Option<PathBuf>,
+    },
+}
+
+/// `stop_it config check [PATH]`: validate the config without starting the daemon
+pub fn run_config(args: ConfigArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match args.action {
+        ConfigAction::Check { path } => {
+            let path = path.unwrap_or_else(paths::config_file);
+            if !path.exists() {
+                println!("No config at {}, using defaults", path.display());
+                return Ok(());
+            }
+            let config = Config::load_from(&path)?;
+            match config.profile {
+                Some(profile) => println!("✓ {} is valid (profile {})", path.display(), profile),
+                None => println!("✓ {} is valid", path.display()),
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use stop_it::config::paths;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+/// How long to wait for each daemon to stop or come up
+const WAIT_SECONDS: u64 = 10;
+
+/// `stop_it daemonize`: move a session running in the foreground to a
+/// background daemon. The foreground process saves its state and exits, then
+/// the systemd service (or a detached process without one) resumes the
+/// running Pomodoro from the saved phase.
+pub async fn run_daemonize() -> Result<(), Box<dyn std::error::Error>> {
+    let socket = socket_path();
+    let mut client = ControlClient::connect(&socket).await?;
+    let pid = client.daemon_pid();
+    if let ServerMessage::Ack { message } = client.request(&ClientMessage::Handoff).await? {
+        println!("🍅 {}", message);
+    }
+    drop(client);
+
+    // Wait for the process to be gone, so its port is free again
+    wait_for(
+        || match pid {
+            Some(pid) => !Path::new(&format!("/proc/{}", pid)).exists(),
+            None => !socket.exists(), // removed on the way out
+        },
+        "the foreground session to stop",
+    )
+    .await?;
+
+    if has_systemd_unit() {
+        let status = Command::new("systemctl")
+            .args(["--user", "start", "stop-it.service"])
+            .status()?;
+        if !status.success() {
+            return Err("systemctl --user start stop-it.service failed".into());
+        }
+        println!("▶️  Started stop-it.service (journalctl --user -u stop-it -f for logs)");
+    } else {
+        let log_file = paths::data_dir().join("daemon.log");
+        std::fs::create_dir_all(paths::data_dir())?;
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_file)?;
+        let mut command = Command::new(std::env::current_exe()?);
+        command
+            .arg("daemon")
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        detach(&mut command);
+        let child = command.spawn()?;
+        println!(
+            "▶️  Started daemon (pid {}, logging to {})",
+            child.id(),
+            log_file.display()
+        );
+    }
+
+    wait_for(|| socket.exists(), "the background daemon to start").await?;
+    println!("Run `stop_it attach` to follow it");
+    Ok(())
+}
+
+/// Own process group, so closing the terminal doesn't take it along
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+/// No console, so closing the terminal doesn't take it along
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    command.creation_flags(DETACHED_PROCESS);
+}
+
+/// Whether `stop_it init` or `stop_it install-service` installed the
+/// systemd user service
+fn has_systemd_unit() -> bool {
+    Command::new("systemctl")
+        .args(["--user", "cat", "stop-it.service"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+async fn wait_for(done: impl Fn() -> bool, what: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for _ in 0..WAIT_SECONDS * 10 {
+        if done() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err(format!("Timed out waiting for {}", what).into())
+}
@@ -0,0 +1,279 @@
+use chrono::{Local, NaiveDate};
+use clap::Args;
+use flate2::read::GzDecoder;
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stop_it::config::paths;
+use stop_it::config::settings::Config;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::daemon::debug_bundle::{Anonymizer, DebugBundle, redact_config};
+use stop_it::daemon::log_files::LogFiles;
+use stop_it::input::activity::InputSource;
+use stop_it::window::backend::Backend;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+/// Environment variables the session detection looks at; only whether they
+/// are set goes in the bundle
+const SESSION_VARIABLES: [&str; 8] = [
+    "XDG_SESSION_TYPE",
+    "XDG_CURRENT_DESKTOP",
+    "WAYLAND_DISPLAY",
+    "DISPLAY",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "SWAYSOCK",
+    "KDE_FULL_SESSION",
+    "GNOME_SETUP_DISPLAY",
+];
+
+#[derive(Debug, Args)]
+pub struct DebugBundleArgs {
+    /// Where to write the archive, `stop_it-debug-<date>-<time>.tar.gz` in
+    /// the current directory by default
+    #[arg(long, short, value_name = "PATH")]
+    output: Option<PathBuf>,
+    /// Log files of the last N days, today included
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(i64).range(1..))]
+    days: i64,
+    /// How many of the running daemon's latest events to include
+    #[arg(long, value_name = "COUNT", default_value_t = 500)]
+    events: usize,
+}
+
+/// `stop_it debug-bundle`: the config without secrets, recent logs, what the
+/// environment detection found and the running daemon's latest events, in
+/// one `.tar.gz` with domains, URLs and home paths anonymized
+pub async fn run_debug_bundle(args: DebugBundleArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let now = Local::now();
+    let anonymizer = Anonymizer::from_env();
+    let mut bundle = DebugBundle::new();
+    let mut environment = environment();
+
+    let config_path = paths::config_file();
+    let config = match std::fs::read_to_string(&config_path) {
+        Ok(text) => {
+            match redact_config(&text, &anonymizer) {
+                Ok(redacted) => bundle.add("config.toml", redacted),
+                Err(e) => writeln!(environment, "config.toml: not valid TOML ({})", e)?,
+            }
+            match Config::load() {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    let error = anonymizer.anonymize(&e.to_string());
+                    writeln!(environment, "config: invalid\n{}", error)?;
+                    None
+                }
+            }
+        }
+        Err(_) => {
+            writeln!(environment, "config: none, defaults apply")?;
+            Some(Config::default())
+        }
+    };
+    if let Some(config) = &config {
+        let detected = Backend::detect(config, Backend::is_available);
+        writeln!(
+            environment,
+            "detected backend: {}",
+            detected.map_or("none (focus tracking off or no session found)", |backend| {
+                backend.name()
+            })
+        )?;
+        writeln!(
+            environment,
+            "input: sample = {}, source = {}",
+            config.input.sample,
+            config.input.source.name()
+        )?;
+        writeln!(environment, "log files: {}", config.log.files)?;
+    }
+
+    for (name, contents) in log_files(&LogFiles::default_dir(), now.date_naive(), args.days) {
+        bundle.add(format!("logs/{}", name), anonymizer.anonymize(&contents));
+    }
+    if let Some(journal) = journal() {
+        bundle.add("logs/journal.log", anonymizer.anonymize(&journal));
+    }
+
+    match daemon_report(args.events).await {
+        Ok((status, events)) => {
+            writeln!(environment, "daemon: running")?;
+            bundle.add("daemon/status.json", anonymizer.anonymize(&status));
+            bundle.add("daemon/inspect.jsonl", anonymizer.anonymize(&events));
+        }
+        Err(e) => writeln!(environment, "daemon: not reachable ({})", e)?,
+    }
+    bundle.add("environment.txt", anonymizer.anonymize(&environment));
+
+    let root = format!("stop_it-debug-{}", now.format("%Y%m%d-%H%M%S"));
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", root)));
+    let file = std::fs::File::create(&output)
+        .map_err(|e| format!("Could not create {}: {}", output.display(), e))?;
+    bundle.write(file, &root, now.timestamp())?;
+    println!("✅ Wrote {}:", output.display());
+    for path in bundle.paths() {
+        println!("   {}", path);
+    }
+    println!(
+        "Tokens and URLs with credentials are removed, domains hashed and URLs cut to their \
+         host. Look it over before attaching it to an issue."
+    );
+    Ok(())
+}
+
+/// Build, platform and session detection
+fn environment() -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "stop_it {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        text,
+        "platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let features: Vec<&str> = [
+        ("websocket", cfg!(feature = "websocket")),
+        ("title-fallback", cfg!(feature = "title-fallback")),
+        ("dbus", cfg!(feature = "dbus")),
+        ("webhooks", cfg!(feature = "webhooks")),
+        ("remote-config", cfg!(feature = "remote-config")),
+        ("share", cfg!(feature = "share")),
+        ("gzip", cfg!(feature = "gzip")),
+        ("postgres", cfg!(feature = "postgres")),
+        ("otel", cfg!(feature = "otel")),
+        ("windows-focus", cfg!(feature = "windows-focus")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature)
+    .collect();
+    let _ = writeln!(text, "features: {}", features.join(", "));
+    let _ = writeln!(
+        text,
+        "session: {}",
+        paths::session().unwrap_or_else(|| "-".to_string())
+    );
+    for variable in SESSION_VARIABLES {
+        let _ = writeln!(
+            text,
+            "${}: {}",
+            variable,
+            if std::env::var_os(variable).is_some() {
+                "set"
+            } else {
+                "unset"
+            }
+        );
+    }
+    for backend in Backend::ALL {
+        if backend == Backend::None {
+            continue;
+        }
+        let _ = writeln!(
+            text,
+            "backend {}: {}",
+            backend.name(),
+            if !backend.is_supported() {
+                "not in this build"
+            } else if backend.is_available() {
+                "available"
+            } else {
+                "no session"
+            }
+        );
+    }
+    for source in [
+        InputSource::Evdev,
+        InputSource::Wayland,
+        InputSource::Logind,
+    ] {
+        let _ = writeln!(
+            text,
+            "input source {}: {}",
+            source.name(),
+            if source.is_supported() {
+                "supported"
+            } else {
+                "not in this build"
+            }
+        );
+    }
+    text
+}
+
+/// `activity-<date>.log` files of the last `days` days, gzipped ones unpacked
+fn log_files(dir: &Path, today: NaiveDate, days: i64) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(String, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_name()?.to_str()?.to_string();
+            let day =
+                NaiveDate::parse_from_str(name.strip_prefix("activity-")?.get(..10)?, "%Y-%m-%d")
+                    .ok()?;
+            if (today - day).num_days() >= days {
+                return None;
+            }
+            let mut contents = String::new();
+            if name.ends_with(".log.gz") {
+                GzDecoder::new(std::fs::File::open(&path).ok()?)
+                    .read_to_string(&mut contents)
+                    .ok()?;
+                Some((name.trim_end_matches(".gz").to_string(), contents))
+            } else if name.ends_with(".log") {
+                Some((name, std::fs::read_to_string(&path).ok()?))
+            } else {
+                None
+            }
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// The systemd user service's recent journal, when there is one
+fn journal() -> Option<String> {
+    let output = Command::new("journalctl")
+        .args([
+            "--user",
+            "-u",
+            "stop-it",
+            "-n",
+            "2000",
+            "--no-pager",
+            "-o",
+            "short-iso",
+        ])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).into_owned();
+    (output.status.success() && !text.trim().is_empty() && !text.contains("-- No entries --"))
+        .then_some(text)
+}
+
+/// The running daemon's timer state and latest events, titles and URLs
+/// scrubbed
+async fn daemon_report(events: usize) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let mut client = ControlClient::connect(&socket_path()).await?;
+    let status = match client.request(&ClientMessage::GetStatus).await? {
+        ServerMessage::TimerState(state) => serde_json::to_string_pretty(&state)?,
+        other => return Err(format!("Unexpected answer from the daemon: {:?}", other).into()),
+    };
+    let events = match client.request(&ClientMessage::Inspect { events }).await? {
+        ServerMessage::Events { events } => events,
+        other => return Err(format!("Unexpected answer from the daemon: {:?}", other).into()),
+    };
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(&event.scrubbed())?);
+        lines.push('\n');
+    }
+    Ok((status, lines))
+}
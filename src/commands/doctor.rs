@@ -0,0 +1,72 @@
+use crate::commands::status::fetch_state;
+use stop_it::config::paths;
+use stop_it::config::settings::Config;
+use stop_it::window::backend::Backend;
+
+/// `stop_it doctor`: check the config, the focus backend, the running daemon
+/// and the browser extension's connection, with a hint for each problem.
+/// Fails when anything needs fixing.
+pub async fn run_doctor() -> Result<(), Box<dyn std::error::Error>> {
+    let mut healthy = true;
+
+    let path = paths::config_file();
+    let config = if !path.exists() {
+        println!(
+            "✓ No config at {}, using defaults (`stop_it init` writes one)",
+            path.display()
+        );
+        Config::default()
+    } else {
+        match Config::load() {
+            Ok(config) => {
+                println!("✓ {} is valid", path.display());
+                config
+            }
+            Err(e) => {
+                healthy = false;
+                println!("✗ {}", e);
+                Config::default()
+            }
+        }
+    };
+
+    match Backend::detect(&config, Backend::is_available) {
+        Some(backend) => println!("✓ Focus tracking: {}", backend),
+        None => println!(
+            "✓ Focus tracking off or no supported session found: sites come from the \
+             browser extension only"
+        ),
+    }
+
+    let state = match fetch_state().await {
+        Ok(state) => {
+            println!("✓ The daemon is running");
+            state
+        }
+        Err(e) => {
+            println!("✗ The daemon is not reachable: {}", e);
+            println!("  Start it with `stop_it run`, or `stop_it install-service` on Linux");
+            return Err("Some checks failed".into());
+        }
+    };
+
+    if !cfg!(feature = "websocket") {
+        healthy = false;
+        println!("✗ This build has no WebSocket server, the browser extension can't connect");
+    } else if state.extensions == 0 {
+        healthy = false;
+        println!("✗ No browser extension is connected");
+        println!(
+            "  Build it with `npm run build` in browser-extension/ and load the dist folder \
+             as an unpacked extension"
+        );
+    } else {
+        println!("✓ Browser extensions connected: {}", state.extensions);
+    }
+
+    if healthy {
+        Ok(())
+    } else {
+        Err("Some checks failed".into())
+    }
+}
@@ -0,0 +1,67 @@
+use crate::commands::util::RangeArgs;
+use chrono::Local;
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+use stop_it::config::settings::Config;
+use stop_it::stats::calendar::Calendar;
+use stop_it::stats::export::{self, Row};
+use stop_it::stats::filter::Filter;
+use stop_it::storage::backend::open_storage_read_only;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+    /// Output format
+    #[arg(long, value_enum, default_value = "csv")]
+    format: ExportFormat,
+    /// The Pomodoro history instead of the time per domain and day
+    #[arg(long, conflicts_with = "filter")]
+    pomodoros: bool,
+    /// Only count matching activity, e.g. 'category=social'
+    #[arg(long)]
+    filter: Option<String>,
+    /// Write to a file instead of stdout
+    #[arg(long, short, value_name = "PATH")]
+    out: Option<PathBuf>,
+}
+
+/// Dump stored activity for spreadsheets and scripts; safe to run while the
+/// daemon is writing
+pub fn run_export(args: ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    let calendar = Calendar::new(&config.day);
+    let (_, range) = args.range.resolve(&calendar, Local::now());
+    let filter = args.filter.as_deref().map(Filter::parse).transpose()?;
+
+    let mut storage = open_storage_read_only(config.storage.url.as_deref())?;
+    let store = storage.load(&range)?;
+    let output = if args.pomodoros {
+        render(&export::pomodoro_rows(&store, &range), args.format)?
+    } else {
+        let rows = export::domain_rows(&store, &calendar, &range, filter.as_ref());
+        render(&rows, args.format)?
+    };
+
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, output)?;
+            eprintln!("Exported to {}", path.display());
+        }
+        None => print!("{}", output),
+    }
+    Ok(())
+}
+
+fn render<R: Row>(rows: &[R], format: ExportFormat) -> Result<String, serde_json::Error> {
+    Ok(match format {
+        ExportFormat::Csv => export::to_csv(rows),
+        ExportFormat::Json => serde_json::to_string_pretty(rows)? + "\n",
+    })
+}
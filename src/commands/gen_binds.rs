@@ -0,0 +1,91 @@
+use clap::{Args, ValueEnum};
+
+/// A keybinding for one daemon control command
+struct Binding {
+    action: &'static str,
+    key: &'static str,
+    args: &'static [&'static str],
+}
+
+/// Every bindable control command. Only subcommands this binary actually
+/// implements belong here, so generated configs can't call missing commands.
+const BINDINGS: &[Binding] = &[
+    Binding {
+        action: "status popup",
+        key: "I",
+        args: &["status", "--notify"],
+    },
+    Binding {
+        action: "pause",
+        key: "P",
+        args: &["pause"],
+    },
+    Binding {
+        action: "resume",
+        key: "R",
+        args: &["resume"],
+    },
+    Binding {
+        action: "skip to the next session or break",
+        key: "S",
+        args: &["skip"],
+    },
+    Binding {
+        action: "start the next work session after a break (with --manual)",
+        key: "W",
+        args: &["start"],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum BindFormat {
+    Hyprland,
+    Sway,
+}
+
+#[derive(Debug, Args)]
+pub struct GenBindsArgs {
+    /// Compositor to write bindings for
+    #[arg(long, value_enum)]
+    format: BindFormat,
+}
+
+/// Print ready-to-paste keybinding lines for Hyprland or Sway
+pub fn run_gen_binds(args: GenBindsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let format = args.format;
+    let program = program_name();
+
+    println!(
+        "# stop_it keybindings (generated by `{} gen-binds`)",
+        program
+    );
+    for binding in BINDINGS {
+        let command = format!("{} {}", program, binding.args.join(" "));
+        println!("# {}", binding.action);
+        match format {
+            BindFormat::Hyprland => {
+                println!("bind = SUPER ALT, {}, exec, {}", binding.key, command)
+            }
+            BindFormat::Sway => println!(
+                "bindsym $mod+Alt+{} exec {}",
+                binding.key.to_lowercase(),
+                command
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Name the binary was installed under, so bindings match what's on PATH
+fn program_name() -> String {
+    std::env::args()
+        .next()
+        .as_deref()
+        .and_then(|arg0| {
+            std::path::Path::new(arg0)
+                .file_name()?
+                .to_str()
+                .map(String::from)
+        })
+        .unwrap_or_else(|| "stop_it".to_string())
+}
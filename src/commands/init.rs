@@ -0,0 +1,123 @@
+use crate::commands::service::install_service;
+use crate::commands::util::{ask, confirm};
+use clap::Args;
+use std::fs;
+use std::io;
+use stop_it::config::paths;
+use stop_it::config::settings::{Config, parse_duration};
+
+const DEFAULT_DISTRACTIONS: &str = "youtube.com, reddit.com, twitter.com";
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Install a sandboxed service
+    #[arg(long)]
+    harden: bool,
+}
+
+/// Interactive onboarding: ask a few questions, write a commented config and
+/// optionally install the systemd user service (sandboxed with `--harden`)
+pub fn run_init(args: InitArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let harden = args.harden;
+    println!("🍅 Stop It - Setup");
+    println!("==================\n");
+
+    let path = paths::config_file();
+    if path.exists()
+        && !confirm(
+            &format!("{} already exists. Overwrite it?", path.display()),
+            false,
+        )?
+    {
+        println!("Keeping the existing config.");
+        return Ok(());
+    }
+
+    let work = ask_duration("Work session length", "25m")?;
+    let break_ = ask_duration("Break length", "5m")?;
+    let distractions: Vec<String> = ask(
+        "Distracting sites to watch (comma separated)",
+        DEFAULT_DISTRACTIONS,
+    )?
+    .split(',')
+    .map(|domain| domain.trim().to_string())
+    .filter(|domain| !domain.is_empty())
+    .collect();
+
+    let content = render_config(&work, &break_, &distractions);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    // Catch anything the template and the parser disagree on right away
+    let config = Config::load_from(&path)?;
+    println!("\n✅ Config written to {}", path.display());
+
+    if !cfg!(target_os = "linux") {
+        println!(
+            "\nNo systemd on {}: start `stop_it daemon` with your session instead.",
+            std::env::consts::OS
+        );
+    } else if confirm("Install and start the systemd user service?", true)? {
+        install_service(harden.then_some(&config), true)?;
+    }
+
+    println!(
+        "\nNext: build and load the browser extension (see README), it connects automatically."
+    );
+    Ok(())
+}
+
+fn ask_duration(question: &str, default: &str) -> io::Result<String> {
+    loop {
+        let answer = ask(question, default)?;
+        match parse_duration(&answer) {
+            Some(seconds) if seconds > 0 => return Ok(answer),
+            _ => println!("  Please enter a duration like \"25m\", \"1h\" or \"1h30m\""),
+        }
+    }
+}
+
+fn render_config(work: &str, break_: &str, distractions: &[String]) -> String {
+    let domains = distractions
+        .iter()
+        .map(|domain| format!("\"{}\"", domain))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        r#"# Stop It configuration, generated by `stop_it init`.
+# Check changes with `stop_it config check`; see the README for all options.
+
+[pomodoro]
+work = "{work}"
+break = "{break_}"
+
+[day]
+start = "00:00"     # when a new day begins for statistics
+week_start = "mon"
+
+[focus_alerts]
+enabled = true
+threshold = 0.5     # alert when more than half of the last hour went to distractions
+window_minutes = 60
+work_hours = "09:00-18:00"
+distracting_categories = ["social", "entertainment"]
+distracting_domains = [{domains}]  # subdomains included
+
+# Daily/weekly time budgets per site, e.g.:
+# [budgets."youtube.com"]
+# daily = "30m"
+# weekly = "3h"
+# carryover = true
+
+[goals]
+daily_pomodoros = 8
+
+# Lock the screen during breaks:
+# [breaks]
+# lock = true
+# locker = "hyprlock"
+"#
+    )
+}
@@ -0,0 +1,106 @@
+use clap::Args;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::daemon::trace::TraceEvent;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+#[derive(Debug, Args)]
+pub struct InspectArgs {
+    /// How many of the latest events to show
+    #[arg(long, value_name = "COUNT", default_value_t = 50)]
+    events: usize,
+    /// One JSON event per line, as `stop_it record` writes them
+    #[arg(long)]
+    json: bool,
+}
+
+/// `stop_it inspect`: the domain switches, browser messages and errors the
+/// running daemon kept in memory, oldest first
+pub async fn run_inspect(args: InspectArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = ControlClient::connect(&socket_path()).await?;
+    let events = match client
+        .request(&ClientMessage::Inspect {
+            events: args.events,
+        })
+        .await?
+    {
+        ServerMessage::Events { events } => events,
+        ServerMessage::Error { message } => return Err(message.into()),
+        other => return Err(format!("Unexpected answer from the daemon: {:?}", other).into()),
+    };
+    if events.is_empty() {
+        eprintln!("No events kept (see `[log] recent_events`)");
+    }
+    for event in &events {
+        if args.json {
+            println!("{}", serde_json::to_string(event)?);
+        } else {
+            println!("{}", format_event(event));
+        }
+    }
+    Ok(())
+}
+
+fn format_event(event: &TraceEvent) -> String {
+    match event {
+        TraceEvent::Step { at, window, input } => format!(
+            "{}  step      {} {}",
+            at.format("%H:%M:%S"),
+            window.as_ref().map_or("-", |window| &window.class),
+            input.map_or("-".to_string(), |input| format!("{} inputs", input))
+        ),
+        TraceEvent::Browser { at, message } => {
+            let detail = match message {
+                ClientMessage::TabUpdate(update) => format!(
+                    "tab {} \"{}\"",
+                    update.domain.as_deref().unwrap_or(&update.url),
+                    update.title
+                ),
+                ClientMessage::TabClosed(closed) => {
+                    format!("tab closed {}", closed.domain.as_deref().unwrap_or("-"))
+                }
+                ClientMessage::WindowClosed(closed) => format!(
+                    "window closed {}{}",
+                    closed.domain.as_deref().unwrap_or("-"),
+                    if closed.browser_exiting {
+                        " (browser exiting)"
+                    } else {
+                        ""
+                    }
+                ),
+                ClientMessage::Heartbeat(heartbeat) => format!(
+                    "heartbeat {}{}",
+                    if heartbeat.focused {
+                        "focused"
+                    } else {
+                        "unfocused"
+                    },
+                    if heartbeat.idle { ", idle" } else { "" }
+                ),
+                other => format!("{:?}", other),
+            };
+            format!("{}  browser   {}", at.format("%H:%M:%S"), detail)
+        }
+        TraceEvent::Decision { at, decision } => {
+            let mut line = format!(
+                "{}  decision  {} {}",
+                at.format("%H:%M:%S"),
+                decision.mode,
+                decision.domain.as_deref().unwrap_or("-")
+            );
+            if let Some(category) = &decision.category {
+                line.push_str(&format!(" ({})", category));
+            }
+            if decision.idle {
+                line.push_str(" idle");
+            }
+            if decision.paused {
+                line.push_str(" paused");
+            }
+            line
+        }
+        TraceEvent::Error { at, message } => {
+            format!("{}  error     {}", at.format("%H:%M:%S"), message)
+        }
+    }
+}
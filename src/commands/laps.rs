@@ -0,0 +1,62 @@
+use crate::commands::util::format_duration;
+use chrono::Local;
+use clap::Args;
+use stop_it::config::settings::Config;
+use stop_it::stats::calendar::Calendar;
+use stop_it::stats::focus::FocusMonitor;
+use stop_it::stats::laps::{self, Lap};
+use stop_it::storage::backend::open_storage_read_only;
+
+#[derive(Debug, Args)]
+pub struct LapsArgs {
+    /// One JSON object per phase instead of the table
+    #[arg(long)]
+    json: bool,
+}
+
+/// `stop_it laps`: today's finished work sessions and breaks in order, with
+/// the focus of each work session
+pub fn run_laps(args: LapsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    let calendar = Calendar::new(&config.day);
+    let today = calendar.today();
+
+    let mut storage = open_storage_read_only(config.storage.url.as_deref())?;
+    let store = storage.load(&today)?;
+    let laps = laps::laps(&store, &today, &FocusMonitor::new(&config.focus_alerts));
+    if args.json {
+        for lap in &laps {
+            println!("{}", serde_json::to_string(lap)?);
+        }
+        return Ok(());
+    }
+
+    if laps.is_empty() {
+        println!("No finished phases today");
+        return Ok(());
+    }
+    println!("--- Laps today ({}) ---", calendar.day_of(Local::now()));
+    for lap in &laps {
+        println!("{}", format_lap(lap));
+    }
+    Ok(())
+}
+
+fn format_lap(lap: &Lap) -> String {
+    let focus = match lap.focus_percent {
+        _ if lap.abandoned => "abandoned".to_string(),
+        Some(percent) => format!("focus {:>3}%", percent),
+        None => String::new(),
+    };
+    let line = format!(
+        "{} {}-{}  {:<10} {:>8}  {:<10}  {}",
+        lap.mode.emoji(),
+        lap.start.format("%H:%M"),
+        lap.end.format("%H:%M"),
+        lap.mode.as_str(),
+        format_duration((lap.end - lap.start).num_seconds()),
+        focus,
+        lap.label.as_deref().unwrap_or("")
+    );
+    line.trim_end().to_string()
+}
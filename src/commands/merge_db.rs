@@ -0,0 +1,73 @@
+use crate::commands::util::format_duration;
+use clap::Args;
+use std::path::PathBuf;
+use stop_it::stats::aggregator::TimeRange;
+use stop_it::storage::backend::Storage;
+use stop_it::storage::merge::{Side, merge_stores};
+use stop_it::storage::sqlite_backend::SqliteStorage;
+
+/// Conflicts listed one by one; the rest are only counted
+const MAX_LISTED: usize = 20;
+
+#[derive(Debug, Args)]
+pub struct MergeDbArgs {
+    /// First SQLite database, kept on ties
+    first: PathBuf,
+    /// Second SQLite database
+    second: PathBuf,
+    /// Database to write, which must not exist yet
+    #[arg(long, short, value_name = "PATH")]
+    out: PathBuf,
+}
+
+/// `stop_it merge-db`: combine two databases, e.g. after two daemons ran at
+/// once or an old backup was restored, without touching either of them
+pub fn run_merge_db(args: MergeDbArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.out.exists() {
+        return Err(format!("{} already exists", args.out.display()).into());
+    }
+    let range = TimeRange::all();
+    let first = SqliteStorage::open_read_only(&args.first)?.load(&range)?;
+    let second = SqliteStorage::open_read_only(&args.second)?.load(&range)?;
+    let (merged, report) = merge_stores(&first, &second);
+    SqliteStorage::open(&args.out)?.import(&merged)?;
+
+    println!(
+        "Merged {} segment(s) and {} Pomodoro record(s) into {}",
+        merged.segments().len(),
+        merged.pomodoros().len(),
+        args.out.display()
+    );
+    println!("{} record(s) were in both databases", report.duplicates);
+    if report.conflicts.is_empty() {
+        println!("No conflicts");
+        return Ok(());
+    }
+
+    println!("{} conflict(s):", report.conflicts.len());
+    for conflict in report.conflicts.iter().take(MAX_LISTED) {
+        let (kept, dropped) = match conflict.kept {
+            Side::Second => (&args.second, &args.first),
+            _ => (&args.first, &args.second),
+        };
+        println!(
+            "  {} – {}  {}: kept {} record(s) of {}, dropped {} ({}) of {}",
+            conflict.start.format("%Y-%m-%d %H:%M"),
+            conflict.end.format("%H:%M"),
+            if conflict.pomodoros {
+                "Pomodoros"
+            } else {
+                "activity"
+            },
+            conflict.kept_records,
+            kept.display(),
+            conflict.dropped_records,
+            format_duration(conflict.dropped_seconds),
+            dropped.display()
+        );
+    }
+    if report.conflicts.len() > MAX_LISTED {
+        println!("  … and {} more", report.conflicts.len() - MAX_LISTED);
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+pub mod attach;
+pub mod categorize;
+pub mod config;
+pub mod daemonize;
+#[cfg(feature = "debug-bundle")]
+pub mod debug_bundle;
+pub mod doctor;
+pub mod export;
+pub mod gen_binds;
+pub mod init;
+pub mod inspect;
+pub mod laps;
+pub mod merge_db;
+pub mod plan;
+pub mod record;
+pub mod schema;
+pub mod service;
+#[cfg(feature = "share")]
+pub mod share;
+pub mod stats;
+pub mod status;
+pub mod timer;
+pub mod token;
+#[cfg(feature = "websocket")]
+pub mod top;
+pub mod triage;
+pub mod util;
@@ -0,0 +1,68 @@
+use chrono::{Duration, Local, NaiveDate};
+use clap::Args;
+use stop_it::config::settings::Config;
+use stop_it::pomodoro::plan::{Plans, parse_plan};
+use stop_it::stats::calendar::Calendar;
+
+#[derive(Debug, Args)]
+pub struct PlanArgs {
+    /// Blocks to plan, e.g. "09:00-12:00 deep, 13:00-15:00 admin"
+    #[arg(conflicts_with = "clear")]
+    schedule: Option<String>,
+    /// Plan today (the default)
+    #[arg(long, group = "day")]
+    today: bool,
+    #[arg(long, group = "day")]
+    tomorrow: bool,
+    /// Plan another day, as YYYY-MM-DD
+    #[arg(long, group = "day", value_parser = parse_date)]
+    date: Option<NaiveDate>,
+    /// Remove the plan for the day
+    #[arg(long)]
+    clear: bool,
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| "expected YYYY-MM-DD".to_string())
+}
+
+/// `stop_it plan`: schedule labeled work blocks the daemon starts on its own.
+/// Without a schedule, print the plan for the selected day.
+pub fn run_plan(args: PlanArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    let calendar = Calendar::new(&config.day);
+    let today = calendar.day_of(Local::now());
+
+    let day = match args.date {
+        Some(date) => date,
+        None if args.tomorrow => today + Duration::days(1),
+        None => today,
+    };
+    let clear = args.clear;
+    let spec = args.schedule.as_deref();
+
+    let path = Plans::default_path();
+    let mut plans = Plans::load_from(&path)?;
+    if clear {
+        plans.set(day, Vec::new());
+        plans.save_to(&path)?;
+        println!("Cleared the plan for {}", day.format("%a %Y-%m-%d"));
+        return Ok(());
+    }
+
+    if let Some(spec) = spec {
+        let blocks = parse_plan(spec).map_err(|e| format!("Invalid plan: {}", e))?;
+        plans.set(day, blocks);
+        plans.save_to(&path)?;
+        println!("Planned {}:", day.format("%a %Y-%m-%d"));
+    } else if plans.blocks(day).is_empty() {
+        println!("Nothing planned for {}", day.format("%a %Y-%m-%d"));
+        return Ok(());
+    } else {
+        println!("Plan for {}:", day.format("%a %Y-%m-%d"));
+    }
+    for block in plans.blocks(day) {
+        println!("  {}", block.describe());
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use crate::commands::util::format_duration;
+use clap::Args;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use stop_it::config::settings::parse_duration;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+#[derive(Debug, Args)]
+pub struct RecordArgs {
+    /// How long to record, e.g. 5m
+    #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = parse_record_length)]
+    duration: i64,
+    /// Trace file to write, one JSON event per line
+    #[arg(long, short, value_name = "PATH", default_value = "trace.jsonl")]
+    out: PathBuf,
+    /// Keep window titles, tab titles and full URLs in the trace
+    #[arg(long)]
+    raw: bool,
+}
+
+/// `stop_it record`: capture what the running daemon sees (windows, input,
+/// browser messages) and decides, for bug reports
+pub async fn run_record(args: RecordArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = ControlClient::connect(&socket_path()).await?;
+    if let ServerMessage::Ack { message } = client
+        .request(&ClientMessage::Record { raw: args.raw })
+        .await?
+    {
+        println!("🎙️ {} for {}", message, format_duration(args.duration));
+    }
+
+    let mut out = BufWriter::new(File::create(&args.out)?);
+    let mut events = 0;
+    let deadline = tokio::time::sleep(Duration::from_secs(args.duration as u64));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            _ = tokio::signal::ctrl_c() => break,
+            message = client.recv() => match message? {
+                Some(ServerMessage::Trace { event }) => {
+                    serde_json::to_writer(&mut out, &event)?;
+                    out.write_all(b"\n")?;
+                    events += 1;
+                }
+                Some(ServerMessage::Error { message }) => eprintln!("Warning: {}", message),
+                Some(_) => {}
+                None => {
+                    eprintln!("Daemon stopped");
+                    break;
+                }
+            },
+        }
+    }
+    out.flush()?;
+    println!("Wrote {} events to {}", events, args.out.display());
+    Ok(())
+}
+
+/// Seconds in "5m", "1h" or plain minutes, longer than zero
+fn parse_record_length(value: &str) -> Result<i64, String> {
+    match parse_duration(value) {
+        Some(seconds) if seconds > 0 => Ok(seconds),
+        Some(_) => Err("must be longer than zero".to_string()),
+        None => Err("expected e.g. \"5m\", \"1h\" or minutes".to_string()),
+    }
+}
@@ -0,0 +1,20 @@
+use clap::Args;
+use stop_it::ws::schema::protocol_schema;
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Only the schema of the messages clients send (`client`) or receive (`server`)
+    #[arg(long, value_parser = ["client", "server"])]
+    only: Option<String>,
+}
+
+/// `stop_it schema`: print the JSON Schemas of the WebSocket and control
+/// socket protocol, as `GET /schema` serves them from the running daemon
+pub fn run_schema(args: SchemaArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut schema = protocol_schema();
+    if let Some(only) = args.only {
+        schema = schema[format!("{}_message", only)].take();
+    }
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stop_it::config::settings::Config;
+use stop_it::daemon::sandbox;
+
+/// Passed from the user manager to the daemon: socket and file names follow
+/// the session, as for the CLI running in it, and the focus backend is
+/// detected from them
+const SESSION_VARIABLES: [&str; 6] = [
+    "WAYLAND_DISPLAY",
+    "DISPLAY",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "SWAYSOCK",
+    "XDG_CURRENT_DESKTOP",
+    "KDE_FULL_SESSION",
+];
+
+#[derive(Debug, Args)]
+pub struct ServiceArgs {
+    /// Sandbox the daemon to what the current config needs
+    #[arg(long)]
+    harden: bool,
+}
+
+/// Print the systemd user unit `stop_it init` installs. With `--harden` the
+/// daemon runs with `--harden` inside a sandbox fitted to the current config.
+pub fn run_service(args: ServiceArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = if args.harden {
+        Some(Config::load()?)
+    } else {
+        None
+    };
+    print!(
+        "{}",
+        render_unit(&std::env::current_exe()?, config.as_ref())
+    );
+    Ok(())
+}
+
+#[derive(Debug, Args)]
+pub struct InstallServiceArgs {
+    /// Sandbox the daemon to what the current config needs
+    #[arg(long)]
+    harden: bool,
+    /// Write the unit and reload systemd without enabling or starting it
+    #[arg(long)]
+    no_start: bool,
+}
+
+/// `stop_it install-service`: write the unit `stop_it service` prints to
+/// `~/.config/systemd/user` and enable and start it
+pub fn run_install_service(args: InstallServiceArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !cfg!(target_os = "linux") {
+        return Err(format!(
+            "No systemd on {}: start `stop_it daemon` with your session instead",
+            std::env::consts::OS
+        )
+        .into());
+    }
+    let config = if args.harden {
+        Some(Config::load()?)
+    } else {
+        None
+    };
+    install_service(config.as_ref(), !args.no_start)
+}
+
+/// Write the user unit, reload systemd and, with `start`, enable the unit
+/// and (re)start the daemon
+pub fn install_service(
+    harden: Option<&Config>,
+    start: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let binary = std::env::current_exe()?;
+    let dir = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or("HOME is not set")?
+        .join(".config/systemd/user");
+    fs::create_dir_all(&dir)?;
+
+    let unit = dir.join("stop-it.service");
+    fs::write(&unit, render_unit(&binary, harden))?;
+    println!("✅ Service file created at: {}", unit.display());
+
+    systemctl(&["--user", "daemon-reload"])?;
+    if !start {
+        println!("Start it with `systemctl --user enable --now stop-it.service`");
+        return Ok(());
+    }
+    let session: Vec<&str> = SESSION_VARIABLES
+        .into_iter()
+        .filter(|variable| std::env::var_os(variable).is_some())
+        .collect();
+    if !session.is_empty() {
+        systemctl(&[&["--user", "import-environment"], session.as_slice()].concat())?;
+    }
+    systemctl(&["--user", "enable", "stop-it.service"])?;
+    // Restart rather than start, so a daemon already running picks up the new unit
+    systemctl(&["--user", "restart", "stop-it.service"])?;
+    println!("▶️  Daemon started (journalctl --user -u stop-it -f for logs)");
+    Ok(())
+}
+
+fn systemctl(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("systemctl").args(args).status()?;
+    if !status.success() {
+        return Err(format!("systemctl {} failed", args.join(" ")).into());
+    }
+    Ok(())
+}
+
+/// systemd user unit running the daemon, sandboxed when `harden` gives the
+/// config to fit the sandbox to
+pub fn render_unit(binary: &Path, harden: Option<&Config>) -> String {
+    let mut unit = format!(
+        "[Unit]
+Description=Stop It - Activity Tracker & Pomodoro Timer Daemon
+After=network.target
+
+[Service]
+# The daemon reports READY=1 once its sockets are bound and WATCHDOG=1 as
+# its timer steps; one that stops stepping is restarted
+Type=notify
+NotifyAccess=main
+ExecStart={} daemon{}
+# The session's variables, once in the user manager with `systemctl --user
+# import-environment` (`stop_it install-service` runs it)
+PassEnvironment={}
+WatchdogSec=30
+Restart=on-failure
+RestartSec=5
+
+# Logging
+StandardOutput=journal
+StandardError=journal
+",
+        binary.display(),
+        if harden.is_some() { " --harden" } else { "" },
+        SESSION_VARIABLES.join(" ")
+    );
+    if let Some(config) = harden {
+        unit.push_str(
+            "\n# Sandbox (regenerate with `stop_it service --harden` after config changes)\n",
+        );
+        for line in sandbox::systemd_directives(config) {
+            unit.push_str(&line);
+            unit.push('\n');
+        }
+    }
+    unit.push_str(
+        "
+[Install]
+WantedBy=default.target
+",
+    );
+    unit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_unit_passes_the_session_on() {
+        let unit = render_unit(Path::new("/usr/bin/stop_it"), None);
+        assert!(
+            unit.contains("\nExecStart=/usr/bin/stop_it daemon\n"),
+            "{}",
+            unit
+        );
+        assert!(
+            unit.contains(
+                "\nPassEnvironment=WAYLAND_DISPLAY DISPLAY HYPRLAND_INSTANCE_SIGNATURE SWAYSOCK \
+             XDG_CURRENT_DESKTOP KDE_FULL_SESSION\n"
+            ),
+            "{}",
+            unit
+        );
+        assert!(!unit.contains("Environment=\"WAYLAND_DISPLAY"), "{}", unit);
+
+        let hardened = render_unit(Path::new("/usr/bin/stop_it"), Some(&Config::default()));
+        assert!(hardened.contains("daemon --harden\n"), "{}", hardened);
+        assert!(
+            hardened.contains(
+                "\nPassEnvironment=WAYLAND_DISPLAY DISPLAY HYPRLAND_INSTANCE_SIGNATURE SWAYSOCK \
+             XDG_CURRENT_DESKTOP KDE_FULL_SESSION\n"
+            ),
+            "{}",
+            hardened
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::commands::util::RangeArgs;
+use chrono::Local;
+use clap::Args;
+use std::path::{Path, PathBuf};
+use stop_it::config::settings::Config;
+use stop_it::stats::calendar::Calendar;
+use stop_it::stats::share::ShareCard;
+use stop_it::storage::backend::open_storage_read_only;
+
+/// Where `--font` is looked for when not given
+const FONTS: &[&str] = &[
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+];
+
+#[derive(Debug, Args)]
+pub struct ShareArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+    /// Also list the top N domains (off by default, they can be private)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "3")]
+    domains: Option<usize>,
+    /// Card to write, SVG or PNG by extension [default: stop_it-<date>.svg]
+    #[arg(long, short, value_name = "PATH")]
+    out: Option<PathBuf>,
+    /// TrueType font for PNG cards [default: DejaVu Sans, Noto Sans or Liberation Sans]
+    #[arg(long, value_name = "PATH")]
+    font: Option<PathBuf>,
+}
+
+/// `stop_it share`: a summary card of Pomodoros, focus time and categories
+/// to post in a team channel, rendered locally
+pub fn run_share(args: ShareArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    let calendar = Calendar::new(&config.day);
+    let now = Local::now();
+    let (label, range) = args.range.resolve(&calendar, now);
+
+    let mut storage = open_storage_read_only(config.storage.url.as_deref())?;
+    let store = storage.load(&range)?;
+    let title = format!("{} · {}", label, calendar.day_of(now).format("%a %-d %b"));
+    let card = ShareCard::new(&store, &range, title, args.domains.unwrap_or(0));
+
+    let out = args.out.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "stop_it-{}.svg",
+            calendar.day_of(now).format("%Y-%m-%d")
+        ))
+    });
+    let png = out
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+    if png {
+        let font = match args.font {
+            Some(font) => font,
+            None => FONTS
+                .iter()
+                .map(Path::new)
+                .find(|font| font.exists())
+                .ok_or("No font found for the PNG card, pass one with --font or write an SVG")?
+                .to_path_buf(),
+        };
+        card.write_png(&out, &font)?;
+    } else {
+        std::fs::write(&out, card.to_svg()?)?;
+    }
+    println!("🍅 Wrote {}", out.display());
+    Ok(())
+}
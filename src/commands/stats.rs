@@ -0,0 +1,121 @@
+use crate::commands::util::{RangeArgs, format_duration};
+use chrono::Local;
+use clap::Args;
+use stop_it::config::settings::Config;
+use stop_it::pomodoro::plan::{self, Plans};
+use stop_it::stats::aggregator::Aggregator;
+use stop_it::stats::calendar::Calendar;
+use stop_it::stats::filter::Filter;
+use stop_it::stats::groups::GroupRules;
+use stop_it::storage::backend::open_storage_read_only;
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    range: RangeArgs,
+    /// Number of domains to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+    /// Only count matching activity, e.g. 'category=social AND weekday=sat..sun'
+    #[arg(long)]
+    filter: Option<String>,
+}
+
+/// Print stored statistics; safe to run while the daemon is writing
+pub fn run_stats(args: StatsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    let calendar = Calendar::new(&config.day);
+    let now = Local::now();
+
+    let (label, range) = args.range.resolve(&calendar, now);
+    let top = args.top;
+    let filter = args.filter.as_deref().map(Filter::parse).transpose()?;
+
+    let mut storage = open_storage_read_only(config.storage.url.as_deref())?;
+    let store = storage.load(&range)?;
+    let mut aggregator = Aggregator::new(&store);
+    if let Some(ref filter) = filter {
+        aggregator = aggregator.with_filter(filter);
+    }
+
+    println!(
+        "--- {} ({} - {}) ---",
+        label,
+        range.start.format("%Y-%m-%d %H:%M"),
+        range.end.format("%Y-%m-%d %H:%M")
+    );
+    // Break browsing is reported on its own, apart from work time
+    let break_filter = match &filter {
+        Some(filter) => Filter::And(Box::new(filter.clone()), Box::new(Filter::break_time())),
+        None => Filter::break_time(),
+    };
+    let breaks = Aggregator::new(&store).with_filter(&break_filter);
+    let on_breaks = breaks.total_seconds(&range);
+    println!(
+        "Tracked: {} ({} on breaks)",
+        format_duration(aggregator.total_seconds(&range)),
+        format_duration(on_breaks)
+    );
+    let completed = aggregator.pomodoros(&range).len();
+    let abandoned = aggregator.abandoned(&range).len();
+    if abandoned > 0 {
+        println!(
+            "Pomodoros completed: {} ({} abandoned, {}% completion rate)",
+            completed,
+            abandoned,
+            completed * 100 / (completed + abandoned)
+        );
+    } else {
+        println!("Pomodoros completed: {}", completed);
+    }
+    if config.pomodoro.long_break_after > 0 {
+        println!(
+            "Cycles completed: {} ({} work sessions each)",
+            aggregator.cycles(&range),
+            config.pomodoro.long_break_after
+        );
+    }
+
+    println!("\nTime spent per category:");
+    for (category, seconds) in aggregator.total_by_category(&range) {
+        println!("  {} - {}", category, format_duration(seconds));
+    }
+    let groups = GroupRules::new(&config.groups);
+    if !groups.is_empty() {
+        println!("\nTime spent per group:");
+        for (group, seconds) in aggregator.total_by_group(&range, &groups) {
+            println!("  {} - {}", group, format_duration(seconds));
+        }
+    }
+    println!("\nTop domains:");
+    for (domain, seconds) in aggregator.top_domains(&range, top) {
+        println!("  {} - {}", domain, format_duration(seconds));
+    }
+    if on_breaks > 0 {
+        println!("\nOn breaks:");
+        for (domain, seconds) in breaks.top_domains(&range, top) {
+            println!("  {} - {}", domain, format_duration(seconds));
+        }
+    }
+
+    // Plan vs actual for every planned day in the range
+    let plans = Plans::load_from(&Plans::default_path())?;
+    let mut day = calendar.day_of(range.start);
+    let mut printed = false;
+    while calendar.day_begin(day) < range.end {
+        let blocks = plans.blocks(day);
+        if !blocks.is_empty() {
+            if !printed {
+                println!("\nPlan vs actual:");
+                printed = true;
+            }
+            println!("  {}", day.format("%a %Y-%m-%d"));
+            for report in plan::compare(blocks, &calendar, day, &store) {
+                println!("    {}", report.describe());
+            }
+        }
+        day += chrono::Duration::days(1);
+    }
+
+    Ok(())
+}
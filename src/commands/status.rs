@@ -0,0 +1,380 @@
+use crate::commands::util::format_duration;
+use clap::{Args, ValueEnum};
+use notify_rust::Notification;
+use serde_json::json;
+use std::io::{self, Write};
+use std::time::Duration;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::stats::budget::BudgetStatus;
+#[cfg(feature = "websocket")]
+use stop_it::ws::client::{DaemonClient, daemon_url};
+use stop_it::ws::protocol::{ClientMessage, ServerMessage, TimerState};
+
+/// Delay before reconnecting when following a daemon that isn't running
+#[cfg(feature = "websocket")]
+const RECONNECT_SECONDS: u64 = 5;
+
+/// One-line output formats for status bars
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum BarFormat {
+    Waybar,
+    Polybar,
+    #[value(alias = "i3blocks")]
+    Plain,
+    Eww,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// Print one status bar line (waybar by default with --follow)
+    #[arg(long, value_enum)]
+    format: Option<BarFormat>,
+    /// Print a line on every timer update until interrupted
+    #[arg(long, conflicts_with = "notify")]
+    follow: bool,
+    /// Show the status as a desktop notification
+    #[arg(long)]
+    notify: bool,
+}
+
+/// Print the running daemon's timer and budget status, or show it as a
+/// notification with `--notify`. With `--follow`,
+/// print one line per timer update in the `--format` expected by a status bar.
+pub async fn run_status(args: StatusArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let format = args.format;
+
+    if args.follow {
+        return follow(format.unwrap_or(BarFormat::Waybar)).await;
+    }
+
+    let state = fetch_state().await?;
+    let text = match format {
+        Some(format) => render_bar(&state, format),
+        None => format_status(state),
+    };
+    if args.notify {
+        // Desktop popup, e.g. from a keybinding without a terminal
+        Notification::new()
+            .summary("Stop It - Status")
+            .body(&text)
+            .show()?;
+    } else {
+        println!("{}", text);
+    }
+    Ok(())
+}
+
+/// One timer state from the control socket
+pub async fn fetch_state() -> Result<TimerState, Box<dyn std::error::Error>> {
+    let mut client = ControlClient::connect(&socket_path()).await?;
+    match client.request(&ClientMessage::GetStatus).await? {
+        ServerMessage::TimerState(state) => Ok(state),
+        other => Err(format!("Unexpected answer from the daemon: {:?}", other).into()),
+    }
+}
+
+/// Stream bar lines until interrupted, reconnecting when the daemon restarts
+#[cfg(feature = "websocket")]
+async fn follow(format: BarFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let mut last_line = String::new();
+    loop {
+        if let Ok(mut client) = DaemonClient::connect(&daemon_url()).await {
+            while let Ok(state) = client
+                .recv_matching(|message| match message {
+                    ServerMessage::TimerState(state) => Some(state),
+                    _ => None,
+                })
+                .await
+            {
+                emit(render_bar(&state, format), &mut last_line)?;
+            }
+        }
+        emit(render_offline(format), &mut last_line)?;
+        tokio::time::sleep(Duration::from_secs(RECONNECT_SECONDS)).await;
+    }
+}
+
+/// Poll the control socket every second, for builds without the WebSocket
+/// server
+#[cfg(not(feature = "websocket"))]
+async fn follow(format: BarFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let mut last_line = String::new();
+    loop {
+        let line = match fetch_state().await {
+            Ok(state) => render_bar(&state, format),
+            Err(_) => render_offline(format),
+        };
+        emit(line, &mut last_line)?;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Print `line` unless it repeats the previous one; bars redraw on every line
+fn emit(line: String, last_line: &mut String) -> io::Result<()> {
+    if line == *last_line {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}", line)?;
+    stdout.flush()?;
+    *last_line = line;
+    Ok(())
+}
+
+fn render_bar(state: &TimerState, format: BarFormat) -> String {
+    let emoji = if state.paused {
+        "⏸"
+    } else if state.waiting {
+        "⏳"
+    } else if state.mode == "WORK" {
+        "🍅"
+    } else {
+        "☕"
+    };
+    let clock = format!(
+        "{:02}:{:02}",
+        state.remaining_seconds.max(0) / 60,
+        state.remaining_seconds.max(0) % 60
+    );
+    let over_budget = state
+        .budgets
+        .iter()
+        .any(|budget| !budget.off_day && budget.remaining.is_some_and(|r| r < 0));
+
+    match format {
+        BarFormat::Waybar => {
+            let class = if over_budget {
+                "over-budget"
+            } else if state.mode == "WORK" {
+                "work"
+            } else {
+                "break"
+            };
+            json!({
+                "text": format!("{} {}", emoji, clock),
+                "alt": state.mode.to_lowercase(),
+                "tooltip": tooltip(state),
+                "class": class,
+            })
+            .to_string()
+        }
+        BarFormat::Polybar => {
+            let color = if over_budget {
+                "#e06c75"
+            } else if state.mode == "WORK" {
+                "#e5c07b"
+            } else {
+                "#98c379"
+            };
+            let mut line = format!("%{{F{}}}{} {}%{{F-}}", color, emoji, clock);
+            if over_budget {
+                line = format!("%{{u{}}}%{{+u}}{}%{{-u}}", color, line);
+            }
+            line
+        }
+        BarFormat::Plain => {
+            let mut line = format!("{} {} {}", emoji, state.mode, clock);
+            if let Some(budget) = tightest_budget(&state.budgets)
+                && let Some(remaining) = budget.remaining
+            {
+                line.push_str(&format!(
+                    " | {} {}",
+                    budget.target,
+                    remaining_label(remaining)
+                ));
+            }
+            if let Some(pace) = state.pace_seconds {
+                line.push_str(&format!(" | pace {:+}m", pace / 60));
+            }
+            line
+        }
+        BarFormat::Eww => {
+            let elapsed = state.duration_seconds - state.remaining_seconds;
+            json!({
+                "online": true,
+                "mode": state.mode.to_lowercase(),
+                "icon": emoji,
+                "remaining": clock,
+                "remaining_seconds": state.remaining_seconds,
+                "progress": fraction(elapsed, state.duration_seconds as f64),
+                "domain": state.current_domain.as_deref().unwrap_or(""),
+                "over_budget": over_budget,
+                "pace_seconds": state.pace_seconds,
+                "goal": {
+                    "done": state.pomodoros_today,
+                    "target": state.daily_goal,
+                    "progress": fraction(state.pomodoros_today as i64, state.daily_goal as f64),
+                },
+            })
+            .to_string()
+        }
+    }
+}
+
+/// Completed share of `total`, clamped to 0.0 - 1.0 for progress widgets
+fn fraction(done: i64, total: f64) -> f64 {
+    if total <= 0.0 {
+        return 0.0;
+    }
+    (done as f64 / total).clamp(0.0, 1.0)
+}
+
+fn render_offline(format: BarFormat) -> String {
+    match format {
+        BarFormat::Waybar => json!({
+            "text": "🍅 --:--",
+            "alt": "offline",
+            "tooltip": "stop_it daemon is not running",
+            "class": "offline",
+        })
+        .to_string(),
+        BarFormat::Polybar => "%{F#5c6370}🍅 --:--%{F-}".to_string(),
+        BarFormat::Plain => "🍅 --:--".to_string(),
+        // Same shape as the online payload so widgets never hit missing keys
+        BarFormat::Eww => json!({
+            "online": false,
+            "mode": "offline",
+            "icon": "🍅",
+            "remaining": "--:--",
+            "remaining_seconds": 0,
+            "progress": 0.0,
+            "domain": "",
+            "over_budget": false,
+            "pace_seconds": null,
+            "goal": { "done": 0, "target": 0, "progress": 0.0 },
+        })
+        .to_string(),
+    }
+}
+
+fn tooltip(state: &TimerState) -> String {
+    let mut lines = vec![format!(
+        "{} - {} remaining",
+        state.mode,
+        format_duration(state.remaining_seconds)
+    )];
+    if state.continuous {
+        lines.push("Continuous: no breaks".to_string());
+    } else if state.cycle_length > 0 {
+        lines.push(format!("Cycle: {}/{}", state.cycle, state.cycle_length));
+    }
+    if let Some(domain) = &state.current_domain {
+        lines.push(format!("Current: {}", domain));
+    }
+    if let Some(pace) = state.pace_seconds {
+        lines.push(format!("Pace: {}", pace_label(pace)));
+    }
+    for budget in &state.budgets {
+        if let Some(remaining) = budget.remaining
+            && !budget.off_day
+        {
+            lines.push(format!(
+                "{}: {}",
+                budget_label(budget),
+                remaining_label(remaining)
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Budget closest to running out, ignoring off-days
+fn tightest_budget(budgets: &[BudgetStatus]) -> Option<&BudgetStatus> {
+    budgets
+        .iter()
+        .filter(|budget| !budget.off_day)
+        .filter_map(|budget| budget.remaining.map(|remaining| (remaining, budget)))
+        .min_by_key(|(remaining, _)| *remaining)
+        .map(|(_, budget)| budget)
+}
+
+/// The budget's domain, or its category marked as one
+fn budget_label(budget: &BudgetStatus) -> String {
+    if budget.category {
+        format!("{} (category)", budget.target)
+    } else {
+        budget.target.clone()
+    }
+}
+
+/// Focus time against a typical day by now
+fn pace_label(pace: i64) -> String {
+    if pace.abs() < 60 {
+        "on par with a typical day".to_string()
+    } else if pace > 0 {
+        format!("{} ahead of a typical day", format_duration(pace))
+    } else {
+        format!("{} behind a typical day", format_duration(-pace))
+    }
+}
+
+fn remaining_label(remaining: i64) -> String {
+    if remaining >= 0 {
+        format!("{} left", format_duration(remaining))
+    } else {
+        format!("over by {}", format_duration(-remaining))
+    }
+}
+
+fn format_status(state: TimerState) -> String {
+    let mut lines = vec![format!(
+        "🍅 {} - {} remaining{}",
+        state.mode,
+        format_duration(state.remaining_seconds),
+        if state.paused {
+            " (paused)"
+        } else if state.waiting {
+            " (waiting for `stop_it start`)"
+        } else {
+            ""
+        }
+    )];
+    if state.continuous {
+        lines.push("Continuous mode: no breaks until `stop_it mode pomodoro`".to_string());
+    } else if state.cycle_length > 0 {
+        lines.push(format!(
+            "Cycle: {}/{} work sessions before the long break",
+            state.cycle, state.cycle_length
+        ));
+    }
+    if let Some(domain) = state.current_domain {
+        lines.push(format!("Current: {}", domain));
+    }
+    if let Some(pace) = state.pace_seconds {
+        lines.push(format!("Pace: {} (focus time so far)", pace_label(pace)));
+    }
+
+    if !state.budgets.is_empty() {
+        lines.push("\nBudgets:".to_string());
+    }
+    for budget in state.budgets {
+        if budget.off_day {
+            lines.push(format!(
+                "  {} - off-day, budget not applied",
+                budget_label(&budget)
+            ));
+            continue;
+        }
+        let mut line = format!(
+            "  {} - {} today",
+            budget_label(&budget),
+            format_duration(budget.used_today)
+        );
+        if let Some(allowance) = budget.daily_allowance {
+            line.push_str(&format!(" / {}", format_duration(allowance)));
+        }
+        if let Some(weekly) = budget.weekly_limit {
+            line.push_str(&format!(
+                ", {} / {} this week",
+                format_duration(budget.used_week),
+                format_duration(weekly)
+            ));
+        }
+        if let Some(remaining) = budget.remaining {
+            line.push_str(&format!(" ({})", remaining_label(remaining)));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,45 @@
+use clap::Args;
+use stop_it::control::client::ControlClient;
+use stop_it::control::socket::socket_path;
+use stop_it::pomodoro::pomodoro::TimerMode;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+#[derive(Debug, Args)]
+pub struct ModeArgs {
+    /// `continuous` for work blocks without breaks, `pomodoro` to bring them back
+    #[arg(value_name = "MODE")]
+    mode: TimerMode,
+}
+
+#[derive(Debug, Args)]
+pub struct SnoozeArgs {
+    /// Minutes to put the break off, over `[breaks] snooze`
+    #[arg(value_name = "MINUTES", value_parser = clap::value_parser!(u32).range(1..))]
+    minutes: Option<u32>,
+}
+
+/// `stop_it pause|resume|skip|reset`: send one timer command to the running
+/// daemon and print its answer
+pub async fn run_timer_command(command: ClientMessage) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = ControlClient::connect(&socket_path()).await?;
+    match client.request(&command).await? {
+        ServerMessage::Ack { message } => println!("🍅 {}", message),
+        other => return Err(format!("Unexpected answer from the daemon: {:?}", other).into()),
+    }
+    Ok(())
+}
+
+/// `stop_it snooze [MINUTES]`: put off the break that just started or is
+/// about to
+pub async fn run_snooze(args: SnoozeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    run_timer_command(ClientMessage::Snooze {
+        minutes: args.minutes,
+    })
+    .await
+}
+
+/// `stop_it mode continuous|pomodoro`: turn breaks off or on in the running
+/// daemon
+pub async fn run_mode(args: ModeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    run_timer_command(ClientMessage::SetTimerMode { mode: args.mode }).await
+}
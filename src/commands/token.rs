@@ -0,0 +1,27 @@
+use clap::Args;
+use stop_it::ws::auth::{CLI_PROFILE, generate_token};
+
+#[derive(Debug, Args)]
+pub struct TokenArgs {
+    /// Name of the browser profile, e.g. work-chromium
+    profile: String,
+}
+
+/// `stop_it token`: generate a token for a browser profile and print the
+/// config line granting it access
+pub fn run_token(args: TokenArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let profile = args.profile;
+    if profile == CLI_PROFILE {
+        return Err(format!("'{}' is reserved for local commands", CLI_PROFILE).into());
+    }
+    let token = generate_token()?;
+    println!("Add to [server.tokens] in the config, then restart the daemon:");
+    println!();
+    println!("{:?} = \"{}\"", profile, token);
+    println!();
+    println!(
+        "and paste {} into the extension options of that profile.",
+        token
+    );
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use crate::commands::util::format_duration;
+use clap::{Args, ValueEnum};
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::{cursor, execute, terminal};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Duration;
+use stop_it::ws::client::{DaemonClient, daemon_url};
+use stop_it::ws::protocol::{ClientMessage, ServerMessage, TodayStats};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum SortKey {
+    Time,
+    Name,
+    Delta, // growth since `top` started
+}
+
+impl SortKey {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Time => "time",
+            SortKey::Name => "name",
+            SortKey::Delta => "delta",
+        }
+    }
+}
+
+/// Restores the terminal even when `top` exits with an error
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct TopArgs {
+    /// Initial sort order
+    #[arg(long, value_enum, default_value_t = SortKey::Time)]
+    sort: SortKey,
+    /// Seconds between refreshes
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+}
+
+/// Live, htop-style ranking of today's domains from the running daemon.
+/// Keys: t/n/d sort by time/name/delta, q quits.
+pub async fn run_top(args: TopArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sort = args.sort;
+    let refresh = args.interval;
+
+    let mut client = DaemonClient::connect(&daemon_url()).await?;
+    let baseline: HashMap<String, i64> = fetch_today(&mut client)
+        .await?
+        .domains
+        .into_iter()
+        .map(|total| (total.domain, total.seconds))
+        .collect();
+
+    let _guard = TerminalGuard::enter()?;
+    loop {
+        let stats = fetch_today(&mut client).await?;
+        render(&stats, &baseline, sort)?;
+
+        let key = tokio::task::block_in_place(|| -> io::Result<Option<KeyCode>> {
+            if event::poll(Duration::from_secs(refresh))?
+                && let Event::Key(key) = event::read()?
+            {
+                return Ok(Some(key.code));
+            }
+            Ok(None)
+        })?;
+
+        match key {
+            Some(KeyCode::Char('q')) | Some(KeyCode::Esc) => break,
+            Some(KeyCode::Char('t')) => sort = SortKey::Time,
+            Some(KeyCode::Char('n')) => sort = SortKey::Name,
+            Some(KeyCode::Char('d')) => sort = SortKey::Delta,
+            _ => {}
+        }
+    }
+
+    client.close().await;
+    Ok(())
+}
+
+async fn fetch_today(client: &mut DaemonClient) -> Result<TodayStats, Box<dyn std::error::Error>> {
+    client.send(&ClientMessage::GetTodayStats).await?;
+    client
+        .recv_matching(|message| match message {
+            ServerMessage::TodayStats(stats) => Some(stats),
+            _ => None,
+        })
+        .await
+}
+
+fn render(stats: &TodayStats, baseline: &HashMap<String, i64>, sort: SortKey) -> io::Result<()> {
+    let (_, height) = terminal::size().unwrap_or((80, 24));
+
+    let mut rows: Vec<(&str, i64, i64)> = stats
+        .domains
+        .iter()
+        .map(|total| {
+            let delta = total.seconds - baseline.get(&total.domain).copied().unwrap_or(0);
+            (total.domain.as_str(), total.seconds, delta)
+        })
+        .collect();
+    match sort {
+        SortKey::Time => rows.sort_by_key(|row| std::cmp::Reverse(row.1)),
+        SortKey::Name => rows.sort_by(|a, b| a.0.cmp(b.0)),
+        SortKey::Delta => rows.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1))),
+    }
+
+    // Raw mode needs explicit carriage returns
+    let mut out = String::new();
+    out.push_str(&format!(
+        "🍅 stop_it top - today {} - sorted by {}   [t]ime [n]ame [d]elta [q]uit\r\n\r\n",
+        format_duration(stats.total_seconds),
+        sort.as_str()
+    ));
+    out.push_str(&format!(
+        "{:>3}  {:<40} {:>10} {:>10}\r\n",
+        "#", "DOMAIN", "TODAY", "DELTA"
+    ));
+
+    let visible = (height as usize).saturating_sub(4);
+    for (rank, (domain, seconds, delta)) in rows.iter().take(visible).enumerate() {
+        let delta = if *delta > 0 {
+            format!("+{}", format_duration(*delta))
+        } else {
+            String::new()
+        };
+        out.push_str(&format!(
+            "{:>3}  {:<40} {:>10} {:>10}\r\n",
+            rank + 1,
+            domain,
+            format_duration(*seconds),
+            delta
+        ));
+    }
+
+    let mut stdout = io::stdout();
+    execute!(
+        stdout,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(terminal::ClearType::All)
+    )?;
+    stdout.write_all(out.as_bytes())?;
+    stdout.flush()
+}
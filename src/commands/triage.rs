@@ -0,0 +1,100 @@
+use crate::commands::util::{ask, edit_config, format_duration};
+use chrono::Local;
+use clap::Args;
+use std::collections::BTreeSet;
+use stop_it::config::edit::add_category_rules;
+use stop_it::config::settings::{Config, parse_duration};
+use stop_it::stats::aggregator::TimeRange;
+use stop_it::stats::calendar::Calendar;
+use stop_it::stats::categories::CategoryRules;
+use stop_it::stats::triage;
+use stop_it::storage::backend::open_storage_read_only;
+
+#[derive(Debug, Args)]
+pub struct TriageArgs {
+    /// The last N days, today included
+    #[arg(long, value_name = "N", default_value_t = 7, value_parser = clap::value_parser!(i64).range(1..))]
+    days: i64,
+    /// Time a site needs to be listed, e.g. 30m, over `[triage] min_time`
+    #[arg(long, value_name = "DURATION", value_parser = parse_min_time)]
+    min: Option<i64>,
+}
+
+/// `stop_it triage`: go through the sites that took time without a category
+/// and write a `[categories]` rule for each one answered
+pub fn run_triage(args: TriageArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    let calendar = Calendar::new(&config.day);
+    let now = Local::now();
+    let first = calendar.day_of(now) - chrono::Duration::days(args.days - 1);
+    let range = TimeRange::new(calendar.day_begin(first), now);
+    let min = args.min.unwrap_or(config.triage.min_time);
+
+    let mut storage = open_storage_read_only(config.storage.url.as_deref())?;
+    let store = storage.load(&range)?;
+    let sites = triage::uncategorized(&store, &range, &CategoryRules::new(&config.categories), min);
+    if sites.is_empty() {
+        println!(
+            "Nothing to triage: no uncategorized site took {} in the last {} days",
+            format_duration(min),
+            args.days
+        );
+        return Ok(());
+    }
+
+    let known: BTreeSet<&str> = store
+        .segments()
+        .iter()
+        .filter_map(|segment| segment.category.as_deref())
+        .chain(config.categories.values().map(String::as_str))
+        .flat_map(|categories| categories.split(','))
+        .map(str::trim)
+        .filter(|category| !category.is_empty())
+        .collect();
+    println!(
+        "{} uncategorized site(s) in the last {} days",
+        sites.len(),
+        args.days
+    );
+    if !known.is_empty() {
+        println!(
+            "Categories in use: {}",
+            known.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    println!("Answer with a category, press Enter to skip or `q` to stop.\n");
+
+    let mut rules = Vec::new();
+    for (domain, seconds) in sites {
+        let answer = ask(
+            &format!("{} ({})", domain, format_duration(seconds)),
+            "skip",
+        )?;
+        match answer.as_str() {
+            "q" | "quit" => break,
+            "skip" => {}
+            category => rules.push((domain, category.to_string())),
+        }
+    }
+    if rules.is_empty() {
+        println!("No rules added.");
+        return Ok(());
+    }
+
+    let path = edit_config(|original| add_category_rules(original, &rules))
+        .map_err(|e| format!("Rules not added: {}", e))?;
+    println!(
+        "\n✅ Added {} rule(s) to {}; restart the daemon to apply them",
+        rules.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn parse_min_time(value: &str) -> Result<i64, String> {
+    match parse_duration(value) {
+        Some(seconds) if seconds > 0 => Ok(seconds),
+        Some(_) => Err("must be longer than zero".to_string()),
+        None => Err("expected e.g. \"30m\", \"1h\" or minutes".to_string()),
+    }
+}
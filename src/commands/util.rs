@@ -0,0 +1,83 @@
+use chrono::{DateTime, Local};
+use clap::Args;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use stop_it::config::paths;
+use stop_it::config::settings::Config;
+use stop_it::stats::aggregator::TimeRange;
+use stop_it::stats::calendar::Calendar;
+
+pub use stop_it::stats::format::format_duration;
+
+/// Which days `stats`, `export` and `share` cover: today unless told otherwise
+#[derive(Debug, Args)]
+pub struct RangeArgs {
+    /// Today, the default
+    #[arg(long, conflicts_with_all = ["week", "days"])]
+    today: bool,
+    /// This week instead of today
+    #[arg(long, conflicts_with = "days")]
+    week: bool,
+    /// The last N days, today included
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(i64).range(1..))]
+    days: Option<i64>,
+}
+
+impl RangeArgs {
+    /// A label for the range, and the range itself up to `now`
+    pub fn resolve(&self, calendar: &Calendar, now: DateTime<Local>) -> (String, TimeRange) {
+        if self.week {
+            ("This week".to_string(), calendar.this_week())
+        } else if let Some(days) = self.days {
+            let first = calendar.day_of(now) - chrono::Duration::days(days - 1);
+            (
+                format!("Last {} days", days),
+                TimeRange::new(calendar.day_begin(first), now),
+            )
+        } else {
+            ("Today".to_string(), calendar.today())
+        }
+    }
+}
+
+/// Ask on the terminal; an empty answer gives `default`
+pub fn ask(question: &str, default: &str) -> io::Result<String> {
+    print!("{} [{}]: ", question, default);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+pub fn confirm(question: &str, default: bool) -> io::Result<bool> {
+    let answer = ask(question, if default { "Y/n" } else { "y/N" })?;
+    Ok(match answer.to_lowercase().as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => default,
+    })
+}
+
+/// Rewrite the config file with `edit`, leaving it untouched when the
+/// result is a config the daemon can't read. Returns the file's path.
+pub fn edit_config(
+    edit: impl FnOnce(&str) -> String,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = paths::config_file();
+    let original = if path.exists() {
+        fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, edit(&original))?;
+    if let Err(e) = Config::load_from(&path) {
+        fs::write(&path, original)?;
+        return Err(e);
+    }
+    Ok(path)
+}
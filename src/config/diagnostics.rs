@@ -0,0 +1,37 @@
+/// Append a "did you mean" hint to serde's unknown field errors, e.g.
+/// "unknown field `treshold`, expected one of `enabled`, `threshold`, ..."
+pub fn with_suggestion(message: &str) -> String {
+    match suggest_field(message) {
+        Some(field) => format!("{}\nhelp: did you mean `{}`?", message.trim_end(), field),
+        None => message.to_string(),
+    }
+}
+
+fn suggest_field(message: &str) -> Option<&str> {
+    let rest = message.split("unknown field `").nth(1)?;
+    let (unknown, expected) = rest.split_once('`')?;
+
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2) // names sit between pairs of backticks
+        .map(|candidate| (edit_distance(unknown, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= candidate.len().max(3) / 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two short identifiers
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
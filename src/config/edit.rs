@@ -0,0 +1,74 @@
+/// `content` with `rules` (domain, category) added to its `[categories]`
+/// table, created at the end when missing. Works on the text, so comments
+/// and layout stay as they were.
+pub fn add_category_rules(content: &str, rules: &[(String, String)]) -> String {
+    let lines: String = rules
+        .iter()
+        .map(|(domain, category)| format!("{} = {}\n", quoted(domain), quoted(category)))
+        .collect();
+    let header = content.lines().position(|line| {
+        line.split('#')
+            .next()
+            .is_some_and(|line| line.trim() == "[categories]")
+    });
+    match header {
+        Some(index) => {
+            let mut edited = String::new();
+            for (i, line) in content.lines().enumerate() {
+                edited.push_str(line);
+                edited.push('\n');
+                if i == index {
+                    edited.push_str(&lines);
+                }
+            }
+            edited
+        }
+        None => {
+            let mut edited = content.to_string();
+            if !edited.is_empty() && !edited.ends_with('\n') {
+                edited.push('\n');
+            }
+            if !edited.is_empty() {
+                edited.push('\n');
+            }
+            edited.push_str("[categories]\n");
+            edited.push_str(&lines);
+            edited
+        }
+    }
+}
+
+/// `content` with the `[categories]` rule for `domain` set to `category`:
+/// an existing rule is rewritten in place, a new one added like
+/// `add_category_rules` does
+pub fn set_category_rule(content: &str, domain: &str, category: &str) -> String {
+    let mut in_categories = false;
+    let mut found = false;
+    let mut edited = String::new();
+    for line in content.lines() {
+        let code = line.split('#').next().unwrap_or_default().trim();
+        if code.starts_with('[') {
+            in_categories = code == "[categories]";
+        } else if in_categories
+            && !found
+            && let Some((key, _)) = code.split_once('=')
+            && key.trim().trim_matches('"').eq_ignore_ascii_case(domain)
+        {
+            found = true;
+            edited.push_str(&format!("{} = {}\n", quoted(domain), quoted(category)));
+            continue;
+        }
+        edited.push_str(line);
+        edited.push('\n');
+    }
+    if found {
+        edited
+    } else {
+        add_category_rules(content, &[(domain.to_string(), category.to_string())])
+    }
+}
+
+/// A TOML basic string
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Environment variable selecting a profile, over `active_profile`
+pub const PROFILE_ENV: &str = "STOP_IT_PROFILE";
+
+/// Read a config file with everything it pulls in through
+/// `include = ["blocklists/social.toml"]`. Included files are read first, in
+/// order, and the including file overrides them. Paths are relative to the
+/// file naming them; `~/` is the home directory.
+pub fn load_table(path: &Path) -> Result<Table, String> {
+    load_with_includes(path, &mut Vec::new())
+}
+
+fn load_with_includes(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Table, String> {
+    let canonical = fs::canonicalize(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if stack.contains(&canonical) {
+        return Err(format!("{} includes itself", path.display()));
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut table: Table = content
+        .parse()
+        .map_err(|e| format!("Invalid config {}:\n{}", path.display(), e))?;
+
+    let includes = match table.remove("include") {
+        None => Vec::new(),
+        Some(Value::Array(includes)) => includes,
+        Some(_) => {
+            return Err(format!(
+                "Invalid config {}: `include` must be a list of paths",
+                path.display()
+            ));
+        }
+    };
+
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut merged = Table::new();
+    for include in includes {
+        let Value::String(include) = include else {
+            return Err(format!(
+                "Invalid config {}: `include` must be a list of paths",
+                path.display()
+            ));
+        };
+        merge(
+            &mut merged,
+            load_with_includes(&resolve(dir, &include), stack)?,
+        );
+    }
+    stack.pop();
+
+    merge(&mut merged, table);
+    Ok(merged)
+}
+
+/// Apply the selected profile onto the top-level settings. Profiles live in
+/// `[profile.<name>]` tables holding any config section, and may build on
+/// another profile with `extends = "<name>"`. Returns the profile applied.
+pub fn apply_profile(table: &mut Table, selected: Option<&str>) -> Result<Option<String>, String> {
+    let profiles = match table.remove("profile") {
+        None => Table::new(),
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => return Err("`profile` must hold [profile.<name>] tables".to_string()),
+    };
+    let configured = match table.remove("active_profile") {
+        None => None,
+        Some(Value::String(name)) => Some(name),
+        Some(_) => return Err("`active_profile` must be a profile name".to_string()),
+    };
+    let Some(name) = selected.map(String::from).or(configured) else {
+        return Ok(None);
+    };
+
+    // Walk up the `extends` chain, then apply it from the root down
+    let mut chain: Vec<&str> = Vec::new();
+    let mut next = Some(name.as_str());
+    while let Some(current) = next {
+        if chain.contains(&current) {
+            return Err(format!(
+                "Profile '{}' extends itself ({} -> {})",
+                name,
+                chain.join(" -> "),
+                current
+            ));
+        }
+        let profile = profiles
+            .get(current)
+            .and_then(Value::as_table)
+            .ok_or_else(|| unknown_profile(current, &profiles))?;
+        chain.push(current);
+        next = match profile.get("extends") {
+            None => None,
+            Some(Value::String(parent)) => Some(parent.as_str()),
+            Some(_) => {
+                return Err(format!(
+                    "`extends` of profile '{}' must be a profile name",
+                    current
+                ));
+            }
+        };
+    }
+    for current in chain.iter().rev() {
+        let mut profile = profiles[*current].as_table().cloned().unwrap_or_default();
+        profile.remove("extends");
+        merge(table, profile);
+    }
+    Ok(Some(name))
+}
+
+fn unknown_profile(name: &str, profiles: &Table) -> String {
+    if profiles.is_empty() {
+        return format!("Unknown profile '{}': no [profile.<name>] tables", name);
+    }
+    format!(
+        "Unknown profile '{}' (expected one of: {})",
+        name,
+        profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+    )
+}
+
+/// Merge `overlay` into `base`: tables merge key by key, anything else
+/// (values and lists) is replaced
+pub fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn resolve(dir: &Path, include: &str) -> PathBuf {
+    match include.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(rest),
+        None => dir.join(include),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::settings::Config;
+
+    #[test]
+    fn profiles_build_on_included_rules() {
+        let dir =
+            std::env::temp_dir().join(format!("stop_it-test-profiles-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("blocklists")).unwrap();
+        std::fs::write(
+            dir.join("blocklists/social.toml"),
+            "[budgets.\"reddit.com\"]\ndaily = \"30m\"\n\n[budgets.\"x.com\"]\ndaily = \"10m\"\n",
+        )
+        .unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+    include = ["blocklists/social.toml"]
+    active_profile = "work"
+
+    [budgets."x.com"]
+    daily = "5m"
+
+    [profile.base]
+    pomodoro = { work = "50m" }
+    categories = { "github.com" = "productivity" }
+
+    [profile.work]
+    extends = "base"
+    pomodoro = { break = "10m" }
+    budgets = { "reddit.com" = { daily = "1m" } }
+    "#,
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.profile.as_deref(), Some("work"));
+        assert_eq!(
+            (config.pomodoro.work, config.pomodoro.break_),
+            (50 * 60, 10 * 60)
+        );
+        assert_eq!(config.budgets["reddit.com"].daily, Some(60));
+        assert_eq!(config.budgets["x.com"].daily, Some(5 * 60));
+        assert_eq!(config.categories["github.com"], "productivity");
+
+        // Unknown profiles and include loops are errors, not silently ignored
+        std::fs::write(&path, "active_profile = \"home\"\n[profile.work]\n").unwrap();
+        let error = Config::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("Unknown profile 'home'"), "{}", error);
+        std::fs::write(&path, "include = [\"config.toml\"]\n").unwrap();
+        let error = Config::load_from(&path).unwrap_err().to_string();
+        assert!(error.contains("includes itself"), "{}", error);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod diagnostics;
+pub mod edit;
+pub mod layers;
+pub mod paths;
+pub mod remote;
+pub mod settings;
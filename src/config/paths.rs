@@ -0,0 +1,58 @@
+use std::env;
+use std::path::PathBuf;
+
+fn home_dir() -> PathBuf {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `$XDG_CONFIG_HOME/stop_it` (defaults to `~/.config/stop_it`)
+pub fn config_dir() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".config"))
+        .join("stop_it")
+}
+
+/// `$XDG_DATA_HOME/stop_it` (defaults to `~/.local/share/stop_it`)
+pub fn data_dir() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".local/share"))
+        .join("stop_it")
+}
+
+/// Wayland session the daemon belongs to (e.g. "wayland-1"), so several
+/// seats of the same user each get their own daemon
+pub fn session() -> Option<String> {
+    let display = env::var("WAYLAND_DISPLAY").ok()?;
+    // WAYLAND_DISPLAY may also be an absolute socket path
+    let name = display.rsplit('/').next().unwrap_or(&display);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// `$XDG_RUNTIME_DIR/stop_it/<session>` for per-session files, falling back
+/// to the data directory when there is no runtime directory
+pub fn runtime_dir() -> PathBuf {
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("stop_it"))
+        .unwrap_or_else(data_dir);
+    match session() {
+        Some(session) => dir.join(session),
+        None => dir,
+    }
+}
+
+/// `name.extension` in the data directory, suffixed with the Wayland session
+/// (`name-wayland-1.extension`) for files owned by one daemon
+pub fn session_file(name: &str, extension: &str) -> PathBuf {
+    match session() {
+        Some(session) => data_dir().join(format!("{}-{}.{}", name, session, extension)),
+        None => data_dir().join(format!("{}.{}", name, extension)),
+    }
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.toml")
+}
@@ -0,0 +1,269 @@
+use crate::config::settings::RemoteConfig;
+use ring::digest::{SHA256, digest};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "remote-config")]
+use std::time::Duration;
+use std::time::SystemTime;
+use toml::Table;
+
+/// Settings only local files may set, and why: `(table, key)`, where no key
+/// stands for the whole table, also looked for in every `[profile.<name>]`
+const LOCAL_ONLY_KEYS: [(&str, Option<&str>, &str); 4] = [
+    ("breaks", Some("locker"), "runs a command"),
+    ("notifications", Some("fallback_command"), "runs a command"),
+    ("sounds", Some("player"), "runs a command"),
+    ("webhooks", None, "sends the sites you visit to its URLs"),
+];
+
+/// Fetch the `[remote]` base config, checked against its pinned hash and
+/// signing key. The last copy that passed is cached in `cache_dir`: it is
+/// used without fetching while younger than `refresh`, and in place of a
+/// download that fails or doesn't verify.
+pub fn load_base(remote: &RemoteConfig, cache_dir: &Path) -> Result<Table, String> {
+    let Some(url) = &remote.url else {
+        return Ok(Table::new());
+    };
+    if remote.sha256.is_none() && remote.public_key.is_none() {
+        return Err(format!(
+            "Remote config {}: pin it with `sha256` or `public_key`",
+            url
+        ));
+    }
+    if !is_secure(url) {
+        return Err(format!(
+            "Remote config {}: only https URLs are fetched (http on localhost)",
+            url
+        ));
+    }
+    let cache = cache_dir.join(format!(
+        "remote-{}.toml",
+        &hex(digest(&SHA256, url.as_bytes()).as_ref())[..16]
+    ));
+
+    let cached = read_cached(&cache, remote);
+    let content = match cached {
+        Ok(content) if is_fresh(&cache, remote.refresh) => content,
+        cached => match download(url, remote) {
+            Ok((content, signature)) => {
+                if let Err(e) = write_cache(&cache, &content, signature.as_deref()) {
+                    eprintln!("Failed to cache the remote config: {}", e);
+                }
+                content
+            }
+            Err(e) => {
+                let content = cached.map_err(|_| format!("Remote config {}: {}", url, e))?;
+                eprintln!("Remote config {}: {}, using the cached copy", url, e);
+                content
+            }
+        },
+    };
+
+    let mut table: Table = content
+        .parse()
+        .map_err(|e| format!("Invalid remote config {}:\n{}", url, e))?;
+    for key in ["include", "remote"] {
+        if table.remove(key).is_some() {
+            return Err(format!(
+                "Invalid remote config {}: `{}` only works in local files",
+                url, key
+            ));
+        }
+    }
+    if let Some((key, reason)) = local_only_key(&table) {
+        return Err(format!(
+            "Invalid remote config {}: `{}` {}, set it in a local file",
+            url, key, reason
+        ));
+    }
+    Ok(table)
+}
+
+/// `https://`, or `http://` to this machine
+fn is_secure(url: &str) -> bool {
+    if url.starts_with("https://") {
+        return true;
+    }
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// The first local-only key the remote file sets, e.g. "breaks.locker", and
+/// why it is kept local
+fn local_only_key(table: &Table) -> Option<(String, &'static str)> {
+    let set_in = |table: &Table, prefix: &str| {
+        LOCAL_ONLY_KEYS
+            .iter()
+            .find_map(|(section, key, reason)| match key {
+                Some(key) => table
+                    .get(*section)
+                    .and_then(|section| section.as_table())
+                    .is_some_and(|section| section.contains_key(*key))
+                    .then(|| (format!("{}{}.{}", prefix, section, key), *reason)),
+                None => table
+                    .contains_key(*section)
+                    .then(|| (format!("{}{}", prefix, section), *reason)),
+            })
+    };
+    set_in(table, "").or_else(|| {
+        table
+            .get("profile")
+            .and_then(|profiles| profiles.as_table())?
+            .iter()
+            .find_map(|(name, profile)| set_in(profile.as_table()?, &format!("profile.{}.", name)))
+    })
+}
+
+/// The file and, with a `public_key`, its signature from `<url>.sig`
+#[cfg(feature = "remote-config")]
+fn download(url: &str, remote: &RemoteConfig) -> Result<(String, Option<String>), String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .into();
+    let get = |url: &str| -> Result<String, String> {
+        agent
+            .get(url)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .map_err(|e| e.to_string())
+    };
+    let content = get(url)?;
+    let signature = match remote.public_key {
+        Some(_) => Some(get(&format!("{}.sig", url))?),
+        None => None,
+    };
+    verify(remote, &content, signature.as_deref())?;
+    Ok((content, signature))
+}
+
+/// Only a cached copy can be used without the feature
+#[cfg(not(feature = "remote-config"))]
+fn download(_url: &str, _remote: &RemoteConfig) -> Result<(String, Option<String>), String> {
+    Err("fetching requires building with `--features remote-config`".to_string())
+}
+
+fn verify(remote: &RemoteConfig, content: &str, signature: Option<&str>) -> Result<(), String> {
+    if let Some(expected) = &remote.sha256 {
+        let actual = hex(digest(&SHA256, content.as_bytes()).as_ref());
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!("sha256 is {}, expected {}", actual, expected));
+        }
+    }
+    if let Some(key) = &remote.public_key {
+        let key = unhex(key).ok_or("`public_key` is not hex")?;
+        let signature = signature
+            .and_then(unhex)
+            .ok_or("signature is missing or not hex")?;
+        UnparsedPublicKey::new(&ED25519, key)
+            .verify(content.as_bytes(), &signature)
+            .map_err(|_| "signature does not match `public_key`")?;
+    }
+    Ok(())
+}
+
+/// The cached copy, checked again in case the pins changed since
+fn read_cached(cache: &Path, remote: &RemoteConfig) -> Result<String, String> {
+    let content = fs::read_to_string(cache).map_err(|e| e.to_string())?;
+    let signature = fs::read_to_string(signature_path(cache)).ok();
+    verify(remote, &content, signature.as_deref())?;
+    Ok(content)
+}
+
+fn write_cache(cache: &Path, content: &str, signature: Option<&str>) -> std::io::Result<()> {
+    if let Some(parent) = cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(cache, content)?;
+    match signature {
+        Some(signature) => fs::write(signature_path(cache), signature),
+        None => match fs::remove_file(signature_path(cache)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn signature_path(cache: &Path) -> PathBuf {
+    cache.with_extension("toml.sig")
+}
+
+fn is_fresh(cache: &Path, refresh: i64) -> bool {
+    fs::metadata(cache)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age.as_secs() < refresh.max(0) as u64)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(toml: &str) -> Option<String> {
+        local_only_key(&toml.parse().unwrap()).map(|(key, _)| key)
+    }
+
+    #[test]
+    fn commands_and_webhooks_only_come_from_local_files() {
+        assert_eq!(
+            key("[breaks]\nlocker = \"swaylock\"\n").as_deref(),
+            Some("breaks.locker")
+        );
+        assert_eq!(
+            key("[profile.work.sounds]\nplayer = \"mpv\"\n").as_deref(),
+            Some("profile.work.sounds.player")
+        );
+        assert_eq!(
+            key("[[webhooks]]\nurl = \"https://example.com/hook\"\n").as_deref(),
+            Some("webhooks")
+        );
+        assert_eq!(
+            key("[[profile.work.webhooks]]\nurl = \"https://example.com/hook\"\n").as_deref(),
+            Some("profile.work.webhooks")
+        );
+        // Other settings of the same tables are fine
+        assert_eq!(
+            key("[breaks]\noverlay = true\n[sounds]\nvolume = 0.5\n"),
+            None
+        );
+        assert_eq!(key("[budgets.\"reddit.com\"]\ndaily = \"15m\"\n"), None);
+    }
+
+    #[test]
+    fn only_https_or_plain_http_to_this_machine_is_secure() {
+        assert!(is_secure("https://example.com/stop_it.toml"));
+        assert!(is_secure("http://localhost:8000/stop_it.toml"));
+        assert!(is_secure("http://127.0.0.1/stop_it.toml"));
+        assert!(is_secure("http://[::1]:8000/stop_it.toml"));
+        assert!(!is_secure("http://example.com/stop_it.toml"));
+        assert!(!is_secure("http://localhost.example.com/stop_it.toml"));
+        assert!(!is_secure("http://127.0.0.1.example.com/stop_it.toml"));
+        assert!(!is_secure("http://localhost@example.com/stop_it.toml"));
+        assert!(!is_secure("ftp://localhost/stop_it.toml"));
+        assert!(!is_secure("HTTPS://example.com"));
+    }
+}
@@ -0,0 +1,839 @@
+use crate::config::{diagnostics, layers, paths, remote};
+use crate::input::activity::InputSource;
+use crate::notifications::webhooks::{self, WebhookKind};
+use crate::pomodoro::pomodoro::{
+    POMODORO_BREAK_MINUTES, POMODORO_CYCLE_LENGTH, POMODORO_LONG_BREAK_MINUTES,
+    POMODORO_WORK_MINUTES,
+};
+use crate::stats::visits::EnforcementAction;
+use crate::window::provider::WindowAction;
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Table;
+
+/// User configuration loaded from `~/.config/stop_it/config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub pomodoro: PomodoroConfig,
+    pub day: DayConfig,
+    pub off_days: OffDaysConfig,
+    pub focus_alerts: FocusAlertsConfig,
+    pub budgets: BTreeMap<String, BudgetConfig>, // domain -> budget
+    #[serde(deserialize_with = "deserialize_category_budgets")]
+    pub category_budgets: BTreeMap<String, BudgetConfig>, // category -> budget, or just its daily time
+    #[serde(deserialize_with = "deserialize_limits")]
+    pub limits: BTreeMap<String, i64>, // domain -> seconds per day, minutes in the file
+    pub storage: StorageConfig,
+    pub goals: GoalsConfig,
+    pub hyprland: HyprlandConfig,
+    pub sway: SwayConfig,
+    pub x11: X11Config,
+    pub gnome: GnomeConfig,
+    pub kde: KdeConfig,
+    pub windows: WindowsConfig,
+    pub breaks: BreaksConfig,
+    pub notifications: NotificationsConfig,
+    pub sounds: SoundsConfig,
+    pub exemptions: ExemptionsConfig,
+    pub log: LogConfig,
+    pub server: ServerConfig,
+    pub extension: ExtensionConfig,
+    pub titles: TitlesConfig,
+    pub categories: BTreeMap<String, String>, // domain -> category, overrides the extension's hint
+    pub enforcement: BTreeMap<String, EnforcementAction>, // category -> action during work sessions
+    pub strict: StrictConfig,
+    pub groups: BTreeMap<String, Vec<String>>, // company -> domains, e.g. "Google" = ["google.com", "gmail.com"]
+    pub triage: TriageConfig,
+    pub input: InputConfig,
+    pub pause: PauseConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub telemetry: TelemetryConfig,
+    pub remote: RemoteConfig,
+    #[serde(skip)]
+    pub profile: Option<String>, // applied `[profile.<name>]`, if any
+}
+
+/// Session lengths, in seconds
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroConfig {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub work: i64, // e.g. "25m"
+    #[serde(rename = "break", deserialize_with = "deserialize_duration")]
+    pub break_: i64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub long_break: i64, // e.g. "20m"
+    pub long_break_after: u32, // work sessions per cycle, 0 for short breaks only
+    pub manual: bool,          // breaks that ran out wait for `stop_it start`
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: POMODORO_WORK_MINUTES * 60,
+            break_: POMODORO_BREAK_MINUTES * 60,
+            long_break: POMODORO_LONG_BREAK_MINUTES * 60,
+            long_break_after: POMODORO_CYCLE_LENGTH,
+            manual: false,
+        }
+    }
+}
+
+/// When a "day" and a "week" begin for aggregation and rollover
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DayConfig {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: NaiveTime, // e.g. "04:00" for night owls
+    #[serde(deserialize_with = "deserialize_weekday")]
+    pub week_start: Weekday, // e.g. "mon" or "sun"
+}
+
+impl Default for DayConfig {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::MIN,
+            week_start: Weekday::Mon,
+        }
+    }
+}
+
+/// Days where budgets, streaks and goals don't apply (weekends, vacations)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OffDaysConfig {
+    #[serde(deserialize_with = "deserialize_weekdays")]
+    pub weekdays: Vec<Weekday>, // e.g. ["sat", "sun"]
+    #[serde(deserialize_with = "deserialize_date_ranges")]
+    pub dates: Vec<(NaiveDate, NaiveDate)>, // "2024-12-24" or "2024-12-24..2025-01-01"
+    pub track: bool, // keep tracking activity on off-days
+}
+
+impl Default for OffDaysConfig {
+    fn default() -> Self {
+        Self {
+            weekdays: Vec::new(),
+            dates: Vec::new(),
+            track: true,
+        }
+    }
+}
+
+impl OffDaysConfig {
+    pub fn is_off_day(&self, date: NaiveDate) -> bool {
+        self.weekdays.contains(&date.weekday())
+            || self
+                .dates
+                .iter()
+                .any(|(from, to)| date >= *from && date <= *to)
+    }
+}
+
+/// Proactive "you've been drifting" alerts based on the rolling distraction ratio
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FocusAlertsConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "deserialize_ratio")]
+    pub threshold: f64, // distraction ratio (0.0 - 1.0) that triggers an alert
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub window_minutes: i64,
+    #[serde(deserialize_with = "deserialize_time_span")]
+    pub work_hours: (NaiveTime, NaiveTime), // e.g. "09:00-18:00"
+    pub distracting_categories: Vec<String>,
+    pub distracting_domains: Vec<String>, // also matches subdomains
+    pub allowed_urls: Vec<String>, // URL patterns exempt from enforcement, e.g. "youtube.com/watch?*list=PL*"
+    pub window_action: WindowAction, // what happens to the browser window still on a blocked site
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub grace: i64, // seconds on a blocked site before `window_action`, e.g. "1m"
+}
+
+impl Default for FocusAlertsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 0.5,
+            window_minutes: 60,
+            work_hours: (
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap_or(NaiveTime::MIN),
+                NaiveTime::from_hms_opt(18, 0, 0).unwrap_or(NaiveTime::MIN),
+            ),
+            distracting_categories: vec!["social".to_string(), "entertainment".to_string()],
+            distracting_domains: Vec::new(),
+            allowed_urls: Vec::new(),
+            window_action: WindowAction::None,
+            grace: 60,
+        }
+    }
+}
+
+/// Time budget for a domain (and its subdomains) or a category
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub daily: Option<i64>, // seconds, e.g. "30m"
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub weekly: Option<i64>, // seconds, e.g. "3h"
+    pub carryover: bool, // unused daily budget rolls over to the next day of the week
+    pub work_only: bool, // time on breaks doesn't count
+}
+
+/// Where activity history is persisted
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub url: Option<String>, // sqlite://, jsonl:// or postgres://; defaults to SQLite in the data dir
+    pub journal: bool,       // write-ahead journal so a crash loses at most a second
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            journal: true,
+        }
+    }
+}
+
+/// Daily targets shown by status bars and widgets
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoalsConfig {
+    pub daily_pomodoros: u32, // completed work sessions per day
+}
+
+impl Default for GoalsConfig {
+    fn default() -> Self {
+        Self { daily_pomodoros: 8 }
+    }
+}
+
+/// Hard breaks: lock the screen when a break starts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BreaksConfig {
+    pub lock: bool,
+    pub locker: String,    // shell command that blocks until unlocked
+    pub countdown: bool,   // one notification updated every minute with the time left
+    pub adaptive: bool,    // suggest a break scaled by the work session's length and focus
+    pub auto_accept: bool, // take the suggested length, not only mention it
+    pub overlay: bool,     // the extension pauses media and greys out pages during breaks
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub snooze: i64, // how long `stop_it snooze` and the notification's button put a break off
+}
+
+impl Default for BreaksConfig {
+    fn default() -> Self {
+        Self {
+            lock: false,
+            locker: "hyprlock".to_string(),
+            countdown: false,
+            adaptive: false,
+            auto_accept: false,
+            overlay: false,
+            snooze: 5 * 60,
+        }
+    }
+}
+
+/// Which monitor desktop notifications show up on (Hyprland sessions)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub follow_focus: bool,               // the monitor of the focused window
+    pub critical_everywhere: bool,        // one copy of critical alerts on every monitor
+    pub output_hint: String,              // notification hint carrying the monitor name
+    pub fallback_command: Option<String>, // run per notification while no notification server answers
+}
+
+impl NotificationsConfig {
+    pub fn is_routed(&self) -> bool {
+        self.follow_focus || self.critical_everywhere
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            follow_focus: false,
+            critical_everywhere: false,
+            output_hint: "output".to_string(),
+            fallback_command: None,
+        }
+    }
+}
+
+/// Sound files played when sessions start, since notifications are easy
+/// to miss
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoundsConfig {
+    pub work: Option<PathBuf>,
+    #[serde(rename = "break")]
+    pub break_: Option<PathBuf>,
+    pub long_break: Option<PathBuf>, // `break` when unset
+    pub player: String,              // shell command, given the file as its last argument
+    pub mute: bool,                  // `stop_it daemon --mute`
+}
+
+impl Default for SoundsConfig {
+    fn default() -> Self {
+        Self {
+            work: None,
+            break_: None,
+            long_break: None,
+            player: "paplay".to_string(),
+            mute: false,
+        }
+    }
+}
+
+/// Applications (e.g. a DAW, OBS while streaming) that hold back
+/// notifications and break locks while they have focus
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExemptionsConfig {
+    pub apps: Vec<String>, // window classes, case-insensitive
+}
+
+impl ExemptionsConfig {
+    pub fn matches(&self, class: &str) -> bool {
+        self.apps.iter().any(|app| app.eq_ignore_ascii_case(class))
+    }
+}
+
+/// Where the WebSocket server listens
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub fallback_ports: u16, // try this many following ports when `port` is taken
+    pub tokens: BTreeMap<String, String>, // browser profile -> token; empty accepts any extension
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 8765,
+            fallback_ports: 0,
+            tokens: BTreeMap::new(),
+        }
+    }
+}
+
+/// Running from the browser extension alone, without a focus backend
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtensionConfig {
+    pub only: bool, // focus and idle time come from the extension's heartbeats
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub timeout: i64, // seconds without a heartbeat before tracking stops, e.g. "1m"
+}
+
+impl Default for ExtensionConfig {
+    fn default() -> Self {
+        Self {
+            only: false,
+            timeout: 60,
+        }
+    }
+}
+
+/// Compositor integration for Hyprland sessions
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HyprlandConfig {
+    pub workspace_label: bool, // append mode and remaining time to the active workspace name
+    pub track_focus: bool,     // only credit browser time while a browser window has focus
+}
+
+/// Compositor integration for Sway sessions
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwayConfig {
+    pub track_focus: bool, // as with Hyprland, reading the focused window from the tree
+}
+
+/// Window tracking for X11 sessions (i3, bspwm, XFCE)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct X11Config {
+    pub track_focus: bool, // as with Hyprland, reading the focused window with xprop
+}
+
+/// Window tracking for GNOME Wayland sessions, through the companion
+/// extension in `gnome-extension/`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GnomeConfig {
+    pub track_focus: bool, // as with Hyprland, asking the extension over D-Bus
+}
+
+/// Window tracking for KDE Plasma sessions, through KWin scripting
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KdeConfig {
+    pub track_focus: bool, // as with Hyprland, asking KWin with kdotool
+}
+
+/// Window tracking on Windows, in builds with the `windows-focus` feature
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowsConfig {
+    pub track_focus: bool, // as with Hyprland, reading the foreground window
+}
+
+/// Window titles that change too often (music players, terminals with a
+/// clock) are merged into one activity instead of churning domains
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TitlesConfig {
+    pub max_changes: usize, // title changes within `window` before the title is frozen
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub window: i64, // seconds, e.g. "30s"
+    pub classes: BTreeMap<String, usize>, // window class -> max_changes override
+    pub services: BTreeMap<String, Vec<String>>, // domain -> names shown in page titles
+}
+
+impl Default for TitlesConfig {
+    fn default() -> Self {
+        Self {
+            max_changes: 5,
+            window: 30,
+            classes: BTreeMap::new(),
+            services: BTreeMap::new(),
+        }
+    }
+}
+
+/// Opt-in keyboard/mouse activity sampling, to tell active work apart from
+/// an idle focused window. Only event counts are read, never keys.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub sample: bool,
+    pub source: InputSource,
+    pub pause_when_idle: bool, // also stop the Pomodoro clock while idle
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub idle_after: i64, // seconds without input before tracking pauses, e.g. "2m"
+    #[serde(deserialize_with = "deserialize_ratio")]
+    pub abandon_after: f64, // idle share (0.0 - 1.0) of a work session that abandons it
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            sample: false,
+            source: InputSource::Logind,
+            pause_when_idle: false,
+            idle_after: 120,
+            abandon_after: 0.5,
+        }
+    }
+}
+
+/// Safety net for `pause`: resume on its own after `auto_resume`, with a
+/// warning notification `warning` before
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PauseConfig {
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub auto_resume: Option<i64>, // seconds, e.g. "45m"; None keeps pauses until `resume`
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub warning: i64, // e.g. "5m"
+}
+
+impl Default for PauseConfig {
+    fn default() -> Self {
+        Self {
+            auto_resume: None,
+            warning: 300,
+        }
+    }
+}
+
+/// Allowlist-only work sessions, for deep work: every site but `domains`
+/// calls for `action`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrictConfig {
+    pub domains: Vec<String>, // also matches subdomains; empty leaves strict mode off
+    pub action: EnforcementAction,
+}
+
+impl Default for StrictConfig {
+    fn default() -> Self {
+        Self {
+            domains: Vec::new(),
+            action: EnforcementAction::Nag,
+        }
+    }
+}
+
+/// Weekly reminder about sites that took time without a category, to sort
+/// them with `stop_it triage`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TriageConfig {
+    pub notify: bool, // when a new week starts
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub min_time: i64, // seconds a site needs within the week, e.g. "1h"
+}
+
+impl Default for TriageConfig {
+    fn default() -> Self {
+        Self {
+            notify: true,
+            min_time: 60 * 60,
+        }
+    }
+}
+
+/// HTTP endpoint receiving daemon events as JSON POST requests
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<WebhookKind>, // empty: every event
+    #[serde(default, deserialize_with = "deserialize_template")]
+    pub template: Option<String>, // JSON with {{placeholders}}; None sends the raw event
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>, // e.g. Authorization
+}
+
+/// Daily copies of the daemon log in `~/.local/share/stop_it/logs`, and the
+/// events kept in memory for `stop_it inspect`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub files: bool,
+    pub keep_days: u32,       // delete files older than this; 0 keeps them all
+    pub compress: bool,       // gzip the files of past days
+    pub recent_events: usize, // domain switches, browser messages and errors; 0 keeps none
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            files: false,
+            keep_days: 30,
+            compress: false,
+            recent_events: 500,
+        }
+    }
+}
+
+/// OpenTelemetry trace export (needs `cargo build --features otel`)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: Option<String>, // OTLP/HTTP collector, e.g. "http://localhost:4318"
+}
+
+/// Base config fetched from a URL, e.g. a team's focus policy, with the
+/// local file layered on top
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub url: Option<String>,
+    pub sha256: Option<String>,     // hex digest the file must have
+    pub public_key: Option<String>, // hex Ed25519 key; `<url>.sig` holds the hex signature
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub refresh: i64, // seconds the cached copy is used without fetching
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            sha256: None,
+            public_key: None,
+            refresh: 60 * 60,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from the default location, falling back to defaults
+    /// when the file does not exist
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from(&paths::config_file())
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        let selected = std::env::var(layers::PROFILE_ENV)
+            .ok()
+            .filter(|name| !name.is_empty());
+        let layered = selected.is_some()
+            || content.parse::<toml::Table>().is_ok_and(|table| {
+                ["include", "profile", "active_profile", "remote"]
+                    .iter()
+                    .any(|key| table.contains_key(*key))
+            });
+        if !layered {
+            // Parsed straight from the text, errors keep their line and column
+            return toml::from_str(&content).map_err(|e| invalid(path, e).into());
+        }
+
+        let mut table = layers::load_table(path)?;
+        if let Some(section) = table.get("remote") {
+            // Read on its own first, errors still name the key
+            let settings: Config =
+                toml::Value::Table(Table::from_iter([("remote".to_string(), section.clone())]))
+                    .try_into()
+                    .map_err(|e| invalid(path, e))?;
+            // Local settings win over the shared ones
+            let mut base = remote::load_base(&settings.remote, &paths::data_dir())?;
+            layers::merge(&mut base, table);
+            table = base;
+        }
+        let profile = layers::apply_profile(&mut table, selected.as_deref())
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        let mut config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| invalid(path, e))?;
+        config.profile = profile;
+        Ok(config)
+    }
+}
+
+fn invalid(path: &Path, error: toml::de::Error) -> String {
+    format!(
+        "Invalid config {}:\n{}",
+        path.display(),
+        diagnostics::with_suggestion(&error.to_string())
+    )
+}
+
+/// Parse "HH:MM" (or "HH:MM:SS") into a `NaiveTime`
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .ok()
+}
+
+/// Parse durations like "45m", "3h", "1h30m" or "90s" into seconds.
+/// A bare number is interpreted as minutes.
+pub fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(minutes) = value.parse::<i64>() {
+        return Some(minutes * 60);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' if !number.is_empty() => {
+                let amount: i64 = number.parse().ok()?;
+                total += match c {
+                    'h' => amount * 3600,
+                    'm' => amount * 60,
+                    _ => amount,
+                };
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+
+    if number.is_empty() && total > 0 {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match deserialize_optional_duration(deserializer)? {
+        Some(seconds) if seconds > 0 => Ok(seconds),
+        _ => Err(serde::de::Error::custom(
+            "duration must be longer than zero",
+        )),
+    }
+}
+
+/// `[category_budgets]` entries: a table like the ones under `[budgets]`,
+/// or only the daily time, e.g. `entertainment = "45m"`
+fn deserialize_category_budgets<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, BudgetConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, toml::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(category, value)| {
+            let budget = match value {
+                toml::Value::String(daily) => BudgetConfig {
+                    daily: Some(deserialize_duration(toml::Value::String(daily)).map_err(
+                        |e: toml::de::Error| {
+                            serde::de::Error::custom(format!("{}: {}", category, e.message()))
+                        },
+                    )?),
+                    ..BudgetConfig::default()
+                },
+                value => value.try_into().map_err(|e: toml::de::Error| {
+                    serde::de::Error::custom(format!("{}: {}", category, e.message()))
+                })?,
+            };
+            Ok((category, budget))
+        })
+        .collect()
+}
+
+/// `limits` entries: minutes per day, e.g. `"youtube.com" = 30`
+fn deserialize_limits<'de, D>(deserializer: D) -> Result<BTreeMap<String, i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, i64>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(domain, minutes)| {
+            if minutes > 0 {
+                Ok((domain, minutes * 60))
+            } else {
+                Err(serde::de::Error::custom(format!(
+                    "{}: invalid limit {}, expected minutes above zero",
+                    domain, minutes
+                )))
+            }
+        })
+        .collect()
+}
+
+fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_duration(&value).map(Some).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "invalid duration '{}', expected e.g. \"30m\", \"3h\" or \"1h30m\"",
+            value
+        ))
+    })
+}
+
+fn deserialize_template<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let template = String::deserialize(deserializer)?;
+    webhooks::check_template(&template).map_err(serde::de::Error::custom)?;
+    Ok(Some(template))
+}
+
+fn deserialize_ratio<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = f64::deserialize(deserializer)?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "invalid ratio {}, expected a value between 0.0 and 1.0",
+            value
+        )))
+    }
+}
+
+fn deserialize_positive<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = i64::deserialize(deserializer)?;
+    if value > 0 {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "invalid value {}, expected a positive number",
+            value
+        )))
+    }
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_time(&value).ok_or_else(|| {
+        serde::de::Error::custom(format!("invalid time '{}', expected HH:MM", value))
+    })
+}
+
+fn deserialize_time_span<'de, D>(deserializer: D) -> Result<(NaiveTime, NaiveTime), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    value
+        .split_once('-')
+        .and_then(|(from, to)| Some((parse_time(from.trim())?, parse_time(to.trim())?)))
+        .ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "invalid time span '{}', expected HH:MM-HH:MM",
+                value
+            ))
+        })
+}
+
+fn deserialize_weekday<'de, D>(deserializer: D) -> Result<Weekday, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    value
+        .parse::<Weekday>()
+        .map_err(|_| serde::de::Error::custom(format!("invalid weekday '{}'", value)))
+}
+
+fn deserialize_weekdays<'de, D>(deserializer: D) -> Result<Vec<Weekday>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| {
+            value
+                .parse::<Weekday>()
+                .map_err(|_| serde::de::Error::custom(format!("invalid weekday '{}'", value)))
+        })
+        .collect()
+}
+
+fn deserialize_date_ranges<'de, D>(deserializer: D) -> Result<Vec<(NaiveDate, NaiveDate)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| {
+            let (from, to) = value.split_once("..").unwrap_or((value, value));
+            let parse = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d");
+            match (parse(from), parse(to)) {
+                (Ok(from), Ok(to)) if from <= to => Ok((from, to)),
+                _ => Err(serde::de::Error::custom(format!(
+                    "invalid date or range '{}', expected YYYY-MM-DD[..YYYY-MM-DD]",
+                    value
+                ))),
+            }
+        })
+        .collect()
+}
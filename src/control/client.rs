@@ -0,0 +1,97 @@
+use crate::ws::protocol::{ClientMessage, ServerMessage};
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf};
+
+#[cfg(unix)]
+type Stream = tokio::net::UnixStream;
+#[cfg(windows)]
+type Stream = tokio::net::windows::named_pipe::NamedPipeClient;
+
+/// Connection to the running daemon's control socket
+pub struct ControlClient {
+    lines: Lines<BufReader<ReadHalf<Stream>>>,
+    writer: WriteHalf<Stream>,
+    pid: Option<i32>,
+}
+
+/// The stream and the daemon's process id, when the kernel reports it
+#[cfg(unix)]
+async fn open(path: &Path) -> std::io::Result<(Stream, Option<i32>)> {
+    let stream = Stream::connect(path).await?;
+    let pid = stream.peer_cred().ok().and_then(|cred| cred.pid());
+    Ok((stream, pid))
+}
+
+/// Named pipes have a fixed number of waiting instances: retry for a moment
+/// while all are busy
+#[cfg(windows)]
+async fn open(path: &Path) -> std::io::Result<(Stream, Option<i32>)> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    let mut attempts = 0;
+    loop {
+        match ClientOptions::new().open(path) {
+            Ok(stream) => return Ok((stream, None)),
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < 20 => {
+                attempts += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl ControlClient {
+    pub async fn connect(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let (stream, pid) = open(path).await.map_err(|e| {
+            format!(
+                "Could not connect to daemon at {} ({}). Is it running?",
+                path.display(),
+                e
+            )
+        })?;
+        let (reader, writer) = tokio::io::split(stream);
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            pid,
+        })
+    }
+
+    /// Process id of the daemon, when the kernel reports it
+    pub fn daemon_pid(&self) -> Option<i32> {
+        self.pid
+    }
+
+    pub async fn send(
+        &mut self,
+        message: &ClientMessage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut json = serde_json::to_string(message)?;
+        json.push('\n');
+        self.writer.write_all(json.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Next message from the daemon, `None` once it closed the connection
+    pub async fn recv(&mut self) -> Result<Option<ServerMessage>, Box<dyn std::error::Error>> {
+        match self.lines.next_line().await? {
+            Some(line) => Ok(Some(serde_json::from_str(&line)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Send `message` and wait for the answer; daemon errors become `Err`
+    pub async fn request(
+        &mut self,
+        message: &ClientMessage,
+    ) -> Result<ServerMessage, Box<dyn std::error::Error>> {
+        self.send(message).await?;
+        match self.recv().await? {
+            Some(ServerMessage::Error { message }) => Err(message.into()),
+            Some(reply) => Ok(reply),
+            None => Err("Daemon closed the connection".into()),
+        }
+    }
+}
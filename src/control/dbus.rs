@@ -0,0 +1,160 @@
+use crate::ws::channels::RequestSender;
+use crate::ws::protocol::{ClientMessage, ServerMessage, TimerState};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::oneshot;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Type;
+use zbus::{Connection, connection, fdo, interface};
+
+/// Well-known name the daemon owns on the session bus
+pub const BUS_NAME: &str = "org.stopit.Daemon";
+pub const OBJECT_PATH: &str = "/org/stopit/Daemon";
+
+/// What `GetState` returns, the D-Bus struct `(sxxbbuuss)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct DaemonState {
+    pub mode: String, // "WORK", "BREAK" or "LONG_BREAK"
+    pub remaining_seconds: i64,
+    pub duration_seconds: i64,
+    pub paused: bool,
+    pub waiting: bool, // the break ran out and waits for `start`
+    pub pomodoros_today: u32,
+    pub daily_goal: u32,
+    pub domain: String,   // empty when none
+    pub category: String, // empty when none
+}
+
+impl From<TimerState> for DaemonState {
+    fn from(state: TimerState) -> Self {
+        Self {
+            mode: state.mode,
+            remaining_seconds: state.remaining_seconds,
+            duration_seconds: state.duration_seconds,
+            paused: state.paused,
+            waiting: state.waiting,
+            pomodoros_today: state.pomodoros_today,
+            daily_goal: state.daily_goal,
+            domain: state.current_domain.unwrap_or_default(),
+            category: state.current_category.unwrap_or_default(),
+        }
+    }
+}
+
+/// `org.stopit.Daemon`: the control commands desktop widgets need, answered
+/// by the same request handler as the control socket
+struct DaemonInterface {
+    request_tx: RequestSender,
+}
+
+impl DaemonInterface {
+    async fn request(&self, message: ClientMessage) -> fdo::Result<ServerMessage> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.request_tx
+            .send((message, reply_tx))
+            .map_err(|_| fdo::Error::Failed("The daemon is shutting down".to_string()))?;
+        match reply_rx.await {
+            Ok(ServerMessage::Error { message }) => Err(fdo::Error::Failed(message)),
+            Ok(reply) => Ok(reply),
+            Err(_) => Err(fdo::Error::Failed("The daemon didn't answer".to_string())),
+        }
+    }
+
+    /// The acknowledgement `stop_it pause` and the like print
+    async fn command(&self, message: ClientMessage) -> fdo::Result<String> {
+        match self.request(message).await? {
+            ServerMessage::Ack { message } => Ok(message),
+            other => Err(fdo::Error::Failed(format!("Unexpected reply: {:?}", other))),
+        }
+    }
+}
+
+#[interface(name = "org.stopit.Daemon")]
+impl DaemonInterface {
+    /// Stop the timer and tracking until `Resume`
+    async fn pause(&self) -> fdo::Result<String> {
+        self.command(ClientMessage::Pause).await
+    }
+
+    /// Continue a paused session with the time it had left
+    async fn resume(&self) -> fdo::Result<String> {
+        self.command(ClientMessage::Resume).await
+    }
+
+    /// End the current session or break now and move on to the next
+    async fn skip(&self) -> fdo::Result<String> {
+        self.command(ClientMessage::Skip).await
+    }
+
+    async fn get_state(&self) -> fdo::Result<DaemonState> {
+        match self.request(ClientMessage::GetStatus).await? {
+            ServerMessage::TimerState(state) => Ok(state.into()),
+            other => Err(fdo::Error::Failed(format!("Unexpected reply: {:?}", other))),
+        }
+    }
+
+    /// A session of `mode` started after one of `previous`
+    #[zbus(signal)]
+    async fn mode_changed(
+        emitter: &SignalEmitter<'_>,
+        mode: &str,
+        previous: &str,
+        duration_seconds: i64,
+    ) -> zbus::Result<()>;
+}
+
+/// Connect to the session bus, or the bus at `address`, serve the interface
+/// at `OBJECT_PATH` and take `BUS_NAME`. Fails when another daemon has it.
+pub async fn bind_dbus_service(
+    address: Option<&str>,
+    request_tx: RequestSender,
+) -> Result<Connection, Box<dyn std::error::Error>> {
+    let builder = match address {
+        Some(address) => connection::Builder::address(address)?,
+        None => connection::Builder::session()?,
+    };
+    let connection = builder
+        .serve_at(OBJECT_PATH, DaemonInterface { request_tx })?
+        .name(BUS_NAME)?
+        .replace_existing_names(false)
+        .allow_name_replacements(false)
+        .build()
+        .await
+        .map_err(|e| match e {
+            zbus::Error::NameTaken => format!("{} is taken by another process", BUS_NAME),
+            e => format!("Cannot connect to the D-Bus session bus: {}", e),
+        })?;
+    Ok(connection)
+}
+
+/// Emit `ModeChanged` whenever the broadcast timer state switches sessions,
+/// until the daemon exits
+pub async fn start_dbus_service(
+    connection: Connection,
+    mut states: broadcast::Receiver<ServerMessage>,
+) {
+    let Ok(emitter) = SignalEmitter::new(&connection, OBJECT_PATH) else {
+        return;
+    };
+    let mut mode: Option<String> = None;
+    loop {
+        let state = match states.recv().await {
+            Ok(ServerMessage::TimerState(state)) => state,
+            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        let previous = mode.replace(state.mode.clone());
+        if let Some(previous) = previous
+            && previous != state.mode
+            && let Err(e) = DaemonInterface::mode_changed(
+                &emitter,
+                &state.mode,
+                &previous,
+                state.duration_seconds,
+            )
+            .await
+        {
+            eprintln!("D-Bus: could not emit ModeChanged: {}", e);
+        }
+    }
+}
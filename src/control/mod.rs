@@ -0,0 +1,4 @@
+pub mod client;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+pub mod socket;
@@ -0,0 +1,269 @@
+use crate::daemon::log::Log;
+use crate::daemon::trace::{Trace, TraceEvent};
+use crate::ws::channels::RequestSender;
+use crate::ws::peer;
+use crate::ws::protocol::{ClientMessage, ServerMessage};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, oneshot};
+
+/// Unix socket local tools use to query and drive the running daemon. It
+/// speaks one JSON request per line, answered by one JSON line.
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    crate::config::paths::runtime_dir().join("control.sock")
+}
+
+/// On Windows a named pipe per user takes the place of the Unix socket
+#[cfg(windows)]
+pub fn socket_path() -> PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    PathBuf::from(format!(r"\\.\pipe\stop_it-{}-control", user))
+}
+
+/// The bound control socket, accepting one connection at a time
+#[cfg(unix)]
+pub struct ControlListener(tokio::net::UnixListener);
+
+#[cfg(windows)]
+pub struct ControlListener {
+    path: PathBuf,
+    next: tokio::net::windows::named_pipe::NamedPipeServer, // the instance clients connect to next
+}
+
+/// Bind the control socket at `path`, readable by its owner only
+#[cfg(unix)]
+pub async fn bind_control_socket(
+    path: &Path,
+) -> Result<ControlListener, Box<dyn std::error::Error>> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::{UnixListener, UnixStream};
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+        // The directory guards the socket between bind and chmod
+        fs::set_permissions(parent, fs::Permissions::from_mode(0o700))?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(
+                format!("Another stop_it daemon is listening on {}", path.display()).into(),
+            );
+        }
+        fs::remove_file(path)?; // left over from a crash
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))?;
+    Ok(ControlListener(listener))
+}
+
+/// Create the named pipe at `path`. Its default security only lets the
+/// owner (and administrators) write to it, and remote clients are refused.
+#[cfg(windows)]
+pub async fn bind_control_socket(
+    path: &Path,
+) -> Result<ControlListener, Box<dyn std::error::Error>> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let next = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                format!("Another stop_it daemon is listening on {}", path.display())
+            }
+            _ => format!("Cannot create {}: {}", path.display(), e),
+        })?;
+    Ok(ControlListener {
+        path: path.to_path_buf(),
+        next,
+    })
+}
+
+#[cfg(unix)]
+impl ControlListener {
+    async fn accept(&mut self) -> std::io::Result<tokio::net::UnixStream> {
+        self.0.accept().await.map(|(stream, _)| stream)
+    }
+}
+
+#[cfg(windows)]
+impl ControlListener {
+    async fn accept(
+        &mut self,
+    ) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        self.next.connect().await?;
+        // A pipe instance serves one client: make the next one before handing it out
+        let next = ServerOptions::new()
+            .reject_remote_clients(true)
+            .create(&self.path)?;
+        Ok(std::mem::replace(&mut self.next, next))
+    }
+}
+
+/// Refuse processes of other users, checked per connection in case the
+/// socket permissions were loosened. The error describes the peer.
+#[cfg(unix)]
+fn check_peer(stream: &tokio::net::UnixStream, uid: Option<u32>) -> Result<(), String> {
+    match stream.peer_cred() {
+        Ok(cred) if Some(cred.uid()) == uid => Ok(()),
+        Ok(cred) => Err(format!("uid {} (pid {:?})", cred.uid(), cred.pid())),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// The pipe's security already keeps other users from writing to it
+#[cfg(windows)]
+fn check_peer(
+    _stream: &tokio::net::windows::named_pipe::NamedPipeServer,
+    _uid: Option<u32>,
+) -> Result<(), String> {
+    Ok(())
+}
+
+/// Answer control requests from processes of the daemon's own user;
+/// `attach` requests follow `log`, `record` requests follow `trace` and
+/// `inspect` requests read the events it kept
+pub async fn start_control_socket(
+    mut listener: ControlListener,
+    request_tx: RequestSender,
+    log: Log,
+    trace: Trace,
+) {
+    let uid = peer::current_uid();
+    while let Ok(stream) = listener.accept().await {
+        if let Err(peer) = check_peer(&stream, uid) {
+            eprintln!("Refused control connection from {}", peer);
+            continue;
+        }
+        tokio::spawn(handle_control_connection(
+            stream,
+            request_tx.clone(),
+            log.clone(),
+            trace.clone(),
+        ));
+    }
+}
+
+async fn handle_control_connection(
+    stream: impl AsyncRead + AsyncWrite + Send + 'static,
+    request_tx: RequestSender,
+    log: Log,
+    trace: Trace,
+) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let reply = match serde_json::from_str::<ClientMessage>(&line) {
+            Ok(ClientMessage::Attach) => {
+                // The connection only carries log lines from here on
+                let follower = log.follow();
+                let attached = ServerMessage::Ack {
+                    message: "Attached, Ctrl+C detaches".to_string(),
+                };
+                if write_message(&mut writer, &attached).await {
+                    stream_log(follower, writer).await;
+                }
+                return;
+            }
+            Ok(ClientMessage::Record { raw }) => {
+                let follower = trace.follow();
+                let recording = ServerMessage::Ack {
+                    message: if raw {
+                        "Recording, titles and URLs included".to_string()
+                    } else {
+                        "Recording, titles and URLs scrubbed".to_string()
+                    },
+                };
+                if write_message(&mut writer, &recording).await {
+                    stream_trace(follower, raw, writer).await;
+                }
+                return;
+            }
+            Ok(ClientMessage::Inspect { events }) => ServerMessage::Events {
+                events: trace.recent(events),
+            },
+            Ok(
+                ClientMessage::TabUpdate(_)
+                | ClientMessage::TabClosed(_)
+                | ClientMessage::WindowClosed(_)
+                | ClientMessage::Heartbeat(_)
+                | ClientMessage::BreakAck { .. },
+            ) => ServerMessage::Error {
+                message: "Browser activity is only accepted over the WebSocket".to_string(),
+            },
+            Ok(request) => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                match request_tx.send((request, reply_tx)) {
+                    Ok(()) => reply_rx.await.unwrap_or_else(|_| ServerMessage::Error {
+                        message: "Daemon did not answer".to_string(),
+                    }),
+                    Err(_) => ServerMessage::Error {
+                        message: "Daemon is shutting down".to_string(),
+                    },
+                }
+            }
+            Err(e) => ServerMessage::Error {
+                message: format!("Parse error: {}", e),
+            },
+        };
+        if !write_message(&mut writer, &reply).await {
+            break;
+        }
+    }
+}
+
+/// Forward log lines to an attached console until it goes away
+async fn stream_log(
+    mut follower: broadcast::Receiver<String>,
+    mut writer: impl AsyncWrite + Unpin,
+) {
+    loop {
+        let message = match follower.recv().await {
+            Ok(line) => ServerMessage::LogLine { line },
+            Err(broadcast::error::RecvError::Lagged(skipped)) => ServerMessage::LogLine {
+                line: format!("... {} lines skipped", skipped),
+            },
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if !write_message(&mut writer, &message).await {
+            return;
+        }
+    }
+}
+
+/// Forward trace events to a recording client until it goes away
+async fn stream_trace(
+    mut follower: broadcast::Receiver<TraceEvent>,
+    raw: bool,
+    mut writer: impl AsyncWrite + Unpin,
+) {
+    loop {
+        let message = match follower.recv().await {
+            Ok(event) if raw => ServerMessage::Trace { event },
+            Ok(event) => ServerMessage::Trace {
+                event: event.scrubbed(),
+            },
+            Err(broadcast::error::RecvError::Lagged(skipped)) => ServerMessage::Error {
+                message: format!("{} trace events skipped", skipped),
+            },
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if !write_message(&mut writer, &message).await {
+            return;
+        }
+    }
+}
+
+/// Write one JSON line, `false` once the client is gone
+async fn write_message(writer: &mut (impl AsyncWrite + Unpin), message: &ServerMessage) -> bool {
+    let Ok(mut json) = serde_json::to_string(message) else {
+        return false;
+    };
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await.is_ok()
+}
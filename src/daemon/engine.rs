@@ -0,0 +1,244 @@
+use crate::config::settings::Config;
+use crate::daemon::events::EventStream;
+use crate::daemon::log::Log;
+use crate::daemon::runner::{Daemon, Notifier};
+use crate::daemon::status_file::StatusFile;
+use crate::daemon::systemd::Watchdog;
+use crate::daemon::trace::Trace;
+use crate::daemon::tracker::DomainTracker;
+use crate::input::activity::InputActivity;
+use crate::window::provider::WindowProvider;
+use crate::ws::channels::{
+    self, ActivityReceiver, BrowserEvent, ConnectionReceiver, DaemonChannels, RequestReceiver,
+};
+use crate::ws::protocol::{
+    ClientMessage, HistoryPage, HistoryRequest, ServerMessage, TimerState, TodayStats,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::JoinHandle;
+
+/// The daemon as a library: timer, tracking and notifications running in
+/// the embedding process, driven through method calls instead of the
+/// control socket or WebSocket. Desktop frontends (GTK, iced, ...) build on
+/// this, and so does `stop_it daemon`, which serves the sockets on top.
+pub struct StopItEngine {
+    daemon: Option<Daemon>, // None while the clock task has it
+    tracker: Arc<Mutex<DomainTracker>>,
+    channels: DaemonChannels,
+    receivers: Option<(ActivityReceiver, RequestReceiver, ConnectionReceiver)>, // until handled
+    running: Option<Running>,
+}
+
+/// The clock task, giving the daemon back when it ends
+struct Running {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Daemon>,
+}
+
+impl StopItEngine {
+    pub fn new(
+        config: Config,
+        tracker: DomainTracker,
+        notifier: Notifier,
+        window: Box<dyn WindowProvider>,
+        log: Log,
+    ) -> Self {
+        let (activity_tx, activity_rx) = channels::create_activity_channel();
+        let (request_tx, request_rx) = channels::create_request_channel();
+        let (connection_tx, connection_rx) = channels::create_connection_channel();
+        let channels = DaemonChannels {
+            activity_tx,
+            state_tx: channels::create_state_channel(),
+            badge_tx: channels::create_badge_channel(),
+            request_tx,
+            connection_tx,
+        };
+        let daemon = Daemon::new(config, tracker, notifier, window, &channels, log);
+        Self {
+            tracker: daemon.tracker(),
+            daemon: Some(daemon),
+            channels,
+            receivers: Some((activity_rx, request_rx, connection_rx)),
+            running: None,
+        }
+    }
+
+    /// See `Daemon::with_input`
+    pub fn with_input(self, input: Box<dyn InputActivity>) -> Self {
+        self.configure(|daemon| daemon.with_input(input))
+    }
+
+    /// See `Daemon::with_events`
+    pub fn with_events(self, events: EventStream) -> Self {
+        self.configure(|daemon| daemon.with_events(events))
+    }
+
+    /// See `Daemon::with_trace`
+    pub fn with_trace(self, trace: Trace) -> Self {
+        self.configure(|daemon| daemon.with_trace(trace))
+    }
+
+    /// See `Daemon::with_status_file`
+    pub fn with_status_file(self, status_file: StatusFile) -> Self {
+        self.configure(|daemon| daemon.with_status_file(status_file))
+    }
+
+    /// See `Daemon::with_watchdog`
+    pub fn with_watchdog(self, watchdog: Watchdog) -> Self {
+        self.configure(|daemon| daemon.with_watchdog(watchdog))
+    }
+
+    /// Accept `handoff` requests; a handoff ends the clock task, see
+    /// `finished`
+    pub fn with_handoff(self) -> Self {
+        self.configure(Daemon::with_handoff)
+    }
+
+    fn configure(mut self, configure: impl FnOnce(Daemon) -> Daemon) -> Self {
+        self.daemon = self.daemon.take().map(configure);
+        self
+    }
+
+    /// The channels the WebSocket server and control socket serve clients
+    /// through, for frontends that keep the browser extension working
+    pub fn channels(&self) -> &DaemonChannels {
+        &self.channels
+    }
+
+    pub fn tracker(&self) -> Arc<Mutex<DomainTracker>> {
+        Arc::clone(&self.tracker)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Step the daemon once a second in a task until `stop`. Needs a tokio
+    /// runtime; does nothing when already running.
+    pub fn start(&mut self) {
+        self.spawn_handlers();
+        let Some(mut daemon) = self.daemon.take() else {
+            return;
+        };
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut timer_interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                tokio::select! {
+                    _ = timer_interval.tick() => daemon.step(),
+                    _ = daemon.handoff_requested() => break,
+                    _ = &mut stopped => break,
+                }
+            }
+            daemon
+        });
+        self.running = Some(Running { stop, task });
+    }
+
+    /// Stop stepping; `start` picks up again. Browser activity and requests
+    /// are still handled meanwhile.
+    pub async fn stop(&mut self) {
+        if let Some(running) = self.running.take() {
+            let _ = running.stop.send(());
+            self.daemon = running.task.await.ok();
+        }
+    }
+
+    /// Resolves once the clock task ended by itself, after a handoff;
+    /// right away when not running
+    pub async fn finished(&mut self) {
+        if let Some(running) = self.running.take() {
+            self.daemon = running.task.await.ok();
+        }
+    }
+
+    /// Resolves once a client asked for a handoff, when stepping by hand;
+    /// after `start`, the clock task takes it and `finished` resolves
+    pub fn handoff_requested(&self) -> impl Future<Output = ()> + use<> {
+        let handoff = self.daemon.as_ref().map(Daemon::handoff_requested);
+        async move {
+            match handoff {
+                Some(handoff) => handoff.await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    /// One step by hand instead of `start`, e.g. with a simulated clock;
+    /// does nothing while running
+    pub fn step(&mut self) {
+        self.spawn_handlers();
+        if let Some(daemon) = self.daemon.as_mut() {
+            daemon.step();
+        }
+    }
+
+    /// Handle browser activity, requests and extension connections ahead
+    /// of `start` or the first `step`, which otherwise do it. Needs a tokio
+    /// runtime.
+    pub fn spawn_handlers(&mut self) {
+        if let Some(daemon) = &self.daemon
+            && let Some((activity_rx, request_rx, connection_rx)) = self.receivers.take()
+        {
+            daemon.spawn_handlers(activity_rx, request_rx, connection_rx);
+        }
+    }
+
+    /// What the daemon broadcasts to clients: the timer state every step,
+    /// session summaries, badges, category changes and break overlays
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerMessage> {
+        self.channels.state_tx.subscribe()
+    }
+
+    /// Send a command or query the way the control socket does, e.g.
+    /// `ClientMessage::Pause`. Error replies become errors.
+    pub async fn request(
+        &self,
+        message: ClientMessage,
+    ) -> Result<ServerMessage, Box<dyn std::error::Error>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.channels
+            .request_tx
+            .send((message, reply_tx))
+            .map_err(|_| "The engine's request handler has stopped")?;
+        match reply_rx.await? {
+            ServerMessage::Error { message } => Err(message.into()),
+            reply => Ok(reply),
+        }
+    }
+
+    /// Report browser activity the way the extension does, for frontends
+    /// that follow a browser themselves
+    pub fn report(&self, event: BrowserEvent) -> Result<(), Box<dyn std::error::Error>> {
+        self.channels
+            .activity_tx
+            .send(event)
+            .map_err(|_| "The engine's activity handler has stopped".into())
+    }
+
+    pub async fn status(&self) -> Result<TimerState, Box<dyn std::error::Error>> {
+        match self.request(ClientMessage::GetStatus).await? {
+            ServerMessage::TimerState(state) => Ok(state),
+            other => Err(format!("Unexpected reply: {:?}", other).into()),
+        }
+    }
+
+    pub async fn today_stats(&self) -> Result<TodayStats, Box<dyn std::error::Error>> {
+        match self.request(ClientMessage::GetTodayStats).await? {
+            ServerMessage::TodayStats(stats) => Ok(stats),
+            other => Err(format!("Unexpected reply: {:?}", other).into()),
+        }
+    }
+
+    pub async fn history(
+        &self,
+        request: HistoryRequest,
+    ) -> Result<HistoryPage, Box<dyn std::error::Error>> {
+        match self.request(ClientMessage::GetHistory(request)).await? {
+            ServerMessage::History(page) => Ok(page),
+            other => Err(format!("Unexpected reply: {:?}", other).into()),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Events written at once before the rate limit applies
+const BURST: f64 = 20.0;
+/// Sustained rate of the stream; events beyond it are counted, not written
+const EVENTS_PER_SECOND: f64 = 5.0;
+
+/// What supervisors get to react to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MachineEvent {
+    ModeChanged {
+        mode: String,
+        cycle: u32,
+        pomodoros_today: u32,
+    },
+    BudgetExceeded {
+        domain: String,
+        over_by_seconds: i64,
+    },
+    CategoryBudgetExceeded {
+        category: String,
+        over_by_seconds: i64,
+    },
+    LimitWarning {
+        domain: String,
+        used_seconds: i64,
+        limit_seconds: i64,
+    },
+    LimitReached {
+        domain: String,
+        limit_seconds: i64,
+    },
+    FocusAlert {
+        distraction_percent: u32,
+    },
+    ExtensionConnected {
+        address: String,
+        active: u32,
+    },
+    ExtensionDisconnected {
+        address: String,
+        active: u32,
+    },
+    Dropped {
+        count: u64, // events left out since the last line to stay under the rate
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    time: DateTime<Local>,
+    #[serde(flatten)]
+    event: &'a MachineEvent,
+}
+
+#[derive(Debug)]
+struct Limiter {
+    tokens: f64,
+    refilled: Instant,
+    dropped: u64,
+}
+
+/// Newline-delimited JSON events on stdout (`--machine`), so process
+/// supervisors and wrappers can follow the daemon without the control
+/// socket. A token bucket bounds the rate; a `dropped` line reports what a
+/// burst cost.
+#[derive(Debug, Clone)]
+pub struct EventStream {
+    limiter: Arc<Mutex<Limiter>>,
+    captured: Option<Arc<Mutex<Vec<String>>>>,
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self {
+            limiter: Arc::new(Mutex::new(Limiter {
+                tokens: BURST,
+                refilled: Instant::now(),
+                dropped: 0,
+            })),
+            captured: None,
+        }
+    }
+}
+
+impl EventStream {
+    pub fn stdout() -> Self {
+        Self::default()
+    }
+
+    /// Stream that also keeps every line in memory
+    pub fn capturing() -> Self {
+        Self {
+            captured: Some(Arc::new(Mutex::new(Vec::new()))),
+            ..Self::default()
+        }
+    }
+
+    pub fn emit(&self, time: DateTime<Local>, event: MachineEvent) {
+        let Ok(mut limiter) = self.limiter.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        let elapsed = now.duration_since(limiter.refilled).as_secs_f64();
+        limiter.tokens = (limiter.tokens + elapsed * EVENTS_PER_SECOND).min(BURST);
+        limiter.refilled = now;
+        if limiter.tokens < 1.0 {
+            limiter.dropped += 1;
+            return;
+        }
+        limiter.tokens -= 1.0;
+
+        let dropped = std::mem::take(&mut limiter.dropped);
+        if dropped > 0 {
+            self.write(time, &MachineEvent::Dropped { count: dropped });
+        }
+        self.write(time, &event);
+    }
+
+    fn write(&self, time: DateTime<Local>, event: &MachineEvent) {
+        let Ok(json) = serde_json::to_string(&Line { time, event }) else {
+            return;
+        };
+        let mut stdout = io::stdout().lock();
+        // A supervisor that went away is not the daemon's problem
+        let _ = writeln!(stdout, "{}", json).and_then(|_| stdout.flush());
+        if let Some(captured) = &self.captured
+            && let Ok(mut captured) = captured.lock()
+        {
+            captured.push(json);
+        }
+    }
+
+    /// Lines written so far (empty unless created with `capturing`)
+    pub fn lines(&self) -> Vec<String> {
+        self.captured
+            .as_ref()
+            .and_then(|captured| captured.lock().ok().map(|lines| lines.clone()))
+            .unwrap_or_default()
+    }
+}
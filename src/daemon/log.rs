@@ -0,0 +1,92 @@
+use crate::daemon::log_files::LogFiles;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Set by `--machine`, whose event stream owns stdout
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Send human-readable output to stderr from now on
+pub fn use_stderr() {
+    TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Print a human-readable line: to stdout, or to stderr after `use_stderr`
+pub fn print(line: impl Display) {
+    if TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Daemon log output. Lines go to stdout (the systemd journal), to attached
+/// consoles (`stop_it attach`), optionally to daily files and can also be
+/// captured for inspection.
+#[derive(Debug, Clone)]
+pub struct Log {
+    captured: Option<Arc<Mutex<Vec<String>>>>,
+    followers: broadcast::Sender<String>,
+    files: Option<Arc<Mutex<LogFiles>>>,
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Self {
+            captured: None,
+            followers: broadcast::channel(64).0,
+            files: None,
+        }
+    }
+}
+
+impl Log {
+    pub fn stdout() -> Self {
+        Self::default()
+    }
+
+    /// Log that also keeps every line in memory
+    pub fn capturing() -> Self {
+        Self {
+            captured: Some(Arc::new(Mutex::new(Vec::new()))),
+            ..Self::default()
+        }
+    }
+
+    /// Also write every line to `files`
+    pub fn with_files(mut self, files: LogFiles) -> Self {
+        self.files = Some(Arc::new(Mutex::new(files)));
+        self
+    }
+
+    pub fn line(&self, line: impl Into<String>) {
+        let line = line.into();
+        print(&line);
+        // No followers is fine
+        let _ = self.followers.send(line.clone());
+        if let Some(files) = &self.files
+            && let Ok(mut files) = files.lock()
+        {
+            files.write(&line);
+        }
+        if let Some(captured) = &self.captured
+            && let Ok(mut captured) = captured.lock()
+        {
+            captured.push(line);
+        }
+    }
+
+    /// Lines logged from now on
+    pub fn follow(&self) -> broadcast::Receiver<String> {
+        self.followers.subscribe()
+    }
+
+    /// Lines captured so far (empty unless created with `capturing`)
+    pub fn lines(&self) -> Vec<String> {
+        self.captured
+            .as_ref()
+            .and_then(|captured| captured.lock().ok().map(|lines| lines.clone()))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,205 @@
+use crate::config::settings::LogConfig;
+use crate::daemon::clock::Clock;
+use crate::stats::calendar::Calendar;
+use chrono::NaiveDate;
+#[cfg(feature = "gzip")]
+use flate2::Compression;
+#[cfg(feature = "gzip")]
+use flate2::write::GzEncoder;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One log file per day, `activity-2024-06-01.log`, started when the first
+/// line of a new day comes in. On every new day the files of earlier days are
+/// gzipped with `compress` and deleted after `keep_days`.
+#[derive(Debug)]
+pub struct LogFiles {
+    dir: PathBuf,
+    calendar: Calendar,
+    clock: Arc<dyn Clock>,
+    keep_days: u32,
+    compress: bool,
+    current: Option<(NaiveDate, File)>,
+    failing: bool, // report a broken log directory once, not every line
+}
+
+impl LogFiles {
+    pub fn new(
+        dir: PathBuf,
+        config: &LogConfig,
+        calendar: Calendar,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        if config.compress && !cfg!(feature = "gzip") {
+            eprintln!(
+                "Log files stay uncompressed: `compress` requires building with `--features gzip`"
+            );
+        }
+        Self {
+            dir,
+            calendar,
+            clock,
+            keep_days: config.keep_days,
+            compress: config.compress && cfg!(feature = "gzip"),
+            current: None,
+            failing: false,
+        }
+    }
+
+    /// Default location: `~/.local/share/stop_it/logs`
+    pub fn default_dir() -> PathBuf {
+        crate::config::paths::data_dir().join("logs")
+    }
+
+    pub fn write(&mut self, line: &str) {
+        match self.append(line) {
+            Ok(()) => self.failing = false,
+            Err(e) => {
+                if !self.failing {
+                    eprintln!(
+                        "Failed to write the log file in {}: {}",
+                        self.dir.display(),
+                        e
+                    );
+                    self.failing = true;
+                }
+            }
+        }
+    }
+
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        let today = self.calendar.day_of(self.clock.now());
+        if self.current.as_ref().is_none_or(|(day, _)| *day != today) {
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(file_name(today)))?;
+            self.current = Some((today, file));
+            if let Err(e) = self.tidy(today) {
+                eprintln!("Failed to tidy old log files: {}", e);
+            }
+        }
+        match self.current.as_mut() {
+            Some((_, file)) => writeln!(file, "{}", line),
+            None => Ok(()),
+        }
+    }
+
+    /// Compress and prune the files of days before `today`
+    fn tidy(&self, today: NaiveDate) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(day) = day_of_file(&path) else {
+                continue;
+            };
+            if day >= today {
+                continue;
+            }
+            if self.keep_days > 0 && (today - day).num_days() > self.keep_days as i64 {
+                fs::remove_file(&path)?;
+            } else if self.compress && path.extension().is_some_and(|ext| ext == "log") {
+                #[cfg(feature = "gzip")]
+                gzip(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_name(day: NaiveDate) -> String {
+    format!("activity-{}.log", day.format("%Y-%m-%d"))
+}
+
+/// Day of `activity-<date>.log` or `activity-<date>.log.gz`
+fn day_of_file(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    let date = name
+        .strip_prefix("activity-")?
+        .trim_end_matches(".gz")
+        .strip_suffix(".log")?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Replace `path` with `path.gz`
+#[cfg(feature = "gzip")]
+fn gzip(path: &Path) -> io::Result<()> {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".gz");
+    let mut encoder = GzEncoder::new(File::create(&compressed)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use super::*;
+    use crate::config::settings::Config;
+    use crate::daemon::clock::ManualClock;
+    use crate::daemon::log::Log;
+    use chrono::Duration;
+    use chrono::Local;
+    use chrono::TimeZone;
+
+    #[test]
+    fn log_files_rotate_daily() {
+        let dir = std::env::temp_dir().join(format!("stop_it-test-logs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut config = Config::default();
+        config.log.keep_days = 2;
+        config.log.compress = true;
+        config.day.start = chrono::NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+        let start = Local.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        let log = Log::capturing().with_files(LogFiles::new(
+            dir.clone(),
+            &config.log,
+            Calendar::new(&config.day),
+            clock.clone(),
+        ));
+        let files = || {
+            let mut names: Vec<String> = std::fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            names
+        };
+
+        log.line("first");
+        // Still the 12th until 4am
+        clock.advance(Duration::hours(18));
+        log.line("late");
+        assert_eq!(files(), ["activity-2025-03-12.log"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("activity-2025-03-12.log")).unwrap(),
+            "first\nlate\n"
+        );
+
+        // Past days are compressed, then pruned after `keep_days`
+        for _ in 0..3 {
+            clock.advance(Duration::days(1));
+            log.line("next day");
+        }
+        assert_eq!(
+            files(),
+            [
+                "activity-2025-03-13.log.gz",
+                "activity-2025-03-14.log.gz",
+                "activity-2025-03-15.log"
+            ]
+        );
+        let mut text = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(
+                std::fs::File::open(dir.join("activity-2025-03-14.log.gz")).unwrap(),
+            ),
+            &mut text,
+        )
+        .unwrap();
+        assert_eq!(text, "next day\n");
+    }
+}
@@ -0,0 +1,14 @@
+pub mod clock;
+#[cfg(feature = "debug-bundle")]
+pub mod debug_bundle;
+pub mod engine;
+pub mod events;
+pub mod log;
+pub mod log_files;
+pub mod runner;
+pub mod sandbox;
+pub mod status_file;
+pub mod systemd;
+pub mod telemetry;
+pub mod trace;
+pub mod tracker;
@@ -0,0 +1,1180 @@
+use crate::config::settings::Config;
+use crate::daemon::events::{EventStream, MachineEvent};
+use crate::daemon::log::Log;
+use crate::daemon::status_file::{Status, StatusFile};
+use crate::daemon::systemd::Watchdog;
+use crate::daemon::telemetry;
+use crate::daemon::trace::{Decision, Trace, TraceEvent};
+use crate::daemon::tracker::{DomainTracker, PauseEvent, PlanEvent};
+use crate::input::activity::{InputActivity, NoInputActivity};
+use crate::notifications::manager::{Event, NotificationAction, NotificationManager};
+use crate::notifications::sounds::Sounds;
+use crate::notifications::webhooks::{WebhookEvent, WebhookKind, Webhooks};
+use crate::pomodoro::break_lock::{self, BreakLockOutcome};
+use crate::pomodoro::break_overlay::{BreakOverlay, OverlayTimeout};
+use crate::pomodoro::pomodoro::PomodoroMode;
+use crate::stats::budget::{BudgetStatus, CategoryBudgetAlert};
+use crate::stats::filter::is_domain_or_subdomain;
+use crate::stats::format::format_duration;
+use crate::stats::limits::{LimitAlert, LimitLevel};
+use crate::stats::visits::Enforcement;
+use crate::window::churn::{ChurnChange, TitleChurn};
+use crate::window::provider::{PROVIDER_TIMEOUT, ProviderThread, WindowAction, WindowProvider};
+use crate::window::title::{domain_from_url, normalize_host};
+use crate::ws::channels::{
+    ActivityReceiver, BadgeSender, BrowserEvent, ConnectionEvent, ConnectionReceiver,
+    DaemonChannels, RequestReceiver, RequestSender, StateSender,
+};
+use crate::ws::protocol::{ClientMessage, ServerMessage, TimerState};
+use chrono::{DateTime, Local};
+use notify_rust::Urgency;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// How long to wait for a browser extension before pointing to the setup steps
+const EXTENSION_HINT_SECONDS: u64 = 120;
+
+pub type Notifier = Arc<Mutex<NotificationManager>>;
+
+pub fn notify(notifier: &Notifier, event: Event, message: &str) {
+    if let Ok(mut notifier) = notifier.lock() {
+        notifier.notify(event, message);
+    }
+}
+
+/// The daemon's moving parts: tracker, notifications and broadcasts. The
+/// caller drives it by calling `step` once per (real or simulated) second.
+pub struct Daemon {
+    tracker: Arc<Mutex<DomainTracker>>,
+    config: Config,
+    notifier: Notifier,
+    webhooks: Webhooks,
+    sounds: Option<Sounds>, // `[sounds]`, unless muted
+    window: ProviderThread, // off the runtime, a hung compositor call can't stall it
+    titles: TitleChurn,
+    input: Box<dyn InputActivity>,
+    state_tx: StateSender,
+    badge_tx: BadgeSender,
+    request_tx: RequestSender,                 // for notification buttons
+    overlay: Option<Arc<Mutex<BreakOverlay>>>, // with `[breaks] overlay`
+    log: Log,
+    events: Option<EventStream>,
+    handoff: Option<Arc<Notify>>,
+    status_file: Option<StatusFile>,
+    watchdog: Option<Watchdog>,
+    trace: Trace,
+    last_decision: Option<Decision>, // last one traced
+    recorders: usize,                // `stop_it record` clients at the last step
+    exempt: Option<String>,          // class of the exempt application with focus
+    lock_held: bool,                 // a break lock waits for the exempt application to lose focus
+    window_stalled: bool,            // the focus backend missed its last deadline
+}
+
+impl Daemon {
+    pub fn new(
+        config: Config,
+        tracker: DomainTracker,
+        notifier: Notifier,
+        window: Box<dyn WindowProvider>,
+        channels: &DaemonChannels,
+        log: Log,
+    ) -> Self {
+        Self {
+            tracker: Arc::new(Mutex::new(tracker)),
+            titles: TitleChurn::new(&config.titles),
+            input: Box::new(NoInputActivity),
+            webhooks: Webhooks::new(&config.webhooks),
+            sounds: Sounds::new(&config.sounds),
+            notifier,
+            window: ProviderThread::spawn(window, PROVIDER_TIMEOUT),
+            state_tx: channels.state_tx.clone(),
+            badge_tx: channels.badge_tx.clone(),
+            request_tx: channels.request_tx.clone(),
+            overlay: config
+                .breaks
+                .overlay
+                .then(|| Arc::new(Mutex::new(BreakOverlay::new()))),
+            config,
+            log,
+            events: None,
+            handoff: None,
+            status_file: None,
+            watchdog: None,
+            trace: Trace::new(),
+            last_decision: None,
+            recorders: 0,
+            exempt: None,
+            lock_held: false,
+            window_stalled: false,
+        }
+    }
+
+    /// Sample keyboard/mouse activity to pause tracking while idle
+    pub fn with_input(mut self, input: Box<dyn InputActivity>) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Report mode changes, budget and focus alerts and extension
+    /// connections on `events`
+    pub fn with_events(mut self, events: EventStream) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Report provider outputs, browser messages, tracker decisions and
+    /// errors on `trace`, for `stop_it record` and `stop_it inspect`
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Write the timer state to `status_file` every step, for scripts
+    pub fn with_status_file(mut self, status_file: StatusFile) -> Self {
+        self.status_file = Some(status_file);
+        self
+    }
+
+    /// Keep systemd's watchdog fed every step, so a daemon that stopped
+    /// stepping gets restarted
+    pub fn with_watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Accept `handoff` requests, for a daemon running in the foreground
+    /// that `stop_it daemonize` can replace with a background one
+    pub fn with_handoff(mut self) -> Self {
+        self.handoff = Some(Arc::new(Notify::new()));
+        self
+    }
+
+    /// Resolves once a client asked for a handoff and the tracker saved its
+    /// state; never without `with_handoff`
+    pub fn handoff_requested(&self) -> impl Future<Output = ()> + use<> {
+        let handoff = self.handoff.clone();
+        async move {
+            match handoff {
+                Some(handoff) => handoff.notified().await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    pub fn tracker(&self) -> Arc<Mutex<DomainTracker>> {
+        Arc::clone(&self.tracker)
+    }
+
+    /// Spawn the tasks handling browser activity, client requests and
+    /// extension connections
+    pub fn spawn_handlers(
+        &self,
+        activity_rx: ActivityReceiver,
+        request_rx: RequestReceiver,
+        connection_rx: ConnectionReceiver,
+    ) {
+        tokio::spawn(watch_extension_connections(
+            connection_rx,
+            self.tracker(),
+            Arc::clone(&self.notifier),
+            self.events.clone(),
+            self.log.clone(),
+        ));
+        tokio::spawn(handle_requests(
+            request_rx,
+            self.tracker(),
+            self.handoff.clone(),
+            self.state_tx.clone(),
+            self.log.clone(),
+        ));
+        tokio::spawn(handle_activity(
+            activity_rx,
+            self.tracker(),
+            self.overlay.clone(),
+            Arc::clone(&self.notifier),
+            self.trace.clone(),
+            self.log.clone(),
+        ));
+    }
+
+    /// One second of daemon time: credit the current domain, broadcast the
+    /// timer state and switch sessions when due
+    pub fn step(&mut self) {
+        let _span = telemetry::span("daemon.step");
+        if let Some(watchdog) = self.watchdog.as_mut() {
+            watchdog.ping();
+        }
+        let window = self.window.active_window();
+        let input = self.input.events();
+        let Ok(mut tracker) = self.tracker.lock() else {
+            return;
+        };
+        if self.window.is_answering() == self.window_stalled {
+            self.window_stalled = !self.window_stalled;
+            self.log.line(format!(
+                "[{}] {}",
+                tracker.now().format("%H:%M:%S"),
+                if self.window_stalled {
+                    format!(
+                        "The focus backend didn't answer within {} ms, keeping the last known window",
+                        PROVIDER_TIMEOUT.as_millis()
+                    )
+                } else {
+                    "The focus backend answers again".to_string()
+                }
+            ));
+        }
+        if self.trace.is_recording() {
+            self.trace.emit(|| TraceEvent::Step {
+                at: tracker.now(),
+                window: window.clone(),
+                input,
+            });
+        }
+        tracker.record_input(input);
+        let notifier = &self.notifier;
+        let config = &self.config;
+
+        // Exempt applications hold back notifications and break locks
+        let exempt = window
+            .as_ref()
+            .map(|window| window.class.clone())
+            .filter(|class| config.exemptions.matches(class));
+        if exempt != self.exempt {
+            let time = tracker.now().format("%H:%M:%S");
+            if let Ok(mut notifier) = notifier.lock() {
+                match (&exempt, &self.exempt) {
+                    (Some(class), _) => {
+                        notifier.hold();
+                        self.log.line(format!(
+                            "[{}] 🎬 {} has focus: holding notifications",
+                            time, class
+                        ));
+                    }
+                    (None, Some(class)) => {
+                        let shown = notifier.release();
+                        self.log.line(format!(
+                            "[{}] 🎬 {} lost focus: showing {} held notification(s)",
+                            time, class, shown
+                        ));
+                    }
+                    (None, None) => {}
+                }
+            }
+            self.exempt = exempt;
+            if self.exempt.is_none() && std::mem::take(&mut self.lock_held) {
+                self.spawn_break_lock(&tracker);
+            }
+        }
+
+        let window = window.map(|window| {
+            let (window, change) = self.titles.filter(window, tracker.now());
+            match change {
+                Some(ChurnChange::Frozen(class)) => self.log.line(format!(
+                    "Title of {} changes too often, treating it as one activity",
+                    class
+                )),
+                Some(ChurnChange::Calmed(class)) => {
+                    self.log.line(format!("Title of {} is stable again", class))
+                }
+                None => {}
+            }
+            window
+        });
+        tracker.focus_window(window);
+        // Update time for current domain
+        tracker.tick();
+
+        let pause = match tracker.check_pause() {
+            Some(PauseEvent::Warning { remaining }) => Some(format!(
+                "Still paused: the timer resumes on its own in {}.",
+                format_duration(remaining)
+            )),
+            Some(PauseEvent::AutoResumed { paused_for }) => Some(format!(
+                "Paused for {}: resumed the {} session.",
+                format_duration(paused_for),
+                tracker.mode().as_str()
+            )),
+            None => None,
+        };
+        if let Some(message) = pause {
+            self.log.line(format!("\n⏸ {}", message));
+            if !tracker.is_off_day() {
+                notify(notifier, Event::Pause, &message);
+            }
+        }
+
+        // Broadcast timer state to connected clients (no receivers is fine)
+        let state = tracker.timer_state();
+        let badge = state.badge();
+        self.badge_tx.send_if_modified(|current| {
+            let changed = current.as_ref() != Some(&badge);
+            *current = Some(badge);
+            changed
+        });
+        if let Some(budget) = exceeded_budget(&state) {
+            let over_by = -budget.remaining.unwrap_or(0);
+            let message = format!(
+                "You're over your {} budget by {}.",
+                budget.target,
+                format_duration(over_by)
+            );
+            let event = Event::BudgetExceeded(budget.target.clone());
+            if let Ok(mut notifier) = notifier.lock()
+                && notifier.notify(event, &message)
+            {
+                self.log.line(format!("\n⏳ {}", message));
+                self.webhooks.send(
+                    &webhook_event(WebhookKind::BudgetExceeded, &state, tracker.now(), &message)
+                        .with("domain", budget.target.clone())
+                        .with("over_by_minutes", over_by / 60),
+                );
+                if let Some(events) = &self.events {
+                    events.emit(
+                        tracker.now(),
+                        MachineEvent::BudgetExceeded {
+                            domain: budget.target.clone(),
+                            over_by_seconds: over_by,
+                        },
+                    );
+                }
+            }
+        }
+        if let Some(alert) = tracker.check_category_budget() {
+            let (message, urgency) = category_budget_warning(&alert);
+            self.log.line(format!("\n⏳ {}", message));
+            if let Ok(mut notifier) = notifier.lock() {
+                notifier.notify_with_urgency(
+                    Event::CategoryBudget(alert.category.clone()),
+                    &message,
+                    urgency,
+                );
+            }
+            self.webhooks.send(
+                &webhook_event(WebhookKind::BudgetExceeded, &state, tracker.now(), &message)
+                    .with("category", alert.category.clone())
+                    .with("over_by_minutes", alert.over_by / 60),
+            );
+            if let Some(events) = &self.events {
+                events.emit(
+                    tracker.now(),
+                    MachineEvent::CategoryBudgetExceeded {
+                        category: alert.category,
+                        over_by_seconds: alert.over_by,
+                    },
+                );
+            }
+        }
+        if let Some(alert) = tracker.check_limit() {
+            let (message, urgency) = limit_warning(&alert);
+            self.log.line(format!("\n⏳ {}", message));
+            if let Ok(mut notifier) = notifier.lock() {
+                notifier.notify_with_urgency(
+                    Event::DomainLimit(alert.domain.clone()),
+                    &message,
+                    urgency,
+                );
+            }
+            let event = match alert.level {
+                LimitLevel::Warning => MachineEvent::LimitWarning {
+                    domain: alert.domain.clone(),
+                    used_seconds: alert.used,
+                    limit_seconds: alert.limit,
+                },
+                LimitLevel::Reached => {
+                    self.webhooks.send(
+                        &webhook_event(WebhookKind::LimitReached, &state, tracker.now(), &message)
+                            .with("domain", alert.domain.clone())
+                            .with("limit_minutes", alert.limit / 60),
+                    );
+                    MachineEvent::LimitReached {
+                        domain: alert.domain.clone(),
+                        limit_seconds: alert.limit,
+                    }
+                }
+            };
+            if let Some(events) = &self.events {
+                events.emit(tracker.now(), event);
+            }
+        }
+        let _ = self.state_tx.send(ServerMessage::TimerState(state));
+
+        if let Some(ratio) = tracker.check_focus() {
+            let message = format!(
+                "You've been drifting: {:.0}% of the last {} minutes went to distractions.",
+                ratio * 100.0,
+                config.focus_alerts.window_minutes
+            );
+            self.log.line(format!("\n⚠️  {}", message));
+            notify(notifier, Event::FocusAlert, &message);
+            self.webhooks.send(
+                &webhook_event(
+                    WebhookKind::FocusAlert,
+                    &tracker.timer_state(),
+                    tracker.now(),
+                    &message,
+                )
+                .with("distraction_percent", (ratio * 100.0).round() as i64),
+            );
+            if let Some(events) = &self.events {
+                events.emit(
+                    tracker.now(),
+                    MachineEvent::FocusAlert {
+                        distraction_percent: (ratio * 100.0).round() as u32,
+                    },
+                );
+            }
+        }
+
+        // Acted on once the tracker is unlocked
+        let blocked_stay = tracker.check_blocked_stay();
+
+        if let Some(sites) = tracker.check_triage() {
+            let listed: Vec<String> = sites
+                .iter()
+                .take(3)
+                .map(|(domain, seconds)| format!("{} {}", domain, format_duration(*seconds)))
+                .collect();
+            let message = format!(
+                "Uncategorized last week: {}{} - categorize with `stop_it triage`?",
+                listed.join(", "),
+                if sites.len() > 3 {
+                    format!(" and {} more", sites.len() - 3)
+                } else {
+                    String::new()
+                }
+            );
+            self.log.line(format!("\n🗂  {}", message));
+            if !tracker.is_off_day() {
+                notify(notifier, Event::Triage, &message);
+            }
+        }
+
+        match tracker.check_plan() {
+            Some(PlanEvent::Started(block)) => {
+                let message = format!(
+                    "Planned block '{}' starts now: {}-minute work session.",
+                    block.label,
+                    config.pomodoro.work / 60
+                );
+                self.log.line(format!(
+                    "\n📋 [{}] {}",
+                    tracker.now().format("%H:%M:%S"),
+                    message
+                ));
+                if !tracker.is_off_day() {
+                    notify(notifier, Event::ModeSwitch, &message);
+                }
+            }
+            Some(PlanEvent::Ended(block)) => {
+                self.log.line(format!(
+                    "\n📋 [{}] Planned block {} ended",
+                    tracker.now().format("%H:%M:%S"),
+                    block.describe()
+                ));
+            }
+            None => {}
+        }
+
+        if tracker.check_waiting() {
+            let message = format!(
+                "{} is over! Start the next {}-minute work session when you're back.",
+                match tracker.mode() {
+                    PomodoroMode::LongBreak => "Long break",
+                    _ => "Break",
+                },
+                config.pomodoro.work / 60
+            );
+            self.log.line(format!("\n🔔 {}", message));
+            if tracker.is_off_day() {
+                self.log.line("(off-day: notification suppressed)");
+            } else if let Ok(mut notifier) = notifier.lock() {
+                notifier.close(&Event::CycleComplete);
+                notifier.notify_with_actions(
+                    Event::ModeSwitch,
+                    &message,
+                    vec![self.start_button()],
+                );
+            }
+        }
+
+        if let Some(seconds) = tracker.check_snooze()
+            && !tracker.is_off_day()
+            && let Ok(mut notifier) = notifier.lock()
+        {
+            let message = format!(
+                "Break put off: {} more of work, until {}.",
+                format_duration(seconds),
+                (tracker.now() + chrono::Duration::seconds(tracker.remaining_seconds()))
+                    .format("%H:%M")
+            );
+            // The break notification's buttons are about a break that is gone
+            notifier.close(&Event::CycleComplete);
+            notifier.notify_with_actions(
+                Event::ModeSwitch,
+                &message,
+                vec![self.skip_button("Start break")],
+            );
+        }
+
+        // Check if should switch Pomodoro mode
+        if tracker.should_switch_mode() {
+            let next = tracker.next_mode();
+            // An adapted break is announced with its length, a suggestion after it
+            let suggested = tracker.suggested_break();
+            let (break_minutes, suggestion) = {
+                let configured = match next {
+                    PomodoroMode::LongBreak => config.pomodoro.long_break,
+                    _ => config.pomodoro.break_,
+                };
+                match suggested {
+                    Some(suggested) if config.breaks.auto_accept => (suggested / 60, String::new()),
+                    Some(suggested) if suggested > configured => (
+                        configured / 60,
+                        format!(
+                            " This session earned {} minutes if you can spare them.",
+                            suggested / 60
+                        ),
+                    ),
+                    _ => (configured / 60, String::new()),
+                }
+            };
+            let message = match (tracker.mode(), next) {
+                (PomodoroMode::Work, PomodoroMode::Work) if tracker.is_abandoned() => format!(
+                    "Work block abandoned: idle for {} of it. Continuous mode: the next {}-minute block starts now.",
+                    format_duration(tracker.idle_seconds()),
+                    config.pomodoro.work / 60
+                ),
+                (PomodoroMode::Work, PomodoroMode::Work) => format!(
+                    "Work block complete! Continuous mode: the next {}-minute block starts now.",
+                    config.pomodoro.work / 60
+                ),
+                (PomodoroMode::Work, _) if tracker.is_abandoned() => format!(
+                    "Work session abandoned: idle for {} of it. Time for a {}-minute break.",
+                    format_duration(tracker.idle_seconds()),
+                    config.pomodoro.break_ / 60
+                ),
+                (PomodoroMode::Work, PomodoroMode::LongBreak) => format!(
+                    "Cycle complete: {} work sessions done! Time for a {}-minute long break.{}",
+                    tracker.cycle() + 1,
+                    break_minutes,
+                    suggestion
+                ),
+                (PomodoroMode::Work, _) => format!(
+                    "Work session complete! Time for a {}-minute break.{}",
+                    break_minutes, suggestion
+                ),
+                (PomodoroMode::Break, _) => format!(
+                    "Break is over! Starting {}-minute work session.",
+                    config.pomodoro.work / 60
+                ),
+                (PomodoroMode::LongBreak, _) => format!(
+                    "Long break is over! Starting a new cycle with a {}-minute work session.",
+                    config.pomodoro.work / 60
+                ),
+            };
+
+            self.log.line(format!("\n🔔 {}", message));
+
+            let event = if next == PomodoroMode::LongBreak {
+                Event::CycleComplete
+            } else {
+                Event::ModeSwitch
+            };
+            // Buttons left on the other notification would act on the new phase
+            if let Ok(mut notifier) = notifier.lock() {
+                notifier.close(match event {
+                    Event::CycleComplete => &Event::ModeSwitch,
+                    _ => &Event::CycleComplete,
+                });
+            }
+            if tracker.is_off_day() {
+                self.log.line("(off-day: notification suppressed)");
+            } else if next.is_break()
+                && let Ok(mut notifier) = notifier.lock()
+            {
+                notifier.notify_with_actions(
+                    event,
+                    &message,
+                    vec![self.skip_button("Skip"), self.snooze_button()],
+                );
+            } else {
+                notify(notifier, event, &message);
+            }
+
+            if tracker.mode() == PomodoroMode::Work {
+                tracker.print_stats();
+            }
+
+            let completed = match tracker.mode() {
+                PomodoroMode::Work => WebhookKind::WorkCompleted,
+                PomodoroMode::Break | PomodoroMode::LongBreak => WebhookKind::BreakCompleted,
+            };
+            let minutes = tracker.get_target_seconds() / 60;
+            if let Some(summary) = tracker.switch_mode() {
+                let _ = self.state_tx.send(ServerMessage::SessionSummary(summary));
+            }
+            let state = tracker.timer_state();
+            self.webhooks.send(
+                &webhook_event(completed, &state, tracker.now(), &message)
+                    .with("duration_minutes", minutes),
+            );
+            if let Some(events) = &self.events {
+                events.emit(
+                    tracker.now(),
+                    MachineEvent::ModeChanged {
+                        mode: state.mode,
+                        cycle: state.cycle,
+                        pomodoros_today: state.pomodoros_today,
+                    },
+                );
+            }
+
+            // Held back like notifications, e.g. while a DAW has focus
+            if let Some(sounds) = &self.sounds
+                && !tracker.is_off_day()
+                && self.exempt.is_none()
+            {
+                sounds.play(tracker.mode());
+            }
+
+            if config.breaks.lock && tracker.mode().is_break() && !tracker.is_off_day() {
+                if let Some(class) = &self.exempt {
+                    self.log
+                        .line(format!("(break lock waits for {} to lose focus)", class));
+                    self.lock_held = true;
+                } else {
+                    self.spawn_break_lock(&tracker);
+                }
+            }
+        }
+
+        if config.breaks.countdown {
+            if tracker.mode().is_break() && !tracker.is_waiting() && !tracker.is_off_day() {
+                let minutes = (tracker.remaining_seconds() + 59) / 60;
+                let message = format!("☕ Break: {} min left", minutes);
+                notify(notifier, Event::BreakCountdown, &message);
+            } else if let Ok(mut notifier) = notifier.lock() {
+                notifier.close(&Event::BreakCountdown);
+            }
+        }
+
+        // The extension greys out pages for the break, confirming each change
+        if let Some(overlay) = &self.overlay
+            && let Ok(mut overlay) = overlay.lock()
+        {
+            let now = tracker.now();
+            let on_break = tracker.mode().is_break() && !tracker.is_off_day();
+            let message = if on_break && !overlay.is_shown() {
+                let ends_at = now + chrono::Duration::seconds(tracker.remaining_seconds());
+                Some(overlay.start(now, ends_at))
+            } else if !on_break && overlay.is_shown() {
+                Some(overlay.end(now))
+            } else {
+                match overlay.check(now) {
+                    Some(OverlayTimeout::Resend(message)) => Some(message),
+                    Some(OverlayTimeout::GaveUp { starting }) => {
+                        self.log.line(format!(
+                            "[{}] 🧩 No browser confirmed the {}",
+                            now.format("%H:%M:%S"),
+                            if starting {
+                                "break overlay: pages stay as they are"
+                            } else {
+                                "end of the break: pages may still be greyed out"
+                            }
+                        ));
+                        None
+                    }
+                    None => None,
+                }
+            };
+            if let Some(message) = message {
+                let _ = self.state_tx.send(message);
+            }
+        }
+
+        // A new recording starts with the current decision
+        let recorders = self.trace.recorders();
+        if recorders > self.recorders {
+            self.last_decision = None;
+        }
+        self.recorders = recorders;
+        if self.trace.is_active() {
+            let state = tracker.timer_state();
+            let decision = Decision {
+                mode: state.mode,
+                domain: state.current_domain,
+                category: state.current_category,
+                idle: tracker.is_idle(),
+                paused: state.paused,
+            };
+            if self.last_decision.as_ref() != Some(&decision) {
+                self.last_decision = Some(decision.clone());
+                self.trace.emit(|| TraceEvent::Decision {
+                    at: tracker.now(),
+                    decision,
+                });
+            }
+        } else {
+            self.last_decision = None;
+        }
+
+        if let Some(status_file) = self.status_file.as_mut() {
+            status_file.write(&Status::new(&tracker.timer_state(), tracker.now()));
+        }
+
+        // The focus backend may take its time, the servers shouldn't wait on it
+        drop(tracker);
+
+        if let Some(target) = blocked_stay {
+            let action = config.focus_alerts.window_action;
+            let done = match action {
+                WindowAction::Away => "moved to the previous workspace",
+                WindowAction::Close => "closed the window",
+                WindowAction::None => "left the window alone",
+            };
+            match self.window.act_on_focused(action) {
+                Ok(()) => {
+                    let message = format!(
+                        "Still on {} after {}: {}.",
+                        target,
+                        format_duration(config.focus_alerts.grace),
+                        done
+                    );
+                    self.log.line(format!("🚫 {}", message));
+                    notify(notifier, Event::BlockedVisit(target), &message);
+                }
+                Err(e) => self.log.line(format!(
+                    "Cannot act on the window showing {}: {}",
+                    target, e
+                )),
+            }
+        }
+    }
+
+    /// Button sending `stop_it skip` to the daemon: ends a break, or starts
+    /// one from a work session
+    fn skip_button(&self, label: &str) -> NotificationAction {
+        let request_tx = self.request_tx.clone();
+        NotificationAction::new(label, move || {
+            let (reply_tx, _) = tokio::sync::oneshot::channel();
+            let _ = request_tx.send((ClientMessage::Skip, reply_tx));
+        })
+    }
+
+    /// Button on break notifications sending `stop_it snooze` to the daemon
+    fn snooze_button(&self) -> NotificationAction {
+        let request_tx = self.request_tx.clone();
+        NotificationAction::new(
+            format!("Snooze {} min", (self.config.breaks.snooze + 59) / 60),
+            move || {
+                let (reply_tx, _) = tokio::sync::oneshot::channel();
+                let _ = request_tx.send((ClientMessage::Snooze { minutes: None }, reply_tx));
+            },
+        )
+    }
+
+    /// Button on the notification of a break that ran out, sending
+    /// `stop_it start` to the daemon
+    fn start_button(&self) -> NotificationAction {
+        let request_tx = self.request_tx.clone();
+        NotificationAction::new("Start", move || {
+            let (reply_tx, _) = tokio::sync::oneshot::channel();
+            let _ = request_tx.send((ClientMessage::Start, reply_tx));
+        })
+    }
+
+    /// Lock the screen until the current break ends, if it hasn't already
+    fn spawn_break_lock(&self, tracker: &DomainTracker) {
+        if !tracker.mode().is_break() || tracker.remaining_seconds() == 0 {
+            return;
+        }
+        let break_end =
+            tracker.mode_start() + chrono::Duration::seconds(tracker.get_target_seconds());
+        tokio::spawn(enforce_break(
+            self.config.breaks.locker.clone(),
+            break_end,
+            Arc::clone(&self.notifier),
+            self.log.clone(),
+            self.trace.clone(),
+        ));
+    }
+}
+
+/// Answer stats queries from clients, and handoff requests when enabled.
+/// Category changes are broadcast to every client.
+async fn handle_requests(
+    mut request_rx: RequestReceiver,
+    tracker: Arc<Mutex<DomainTracker>>,
+    handoff: Option<Arc<Notify>>,
+    state_tx: StateSender,
+    log: Log,
+) {
+    while let Some((request, reply_tx)) = request_rx.recv().await {
+        let Ok(mut tracker) = tracker.lock() else {
+            let _ = reply_tx.send(ServerMessage::Error {
+                message: "Tracker unavailable".to_string(),
+            });
+            continue;
+        };
+        let reply = match (request, &handoff) {
+            (ClientMessage::Handoff, Some(handoff)) => {
+                // Segments are written now; the phase was saved when it started
+                tracker.flush();
+                log.line(format!(
+                    "[{}] Handing off to a background daemon",
+                    tracker.now().format("%H:%M:%S")
+                ));
+                handoff.notify_one();
+                ServerMessage::Ack {
+                    message: format!(
+                        "Handed off: {} session, {} left",
+                        tracker.mode().as_str(),
+                        format_duration(tracker.remaining_seconds())
+                    ),
+                }
+            }
+            (ClientMessage::Handoff, None) => ServerMessage::Error {
+                message: "The daemon is not running in the foreground".to_string(),
+            },
+            (ClientMessage::Categorize { domain, category }, _) => {
+                let reply = tracker.handle_request(ClientMessage::Categorize {
+                    domain: domain.clone(),
+                    category: category.clone(),
+                });
+                if matches!(reply, ServerMessage::Ack { .. }) {
+                    let _ = state_tx.send(ServerMessage::CategoryChanged {
+                        domain: domain.trim().to_lowercase(),
+                        category: category.trim().to_string(),
+                    });
+                    let _ = state_tx.send(ServerMessage::TimerState(tracker.timer_state()));
+                }
+                reply
+            }
+            (request, _) => tracker.handle_request(request),
+        };
+        let _ = reply_tx.send(reply);
+    }
+}
+
+/// Apply tab updates and closes from the browser extension
+async fn handle_activity(
+    mut activity_rx: ActivityReceiver,
+    tracker: Arc<Mutex<DomainTracker>>,
+    overlay: Option<Arc<Mutex<BreakOverlay>>>,
+    notifier: Notifier,
+    trace: Trace,
+    log: Log,
+) {
+    while let Some(event) = activity_rx.recv().await {
+        let _span = telemetry::span("daemon.activity");
+        let Ok(mut tracker) = tracker.lock() else {
+            continue;
+        };
+        trace.emit(|| TraceEvent::Browser {
+            at: tracker.now(),
+            message: match &event {
+                BrowserEvent::TabUpdate(message) => ClientMessage::TabUpdate(message.clone()),
+                BrowserEvent::TabClosed(message) => ClientMessage::TabClosed(message.clone()),
+                BrowserEvent::WindowClosed(message) => ClientMessage::WindowClosed(message.clone()),
+                BrowserEvent::Heartbeat(message) => ClientMessage::Heartbeat(message.clone()),
+                BrowserEvent::BreakAck { id } => ClientMessage::BreakAck { id: *id },
+            },
+        });
+        match event {
+            BrowserEvent::TabUpdate(message) => {
+                let domain = match message.domain.as_deref() {
+                    Some(domain) => Some(normalize_host(domain)),
+                    // Fallback: older extensions only send the URL
+                    None => domain_from_url(&message.url),
+                };
+
+                let switched = domain.as_deref() != tracker.current_domain();
+                // Pages past an allowed one may not be allowed, e.g. leaving a playlist
+                if (switched || tracker.on_allowed_url())
+                    && let Some(ref d) = domain
+                {
+                    if switched {
+                        log.line(format!(
+                            "[{}] Browser switched to: {}",
+                            tracker.now().format("%H:%M:%S"),
+                            d
+                        ));
+                    }
+
+                    match tracker.enforce_visit(d, &message.url, message.category.as_deref()) {
+                        Some(Enforcement::Block { target, visits }) => {
+                            let (message, urgency) = blocked_visit_warning(&target, visits);
+                            log.line(format!("🚫 {}", message));
+                            if let Ok(mut notifier) = notifier.lock() {
+                                notifier.notify_with_urgency(
+                                    Event::BlockedVisit(target),
+                                    &message,
+                                    urgency,
+                                );
+                            }
+                        }
+                        Some(Enforcement::Nag { category }) => {
+                            let message =
+                                format!("{} is {} - is it part of the work session?", d, category);
+                            log.line(format!("👀 {}", message));
+                            notify(&notifier, Event::Nag(category), &message);
+                        }
+                        Some(Enforcement::Log { category }) => {
+                            log.line(format!("📝 {} visit during work: {}", category, d));
+                        }
+                        Some(Enforcement::Allowed { pattern }) if switched => {
+                            log.line(format!("✅ {} allowed by the exception {}", d, pattern));
+                        }
+                        Some(Enforcement::Allowed { .. }) | None => {}
+                    }
+                }
+
+                tracker.update(domain, message.category.clone(), message.profile.clone());
+            }
+            BrowserEvent::TabClosed(message) => {
+                let closed = message.domain.as_deref().map(normalize_host);
+                if let Some(domain) =
+                    tracker.close_browser_tab(closed.as_deref(), message.profile.as_deref())
+                {
+                    log.line(format!(
+                        "[{}] Browser tab closed: {}",
+                        tracker.now().format("%H:%M:%S"),
+                        domain
+                    ));
+                }
+            }
+            BrowserEvent::WindowClosed(message) => {
+                let closed = message.domain.as_deref().map(normalize_host);
+                if let Some(domain) =
+                    tracker.close_browser_tab(closed.as_deref(), message.profile.as_deref())
+                {
+                    log.line(format!(
+                        "[{}] Browser {}: stopped tracking {}",
+                        tracker.now().format("%H:%M:%S"),
+                        if message.browser_exiting {
+                            "shut down"
+                        } else {
+                            "window closed"
+                        },
+                        domain
+                    ));
+                }
+            }
+            BrowserEvent::Heartbeat(message) => {
+                tracker.heartbeat(message.focused, message.idle, message.profile.as_deref());
+            }
+            BrowserEvent::BreakAck { id } => {
+                if let Some(overlay) = &overlay
+                    && let Ok(mut overlay) = overlay.lock()
+                {
+                    overlay.acknowledge(id);
+                }
+            }
+        }
+    }
+}
+
+/// Webhook payload with the timer values every event carries
+fn webhook_event(
+    kind: WebhookKind,
+    state: &TimerState,
+    now: DateTime<Local>,
+    message: &str,
+) -> WebhookEvent {
+    WebhookEvent::new(kind, now, message)
+        .with("mode", state.mode.clone())
+        .with("pomodoros_today", state.pomodoros_today)
+        .with("daily_goal", state.daily_goal)
+}
+
+/// Budget for the domain being browsed, when it is used up
+fn exceeded_budget(state: &TimerState) -> Option<&BudgetStatus> {
+    let domain = state.current_domain.as_deref()?;
+    state.budgets.iter().find(|budget| {
+        !budget.category
+            && !budget.off_day
+            && budget.remaining.is_some_and(|remaining| remaining < 0)
+            && is_domain_or_subdomain(domain, &budget.target)
+    })
+}
+
+/// Harsher wording and urgency the further a category goes over its budget
+fn category_budget_warning(alert: &CategoryBudgetAlert) -> (String, Urgency) {
+    match alert.level {
+        1 => (
+            format!("You've used up your {} budget for today.", alert.category),
+            Urgency::Normal,
+        ),
+        2 => (
+            format!(
+                "Still on {}: {} over budget.",
+                alert.category,
+                format_duration(alert.over_by)
+            ),
+            Urgency::Normal,
+        ),
+        _ => (
+            format!(
+                "{} is {} over budget. Time to stop!",
+                alert.category,
+                format_duration(alert.over_by)
+            ),
+            Urgency::Critical,
+        ),
+    }
+}
+
+/// A gentle heads-up close to a daily limit, a critical one when it is used up
+fn limit_warning(alert: &LimitAlert) -> (String, Urgency) {
+    match alert.level {
+        LimitLevel::Warning => (
+            format!(
+                "{} left of today's {} on {}.",
+                format_duration(alert.limit - alert.used),
+                format_duration(alert.limit),
+                alert.domain
+            ),
+            Urgency::Normal,
+        ),
+        LimitLevel::Reached => (
+            format!(
+                "Daily limit reached: {} on {} today. Time to stop!",
+                format_duration(alert.limit),
+                alert.domain
+            ),
+            Urgency::Critical,
+        ),
+    }
+}
+
+/// Harsher wording and urgency for each repeated visit to a blocked domain
+fn blocked_visit_warning(target: &str, visits: u32) -> (String, Urgency) {
+    match visits {
+        1 => (
+            format!("{} is blocked during work sessions.", target),
+            Urgency::Normal,
+        ),
+        2 => (
+            format!(
+                "Back on {} already? That's the second visit this session.",
+                target
+            ),
+            Urgency::Normal,
+        ),
+        n => (
+            format!(
+                "{} again ({} visits this session). Close it and get back to work!",
+                target, n
+            ),
+            Urgency::Critical,
+        ),
+    }
+}
+
+/// Log extension connection changes, stop tracking when the last extension
+/// goes away in browser-only mode, and point to the setup instructions when
+/// no extension shows up shortly after start
+async fn watch_extension_connections(
+    mut connections: ConnectionReceiver,
+    tracker: Arc<Mutex<DomainTracker>>,
+    notifier: Notifier,
+    events: Option<EventStream>,
+    log: Log,
+) {
+    let first = tokio::time::timeout(
+        Duration::from_secs(EXTENSION_HINT_SECONDS),
+        connections.recv(),
+    )
+    .await;
+    let mut next = match first {
+        Ok(event) => event,
+        Err(_) => {
+            let message = "No browser extension has connected yet. Build it with \
+                           `npm run build` in browser-extension/ and load the dist folder \
+                           as an unpacked extension; `stop_it doctor` checks the setup.";
+            log.line(format!("🧩 {}", message));
+            notify(&notifier, Event::Setup, message);
+            connections.recv().await
+        }
+    };
+
+    let mut connected = 0;
+    while let Some(event) = next {
+        let Ok(now) = tracker.lock().map(|tracker| tracker.now()) else {
+            break;
+        };
+        let time = now.format("%H:%M:%S");
+        let machine = match event {
+            ConnectionEvent::ExtensionConnected(addr) => {
+                connected += 1;
+                log.line(format!(
+                    "[{}] 🧩 Browser extension connected from {} ({} active)",
+                    time, addr, connected
+                ));
+                MachineEvent::ExtensionConnected {
+                    address: addr.to_string(),
+                    active: connected,
+                }
+            }
+            ConnectionEvent::ExtensionDisconnected(addr) => {
+                connected = connected.saturating_sub(1);
+                log.line(format!(
+                    "[{}] 🧩 Browser extension at {} disconnected ({} active)",
+                    time, addr, connected
+                ));
+                if connected == 0 {
+                    log.line(format!(
+                        "[{}] No browser extension connected: activity is not tracked",
+                        time
+                    ));
+                    if let Ok(mut tracker) = tracker.lock() {
+                        tracker.browser_disconnected();
+                    }
+                }
+                MachineEvent::ExtensionDisconnected {
+                    address: addr.to_string(),
+                    active: connected,
+                }
+            }
+        };
+        if let Ok(mut tracker) = tracker.lock() {
+            tracker.set_extensions(connected);
+        }
+        if let Some(events) = &events {
+            events.emit(now, machine);
+        }
+        next = connections.recv().await;
+    }
+}
+
+/// Lock the screen for the break and complain when it is unlocked early
+async fn enforce_break(
+    locker: String,
+    break_end: DateTime<Local>,
+    notifier: Notifier,
+    log: Log,
+    trace: Trace,
+) {
+    match break_lock::lock_for_break(&locker, break_end).await {
+        Ok(BreakLockOutcome::Completed) => {}
+        Ok(BreakLockOutcome::SkippedByUnlock { remaining_seconds }) => {
+            log.line(format!(
+                "[{}] Break skipped by unlock ({}s early)",
+                Local::now().format("%H:%M:%S"),
+                remaining_seconds
+            ));
+            let message = format!(
+                "Unlocked {} minutes before the break ends. Step away from the screen!",
+                (remaining_seconds + 59) / 60
+            );
+            notify(&notifier, Event::BreakSkipped, &message);
+        }
+        Err(e) => {
+            let message = format!("Failed to lock screen for break: {}", e);
+            eprintln!("{}", message);
+            trace.error(Local::now(), message);
+        }
+    }
+}
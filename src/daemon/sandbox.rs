@@ -0,0 +1,113 @@
+use crate::config::paths;
+use crate::config::settings::Config;
+use crate::input::activity::InputSource;
+use crate::storage::backend::storage_path;
+use std::path::PathBuf;
+
+/// Directories the daemon writes to: the data directory, the runtime
+/// directory and wherever a file-based storage backend lives
+pub fn writable_dirs(config: &Config) -> Vec<PathBuf> {
+    let data = paths::data_dir();
+    let mut dirs = vec![data.clone(), paths::runtime_dir()];
+    if let Some(parent) = storage_path(config.storage.url.as_deref())
+        .as_deref()
+        .and_then(|path| path.parent())
+        && !parent.starts_with(&data)
+    {
+        dirs.push(parent.to_path_buf());
+    }
+    dirs
+}
+
+/// Startup checks for `--harden`: make the directories the daemon writes to
+/// private and refuse a config other users could edit
+#[cfg(unix)]
+pub fn harden(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let config_file = paths::config_file();
+    if let Ok(meta) = fs::metadata(&config_file)
+        && meta.permissions().mode() & 0o022 != 0
+    {
+        return Err(format!(
+            "{} is writable by other users (fix with `chmod go-w {}`)",
+            config_file.display(),
+            config_file.display()
+        )
+        .into());
+    }
+
+    for dir in writable_dirs(config) {
+        fs::create_dir_all(&dir)?;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn harden(_config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--harden relies on Unix file permissions, which this platform doesn't have".into())
+}
+
+/// systemd sandboxing directives allowing only what the enabled features need
+pub fn systemd_directives(config: &Config) -> Vec<String> {
+    let mut lines = vec![
+        "ProtectSystem=strict".to_string(),
+        "ProtectHome=read-only".to_string(),
+    ];
+    // The runtime directory depends on the session, so name it for systemd.
+    // "-" skips directories that don't exist yet.
+    let writable: Vec<String> = writable_dirs(config)
+        .iter()
+        .map(|dir| {
+            if *dir == paths::runtime_dir() {
+                "-%t/stop_it".to_string()
+            } else {
+                format!("-{}", dir.display())
+            }
+        })
+        .collect();
+    lines.push(format!("ReadWritePaths={}", writable.join(" ")));
+    lines.extend(
+        [
+            "PrivateTmp=yes",
+            "ProtectKernelTunables=yes",
+            "ProtectKernelModules=yes",
+            "ProtectControlGroups=yes",
+            "RestrictNamespaces=yes",
+            "RestrictRealtime=yes",
+            "LockPersonality=yes",
+            "SystemCallArchitectures=native",
+            "RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6",
+        ]
+        .map(String::from),
+    );
+
+    // The screen locker runs inside the sandbox: it needs the GPU, may JIT
+    // shaders and checks passwords through setuid helpers
+    if config.breaks.lock {
+        lines.push("# [breaks] lock: devices, W^X and setuid helpers stay available".to_string());
+    } else {
+        lines.push("NoNewPrivileges=yes".to_string());
+        lines.push("MemoryDenyWriteExecute=yes".to_string());
+        if config.input.sample && config.input.source == InputSource::Evdev {
+            lines.push("# [input] sample: /dev/input stays visible".to_string());
+        } else {
+            lines.push("PrivateDevices=yes".to_string());
+        }
+    }
+
+    if storage_path(config.storage.url.as_deref()).is_none() {
+        lines.push("# remote storage: network access stays open".to_string());
+    } else if !config.webhooks.is_empty() {
+        lines.push("# [[webhooks]]: network access stays open".to_string());
+    } else if config.remote.url.is_some() {
+        lines.push("# [remote]: network access stays open".to_string());
+    } else {
+        // Only the extension's WebSocket connections
+        lines.push("IPAddressDeny=any".to_string());
+        lines.push("IPAddressAllow=localhost".to_string());
+    }
+    lines
+}
@@ -0,0 +1,91 @@
+use crate::config::paths;
+use crate::ws::protocol::TimerState;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What `status.json` holds: enough for a prompt segment or a status bar
+/// script, without talking to the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub mode: String, // "WORK", "BREAK" or "LONG_BREAK"
+    pub remaining_seconds: i64,
+    pub ends_at: Option<DateTime<Local>>, // None while paused
+    pub paused: bool,
+    pub pomodoros_today: u32,
+    pub daily_goal: u32,
+    pub domain: Option<String>,
+    pub category: Option<String>,
+    pub updated_at: DateTime<Local>, // a stale file means the daemon is gone
+}
+
+impl Status {
+    pub fn new(state: &TimerState, now: DateTime<Local>) -> Self {
+        Self {
+            mode: state.mode.clone(),
+            remaining_seconds: state.remaining_seconds,
+            ends_at: (!state.paused).then(|| now + Duration::seconds(state.remaining_seconds)),
+            paused: state.paused,
+            pomodoros_today: state.pomodoros_today,
+            daily_goal: state.daily_goal,
+            domain: state.current_domain.clone(),
+            category: state.current_category.clone(),
+            updated_at: now,
+        }
+    }
+}
+
+/// Keeps `status.json` in the runtime directory up to date. Each write goes
+/// to a temporary file renamed over the old one, so readers never see a
+/// half-written file.
+#[derive(Debug)]
+pub struct StatusFile {
+    path: PathBuf,
+    failing: bool, // the last write failed and was reported
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            failing: false,
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        paths::runtime_dir().join("status.json")
+    }
+
+    pub fn load_from(path: &Path) -> Result<Status, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid status file {}: {}", path.display(), e).into())
+    }
+
+    /// Write `status`; a failure is reported once until a write succeeds again
+    pub fn write(&mut self, status: &Status) {
+        match self.save(status) {
+            Ok(()) => self.failing = false,
+            Err(e) if !self.failing => {
+                eprintln!(
+                    "Failed to write the status file {}: {}",
+                    self.path.display(),
+                    e
+                );
+                self.failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn save(&self, status: &Status) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(status)?)?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+}
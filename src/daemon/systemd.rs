@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// The service manager's notification socket (`sd_notify`), set in
+/// `$NOTIFY_SOCKET` for `Type=notify` units
+#[derive(Debug, Clone)]
+pub struct ServiceNotifier {
+    socket: PathBuf, // a leading '@' names an abstract socket
+}
+
+impl ServiceNotifier {
+    pub fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// `None` when not started by systemd with `Type=notify`
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("NOTIFY_SOCKET")
+            .filter(|socket| !socket.is_empty())
+            .map(|socket| Self::new(PathBuf::from(socket)))
+    }
+
+    /// Send `state`, newline-separated assignments such as `READY=1` or
+    /// `STATUS=...`
+    #[cfg(unix)]
+    pub fn notify(&self, state: &str) -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::net::UnixDatagram;
+
+        let socket = UnixDatagram::unbound()?;
+        match self.socket.to_str().and_then(|path| path.strip_prefix('@')) {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                use std::os::unix::net::SocketAddr;
+
+                let address = SocketAddr::from_abstract_name(name)?;
+                socket.send_to_addr(state.as_bytes(), &address)?;
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            Some(_) => return Err("abstract sockets are Linux only".into()),
+            None => {
+                socket.send_to(state.as_bytes(), &self.socket)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn notify(&self, _state: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Err("sd_notify needs Unix sockets, which this platform doesn't have".into())
+    }
+}
+
+/// Keeps systemd's watchdog (`WatchdogSec=`) from restarting the daemon by
+/// sending `WATCHDOG=1` at half the timeout, as long as the daemon steps
+#[derive(Debug)]
+pub struct Watchdog {
+    notifier: ServiceNotifier,
+    interval: Duration,
+    last: Option<Instant>, // last `WATCHDOG=1` sent
+}
+
+impl Watchdog {
+    pub fn new(notifier: ServiceNotifier, timeout: Duration) -> Self {
+        Self {
+            notifier,
+            interval: timeout / 2,
+            last: None,
+        }
+    }
+
+    /// From `$WATCHDOG_USEC`, when systemd watches this process
+    pub fn from_env(notifier: ServiceNotifier) -> Option<Self> {
+        // Meant for another process when the PID doesn't match, e.g. a child
+        if let Ok(pid) = std::env::var("WATCHDOG_PID")
+            && pid.parse() != Ok(std::process::id())
+        {
+            return None;
+        }
+        let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+        (usec > 0).then(|| Self::new(notifier, Duration::from_micros(usec)))
+    }
+
+    /// Send `WATCHDOG=1` if half the timeout passed since the last one.
+    /// Returns whether it was sent.
+    pub fn ping(&mut self) -> bool {
+        if self.last.is_some_and(|last| last.elapsed() < self.interval) {
+            return false;
+        }
+        self.last = Some(Instant::now());
+        self.notifier.notify("WATCHDOG=1").is_ok()
+    }
+}
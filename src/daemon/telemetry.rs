@@ -0,0 +1,85 @@
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{Span as _, Status, TraceContextExt, Tracer};
+#[cfg(feature = "otel")]
+use opentelemetry::{Context, ContextGuard, KeyValue, global};
+
+/// Start exporting spans to the OTLP/HTTP collector at `endpoint`. Without
+/// the `otel` feature spans are no-ops and this fails.
+#[cfg(feature = "otel")]
+pub fn init(endpoint: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name("stop_it")
+                .build(),
+        )
+        .build();
+    global::set_tracer_provider(provider);
+    Ok(())
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init(_endpoint: &str) -> Result<(), Box<dyn std::error::Error>> {
+    Err("trace export requires building with `--features otel`".into())
+}
+
+/// Span covering the rest of the current scope. Spans started while it is
+/// alive on the same thread become its children.
+#[must_use]
+pub struct Span {
+    #[cfg(feature = "otel")]
+    _guard: ContextGuard,
+}
+
+impl Span {
+    /// Attach a value worth seeing in traces, e.g. the number of saved segments
+    pub fn record(&self, _key: &'static str, _value: i64) {
+        #[cfg(feature = "otel")]
+        Context::current()
+            .span()
+            .set_attribute(KeyValue::new(_key, _value));
+    }
+
+    /// Mark the span as failed, e.g. for a dropped event
+    pub fn fail(&self, _message: &str) {
+        #[cfg(feature = "otel")]
+        Context::current()
+            .span()
+            .set_status(Status::error(_message.to_string()));
+    }
+}
+
+pub fn span(_name: &'static str) -> Span {
+    Span {
+        #[cfg(feature = "otel")]
+        _guard: Context::current_with_span(global::tracer("stop_it").start(_name)).attach(),
+    }
+}
+
+/// Span for async code, which can't keep a span attached across `.await`
+#[must_use]
+pub struct AsyncSpan {
+    #[cfg(feature = "otel")]
+    span: opentelemetry::global::BoxedSpan,
+}
+
+impl AsyncSpan {
+    pub fn fail(&mut self, _message: &str) {
+        #[cfg(feature = "otel")]
+        self.span.set_status(Status::error(_message.to_string()));
+    }
+}
+
+pub fn async_span(_name: &'static str) -> AsyncSpan {
+    AsyncSpan {
+        #[cfg(feature = "otel")]
+        span: global::tracer("stop_it").start(_name),
+    }
+}
@@ -0,0 +1,188 @@
+use crate::window::provider::ActiveWindow;
+use crate::ws::protocol::ClientMessage;
+use chrono::{DateTime, Local};
+use ring::digest::{SHA256, digest};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// One line of a `stop_it record` trace: what the daemon saw and what it
+/// made of it, in the order it happened. All but steps are also kept for
+/// `stop_it inspect`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// Provider outputs read by one daemon step
+    Step {
+        at: DateTime<Local>,
+        window: Option<ActiveWindow>,
+        input: Option<u64>, // input events since the last step, without sampling None
+    },
+    /// A message from the browser extension
+    Browser {
+        at: DateTime<Local>,
+        message: ClientMessage,
+    },
+    /// The tracker's state after a step, whenever it changes
+    Decision {
+        at: DateTime<Local>,
+        #[serde(flatten)]
+        decision: Decision,
+    },
+    /// Something that went wrong, e.g. a failed write
+    Error {
+        at: DateTime<Local>,
+        message: String,
+    },
+}
+
+/// What the tracker made of its inputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Decision {
+    pub mode: String,
+    pub domain: Option<String>, // credited with the current second
+    pub category: Option<String>,
+    pub idle: bool,
+    pub paused: bool,
+}
+
+impl TraceEvent {
+    /// Drop what a bug report doesn't need: window and tab titles become a
+    /// hash (equal titles stay equal) and URLs keep only their origin
+    pub fn scrubbed(self) -> Self {
+        match self {
+            TraceEvent::Step { at, window, input } => TraceEvent::Step {
+                at,
+                window: window.map(|window| ActiveWindow {
+                    title: scrub(&window.title),
+                    ..window
+                }),
+                input,
+            },
+            TraceEvent::Browser {
+                at,
+                message: ClientMessage::TabUpdate(mut update),
+            } => {
+                update.title = scrub(&update.title);
+                update.url = origin(&update.url);
+                TraceEvent::Browser {
+                    at,
+                    message: ClientMessage::TabUpdate(update),
+                }
+            }
+            event => event,
+        }
+    }
+}
+
+fn scrub(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let hash = digest(&SHA256, text.as_bytes());
+    let hex: String = hash.as_ref()[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("<{} chars #{}>", text.chars().count(), hex)
+}
+
+/// `https://example.com/a/b?c` -> `https://example.com`
+fn origin(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return scrub(url);
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    format!("{}://{}", scheme, host)
+}
+
+/// Fans trace events out to `stop_it record` clients and keeps the latest
+/// ones for `stop_it inspect`. Nothing is built while no one is recording and
+/// no events are kept.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    followers: broadcast::Sender<TraceEvent>,
+    recent: Option<Arc<Mutex<VecDeque<TraceEvent>>>>,
+    capacity: usize,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self {
+            followers: broadcast::channel(256).0,
+            recent: None,
+            capacity: 0,
+        }
+    }
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the last `capacity` events other than steps, 0 for none
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.recent = (capacity > 0).then(|| Arc::new(Mutex::new(VecDeque::new())));
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorders() > 0
+    }
+
+    pub fn recorders(&self) -> usize {
+        self.followers.receiver_count()
+    }
+
+    /// Whether emitted events go anywhere
+    pub fn is_active(&self) -> bool {
+        self.recent.is_some() || self.is_recording()
+    }
+
+    pub fn emit(&self, event: impl FnOnce() -> TraceEvent) {
+        if !self.is_active() {
+            return;
+        }
+        let event = event();
+        // Steps come every second and would crowd everything else out
+        if let Some(recent) = &self.recent
+            && !matches!(event, TraceEvent::Step { .. })
+            && let Ok(mut recent) = recent.lock()
+        {
+            if recent.len() == self.capacity {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        // No followers is fine
+        let _ = self.followers.send(event);
+    }
+
+    pub fn error(&self, at: DateTime<Local>, message: impl Into<String>) {
+        self.emit(|| TraceEvent::Error {
+            at,
+            message: message.into(),
+        });
+    }
+
+    /// Up to `count` of the kept events, oldest first
+    pub fn recent(&self, count: usize) -> Vec<TraceEvent> {
+        let Some(Ok(recent)) = self.recent.as_ref().map(|recent| recent.lock()) else {
+            return Vec::new();
+        };
+        recent
+            .iter()
+            .skip(recent.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    /// Events emitted from now on
+    pub fn follow(&self) -> broadcast::Receiver<TraceEvent> {
+        self.followers.subscribe()
+    }
+}
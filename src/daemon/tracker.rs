@@ -0,0 +1,1502 @@
+use crate::config::settings::{
+    BreaksConfig, Config, OffDaysConfig, PauseConfig, PomodoroConfig, TriageConfig,
+};
+use crate::daemon::clock::Clock;
+use crate::daemon::log::Log;
+use crate::daemon::telemetry;
+use crate::daemon::trace::Trace;
+use crate::pomodoro::adaptive;
+use crate::pomodoro::phase::SavedPhase;
+use crate::pomodoro::plan::{self, PlanWatcher, PlannedBlock};
+use crate::pomodoro::pomodoro::{PomodoroMode, TimerMode};
+use crate::stats::aggregator::{Aggregator, TimeRange};
+use crate::stats::budget::{
+    BudgetStatus, BudgetTracker, CATEGORY_BUDGET_STEP, CategoryBudgetAlert,
+};
+use crate::stats::calendar::Calendar;
+use crate::stats::categories::CategoryRules;
+use crate::stats::filter::is_domain_or_subdomain;
+use crate::stats::focus::FocusMonitor;
+use crate::stats::format::format_duration;
+use crate::stats::history::DayPages;
+use crate::stats::limits::{DomainLimits, LimitAlert};
+use crate::stats::pace::PaceTracker;
+use crate::stats::triage;
+use crate::stats::visits::{Enforcement, VisitTracker};
+use crate::storage::activity_store::{ActivityStore, CategorySource, PomodoroRecord};
+use crate::storage::backend::Storage;
+use crate::window::provider::{ActiveWindow, WindowAction};
+use crate::window::services::ServiceNames;
+use crate::window::title::domain_from_title_with;
+use crate::ws::protocol::{
+    CategoryTotal, ClientMessage, DayHistory, DomainTotal, HistoryPage, HistoryRequest,
+    ServerMessage, SessionSummary, TimerState, TodayStats,
+};
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Category of the current activity and who decided it
+type Category = (String, CategorySource);
+
+/// Planned block boundaries crossed by the daemon
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanEvent {
+    Started(PlannedBlock),
+    Ended(PlannedBlock),
+}
+
+/// Steps of a pause left running past `[pause] auto_resume`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PauseEvent {
+    Warning { remaining: i64 }, // seconds until the automatic resume
+    AutoResumed { paused_for: i64 },
+}
+
+/// Pomodoro timer and per-domain time tracking, driven once per second by
+/// the daemon loop
+#[derive(Debug)]
+pub struct DomainTracker {
+    current_domain: Option<String>,
+    current_category: Option<Category>,
+    current_profile: Option<String>, // browser profile that sent the last tab update
+    categories: CategoryRules,
+    store: ActivityStore,
+    storage: Box<dyn Storage>,
+    persisted: usize, // segments before this index are final and saved
+    last_flush: DateTime<Local>,
+    flush_seconds: i64, // how often the open segment is saved
+    session_start: DateTime<Local>,
+    mode: PomodoroMode,
+    mode_start: DateTime<Local>,
+    cycle: u32,                  // work sessions completed in the current cycle
+    phase_path: Option<PathBuf>, // where the running phase is saved for restarts
+    paused_since: Option<DateTime<Local>>,
+    pause_warned: bool,
+    skip_requested: bool, // the next step switches phase
+    continuous: bool,     // `stop_it mode continuous`: work blocks without breaks
+    snoozed: i64,         // seconds the running work session got by snoozing its break
+    manual: bool,         // `[pomodoro] manual`: breaks wait for `start` once over
+    waiting_announced: bool,
+    snooze_unannounced: Option<i64>, // seconds of the last snooze, until reported
+    pause_limits: PauseConfig,
+    calendar: Calendar,
+    current_day: NaiveDate,
+    off_days: OffDaysConfig,
+    focus: FocusMonitor,
+    budgets: BudgetTracker,
+    budget_statuses: Vec<BudgetStatus>, // as of the last tick, read several times per step
+    budget_alerts: HashMap<String, u32>, // category -> alert level reached today
+    limits: DomainLimits,
+    pace: PaceTracker,
+    daily_goal: u32,
+    visits: VisitTracker,
+    grace: Option<i64>, // `[focus_alerts] grace` when a window action is set
+    blocked_stay: Option<(String, String, DateTime<Local>)>, // target, domain, since
+    triage: TriageConfig,
+    untriaged: Vec<(String, i64)>, // uncategorized sites of the finished week, until reported
+    session_lengths: PomodoroConfig,
+    breaks: BreaksConfig,
+    break_length: Option<i64>, // adapted length of the running break
+    browser_tab: Option<(Option<String>, Option<Category>)>, // last tab while another window has focus
+    extension_seen: bool,
+    extensions: u32, // extensions connected to the WebSocket server now
+    heartbeat_timeout: Option<i64>, // `[extension] timeout`, when running from the extension alone
+    last_heard: Option<DateTime<Local>>, // last message of the extension, in browser-only mode
+    services: ServiceNames,
+    plan: Option<PlanWatcher>,
+    active_block: Option<PlannedBlock>,
+    idle_after: i64,
+    abandon_after: f64,
+    last_input: Option<DateTime<Local>>, // None until input is sampled
+    idle: bool,
+    idle_seconds: i64,     // during the current phase
+    pause_when_idle: bool, // `[input] pause_when_idle`
+    idle_paused: bool,     // the running pause started when input stopped
+    clock: Arc<dyn Clock>,
+    log: Log,
+    trace: Trace,
+}
+
+impl DomainTracker {
+    pub fn new(
+        config: &Config,
+        mut storage: Box<dyn Storage>,
+        clock: Arc<dyn Clock>,
+        log: Log,
+    ) -> Result<Self, Box<dyn Error>> {
+        let now = clock.now();
+        let calendar = Calendar::new(&config.day);
+        log.line(format!(
+            "=== Session started at {} ===",
+            now.format("%Y-%m-%d %H:%M:%S")
+        ));
+
+        // Budgets and day summaries need this week's (and yesterday's) history
+        let store = storage.load(&TimeRange::new(Self::retention_start(&calendar, now), now))?;
+        let limits = DomainLimits::new(config).with_history(&store, now);
+        let mut pace = PaceTracker::new(config);
+        let today = calendar.day_of(now);
+        pace.learn(&storage.load(&pace.history_range(today))?, today);
+        let budgets = BudgetTracker::new(config);
+        let budget_statuses = budgets.statuses(&store, now);
+        Ok(Self {
+            current_domain: None,
+            current_category: None,
+            current_profile: None,
+            categories: CategoryRules::new(&config.categories),
+            persisted: store.segments().len().saturating_sub(1),
+            store,
+            storage,
+            last_flush: now,
+            // Journal appends are cheap, so save every second when it is enabled
+            flush_seconds: if config.storage.journal { 1 } else { 60 },
+            session_start: now,
+            mode: PomodoroMode::Work,
+            mode_start: now,
+            cycle: 0,
+            phase_path: None,
+            paused_since: None,
+            pause_warned: false,
+            skip_requested: false,
+            continuous: false,
+            snoozed: 0,
+            manual: config.pomodoro.manual,
+            waiting_announced: false,
+            snooze_unannounced: None,
+            pause_limits: config.pause.clone(),
+            calendar,
+            current_day: calendar.day_of(now),
+            off_days: config.off_days.clone(),
+            focus: FocusMonitor::new(&config.focus_alerts),
+            budgets,
+            budget_statuses,
+            budget_alerts: HashMap::new(),
+            limits,
+            pace,
+            daily_goal: config.goals.daily_pomodoros,
+            visits: VisitTracker::new(&config.focus_alerts, &config.enforcement, &config.strict),
+            grace: (config.focus_alerts.window_action != WindowAction::None)
+                .then_some(config.focus_alerts.grace),
+            blocked_stay: None,
+            triage: config.triage.clone(),
+            untriaged: Vec::new(),
+            session_lengths: config.pomodoro.clone(),
+            breaks: config.breaks.clone(),
+            break_length: None,
+            browser_tab: None,
+            extension_seen: false,
+            extensions: 0,
+            heartbeat_timeout: config.extension.only.then_some(config.extension.timeout),
+            last_heard: None,
+            services: ServiceNames::new(&config.titles.services),
+            plan: None,
+            active_block: None,
+            idle_after: config.input.idle_after,
+            abandon_after: config.input.abandon_after,
+            last_input: None,
+            idle: false,
+            idle_seconds: 0,
+            pause_when_idle: config.input.pause_when_idle,
+            idle_paused: false,
+            clock,
+            log,
+            trace: Trace::new(),
+        })
+    }
+
+    /// Report failures on `trace` too, for `stop_it inspect`
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Follow the schedule planned with `stop_it plan`
+    pub fn with_plan(mut self, plan: PlanWatcher) -> Self {
+        self.plan = Some(plan);
+        self
+    }
+
+    /// Save the running phase to `path` on every switch, and pick up the one
+    /// saved there by a previous run if it belongs to today
+    pub fn with_saved_phase(mut self, path: PathBuf) -> Self {
+        let saved = SavedPhase::load_from(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            None
+        });
+        self.phase_path = Some(path);
+        if let Some(saved) = saved.filter(|saved| saved.day == self.current_day) {
+            self.restore_phase(saved);
+        }
+        self.remember_phase();
+        self
+    }
+
+    fn restore_phase(&mut self, saved: SavedPhase) {
+        self.cycle = saved.cycle;
+        self.continuous = saved.continuous;
+        let length = saved.length.unwrap_or_else(|| self.length_of(saved.mode)) + saved.snoozed;
+        let now = self.now();
+        // A phase that ran out while the daemon was down is not resumed,
+        // unless a break waiting for `start`; a paused one stopped running
+        // when it was paused
+        let until = saved.paused_since.unwrap_or(now);
+        let waiting = self.manual && saved.mode.is_break();
+        if saved.start <= until && ((until - saved.start).num_seconds() < length || waiting) {
+            self.mode = saved.mode;
+            self.mode_start = saved.start;
+            self.paused_since = saved.paused_since;
+            self.break_length = saved.length;
+            self.snoozed = saved.snoozed;
+            self.log.line(format!(
+                "Resumed {} session started at {} ({} completed this cycle{})",
+                self.mode.as_str(),
+                saved.start.format("%H:%M:%S"),
+                self.cycle,
+                if self.paused_since.is_some() {
+                    ", paused"
+                } else {
+                    ""
+                }
+            ));
+        }
+    }
+
+    /// Oldest instant kept in memory: start of the week or of yesterday, whichever is earlier
+    fn retention_start(calendar: &Calendar, now: DateTime<Local>) -> DateTime<Local> {
+        let today = calendar.day_of(now);
+        let yesterday = today - chrono::Duration::days(1);
+        calendar
+            .day_begin(calendar.week_of(today))
+            .min(calendar.day_begin(yesterday))
+    }
+
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.now()
+    }
+
+    fn report(&self, message: String) {
+        eprintln!("{}", message);
+        self.trace.error(self.now(), message);
+    }
+
+    pub fn mode(&self) -> PomodoroMode {
+        self.mode
+    }
+
+    pub fn mode_start(&self) -> DateTime<Local> {
+        self.mode_start
+    }
+
+    pub fn cycle(&self) -> u32 {
+        self.cycle
+    }
+
+    pub fn current_domain(&self) -> Option<&str> {
+        self.current_domain.as_deref()
+    }
+
+    /// Save finished segments, and the open one every `flush_seconds` (or when forced)
+    fn persist(&mut self, force: bool) {
+        let now = self.now();
+        let segments = self.store.segments();
+        let open = segments.len().saturating_sub(1);
+        let flush = force || (now - self.last_flush).num_seconds() >= self.flush_seconds;
+        if self.persisted >= open && !flush {
+            return;
+        }
+
+        let span = telemetry::span("storage.write");
+        let closed = &segments[self.persisted.min(open)..open];
+        span.record("segments", closed.len() as i64 + i64::from(flush));
+        for segment in closed {
+            if let Err(e) = self.storage.save_segment(segment) {
+                self.report(format!("Failed to save activity: {}", e));
+                span.fail(&e.to_string());
+            }
+        }
+        self.persisted = open;
+
+        if flush {
+            if let Some(segment) = segments.last()
+                && let Err(e) = self.storage.save_segment(segment)
+            {
+                self.report(format!("Failed to save activity: {}", e));
+                span.fail(&e.to_string());
+            }
+            self.last_flush = now;
+        }
+    }
+
+    /// Time spent in the current phase; stands still while paused
+    fn get_mode_duration(&self) -> i64 {
+        (self.paused_since.unwrap_or_else(|| self.now()) - self.mode_start).num_seconds()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+
+    /// Stop the Pomodoro clock and activity tracking. Returns `false` when
+    /// already paused.
+    pub fn pause(&mut self) -> bool {
+        if self.paused_since.is_some() {
+            return false;
+        }
+        let now = self.now();
+        self.paused_since = Some(now);
+        self.pause_warned = false;
+        // End the open segment at the pause
+        self.persist(true);
+        self.remember_phase();
+        self.log.line(format!(
+            "[{}] ⏸ Paused {} session with {} left",
+            now.format("%H:%M:%S"),
+            self.mode.as_str(),
+            format_duration(self.remaining_seconds())
+        ));
+        true
+    }
+
+    /// Restart the clock where it stopped. Returns how long the pause lasted,
+    /// `None` when not paused.
+    pub fn resume(&mut self) -> Option<i64> {
+        let paused_since = self.paused_since.take()?;
+        self.idle_paused = false;
+        let now = self.now();
+        let paused_for = now - paused_since;
+        self.mode_start += paused_for;
+        self.remember_phase();
+        self.log.line(format!(
+            "[{}] ▶ Resumed {} session after {}",
+            now.format("%H:%M:%S"),
+            self.mode.as_str(),
+            format_duration(paused_for.num_seconds())
+        ));
+        Some(paused_for.num_seconds())
+    }
+
+    /// Warn ahead of `[pause] auto_resume`, then resume when it is reached,
+    /// so a forgotten pause doesn't stop tracking for the rest of the day
+    pub fn check_pause(&mut self) -> Option<PauseEvent> {
+        let limit = self.pause_limits.auto_resume?;
+        let paused_for = (self.now() - self.paused_since?).num_seconds();
+        if paused_for >= limit {
+            return self
+                .resume()
+                .map(|paused_for| PauseEvent::AutoResumed { paused_for });
+        }
+        let remaining = limit - paused_for;
+        if remaining <= self.pause_limits.warning && !self.pause_warned {
+            self.pause_warned = true;
+            return Some(PauseEvent::Warning { remaining });
+        }
+        None
+    }
+
+    /// Start the next Pomodoro phase. Returns the recap of the work session
+    /// when one just finished.
+    pub fn switch_mode(&mut self) -> Option<SessionSummary> {
+        let now = self.now();
+        self.save_phase(now);
+        let summary = (self.mode == PomodoroMode::Work)
+            .then(|| self.session_summary(&TimeRange::new(self.mode_start, now)));
+        let next = self.next_mode();
+        let suggested = self.suggested_break();
+        match self.mode {
+            // Neither brings the long break closer
+            PomodoroMode::Work if self.is_abandoned() || self.continuous => {}
+            PomodoroMode::Work => self.cycle += 1,
+            PomodoroMode::Break => {}
+            PomodoroMode::LongBreak => self.cycle = 0, // a new cycle begins
+        }
+        self.mode = next;
+        self.mode_start = now;
+        self.break_length = suggested.filter(|_| self.breaks.auto_accept);
+        self.snoozed = 0;
+        self.waiting_announced = false;
+        self.paused_since = None; // a skipped phase may have been paused
+        self.skip_requested = false;
+        self.visits.reset();
+        self.blocked_stay = None;
+        self.idle_seconds = 0;
+        self.remember_phase();
+        let msg = match summary {
+            Some(_) if self.mode == PomodoroMode::Work => {
+                format!("[{}] Started a new WORK block", now.format("%H:%M:%S"))
+            }
+            _ => format!(
+                "[{}] Switched to {} mode",
+                now.format("%H:%M:%S"),
+                self.mode.as_str()
+            ),
+        };
+        self.log.line(format!("\n{} {}", self.mode.emoji(), msg));
+        summary
+    }
+
+    /// Apply a `[categories]` rule written while running: today's activity
+    /// on `domain` and its subdomains is re-tagged and saved again, and the
+    /// current tab follows. Returns the seconds re-tagged.
+    pub fn categorize_domain(&mut self, domain: &str, category: &str) -> i64 {
+        self.categories.set(domain, category);
+        let today = self.today();
+        let changed = self.categories.reapply(&mut self.store, &today, domain);
+        for segment in &changed {
+            if let Err(e) = self.storage.save_segment(segment) {
+                self.report(format!("Failed to save activity: {}", e));
+            }
+        }
+        let rules = &self.categories;
+        let recategorize = |tab: &mut Option<String>, category: &mut Option<Category>| {
+            if let Some(current) = tab
+                && is_domain_or_subdomain(current, domain)
+            {
+                *category = rules.categorize(current, None);
+            }
+        };
+        recategorize(&mut self.current_domain, &mut self.current_category);
+        if let Some((tab, category)) = self.browser_tab.as_mut() {
+            recategorize(tab, category);
+        }
+        self.budget_statuses = self.budgets.statuses(&self.store, self.now());
+        let seconds = changed.iter().map(|segment| segment.seconds).sum();
+        self.log.line(format!(
+            "[{}] 🏷 {} is now {}: {} of today re-tagged",
+            self.now().format("%H:%M:%S"),
+            domain,
+            category,
+            format_duration(seconds)
+        ));
+        seconds
+    }
+
+    pub fn timer_mode(&self) -> TimerMode {
+        if self.continuous {
+            TimerMode::Continuous
+        } else {
+            TimerMode::Pomodoro
+        }
+    }
+
+    /// Turn breaks off or back on. A running break ends now when they are
+    /// turned off; a running work session keeps its length either way.
+    /// Returns `false` when already in `mode`.
+    pub fn set_timer_mode(&mut self, mode: TimerMode) -> bool {
+        if mode == self.timer_mode() {
+            return false;
+        }
+        let now = self.now();
+        self.continuous = mode == TimerMode::Continuous;
+        if self.continuous && self.mode.is_break() {
+            self.restart_work(now);
+        } else {
+            self.remember_phase();
+        }
+        self.log.line(format!(
+            "[{}] {}",
+            now.format("%H:%M:%S"),
+            match mode {
+                TimerMode::Continuous => "⏩ Continuous mode: work blocks without breaks",
+                TimerMode::Pomodoro => "🍅 Pomodoro mode: breaks are back",
+            }
+        ));
+        true
+    }
+
+    /// Record the current phase as finished at `now`
+    fn save_phase(&mut self, now: DateTime<Local>) {
+        let record = PomodoroRecord {
+            mode: self.mode,
+            start: self.mode_start,
+            end: now,
+            abandoned: self.is_abandoned(),
+            label: self
+                .active_block
+                .as_ref()
+                .filter(|_| self.mode == PomodoroMode::Work)
+                .map(|block| block.label.clone()),
+        };
+        if let Err(e) = self.storage.save_pomodoro(&record) {
+            self.report(format!("Failed to save Pomodoro: {}", e));
+        }
+        self.store.record_pomodoro(record);
+    }
+
+    /// Save the running phase for the next start of the daemon
+    fn remember_phase(&self) {
+        let Some(path) = &self.phase_path else {
+            return;
+        };
+        let saved = SavedPhase {
+            mode: self.mode,
+            start: self.mode_start,
+            day: self.current_day,
+            cycle: self.cycle,
+            paused_since: self.paused_since,
+            length: self.break_length,
+            continuous: self.continuous,
+            snoozed: self.snoozed,
+        };
+        if let Err(e) = saved.save_to(path) {
+            self.report(format!("Failed to save the Pomodoro phase: {}", e));
+        }
+    }
+
+    /// Follow the day's plan: entering a planned block starts a fresh work
+    /// session, dropping an unfinished one
+    pub fn check_plan(&mut self) -> Option<PlanEvent> {
+        let now = self.now();
+        let block = self
+            .plan
+            .as_mut()?
+            .block_at(&self.calendar, self.current_day, now);
+        if block == self.active_block {
+            return None;
+        }
+
+        let previous = std::mem::replace(&mut self.active_block, block.clone());
+        let Some(block) = block else {
+            return previous.map(PlanEvent::Ended);
+        };
+        if self.mode == PomodoroMode::LongBreak {
+            self.cycle = 0;
+        }
+        self.restart_work(now);
+        Some(PlanEvent::Started(block))
+    }
+
+    /// Start a work session at `now`, dropping an unfinished one; breaks
+    /// are recorded up to `now`
+    fn restart_work(&mut self, now: DateTime<Local>) {
+        if self.mode.is_break() {
+            self.save_phase(now);
+        }
+        self.mode = PomodoroMode::Work;
+        self.mode_start = now;
+        self.break_length = None;
+        self.snoozed = 0;
+        self.waiting_announced = false;
+        self.paused_since = None; // the session starts now, paused or not
+        self.skip_requested = false;
+        self.visits.reset();
+        self.idle_seconds = 0;
+        self.remember_phase();
+    }
+
+    /// Have the next step end the current phase as if its time were up.
+    /// Returns the phase that follows.
+    pub fn skip(&mut self) -> PomodoroMode {
+        self.skip_requested = true;
+        self.log.line(format!(
+            "[{}] ⏭ Skipping the rest of the {} session",
+            self.now().format("%H:%M:%S"),
+            self.mode.as_str()
+        ));
+        self.next_mode()
+    }
+
+    /// Put the break off by `seconds`: a break that just started gives way
+    /// to the work session before it, which goes on, and a work session
+    /// ending sooner is extended
+    pub fn snooze(&mut self, seconds: i64) -> Result<(), String> {
+        if self.continuous {
+            return Err("No breaks to snooze in continuous mode".to_string());
+        }
+        if self.is_paused() {
+            return Err("Paused: resume first".to_string());
+        }
+        let now = self.now();
+        if self.mode.is_break() {
+            let Some(work) = self
+                .store
+                .pomodoros()
+                .last()
+                .filter(|record| record.mode == PomodoroMode::Work && record.end == self.mode_start)
+                .cloned()
+            else {
+                return Err("This break doesn't follow a work session".to_string());
+            };
+            // Recorded again when it ends
+            self.store.pop_pomodoro();
+            if !work.abandoned {
+                self.cycle = self.cycle.saturating_sub(1);
+            }
+            self.mode = PomodoroMode::Work;
+            self.mode_start = work.start;
+            self.break_length = None;
+            self.skip_requested = false;
+        } else if self.remaining_seconds() >= seconds {
+            return Err(format!(
+                "The break is still {} away",
+                format_duration(self.remaining_seconds())
+            ));
+        }
+        self.snoozed = self.get_mode_duration() + seconds - self.length_of(PomodoroMode::Work);
+        self.snooze_unannounced = Some(seconds);
+        self.remember_phase();
+        self.log.line(format!(
+            "[{}] 💤 Break snoozed: {} more of WORK",
+            now.format("%H:%M:%S"),
+            format_duration(seconds)
+        ));
+        Ok(())
+    }
+
+    /// End the break now and start the next work session, the way out of a
+    /// break that ran out with `[pomodoro] manual`
+    pub fn start(&mut self) -> Result<(), String> {
+        if !self.mode.is_break() {
+            return Err("Already in a WORK session".to_string());
+        }
+        if self.skip_requested {
+            return Err("Already starting".to_string());
+        }
+        self.skip_requested = true;
+        self.log.line(format!(
+            "[{}] ▶ Starting the WORK session",
+            self.now().format("%H:%M:%S")
+        ));
+        Ok(())
+    }
+
+    /// Whether the break ran out and waits for `start`, with `[pomodoro] manual`
+    pub fn is_waiting(&self) -> bool {
+        self.manual
+            && self.mode.is_break()
+            && !self.skip_requested
+            && self.get_mode_duration() >= self.get_target_seconds()
+    }
+
+    /// Whether the break just ran out with `[pomodoro] manual`, once per break
+    pub fn check_waiting(&mut self) -> bool {
+        if !self.is_waiting() || self.waiting_announced {
+            return false;
+        }
+        self.waiting_announced = true;
+        true
+    }
+
+    /// Seconds the break was just put off by, once per snooze
+    pub fn check_snooze(&mut self) -> Option<i64> {
+        self.snooze_unannounced.take()
+    }
+
+    /// Start over with a fresh work session and cycle
+    pub fn reset(&mut self) {
+        let now = self.now();
+        self.cycle = 0;
+        self.restart_work(now);
+        self.log.line(format!(
+            "[{}] ↺ Reset: new cycle, {} work session",
+            now.format("%H:%M:%S"),
+            format_duration(self.get_target_seconds())
+        ));
+    }
+
+    pub fn active_block(&self) -> Option<&PlannedBlock> {
+        self.active_block.as_ref()
+    }
+
+    fn session_summary(&self, session: &TimeRange) -> SessionSummary {
+        let aggregator = Aggregator::new(&self.store);
+        let focus = 1.0 - self.focus.distraction_ratio_in(&self.store, session);
+        SessionSummary {
+            duration_seconds: (session.end - session.start).num_seconds(),
+            top_domains: aggregator
+                .top_domains(session, 5)
+                .into_iter()
+                .map(|(domain, seconds)| DomainTotal { domain, seconds })
+                .collect(),
+            focus_percent: (focus * 100.0).round() as u32,
+            idle_seconds: self.idle_seconds,
+            pomodoros_today: aggregator.pomodoros(&self.today()).len() as u32,
+            daily_goal: self.daily_goal,
+        }
+    }
+
+    /// Record the tab reported by the browser extension, with its category
+    /// hint and the profile its token belongs to
+    pub fn update(
+        &mut self,
+        domain: Option<String>,
+        hint: Option<String>,
+        profile: Option<String>,
+    ) {
+        self.extension_seen = true;
+        if self.heartbeat_timeout.is_some() {
+            self.last_heard = Some(self.now());
+        }
+        // Also while stashed: the stashed tab always comes from the latest update
+        self.current_profile = profile;
+        let category = domain
+            .as_deref()
+            .and_then(|domain| self.categories.categorize(domain, hint.as_deref()));
+        if self.browser_tab.is_some() {
+            // Another window has focus: remember the tab for when the browser is back
+            self.browser_tab = Some((domain, category));
+            return;
+        }
+        self.current_domain = domain;
+        self.current_category = category;
+    }
+
+    /// Stop crediting the browser right away when the extension reports the
+    /// tab or window it was on closed, instead of when the next update comes.
+    /// Closes from another profile than the one being credited, or for
+    /// another site than `domain` when it is given, are stale and ignored.
+    /// Returns the domain that stopped being credited.
+    pub fn close_browser_tab(
+        &mut self,
+        domain: Option<&str>,
+        profile: Option<&str>,
+    ) -> Option<String> {
+        if profile != self.current_profile.as_deref() {
+            return None;
+        }
+        // While another window has focus the tab waits in `browser_tab`
+        let (current, category) = match self.browser_tab.as_mut() {
+            Some((current, category)) => (current, category),
+            None => (&mut self.current_domain, &mut self.current_category),
+        };
+        if domain.is_some() && domain != current.as_deref() {
+            return None;
+        }
+        *category = None;
+        let closed = current.take();
+        // Write the segment now, so it ends at the close
+        self.persist(true);
+        closed
+    }
+
+    /// Follow the focused window: time is only credited while a browser is in
+    /// front. Without an extension the site is read from the window title.
+    pub fn focus_window(&mut self, window: Option<ActiveWindow>) {
+        let Some(window) = window else {
+            return;
+        };
+
+        self.focus_browser(window.is_browser());
+        if window.is_browser() && !self.extension_seen {
+            self.current_domain = domain_from_title_with(&window.title, &self.services);
+            self.current_category = self
+                .current_domain
+                .as_deref()
+                .and_then(|domain| self.categories.categorize(domain, None));
+        }
+    }
+
+    /// Stash the browser's tab while another window has focus, and bring it
+    /// back when the browser is in front again
+    fn focus_browser(&mut self, focused: bool) {
+        if !focused {
+            if self.browser_tab.is_none() {
+                self.browser_tab = Some((self.current_domain.take(), self.current_category.take()));
+            }
+            return;
+        }
+        if let Some((domain, category)) = self.browser_tab.take() {
+            self.current_domain = domain;
+            self.current_category = category;
+        }
+    }
+
+    /// Follow the extension's heartbeat when running from it alone: whether
+    /// a browser window has focus and the browser saw input lately. Losing
+    /// focus only counts from the profile being credited, as other browsers
+    /// report themselves unfocused all along.
+    pub fn heartbeat(&mut self, focused: bool, idle: bool, profile: Option<&str>) {
+        if self.heartbeat_timeout.is_none() {
+            return;
+        }
+        self.last_heard = Some(self.now());
+        if focused || profile == self.current_profile.as_deref() {
+            self.focus_browser(focused);
+        }
+        self.set_idle(idle, || "The browser reports no input".to_string());
+    }
+
+    /// How many browser extensions are connected, reported in `TimerState`
+    pub fn set_extensions(&mut self, connected: u32) {
+        self.extensions = connected;
+    }
+
+    /// Stop crediting the browser once the last extension disconnected, in
+    /// browser-only mode
+    pub fn browser_disconnected(&mut self) {
+        if self.heartbeat_timeout.is_some() {
+            self.last_heard = None;
+            self.forget_browser();
+        }
+    }
+
+    /// Stop crediting the browser when it went quiet for `[extension]
+    /// timeout`, e.g. when the machine was suspended with the browser open
+    fn check_heartbeat(&mut self) {
+        let Some(timeout) = self.heartbeat_timeout else {
+            return;
+        };
+        let now = self.now();
+        if self
+            .last_heard
+            .is_some_and(|heard| (now - heard).num_seconds() >= timeout)
+        {
+            self.last_heard = None;
+            if self.forget_browser() {
+                self.log.line(format!(
+                    "[{}] 🧩 No heartbeat from the browser for {}: tracking stopped",
+                    now.format("%H:%M:%S"),
+                    format_duration(timeout)
+                ));
+            }
+        }
+    }
+
+    fn forget_browser(&mut self) -> bool {
+        let tracked = self.current_domain.is_some()
+            || self
+                .browser_tab
+                .as_ref()
+                .is_some_and(|(domain, _)| domain.is_some());
+        self.current_domain = None;
+        self.current_category = None;
+        self.browser_tab = None;
+        // Write the segment now, so it ends where the browser went away
+        self.persist(true);
+        tracked
+    }
+
+    /// Print the summary of the finished day when the configured day boundary is crossed
+    fn check_day_rollover(&mut self) {
+        let now = self.now();
+        let today = self.calendar.day_of(now);
+        if today == self.current_day {
+            return;
+        }
+
+        let finished = self.calendar.day_range(self.current_day);
+        let aggregator = Aggregator::new(&self.store);
+        self.log.line(format!(
+            "\n=== Day {} finished ===",
+            self.current_day.format("%Y-%m-%d")
+        ));
+        self.log.line(format!(
+            "Pomodoros completed: {}",
+            aggregator.pomodoros(&finished).len()
+        ));
+        for (domain, seconds) in aggregator.top_domains(&finished, 10) {
+            self.log.line(format!(
+                "  {} - {}m {}s",
+                domain,
+                seconds / 60,
+                seconds % 60
+            ));
+        }
+        if let Some(watcher) = self.plan.as_mut() {
+            let blocks = watcher.plans().blocks(self.current_day).to_vec();
+            if !blocks.is_empty() {
+                self.log.line("Plan vs actual:");
+                for report in plan::compare(&blocks, &self.calendar, self.current_day, &self.store)
+                {
+                    self.log.line(format!("  {}", report.describe()));
+                }
+            }
+        }
+        // Before pruning, which drops the finished week
+        if self.triage.notify
+            && self.calendar.week_of(today) != self.calendar.week_of(self.current_day)
+        {
+            self.untriaged = triage::uncategorized(
+                &self.store,
+                &self.calendar.week_range(self.current_day),
+                &self.categories,
+                self.triage.min_time,
+            );
+        }
+        self.current_day = today;
+        self.cycle = 0;
+        self.budget_alerts.clear();
+        self.limits.reset();
+        if self.continuous {
+            // Deep work is decided day by day
+            self.continuous = false;
+            self.log
+                .line("🍅 Continuous mode ended with the day: breaks are back");
+        }
+        self.remember_phase();
+
+        self.persist(true);
+        self.store
+            .prune_before(Self::retention_start(&self.calendar, now));
+        self.persisted = self.store.segments().len().saturating_sub(1);
+        match self.storage.load(&self.pace.history_range(today)) {
+            Ok(history) => self.pace.learn(&history, today),
+            Err(e) => self.report(format!("Failed to load the history for the pace: {}", e)),
+        }
+
+        if self.is_off_day() {
+            self.log
+                .line("🌴 Today is an off-day: notifications are paused");
+        }
+    }
+
+    pub fn is_off_day(&self) -> bool {
+        self.off_days.is_off_day(self.current_day)
+    }
+
+    /// Record the input events sampled during the last second (`None` when
+    /// input isn't sampled)
+    pub fn record_input(&mut self, events: Option<u64>) {
+        let Some(events) = events else {
+            return;
+        };
+        let now = self.now();
+        if events > 0 || self.last_input.is_none() {
+            self.last_input = Some(now);
+        }
+        let idle = self
+            .last_input
+            .is_some_and(|last| (now - last).num_seconds() >= self.idle_after);
+        let idle_after = self.idle_after;
+        self.set_idle(idle, || format!("No input for {}s", idle_after));
+    }
+
+    /// Pause tracking while idle, `why` telling what says so
+    fn set_idle(&mut self, idle: bool, why: impl FnOnce() -> String) {
+        let now = self.now();
+        match (std::mem::replace(&mut self.idle, idle), idle) {
+            (false, true) => {
+                self.log.line(format!(
+                    "[{}] 💤 {}, tracking paused",
+                    now.format("%H:%M:%S"),
+                    why()
+                ));
+                // A pause of the user's own is left alone, now and on return
+                if self.pause_when_idle && self.pause() {
+                    self.idle_paused = true;
+                }
+            }
+            (true, false) => {
+                self.log
+                    .line(format!("[{}] Input resumed", now.format("%H:%M:%S")));
+                if std::mem::take(&mut self.idle_paused) {
+                    self.resume();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the running work session was spent idle for at least
+    /// `[input] abandon_after` of its time, e.g. after leaving without
+    /// pausing. Only known with input sampling.
+    pub fn is_abandoned(&self) -> bool {
+        let elapsed = self.get_mode_duration();
+        self.mode == PomodoroMode::Work
+            && elapsed > 0
+            && self.idle_seconds as f64 >= self.abandon_after * elapsed as f64
+    }
+
+    /// Seconds of the current phase spent idle
+    pub fn idle_seconds(&self) -> i64 {
+        self.idle_seconds
+    }
+
+    /// Whether there was no input for `[input] idle_after`
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Credit one second to the current domain and bring the budgets up to date
+    pub fn tick(&mut self) {
+        self.credit_second();
+        self.budget_statuses = self.budgets.statuses(&self.store, self.now());
+    }
+
+    fn credit_second(&mut self) {
+        self.check_day_rollover();
+        self.check_heartbeat();
+        if self.is_off_day() && !self.off_days.track {
+            return;
+        }
+        if self.is_paused() {
+            self.persist(false);
+            return;
+        }
+        if self.is_idle() {
+            self.idle_seconds += 1;
+            self.persist(false);
+            return;
+        }
+        if let Some(current) = self.current_domain.clone() {
+            let now = self.now();
+            self.store.record_second(
+                &current,
+                self.current_category
+                    .as_ref()
+                    .map(|(name, source)| (name.as_str(), *source)),
+                self.current_profile.as_deref(),
+                self.mode.is_break(),
+                now,
+            );
+        }
+        self.persist(false);
+    }
+
+    /// Write the open segment now, e.g. before the daemon exits
+    pub fn flush(&mut self) {
+        self.persist(true);
+    }
+
+    /// Distraction ratio when the rolling focus alert should fire
+    pub fn check_focus(&mut self) -> Option<f64> {
+        if self.is_off_day() || self.is_paused() || self.mode != PomodoroMode::Work {
+            return None;
+        }
+        let now = self.now();
+        self.focus.check(&self.store, now)
+    }
+
+    /// A category budget the current site is past, when it was just used up
+    /// or went another `CATEGORY_BUDGET_STEP` over since the last alert
+    pub fn check_category_budget(&mut self) -> Option<CategoryBudgetAlert> {
+        if self.is_off_day() || self.is_paused() {
+            return None;
+        }
+        let (categories, _) = self.current_category.as_ref()?;
+        let (category, over_by) = self
+            .budget_statuses
+            .iter()
+            .filter(|budget| {
+                budget.category
+                    && categories
+                        .split(',')
+                        .any(|name| name.trim().eq_ignore_ascii_case(&budget.target))
+            })
+            .find_map(|budget| {
+                Some((budget.target.clone(), -budget.remaining.filter(|r| *r < 0)?))
+            })?;
+        let level = 1 + (over_by / CATEGORY_BUDGET_STEP) as u32;
+        let alerted = self.budget_alerts.entry(category.clone()).or_default();
+        if level <= *alerted {
+            return None;
+        }
+        *alerted = level;
+        Some(CategoryBudgetAlert {
+            category,
+            over_by,
+            level,
+        })
+    }
+
+    /// A `limits` entry the current site just used 80% of, or all of
+    pub fn check_limit(&mut self) -> Option<LimitAlert> {
+        if self.is_off_day() || self.is_paused() {
+            return None;
+        }
+        let now = self.now();
+        let domain = self.current_domain.as_deref()?;
+        self.limits.check(&self.store, now, domain)
+    }
+
+    /// Sites that took time last week without a category, once when a new
+    /// week starts (with `[triage] notify`)
+    pub fn check_triage(&mut self) -> Option<Vec<(String, i64)>> {
+        let untriaged = std::mem::take(&mut self.untriaged);
+        (!untriaged.is_empty()).then_some(untriaged)
+    }
+
+    fn get_session_duration(&self) -> i64 {
+        (self.now() - self.session_start).num_seconds()
+    }
+
+    pub fn get_target_seconds(&self) -> i64 {
+        self.break_length
+            .unwrap_or_else(|| self.length_of(self.mode))
+            + self.snoozed
+    }
+
+    /// With `[breaks] adaptive`, the break the running work session earned
+    /// so far, from its length and focus; `None` otherwise
+    pub fn suggested_break(&self) -> Option<i64> {
+        if !self.breaks.adaptive
+            || self.mode != PomodoroMode::Work
+            || self.continuous
+            || self.is_abandoned()
+        {
+            return None;
+        }
+        let session = TimeRange::new(self.mode_start, self.now());
+        let focus = 1.0 - self.focus.distraction_ratio_in(&self.store, &session);
+        Some(adaptive::suggest_break(
+            self.length_of(self.next_mode()),
+            self.length_of(PomodoroMode::Work),
+            self.get_mode_duration(),
+            focus,
+        ))
+    }
+
+    fn length_of(&self, mode: PomodoroMode) -> i64 {
+        match mode {
+            PomodoroMode::Work => self.session_lengths.work,
+            PomodoroMode::Break => self.session_lengths.break_,
+            PomodoroMode::LongBreak => self.session_lengths.long_break,
+        }
+    }
+
+    /// Phase `switch_mode` goes to: the break after the last work session
+    /// of a cycle is a long one, and in continuous mode there are none
+    pub fn next_mode(&self) -> PomodoroMode {
+        let after = self.session_lengths.long_break_after;
+        match self.mode {
+            PomodoroMode::Work if self.continuous => PomodoroMode::Work,
+            PomodoroMode::Work if after > 0 && !self.is_abandoned() && self.cycle + 1 >= after => {
+                PomodoroMode::LongBreak
+            }
+            PomodoroMode::Work => PomodoroMode::Break,
+            PomodoroMode::Break | PomodoroMode::LongBreak => PomodoroMode::Work,
+        }
+    }
+
+    /// What a switch to `domain` at `url` calls for during this work session,
+    /// counting it if blocked. `hint` is the category sent by the extension.
+    pub fn enforce_visit(
+        &mut self,
+        domain: &str,
+        url: &str,
+        hint: Option<&str>,
+    ) -> Option<Enforcement> {
+        if self.mode != PomodoroMode::Work || self.is_off_day() {
+            return None;
+        }
+        let over_budget: Vec<String> = self
+            .budget_statuses
+            .iter()
+            .filter(|budget| {
+                !budget.category && !budget.off_day && budget.remaining.is_some_and(|r| r < 0)
+            })
+            .map(|budget| budget.target.clone())
+            .collect();
+        let category = self.categories.categorize(domain, hint);
+        let enforcement = self.visits.enforce(
+            domain,
+            url,
+            category.as_ref().map(|(category, _)| category.as_str()),
+            &over_budget,
+        );
+        self.blocked_stay = match (&enforcement, self.grace) {
+            (Some(Enforcement::Block { target, .. }), Some(_)) => {
+                Some((target.clone(), domain.to_string(), self.now()))
+            }
+            _ => None,
+        };
+        enforcement
+    }
+
+    /// The blocked target whose site still has focus `[focus_alerts] grace`
+    /// after the visit began, once per visit. Time in other windows counts,
+    /// so coming back to the site after the grace period acts right away.
+    pub fn check_blocked_stay(&mut self) -> Option<String> {
+        let grace = self.grace?;
+        let (_, domain, since) = self.blocked_stay.as_ref()?;
+        if self.is_paused()
+            || self.current_domain.as_ref() != Some(domain)
+            || (self.now() - *since).num_seconds() < grace
+        {
+            return None;
+        }
+        self.blocked_stay.take().map(|(target, _, _)| target)
+    }
+
+    /// Whether the current page was let through by an `allowed_urls`
+    /// exception, so the next page on the same site is checked too
+    pub fn on_allowed_url(&self) -> bool {
+        self.visits.is_excepted()
+    }
+
+    pub fn remaining_seconds(&self) -> i64 {
+        (self.get_target_seconds() - self.get_mode_duration()).max(0)
+    }
+
+    pub fn should_switch_mode(&mut self) -> bool {
+        if self.skip_requested {
+            return true;
+        }
+        !(self.manual && self.mode.is_break())
+            && self.get_mode_duration() >= self.get_target_seconds()
+    }
+
+    fn today(&self) -> TimeRange {
+        self.calendar.day_range(self.calendar.day_of(self.now()))
+    }
+
+    fn today_stats(&self) -> TodayStats {
+        let today = self.today();
+        let aggregator = Aggregator::new(&self.store);
+        TodayStats {
+            total_seconds: aggregator.total_seconds(&today),
+            domains: aggregator
+                .top_domains(&today, usize::MAX)
+                .into_iter()
+                .map(|(domain, seconds)| DomainTotal { domain, seconds })
+                .collect(),
+        }
+    }
+
+    /// One page of per-day history, read from storage since the tracker only
+    /// keeps recent days in memory
+    fn history(&mut self, request: HistoryRequest) -> Result<HistoryPage, Box<dyn Error>> {
+        let to = request.to.unwrap_or(self.current_day);
+        let from = request.from.unwrap_or(to - chrono::Duration::days(29));
+        let pages = DayPages::new(from, to, request.page_size)?;
+        let days = pages.days(request.page);
+
+        let store = match (days.first(), days.last()) {
+            (Some(first), Some(last)) => {
+                // Include the open segment
+                self.persist(true);
+                let range = TimeRange::new(
+                    self.calendar.day_begin(*first),
+                    self.calendar.day_range(*last).end,
+                );
+                self.storage.load(&range)?
+            }
+            _ => ActivityStore::default(),
+        };
+        let aggregator = Aggregator::new(&store);
+        Ok(HistoryPage {
+            from,
+            to,
+            page: request.page,
+            pages: pages.pages(),
+            days: days
+                .into_iter()
+                .map(|date| {
+                    let range = self.calendar.day_range(date);
+                    DayHistory {
+                        date,
+                        total_seconds: aggregator.total_seconds(&range),
+                        pomodoros: aggregator.pomodoros(&range).len() as u32,
+                        categories: aggregator
+                            .total_by_category(&range)
+                            .into_iter()
+                            .map(|(category, seconds)| CategoryTotal { category, seconds })
+                            .collect(),
+                        domains: aggregator
+                            .top_domains(&range, request.top_domains)
+                            .into_iter()
+                            .map(|(domain, seconds)| DomainTotal { domain, seconds })
+                            .collect(),
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    /// Answer a client request received over the WebSocket API
+    pub fn handle_request(&mut self, request: ClientMessage) -> ServerMessage {
+        match request {
+            ClientMessage::GetTodayStats => ServerMessage::TodayStats(self.today_stats()),
+            ClientMessage::GetHistory(request) => match self.history(request) {
+                Ok(page) => ServerMessage::History(page),
+                Err(e) => ServerMessage::Error {
+                    message: e.to_string(),
+                },
+            },
+            ClientMessage::Pause => {
+                if self.pause() {
+                    ServerMessage::Ack {
+                        message: format!(
+                            "Paused {} session, {} left",
+                            self.mode.as_str(),
+                            format_duration(self.remaining_seconds())
+                        ),
+                    }
+                } else {
+                    ServerMessage::Error {
+                        message: "Already paused".to_string(),
+                    }
+                }
+            }
+            ClientMessage::GetStatus => ServerMessage::TimerState(self.timer_state()),
+            ClientMessage::Skip => {
+                if self.skip_requested {
+                    return ServerMessage::Error {
+                        message: "Already skipping".to_string(),
+                    };
+                }
+                let current = self.mode;
+                let next = self.skip();
+                ServerMessage::Ack {
+                    message: format!(
+                        "Skipped {} session, {} starts now",
+                        current.as_str(),
+                        next.as_str()
+                    ),
+                }
+            }
+            ClientMessage::Reset => {
+                self.reset();
+                ServerMessage::Ack {
+                    message: format!(
+                        "Reset: new cycle, {} left in the WORK session",
+                        format_duration(self.remaining_seconds())
+                    ),
+                }
+            }
+            ClientMessage::Start => match self.start() {
+                Ok(()) => ServerMessage::Ack {
+                    message: "WORK session starts now".to_string(),
+                },
+                Err(message) => ServerMessage::Error { message },
+            },
+            ClientMessage::Snooze { minutes } => {
+                let seconds = minutes.map_or(self.breaks.snooze, |minutes| i64::from(minutes) * 60);
+                if seconds <= 0 {
+                    return ServerMessage::Error {
+                        message: "Snooze for at least a minute".to_string(),
+                    };
+                }
+                match self.snooze(seconds) {
+                    Ok(()) => ServerMessage::Ack {
+                        message: format!(
+                            "Break snoozed: {} more of WORK",
+                            format_duration(self.remaining_seconds())
+                        ),
+                    },
+                    Err(message) => ServerMessage::Error { message },
+                }
+            }
+            ClientMessage::SetTimerMode { mode } => {
+                if !self.set_timer_mode(mode) {
+                    return ServerMessage::Error {
+                        message: format!("Already in {} mode", mode.name()),
+                    };
+                }
+                ServerMessage::Ack {
+                    message: match mode {
+                        TimerMode::Continuous => format!(
+                            "Continuous mode: no breaks, a summary after every {} of work",
+                            format_duration(self.length_of(PomodoroMode::Work))
+                        ),
+                        TimerMode::Pomodoro => format!(
+                            "Pomodoro mode: a break follows this work session, {} left",
+                            format_duration(self.remaining_seconds())
+                        ),
+                    },
+                }
+            }
+            ClientMessage::Categorize { domain, category } => {
+                let domain = domain.trim().to_lowercase();
+                let category = category.trim();
+                if domain.is_empty() || category.is_empty() {
+                    return ServerMessage::Error {
+                        message: "Both a domain and a category are needed".to_string(),
+                    };
+                }
+                let seconds = self.categorize_domain(&domain, category);
+                ServerMessage::Ack {
+                    message: format!(
+                        "{} is now {}, {} of today re-tagged",
+                        domain,
+                        category,
+                        format_duration(seconds)
+                    ),
+                }
+            }
+            ClientMessage::Resume => match self.resume() {
+                Some(paused_for) => ServerMessage::Ack {
+                    message: format!(
+                        "Resumed {} session after {}, {} left",
+                        self.mode.as_str(),
+                        format_duration(paused_for),
+                        format_duration(self.remaining_seconds())
+                    ),
+                },
+                None => ServerMessage::Error {
+                    message: "Not paused".to_string(),
+                },
+            },
+            ClientMessage::TabUpdate(_)
+            | ClientMessage::TabClosed(_)
+            | ClientMessage::WindowClosed(_)
+            | ClientMessage::Heartbeat(_)
+            | ClientMessage::BreakAck { .. } => ServerMessage::Error {
+                message: "Browser activity is not a request".to_string(),
+            },
+            ClientMessage::Attach
+            | ClientMessage::Handoff
+            | ClientMessage::Record { .. }
+            | ClientMessage::Inspect { .. } => ServerMessage::Error {
+                message: "Only accepted on the control socket".to_string(),
+            },
+        }
+    }
+
+    pub fn timer_state(&self) -> TimerState {
+        TimerState {
+            mode: self.mode.as_str().to_string(),
+            remaining_seconds: self.remaining_seconds(),
+            duration_seconds: self.get_target_seconds(),
+            pomodoros_today: Aggregator::new(&self.store).pomodoros(&self.today()).len() as u32,
+            cycle: self.cycle,
+            cycle_length: self.session_lengths.long_break_after,
+            daily_goal: if self.is_off_day() {
+                0
+            } else {
+                self.daily_goal
+            },
+            current_domain: self.current_domain.clone(),
+            current_category: self.current_category.as_ref().map(|(name, _)| name.clone()),
+            block: self.active_block.as_ref().map(|block| block.label.clone()),
+            category_source: self.current_category.as_ref().map(|(_, source)| *source),
+            budgets: self.budget_statuses.clone(),
+            paused: self.is_paused(),
+            waiting: self.is_waiting(),
+            continuous: self.continuous,
+            pace_seconds: self.pace.pace(&self.store, self.now()),
+            extensions: self.extensions,
+        }
+    }
+
+    pub fn print_stats(&self) {
+        let mut lines = vec![
+            "\n--- Session Statistics ---".to_string(),
+            format!(
+                "Session duration: {} minutes",
+                self.get_session_duration() / 60
+            ),
+            "\nTime spent per domain:".to_string(),
+        ];
+
+        let session = TimeRange::new(self.session_start, self.now());
+        let aggregator = Aggregator::new(&self.store);
+        for (domain, seconds) in aggregator.top_domains(&session, usize::MAX) {
+            lines.push(format!(
+                "  {} - {}m {}s",
+                domain,
+                seconds / 60,
+                seconds % 60
+            ));
+        }
+
+        let by_category = aggregator.total_by_category(&session);
+        if !by_category.is_empty() {
+            lines.push("\nTime spent per category:".to_string());
+            for (category, seconds) in by_category {
+                lines.push(format!(
+                    "  {} - {}m {}s",
+                    category,
+                    seconds / 60,
+                    seconds % 60
+                ));
+            }
+        }
+        lines.push("------------------------\n".to_string());
+        for line in lines {
+            self.log.line(line);
+        }
+    }
+}
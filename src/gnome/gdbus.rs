@@ -0,0 +1,83 @@
+use crate::window::provider::ActiveWindow;
+
+/// Where the companion extension in `gnome-extension/` exports the focused
+/// window
+pub const OBJECT_PATH: &str = "/org/gnome/Shell/Extensions/StopIt";
+pub const METHOD: &str = "org.gnome.Shell.Extensions.StopIt.FocusedWindow";
+
+/// Whether we are running inside a GNOME Wayland session (GNOME on X11 is
+/// covered by `xprop`)
+pub fn is_available() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        && std::env::var("XDG_CURRENT_DESKTOP")
+            .is_ok_and(|desktop| desktop.split(':').any(|name| name == "GNOME"))
+}
+
+/// The window in `gdbus call` output for `FocusedWindow`, e.g.
+/// `('firefox', 'GitHub — Mozilla Firefox')`; empty strings when no window
+/// has focus
+pub fn parse_focused_window(output: &str) -> Option<ActiveWindow> {
+    let mut strings = gvariant_strings(output).into_iter();
+    Some(ActiveWindow {
+        class: strings.next()?,
+        title: strings.next()?,
+    })
+}
+
+/// The string literals of a printed GVariant, e.g. `('a', "it's")` ->
+/// `["a", "it's"]`. GLib quotes with `'` unless the string holds one, and
+/// escapes control characters as `\n` or `\u0001`.
+fn gvariant_strings(value: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current: Option<(char, String)> = None; // quote and content so far
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (&mut current, c) {
+            (None, '\'' | '"') => current = Some((c, String::new())),
+            (None, _) => {}
+            (Some((_, string)), '\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('r') => string.push('\r'),
+                Some('a') => string.push('\x07'),
+                Some('b') => string.push('\x08'),
+                Some('f') => string.push('\x0c'),
+                Some('v') => string.push('\x0b'),
+                Some(unicode @ ('u' | 'U')) => {
+                    let digits = if unicode == 'u' { 4 } else { 8 };
+                    let hex: String = chars.by_ref().take(digits).collect();
+                    string.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+                }
+                escaped => string.extend(escaped),
+            },
+            (Some((quote, _)), c) if c == *quote => {
+                strings.extend(current.take().map(|(_, string)| string))
+            }
+            (Some((_, string)), c) => string.push(c),
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gdbus_output_yields_the_focused_window() {
+        let window = parse_focused_window("('firefox', 'GitHub — Mozilla Firefox')\n").unwrap();
+        assert_eq!(window.class, "firefox");
+        assert_eq!(window.title, "GitHub — Mozilla Firefox");
+        // Titles holding a single quote are double-quoted, control characters escaped
+        let window = parse_focused_window(
+            "('org.gnome.TextEditor', \"Rust's book\\t\\u00a0\\\"draft\\\"\")",
+        )
+        .unwrap();
+        assert_eq!(window.class, "org.gnome.TextEditor");
+        assert_eq!(window.title, "Rust's book\t\u{a0}\"draft\"");
+        // Nothing has focus, e.g. on an empty workspace
+        let window = parse_focused_window("('', '')").unwrap();
+        assert_eq!((window.class.as_str(), window.title.as_str()), ("", ""));
+        assert_eq!(parse_focused_window(""), None);
+    }
+}
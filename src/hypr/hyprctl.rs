@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tokio::process::Command;
+
+/// The subset of `hyprctl -j activeworkspace` we use
+#[derive(Debug, Clone, Deserialize)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Runs hyprctl against one Hyprland instance. The instance signature can be
+/// re-resolved after the compositor restarts and gets a new one.
+#[derive(Debug, Clone, Default)]
+pub struct Hyprctl {
+    instance: Option<String>, // None: whatever HYPRLAND_INSTANCE_SIGNATURE says
+}
+
+/// Whether we are running inside a Hyprland session
+pub fn is_available() -> bool {
+    std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() || latest_instance().is_some()
+}
+
+/// Signature of the most recently started Hyprland instance, from the
+/// sockets under `$XDG_RUNTIME_DIR/hypr` (or `/tmp/hypr` on older versions)
+pub fn latest_instance() -> Option<String> {
+    let runtime_dirs = [
+        std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("hypr")),
+        Some(PathBuf::from("/tmp/hypr")),
+    ];
+
+    runtime_dirs
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().join(".socket.sock").exists())
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, entry.file_name().into_string().ok()?))
+        })
+        .max_by_key(|(modified, _): &(SystemTime, String)| *modified)
+        .map(|(_, signature)| signature)
+}
+
+impl Hyprctl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switch to the newest running instance, e.g. after a compositor restart
+    pub fn reresolve(&mut self) {
+        if let Some(signature) = latest_instance() {
+            self.instance = Some(signature);
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("hyprctl");
+        if let Some(instance) = &self.instance {
+            command.args(["--instance", instance]);
+        }
+        command
+    }
+
+    pub async fn active_workspace(&self) -> Result<Workspace, Box<dyn std::error::Error>> {
+        let output = self
+            .command()
+            .args(["-j", "activeworkspace"])
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(format!("hyprctl activeworkspace failed: {}", output.status).into());
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    /// Run `hyprctl dispatch <dispatcher> <args>`
+    pub async fn dispatch(
+        &self,
+        dispatcher: &str,
+        args: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let output = self
+            .command()
+            .args(["dispatch", dispatcher, args])
+            .output()
+            .await?;
+        // hyprctl exits 0 even when the dispatcher fails, answering with the error
+        let reply = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || reply.trim() != "ok" {
+            return Err(format!("hyprctl dispatch {} failed: {}", dispatcher, reply.trim()).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,3 @@
+pub mod hyprctl;
+pub mod socket2;
+pub mod workspace_label;
@@ -0,0 +1,204 @@
+use crate::hypr::hyprctl::latest_instance;
+use crate::window::provider::ActiveWindow;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Longest pause between connection attempts while Hyprland is unreachable
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Hyprland's event socket, `$XDG_RUNTIME_DIR/hypr/<signature>/.socket2.sock`
+/// (or under `/tmp/hypr` on older versions). Falls back to the newest
+/// instance when HYPRLAND_INSTANCE_SIGNATURE is unset or stale.
+pub fn socket_path() -> Option<PathBuf> {
+    let runtime_dirs = [
+        std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("hypr")),
+        Some(PathBuf::from("/tmp/hypr")),
+    ];
+    let find = |signature: &str| {
+        runtime_dirs
+            .iter()
+            .flatten()
+            .map(|dir| dir.join(signature).join(".socket2.sock"))
+            .find(|path| path.exists())
+    };
+    std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .ok()
+        .and_then(|signature| find(&signature))
+        .or_else(|| find(&latest_instance()?))
+}
+
+/// The window in an `activewindow>>class,title` event line; classes hold no
+/// commas, titles may. Both are empty when focus moves to an empty
+/// workspace. Other events give `None`.
+pub fn parse_active_window(line: &str) -> Option<ActiveWindow> {
+    let (class, title) = line.strip_prefix("activewindow>>")?.split_once(',')?;
+    Some(ActiveWindow {
+        class: class.to_string(),
+        title: title.to_string(),
+    })
+}
+
+/// Follows `activewindow` events on the event socket in a background
+/// thread, so the focused window is known without running hyprctl
+#[derive(Debug)]
+pub struct EventListener {
+    focused: Arc<Mutex<Option<ActiveWindow>>>, // None until the first event
+    connected: Arc<AtomicBool>,
+}
+
+impl EventListener {
+    /// Listen on the socket `socket` finds, looked up again on every
+    /// reconnect since a restarted compositor gets a new signature
+    pub fn spawn(socket: impl Fn() -> Option<PathBuf> + Send + 'static) -> Self {
+        let listener = Self {
+            focused: Arc::new(Mutex::new(None)),
+            connected: Arc::new(AtomicBool::new(false)),
+        };
+        let focused = Arc::clone(&listener.focused);
+        let connected = Arc::clone(&listener.connected);
+        thread::spawn(move || follow(socket, &focused, &connected));
+        listener
+    }
+
+    /// The focused window, `None` while disconnected or before the first
+    /// event arrived
+    pub fn latest(&self) -> Option<ActiveWindow> {
+        if !self.connected.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.focused.lock().ok()?.clone()
+    }
+
+    /// Start from `window`, read some other way, until an event arrives.
+    /// The socket only reports changes.
+    pub fn seed(&self, window: &ActiveWindow) {
+        if self.connected.load(Ordering::Relaxed)
+            && let Ok(mut focused) = self.focused.lock()
+            && focused.is_none()
+        {
+            *focused = Some(window.clone());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn connect(path: &std::path::Path) -> std::io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(path)
+}
+
+/// Hyprland only runs where there are Unix sockets
+#[cfg(not(unix))]
+fn connect(_path: &std::path::Path) -> std::io::Result<std::fs::File> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+fn follow(
+    socket: impl Fn() -> Option<PathBuf>,
+    focused: &Mutex<Option<ActiveWindow>>,
+    connected: &AtomicBool,
+) {
+    let mut backoff = Duration::ZERO;
+    let mut reported = false;
+    loop {
+        let stream = socket()
+            .ok_or_else(|| "no event socket found".to_string())
+            .and_then(|path| connect(&path).map_err(|e| format!("{}: {}", path.display(), e)));
+        match stream {
+            Ok(stream) => {
+                backoff = Duration::ZERO;
+                reported = false;
+                connected.store(true, Ordering::Relaxed);
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    if let Some(window) = parse_active_window(&line)
+                        && let Ok(mut focused) = focused.lock()
+                    {
+                        *focused = Some(window);
+                    }
+                }
+                connected.store(false, Ordering::Relaxed);
+                if let Ok(mut focused) = focused.lock() {
+                    *focused = None;
+                }
+            }
+            Err(e) if !reported => {
+                eprintln!(
+                    "Hyprland: cannot follow window events ({}), polling hyprctl instead",
+                    e
+                );
+                reported = true;
+            }
+            Err(_) => {}
+        }
+        backoff = (backoff * 2).clamp(Duration::from_secs(1), MAX_BACKOFF);
+        thread::sleep(backoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn hyprland_events_yield_the_focused_window() {
+        let window = parse_active_window("activewindow>>kitty,vim: a, b").unwrap();
+        assert_eq!(
+            (window.class.as_str(), window.title.as_str()),
+            ("kitty", "vim: a, b")
+        );
+        assert!(parse_active_window("activewindowv2>>5612a8c0").is_none());
+        assert!(parse_active_window("workspace>>2").is_none());
+
+        let dir = std::env::temp_dir().join(format!("stop_it-test-socket2-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".socket2.sock");
+        let server = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let events = EventListener::spawn(move || Some(path.clone()));
+        let latest = |expected: Option<(&str, &str)>| {
+            for _ in 0..200 {
+                let window = events.latest();
+                let window = window
+                    .as_ref()
+                    .map(|window| (window.class.as_str(), window.title.as_str()));
+                if window == expected {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            panic!("never saw {:?}", expected);
+        };
+
+        let (mut stream, _) = server.accept().unwrap();
+        // Nothing known until an event arrives or the window is read otherwise;
+        // seeding only counts once the listener thread noted the connection
+        latest(None);
+        let firefox = ActiveWindow {
+            class: "firefox".to_string(),
+            title: "GitHub".to_string(),
+        };
+        for _ in 0..200 {
+            events.seed(&firefox);
+            if events.latest().is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        latest(Some(("firefox", "GitHub")));
+        writeln!(stream, "workspace>>2\nactivewindow>>kitty,~/src").unwrap();
+        latest(Some(("kitty", "~/src")));
+        writeln!(stream, "activewindow>>,").unwrap();
+        latest(Some(("", "")));
+        // Gone with the compositor, so the provider falls back to hyprctl
+        drop(stream);
+        latest(None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::daemon::log;
+use crate::hypr::hyprctl::{Hyprctl, Workspace};
+use crate::ws::protocol::{ServerMessage, TimerState};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Longest pause between attempts while Hyprland is unreachable
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Show the current mode and remaining minutes in the name of the active
+/// Hyprland workspace, e.g. "2 🍅 24m", so the timer is visible without a bar.
+/// The original name is restored when focus moves to another workspace.
+pub async fn run(mut states: broadcast::Receiver<ServerMessage>) {
+    let mut label = WorkspaceLabel {
+        hyprctl: Hyprctl::new(),
+        labeled: None,
+    };
+    let mut backoff = Duration::ZERO;
+    let mut retry_at = Instant::now();
+
+    loop {
+        let state = match states.recv().await {
+            Ok(ServerMessage::TimerState(state)) => state,
+            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        if Instant::now() < retry_at {
+            continue;
+        }
+
+        match label.update(&state).await {
+            Ok(()) if !backoff.is_zero() => {
+                log::print("Hyprland: connection restored");
+                backoff = Duration::ZERO;
+            }
+            Ok(()) => {}
+            Err(e) => {
+                // Warn once, then retry quietly with exponential backoff
+                if backoff.is_zero() {
+                    eprintln!("Hyprland: {} (retrying in the background)", e);
+                }
+                backoff = (backoff * 2).clamp(Duration::from_secs(1), MAX_BACKOFF);
+                retry_at = Instant::now() + backoff;
+                // The compositor may have restarted under a new instance
+                // signature, taking our workspace names with it
+                label.hyprctl.reresolve();
+                label.labeled = None;
+            }
+        }
+    }
+
+    label.restore_labeled().await;
+}
+
+struct WorkspaceLabel {
+    hyprctl: Hyprctl,
+    labeled: Option<(Workspace, String)>, // original workspace, label shown
+}
+
+impl WorkspaceLabel {
+    async fn update(&mut self, state: &TimerState) -> Result<(), Box<dyn std::error::Error>> {
+        let label = label_for(state);
+        let active = self.hyprctl.active_workspace().await?;
+
+        match &self.labeled {
+            Some((workspace, shown)) if workspace.id == active.id && *shown == label => {
+                return Ok(());
+            }
+            Some((workspace, _)) if workspace.id != active.id => self.restore_labeled().await,
+            _ => {}
+        }
+
+        // `active.name` already carries our label when we renamed it before
+        let original = match self.labeled.take() {
+            Some((workspace, _)) if workspace.id == active.id => workspace,
+            _ => Workspace {
+                // Drop a label left behind by a previous run
+                name: strip_label(&active.name).to_string(),
+                ..active
+            },
+        };
+        let name = format!("{} {}", original.name, label);
+        self.hyprctl
+            .dispatch("renameworkspace", &format!("{} {}", original.id, name))
+            .await?;
+        self.labeled = Some((original, label));
+        Ok(())
+    }
+
+    async fn restore_labeled(&mut self) {
+        if let Some((workspace, _)) = self.labeled.take() {
+            let args = format!("{} {}", workspace.id, workspace.name);
+            if let Err(e) = self.hyprctl.dispatch("renameworkspace", &args).await {
+                eprintln!("Hyprland: {}", e);
+            }
+        }
+    }
+}
+
+fn strip_label(name: &str) -> &str {
+    [" 🍅 ", " ☕ "]
+        .iter()
+        .find_map(|marker| name.find(marker).map(|i| &name[..i]))
+        .unwrap_or(name)
+}
+
+/// Minute resolution keeps the number of dispatches low
+fn label_for(state: &TimerState) -> String {
+    let emoji = if state.mode == "WORK" { "🍅" } else { "☕" };
+    format!("{} {}m", emoji, (state.remaining_seconds + 59) / 60)
+}
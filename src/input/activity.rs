@@ -0,0 +1,130 @@
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+/// Offset of the event type within `struct input_event`, after its
+/// timestamp of two `__kernel_ulong_t`: 16 on 64-bit Linux, 8 on 32-bit
+const TYPE_OFFSET: usize = 2 * size_of::<std::ffi::c_ulong>();
+
+/// Size of `struct input_event`: the timestamp, then type, code (`u16`) and
+/// value (`i32`)
+const INPUT_EVENT_SIZE: usize = TYPE_OFFSET + 8;
+
+/// Where `[input] sample` learns about keyboard/mouse activity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputSource {
+    Evdev,   // the devices in /dev/input, opt-in: needs the `input` group
+    Wayland, // the ext-idle-notify protocol, through swayidle
+    #[default]
+    Logind, // the session's IdleHint, set by the desktop after its own idle delay
+}
+
+impl InputSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            InputSource::Evdev => "evdev",
+            InputSource::Wayland => "wayland",
+            InputSource::Logind => "logind",
+        }
+    }
+
+    /// Whether this build can sample input this way: evdev is Linux's,
+    /// swayidle and logind come with Linux and BSD desktops
+    pub fn is_supported(self) -> bool {
+        match self {
+            InputSource::Evdev => cfg!(target_os = "linux"),
+            InputSource::Wayland | InputSource::Logind => cfg!(all(unix, not(target_os = "macos"))),
+        }
+    }
+}
+
+/// Reports how much keyboard/mouse input happened, without what it was
+pub trait InputActivity: Send {
+    /// Input events since the previous call, `None` when unknown
+    fn events(&mut self) -> Option<u64>;
+}
+
+/// Input sampling disabled
+#[derive(Debug, Default)]
+pub struct NoInputActivity;
+
+impl InputActivity for NoInputActivity {
+    fn events(&mut self) -> Option<u64> {
+        None
+    }
+}
+
+/// Counts events from the evdev devices in `/dev/input`. Only the event
+/// type is looked at, to skip synchronization markers: key codes and
+/// pointer positions are never read. Needs read access to the devices,
+/// usually membership in the `input` group.
+#[derive(Debug)]
+pub struct EvdevActivity {
+    count: Arc<AtomicU64>,
+    last: u64,
+}
+
+impl EvdevActivity {
+    pub fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        let count = Arc::new(AtomicU64::new(0));
+        let mut opened = 0;
+        let mut denied = false;
+
+        for entry in fs::read_dir("/dev/input")? {
+            let path = entry?.path();
+            let is_event_device = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("event"));
+            if !is_event_device {
+                continue;
+            }
+            match File::open(&path) {
+                Ok(device) => {
+                    let count = Arc::clone(&count);
+                    thread::spawn(move || count_events(device, &count));
+                    opened += 1;
+                }
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => denied = true,
+                Err(_) => {}
+            }
+        }
+
+        if opened == 0 {
+            return Err(if denied {
+                "Cannot read /dev/input: add your user to the `input` group or disable [input] sample"
+                    .into()
+            } else {
+                "No input devices found in /dev/input".into()
+            });
+        }
+        Ok(Self { count, last: 0 })
+    }
+}
+
+impl InputActivity for EvdevActivity {
+    fn events(&mut self) -> Option<u64> {
+        let count = self.count.load(Ordering::Relaxed);
+        let events = count - self.last;
+        self.last = count;
+        Some(events)
+    }
+}
+
+fn count_events(mut device: File, count: &AtomicU64) {
+    let mut buffer = [0u8; INPUT_EVENT_SIZE * 64];
+    while let Ok(read) = device.read(&mut buffer) {
+        if read == 0 {
+            break;
+        }
+        let events = buffer[..read]
+            .chunks_exact(INPUT_EVENT_SIZE)
+            .filter(|event| event[TYPE_OFFSET..TYPE_OFFSET + 2] != [0, 0]) // EV_SYN
+            .count();
+        count.fetch_add(events as u64, Ordering::Relaxed);
+    }
+}
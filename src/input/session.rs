@@ -0,0 +1,149 @@
+use crate::input::activity::InputActivity;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const UNKNOWN: u8 = 0;
+const ACTIVE: u8 = 1;
+const IDLE: u8 = 2;
+
+/// How often logind's IdleHint is read
+const LOGIND_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether the session is idle, as the latest line of `swayidle` output or
+/// `loginctl` answer said. Counts as one input event per second while
+/// active, so `[input] idle_after` applies the same way as with evdev.
+fn events(state: &AtomicU8) -> Option<u64> {
+    match state.load(Ordering::Relaxed) {
+        ACTIVE => Some(1),
+        IDLE => Some(0),
+        _ => None,
+    }
+}
+
+/// Learns about input through the compositor's ext-idle-notify protocol,
+/// with `swayidle` reporting idle after a second without input. Works with
+/// any compositor that supports the protocol (Sway, Hyprland, KDE, niri),
+/// without access to /dev/input.
+#[derive(Debug)]
+pub struct WaylandIdle {
+    swayidle: Child,
+    state: Arc<AtomicU8>,
+}
+
+impl WaylandIdle {
+    pub fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            return Err("No Wayland session found".into());
+        }
+        let mut swayidle = Command::new("swayidle")
+            .args(["-w", "timeout", "1", "echo idle", "resume", "echo active"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Cannot run swayidle: {}", e))?;
+        let stdout = swayidle.stdout.take().ok_or("swayidle has no output")?;
+        // Active until swayidle says otherwise: it only reports changes
+        let state = Arc::new(AtomicU8::new(ACTIVE));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match line.trim() {
+                    "idle" => shared.store(IDLE, Ordering::Relaxed),
+                    "active" => shared.store(ACTIVE, Ordering::Relaxed),
+                    _ => {}
+                }
+            }
+            eprintln!("swayidle exited: idle detection stopped");
+            shared.store(UNKNOWN, Ordering::Relaxed);
+        });
+        Ok(Self { swayidle, state })
+    }
+}
+
+impl InputActivity for WaylandIdle {
+    fn events(&mut self) -> Option<u64> {
+        events(&self.state)
+    }
+}
+
+impl Drop for WaylandIdle {
+    fn drop(&mut self) {
+        let _ = self.swayidle.kill();
+        let _ = self.swayidle.wait();
+    }
+}
+
+/// Reads logind's IdleHint for this session (or the user's graphical one,
+/// when running as a service) every few seconds. The desktop sets the hint
+/// after its own idle delay, before `[input] idle_after` starts counting.
+#[derive(Debug)]
+pub struct LogindIdle {
+    state: Arc<AtomicU8>,
+}
+
+impl LogindIdle {
+    pub fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        // Fail on start rather than in the background when logind can't answer
+        let idle = idle_hint(&session)?;
+        let state = Arc::new(AtomicU8::new(if idle { IDLE } else { ACTIVE }));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            let mut failing = false;
+            loop {
+                thread::sleep(LOGIND_INTERVAL);
+                match idle_hint(&session) {
+                    Ok(idle) => {
+                        failing = false;
+                        shared.store(if idle { IDLE } else { ACTIVE }, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        if !failing {
+                            eprintln!("logind: cannot read the idle hint: {}", e);
+                            failing = true;
+                        }
+                        shared.store(UNKNOWN, Ordering::Relaxed);
+                    }
+                }
+            }
+        });
+        Ok(Self { state })
+    }
+}
+
+impl InputActivity for LogindIdle {
+    fn events(&mut self) -> Option<u64> {
+        events(&self.state)
+    }
+}
+
+/// `loginctl show-session <session> -p IdleHint --value`, "yes" or "no"
+fn idle_hint(session: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let output = Command::new("loginctl")
+        .args(["show-session", session, "-p", "IdleHint", "--value"])
+        .output()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    parse_idle_hint(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| "unexpected loginctl output".into())
+}
+
+/// The IdleHint property as printed by `loginctl --value`
+pub fn parse_idle_hint(output: &str) -> Option<bool> {
+    match output.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,32 @@
+use crate::window::provider::ActiveWindow;
+
+/// Whether we are running inside a KDE Plasma session
+pub fn is_available() -> bool {
+    std::env::var_os("KDE_FULL_SESSION").is_some()
+}
+
+/// The window in `kdotool getactivewindow getwindowclassname getwindowname`
+/// output: the class on the first line, the title on the second. kdotool
+/// prints nothing when no window has focus.
+pub fn parse_window(output: &str) -> ActiveWindow {
+    let mut lines = output.lines();
+    ActiveWindow {
+        class: lines.next().unwrap_or_default().to_string(),
+        title: lines.next().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kdotool_output_yields_the_focused_window() {
+        let window = parse_window("org.kde.dolphin\nHome — Dolphin\n");
+        assert_eq!(window.class, "org.kde.dolphin");
+        assert_eq!(window.title, "Home — Dolphin");
+        // Nothing has focus, e.g. on an empty desktop
+        let window = parse_window("");
+        assert_eq!((window.class.as_str(), window.title.as_str()), ("", ""));
+    }
+}
@@ -0,0 +1,16 @@
+pub mod config;
+pub mod control;
+pub mod daemon;
+pub mod gnome;
+pub mod hypr;
+pub mod input;
+pub mod kde;
+pub mod notifications;
+pub mod pomodoro;
+pub mod stats;
+pub mod storage;
+pub mod sway;
+pub mod window;
+pub mod windows;
+pub mod ws;
+pub mod x11;
@@ -1,198 +1,413 @@
-use chrono::{DateTime, Local};
-use notify_rust::Notification;
-use std::collections::HashMap;
+use clap::Parser;
+use cli::{Cli, Command, DaemonArgs};
 use std::sync::{Arc, Mutex};
-use tokio::time::{Duration, interval};
-mod pomodoro;
-mod ws;
+use stop_it::config::paths;
+use stop_it::config::settings::Config;
+use stop_it::daemon::clock::SystemClock;
+use stop_it::daemon::engine::StopItEngine;
+use stop_it::daemon::events::EventStream;
+use stop_it::daemon::log::Log;
+use stop_it::daemon::log_files::LogFiles;
+use stop_it::daemon::runner::{Notifier, notify};
+use stop_it::daemon::status_file::StatusFile;
+use stop_it::daemon::systemd::{ServiceNotifier, Watchdog};
+use stop_it::daemon::trace::Trace;
+use stop_it::daemon::tracker::DomainTracker;
+use stop_it::daemon::{log, sandbox, telemetry};
+use stop_it::input::activity::{EvdevActivity, InputActivity, InputSource};
+use stop_it::input::session::{LogindIdle, WaylandIdle};
+use stop_it::notifications::fallback::Fallback;
+use stop_it::notifications::manager::{Event, NotificationManager};
+use stop_it::notifications::routing::{HyprlandOutputs, Routing};
+use stop_it::pomodoro::phase::SavedPhase;
+use stop_it::pomodoro::plan::{PlanWatcher, Plans};
+use stop_it::stats::calendar::Calendar;
+use stop_it::storage::backend::{default_journal_path, open_storage};
+use stop_it::storage::journal::JournaledStorage;
+use stop_it::window::backend::Backend;
+use stop_it::window::provider::{NoWindowProvider, WindowProvider};
+#[cfg(feature = "websocket")]
+use stop_it::ws;
+#[cfg(feature = "websocket")]
+use stop_it::ws::auth::{CLI_PROFILE, Tokens, generate_token, write_cli_token};
+use stop_it::ws::protocol::ClientMessage;
+use stop_it::{control, hypr};
+use tokio::time::Duration;
 
-#[derive(Debug)]
-struct DomainTracker {
-    time_spent: HashMap<String, i64>, // domain -> seconds
-    current_domain: Option<String>,
-    session_start: DateTime<Local>,
-    mode: pomodoro::pomodoro::PomodoroMode,
-    mode_start: DateTime<Local>,
-}
+mod cli;
+mod commands;
 
-impl DomainTracker {
-    fn new() -> Self {
-        let now = Local::now();
-        println!(
-            "=== Session started at {} ===",
-            now.format("%Y-%m-%d %H:%M:%S")
-        );
-        Self {
-            time_spent: HashMap::new(),
-            current_domain: None,
-            session_start: now,
-            mode: pomodoro::pomodoro::PomodoroMode::Work,
-            mode_start: now,
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Some(Command::Run(args)) => run_daemon_mode(true, args).await,
+        Some(Command::Daemon(args)) => run_daemon_mode(false, args).await,
+        Some(Command::Status(args)) => commands::status::run_status(args).await,
+        Some(Command::Pause) => commands::timer::run_timer_command(ClientMessage::Pause).await,
+        Some(Command::Resume) => commands::timer::run_timer_command(ClientMessage::Resume).await,
+        Some(Command::Skip) => commands::timer::run_timer_command(ClientMessage::Skip).await,
+        Some(Command::Reset) => commands::timer::run_timer_command(ClientMessage::Reset).await,
+        Some(Command::Start) => commands::timer::run_timer_command(ClientMessage::Start).await,
+        Some(Command::Snooze(args)) => commands::timer::run_snooze(args).await,
+        Some(Command::Mode(args)) => commands::timer::run_mode(args).await,
+        Some(Command::Stats(args)) => commands::stats::run_stats(args),
+        Some(Command::Export(args)) => commands::export::run_export(args),
+        #[cfg(feature = "share")]
+        Some(Command::Share(args)) => commands::share::run_share(args),
+        Some(Command::MergeDb(args)) => commands::merge_db::run_merge_db(args),
+        Some(Command::Laps(args)) => commands::laps::run_laps(args),
+        #[cfg(feature = "websocket")]
+        Some(Command::Top(args)) => commands::top::run_top(args).await,
+        Some(Command::Triage(args)) => commands::triage::run_triage(args),
+        Some(Command::Categorize(args)) => commands::categorize::run_categorize(args).await,
+        Some(Command::GenBinds(args)) => commands::gen_binds::run_gen_binds(args),
+        Some(Command::Config(args)) => commands::config::run_config(args),
+        Some(Command::Init(args)) => commands::init::run_init(args),
+        Some(Command::Plan(args)) => commands::plan::run_plan(args),
+        Some(Command::Service(args)) => commands::service::run_service(args),
+        Some(Command::InstallService(args)) => commands::service::run_install_service(args),
+        Some(Command::Doctor) => commands::doctor::run_doctor().await,
+        Some(Command::Attach) => commands::attach::run_attach().await,
+        Some(Command::Daemonize) => commands::daemonize::run_daemonize().await,
+        Some(Command::Record(args)) => commands::record::run_record(args).await,
+        Some(Command::Inspect(args)) => commands::inspect::run_inspect(args).await,
+        #[cfg(feature = "debug-bundle")]
+        Some(Command::DebugBundle(args)) => commands::debug_bundle::run_debug_bundle(args).await,
+        Some(Command::Token(args)) => commands::token::run_token(args),
+        Some(Command::Schema(args)) => commands::schema::run_schema(args),
+        None => {
+            run_daemon_mode(
+                !cli.daemon,
+                DaemonArgs {
+                    harden: cli.harden,
+                    ..DaemonArgs::default()
+                },
+            )
+            .await
         }
+    };
+    // Display instead of Debug, so multi-line errors (config diagnostics) stay readable
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
+}
 
-    fn get_mode_duration(&self) -> i64 {
-        (Local::now() - self.mode_start).num_seconds()
+/// Run in daemon mode - WebSocket server + Pomodoro timer + activity tracking.
+/// In the `foreground`, `stop_it daemonize` can hand the running session
+/// over to a background daemon.
+async fn run_daemon_mode(
+    foreground: bool,
+    args: DaemonArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.machine {
+        log::use_stderr();
     }
-
-    fn switch_mode(&mut self) {
-        self.mode = match self.mode {
-            pomodoro::pomodoro::PomodoroMode::Work => pomodoro::pomodoro::PomodoroMode::Break,
-            pomodoro::pomodoro::PomodoroMode::Break => pomodoro::pomodoro::PomodoroMode::Work,
-        };
-        self.mode_start = Local::now();
-        let msg = format!(
-            "[{}] Switched to {} mode",
-            Local::now().format("%H:%M:%S"),
-            self.mode.as_str()
-        );
-        println!("\n{} {}", self.mode.emoji(), msg);
+    if foreground {
+        log::print("🍅 Stop It - Foreground Mode");
+    } else {
+        log::print("🍅 Stop It - Daemon Mode");
     }
-
-    fn update(&mut self, domain: Option<String>) {
-        if let Some(ref current) = self.current_domain {
-            *self.time_spent.entry(current.clone()).or_insert(0) += 1;
+    log::print("======================================================");
+    let first_run = !paths::config_file().exists();
+    let mut config = Config::load()?;
+    args.apply(&mut config);
+    if let Some(endpoint) = &config.telemetry.otlp_endpoint {
+        match telemetry::init(endpoint) {
+            Ok(()) => log::print(format!("Exporting traces to {}", endpoint)),
+            Err(e) => eprintln!("Trace export disabled: {}", e),
         }
-        self.current_domain = domain;
     }
-
-    fn get_session_duration(&self) -> i64 {
-        (Local::now() - self.session_start).num_seconds()
-    }
-
-    fn should_switch_mode(&mut self) -> bool {
-        let mode_minutes = self.get_mode_duration() / 60;
-        let target_minutes = match self.mode {
-            pomodoro::pomodoro::PomodoroMode::Work => pomodoro::pomodoro::POMODORO_WORK_MINUTES,
-            pomodoro::pomodoro::PomodoroMode::Break => pomodoro::pomodoro::POMODORO_BREAK_MINUTES,
-        };
-
-        mode_minutes >= target_minutes
+    if args.harden {
+        sandbox::harden(&config)?;
+        log::print("🔒 Hardened: private data and runtime directories");
     }
-
-    fn print_stats(&self) {
-        println!("\n--- Session Statistics ---");
-        println!(
-            "Session duration: {} minutes",
-            self.get_session_duration() / 60
-        );
-        println!("\nTime spent per domain:");
-
-        let mut sorted: Vec<_> = self.time_spent.iter().collect();
-        sorted.sort_by(|a, b| b.1.cmp(a.1));
-
-        for (domain, seconds) in sorted {
-            let minutes = seconds / 60;
-            let secs = seconds % 60;
-            println!("  {} - {}m {}s", domain, minutes, secs);
+    let mut notifications = NotificationManager::new()
+        .with_fallback(Fallback::new(config.notifications.fallback_command.clone()));
+    if config.notifications.is_routed() {
+        if cfg!(not(all(unix, not(target_os = "macos")))) {
+            eprintln!(
+                "Notification routing needs an XDG notification server, which {} doesn't have",
+                std::env::consts::OS
+            );
+        } else if hypr::hyprctl::is_available() {
+            notifications = notifications.with_routing(
+                Routing::new(Box::new(HyprlandOutputs), &config.notifications.output_hint)
+                    .with_follow_focus(config.notifications.follow_focus)
+                    .with_critical_everywhere(config.notifications.critical_everywhere),
+            );
+        } else {
+            eprintln!("Notification routing enabled but no Hyprland session found");
         }
-        println!("------------------------\n");
     }
-}
-
-fn send_notification(message: &str) -> Result<(), Box<dyn std::error::Error>> {
-    Notification::new()
-        .summary("Stop It - Pomodoro Alert")
-        .body(message)
-        .timeout(0) // No auto-dismiss
-        .show()?;
-    Ok(())
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    run_daemon_mode().await
-}
-
-/// Run in daemon mode - WebSocket server + Pomodoro timer + activity tracking
-async fn run_daemon_mode() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🍅 Stop It - Daemon Mode");
-    println!("======================================================");
-    println!(
+    let notifier: Notifier = Arc::new(Mutex::new(notifications));
+    if first_run {
+        let message = "No config found, using defaults. Run `stop_it init` to set up \
+                       session lengths, distracting sites and the systemd service, and \
+                       `stop_it doctor` to check the setup.";
+        log::print(format!("👋 {}", message));
+        notify(&notifier, Event::Setup, message);
+    }
+    if let Some(profile) = &config.profile {
+        log::print(format!("Config profile: {}", profile));
+    }
+    log::print(format!(
         "Pomodoro settings: {}min work / {}min break",
-        pomodoro::pomodoro::POMODORO_WORK_MINUTES,
-        pomodoro::pomodoro::POMODORO_BREAK_MINUTES
-    );
-    println!("Running WebSocket server on ws://127.0.0.1:8765");
-    println!("Tracking browser activity via WebSocket\n");
+        config.pomodoro.work / 60,
+        config.pomodoro.break_ / 60
+    ));
+    log::print(format!(
+        "Day starts at {} (weeks start on {})",
+        config.day.start.format("%H:%M"),
+        config.day.week_start
+    ));
 
-    // Create activity channel for browser messages
-    let (activity_tx, mut activity_rx) = ws::websocket_server::create_activity_channel();
+    if let Some(session) = paths::session() {
+        log::print(format!("Wayland session: {}", session));
+    }
+    // Bind before anything else so a taken port fails fast with a clear message
+    #[cfg(feature = "websocket")]
+    let (listener, tokens) = bind_websocket_server(&config).await?;
+    let control_path = control::socket::socket_path();
+    let control_listener = control::socket::bind_control_socket(&control_path).await?;
+    log::print(format!("Control socket: {}", control_path.display()));
+    #[cfg(feature = "websocket")]
+    log::print("Tracking browser activity via WebSocket\n");
+    #[cfg(not(feature = "websocket"))]
+    log::print("Built without the WebSocket server: the browser extension can't connect\n");
+    #[cfg(not(feature = "websocket"))]
+    if config.extension.only {
+        return Err(
+            "Browser-only mode needs the WebSocket server, which this build leaves out".into(),
+        );
+    }
 
-    // Shared tracker wrapped in Arc<Mutex<>> for thread-safe access
-    let tracker = Arc::new(Mutex::new(DomainTracker::new()));
-    let tracker_clone = Arc::clone(&tracker);
+    let mut storage = open_storage(config.storage.url.as_deref())?;
+    if config.storage.journal {
+        storage = Box::new(JournaledStorage::open(&default_journal_path(), storage)?);
+    }
+    let mut log = Log::stdout();
+    if config.log.files {
+        log = log.with_files(LogFiles::new(
+            LogFiles::default_dir(),
+            &config.log,
+            Calendar::new(&config.day),
+            Arc::new(SystemClock),
+        ));
+    }
+    let trace = Trace::new().with_history(config.log.recent_events);
+    let tracker = DomainTracker::new(&config, storage, Arc::new(SystemClock), log.clone())?
+        .with_trace(trace.clone())
+        .with_plan(PlanWatcher::new(Plans::default_path()))
+        .with_saved_phase(SavedPhase::default_path());
+    if tracker.is_off_day() {
+        log::print("🌴 Today is an off-day: notifications are paused");
+    }
 
-    // Spawn WebSocket server
-    let ws_addr = "127.0.0.1:8765".parse()?;
-    tokio::spawn(async move {
-        if let Err(e) = ws::websocket_server::start_websocket_server(ws_addr, activity_tx).await {
-            eprintln!("WebSocket server error: {}", e);
+    let workspace_label = config.hyprland.workspace_label;
+    let backend = match args.backend {
+        _ if config.extension.only => {
+            log::print("Browser-only: focus and idle time come from the extension's heartbeats");
+            Some(Backend::None)
         }
-    });
-
-    // Spawn browser activity processor
-    tokio::spawn(async move {
-        while let Some(message) = activity_rx.recv().await {
-            if let Ok(mut tracker) = tracker_clone.lock() {
-                let domain = message.domain.clone().or_else(|| {
-                    // Fallback: try to extract domain from URL
-                    message.url.split('/').nth(2).map(|s| s.to_string())
-                });
-
-                if domain != tracker.current_domain {
-                    if let Some(ref d) = domain {
-                        let msg = format!(
-                            "[{}] Browser switched to: {}",
-                            Local::now().format("%H:%M:%S"),
-                            d
-                        );
-                        println!("{}", msg);
-                    }
-                }
-
-                tracker.update(domain);
+        Some(backend) if !backend.is_supported() => {
+            return Err(format!(
+                "The {} backend isn't supported on {} by this build (supported: {})",
+                backend,
+                std::env::consts::OS,
+                backend_names(&Backend::supported())
+            )
+            .into());
+        }
+        Some(backend) => {
+            if !backend.is_available() {
+                eprintln!(
+                    "No {} session found, using the {} backend anyway",
+                    backend, backend
+                );
+            }
+            Some(backend)
+        }
+        None => Backend::detect(&config, Backend::is_available),
+    };
+    let window: Box<dyn WindowProvider> = match backend {
+        Some(backend) => {
+            if backend != Backend::None {
+                log::print(format!("Focused window from: {}", backend));
+            }
+            backend.provider()
+        }
+        None if !Backend::X11.is_enabled(&config) => {
+            if !config.exemptions.apps.is_empty() {
+                eprintln!(
+                    "Exempt apps need `track_focus` (Hyprland, Sway, GNOME, KDE, X11 or Windows) to know which window has focus"
+                );
             }
+            Box::new(NoWindowProvider)
         }
-    });
+        None if Backend::supported().is_empty() => {
+            eprintln!(
+                "Focus tracking enabled but this build has no focus backend for {}",
+                std::env::consts::OS
+            );
+            Box::new(NoWindowProvider)
+        }
+        None => {
+            eprintln!(
+                "Focus tracking enabled but no session found for the backends this build supports ({})",
+                backend_names(&Backend::supported())
+            );
+            Box::new(NoWindowProvider)
+        }
+    };
 
-    // Main loop: Pomodoro timer
-    let mut timer_interval = interval(Duration::from_secs(1));
+    let sample_input = config.input.sample;
+    let input_source = config.input.source;
+    let browser_only = config.extension.only;
+    let mut engine = StopItEngine::new(config, tracker, notifier, window, log.clone())
+        .with_trace(trace.clone())
+        .with_status_file(StatusFile::new(StatusFile::default_path()));
+    if foreground {
+        engine = engine.with_handoff();
+        log::print("Run `stop_it daemonize` to move this session to the background\n");
+    }
+    if args.machine {
+        engine = engine.with_events(EventStream::stdout());
+    }
+    if workspace_label {
+        if hypr::hyprctl::is_available() {
+            tokio::spawn(hypr::workspace_label::run(engine.subscribe()));
+        } else {
+            eprintln!("Hyprland workspace label enabled but no Hyprland session found");
+        }
+    }
+    if sample_input && browser_only {
+        eprintln!("Input sampling disabled: idle time comes from the browser extension");
+    } else if sample_input && !input_source.is_supported() {
+        eprintln!(
+            "Input sampling disabled: the {} source isn't supported on {}",
+            input_source.name(),
+            std::env::consts::OS
+        );
+    } else if sample_input {
+        if input_source == InputSource::Evdev {
+            eprintln!(
+                "Reading /dev/input: with the `input` group, every program you run can log \
+                 keystrokes. `source = \"logind\"` or `\"wayland\"` only see idle time."
+            );
+        }
+        let input: Result<Box<dyn InputActivity>, _> = match input_source {
+            InputSource::Evdev => EvdevActivity::open().map(|input| Box::new(input) as _),
+            InputSource::Wayland => WaylandIdle::open().map(|input| Box::new(input) as _),
+            InputSource::Logind => LogindIdle::open().map(|input| Box::new(input) as _),
+        };
+        match input {
+            Ok(input) => engine = engine.with_input(input),
+            Err(e) => eprintln!("Input sampling disabled: {}", e),
+        }
+    }
+    let service = ServiceNotifier::from_env();
+    if let Some(watchdog) = service.clone().and_then(Watchdog::from_env) {
+        engine = engine.with_watchdog(watchdog);
+    }
+    engine.start();
 
-    loop {
-        timer_interval.tick().await;
+    tokio::spawn(control::socket::start_control_socket(
+        control_listener,
+        engine.channels().request_tx.clone(),
+        log,
+        trace,
+    ));
+    // Spawn WebSocket server
+    #[cfg(feature = "websocket")]
+    tokio::spawn(ws::websocket_server::start_websocket_server(
+        listener,
+        engine.channels().clone(),
+        tokens,
+    ));
 
-        if let Ok(mut tracker) = tracker.lock() {
-            // Update time for current domain
-            if let Some(current) = tracker.current_domain.clone() {
-                *tracker.time_spent.entry(current).or_insert(0) += 1;
-            }
+    #[cfg(feature = "dbus")]
+    match control::dbus::bind_dbus_service(None, engine.channels().request_tx.clone()).await {
+        Ok(connection) => {
+            log::print(format!(
+                "D-Bus: {} on the session bus",
+                control::dbus::BUS_NAME
+            ));
+            tokio::spawn(control::dbus::start_dbus_service(
+                connection,
+                engine.subscribe(),
+            ));
+        }
+        Err(e) => eprintln!("D-Bus interface disabled: {}", e),
+    }
 
-            // Check if should switch Pomodoro mode
-            if tracker.should_switch_mode() {
-                let message = match tracker.mode {
-                    pomodoro::pomodoro::PomodoroMode::Work => format!(
-                        "Work session complete! Time for a {}-minute break.",
-                        pomodoro::pomodoro::POMODORO_BREAK_MINUTES
-                    ),
-                    pomodoro::pomodoro::PomodoroMode::Break => format!(
-                        "Break is over! Starting {}-minute work session.",
-                        pomodoro::pomodoro::POMODORO_WORK_MINUTES
-                    ),
-                };
+    // Sockets are bound and the timer runs: `systemctl start` can return
+    if let Some(service) = &service
+        && let Err(e) = service.notify("READY=1")
+    {
+        eprintln!("Could not tell systemd the daemon is ready: {}", e);
+    }
 
-                println!("\n🔔 {}", message);
+    // Runs until a handoff
+    engine.finished().await;
 
-                if let Err(e) = send_notification(&message) {
-                    eprintln!("Failed to send notification: {}", e);
-                }
+    // Let the control socket deliver the handoff reply, then make way for
+    // the background daemon
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let _ = std::fs::remove_file(&control_path);
+    log::print("Session handed off to the background daemon");
+    Ok(())
+}
 
-                if tracker.mode == pomodoro::pomodoro::PomodoroMode::Work {
-                    tracker.print_stats();
-                }
+/// "hyprland, sway, ...", or "none" when empty
+fn backend_names(backends: &[Backend]) -> String {
+    if backends.is_empty() {
+        return "none".to_string();
+    }
+    backends
+        .iter()
+        .map(|backend| backend.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-                tracker.switch_mode();
-            }
-        }
+/// Listen for the browser extension and local WebSocket clients, with the
+/// tokens they must present
+#[cfg(feature = "websocket")]
+async fn bind_websocket_server(
+    config: &Config,
+) -> Result<(tokio::net::TcpListener, Tokens), Box<dyn std::error::Error>> {
+    let listener = ws::websocket_server::bind_websocket_server(
+        config.server.port,
+        config.server.fallback_ports,
+    )
+    .await?;
+    let port = listener.local_addr()?.port();
+    if let Err(e) = ws::discovery::advertise_port(port) {
+        eprintln!("Failed to advertise port {}: {}", port, e);
+    }
+    log::print(format!(
+        "Running WebSocket server on ws://127.0.0.1:{}",
+        port
+    ));
+    let mut tokens = Tokens::new(&config.server.tokens);
+    if tokens.is_enabled() {
+        // Local commands (`status`, `top`) read theirs from the runtime directory
+        let token = generate_token()?;
+        write_cli_token(&token)?;
+        tokens.add(CLI_PROFILE, token);
+        log::print(format!(
+            "WebSocket clients need a token: {}",
+            config
+                .server
+                .tokens
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
     }
+    Ok((listener, tokens))
 }
@@ -0,0 +1,45 @@
+use crate::daemon::log;
+use notify_rust::Urgency;
+use std::process::{Command, Stdio};
+
+/// How notifications reach the user while the desktop's notification server
+/// is unavailable: a terminal bell and a banner in the log, and the
+/// configured command if any
+#[derive(Debug, Clone, Default)]
+pub struct Fallback {
+    command: Option<String>, // run with `sh -c`, STOP_IT_SUMMARY/STOP_IT_BODY/STOP_IT_URGENCY set
+}
+
+impl Fallback {
+    pub fn new(command: Option<String>) -> Self {
+        Self { command }
+    }
+
+    pub fn show(&self, summary: &str, body: &str, urgency: Urgency) {
+        log::print(format!("\x07\n🔔 ===== {} =====\n🔔 {}\n", summary, body));
+        let Some(command) = &self.command else {
+            return;
+        };
+        let child = Command::new("sh")
+            .args(["-c", command])
+            .env("STOP_IT_SUMMARY", summary)
+            .env("STOP_IT_BODY", body)
+            .env(
+                "STOP_IT_URGENCY",
+                match urgency {
+                    Urgency::Low => "low",
+                    Urgency::Normal => "normal",
+                    Urgency::Critical => "critical",
+                },
+            )
+            .stdin(Stdio::null())
+            .spawn();
+        match child {
+            // Reaped in the background, a slow command doesn't hold up the daemon
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => eprintln!("Failed to run the notification fallback command: {}", e),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use crate::notifications::fallback::Fallback;
+use crate::notifications::routing::Routing;
+use notify_rust::{Notification, NotificationHandle, Urgency};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Failed notifications in a row before switching to the fallback
+const FALLBACK_AFTER_FAILURES: u32 = 3;
+/// How often the notification server is tried again while falling back
+const RETRY_SERVER_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Kinds of desktop notifications the daemon sends. Events carrying a domain
+/// are rate-limited per domain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event {
+    ModeSwitch,
+    CycleComplete,
+    FocusAlert,
+    BudgetExceeded(String),
+    CategoryBudget(String), // category
+    DomainLimit(String),    // domain from `limits`
+    BlockedVisit(String),
+    Nag(String), // category
+    BreakCountdown,
+    BreakSkipped,
+    Pause,
+    Setup,
+    Triage,
+}
+
+impl Event {
+    /// Minimum time between two notifications of this event
+    fn cooldown(&self) -> Duration {
+        match self {
+            Event::BudgetExceeded(_) | Event::FocusAlert | Event::BreakSkipped => {
+                Duration::from_secs(10 * 60)
+            }
+            Event::Nag(_) => Duration::from_secs(5 * 60),
+            // Every visit counts, the wording changes with the visit count
+            Event::BlockedVisit(_) => Duration::ZERO,
+            // Paced by how far over budget the category goes
+            Event::CategoryBudget(_) => Duration::ZERO,
+            // A warning, then the limit, once a day each
+            Event::DomainLimit(_) => Duration::ZERO,
+            Event::ModeSwitch
+            | Event::CycleComplete
+            | Event::BreakCountdown
+            | Event::Pause
+            | Event::Setup
+            | Event::Triage => Duration::ZERO,
+        }
+    }
+
+    fn summary(&self) -> &'static str {
+        match self {
+            Event::ModeSwitch => "Stop It - Pomodoro Alert",
+            Event::CycleComplete => "Stop It - Cycle Complete",
+            Event::FocusAlert => "Stop It - Focus",
+            Event::BudgetExceeded(_) | Event::CategoryBudget(_) => "Stop It - Budget",
+            Event::DomainLimit(_) => "Stop It - Daily Limit",
+            Event::BlockedVisit(_) => "Stop It - Distraction",
+            Event::Nag(_) => "Stop It - Distraction",
+            Event::BreakCountdown => "Stop It - Break",
+            Event::BreakSkipped => "Stop It - Break",
+            Event::Pause => "Stop It - Paused",
+            Event::Setup => "Stop It - Setup",
+            Event::Triage => "Stop It - Triage",
+        }
+    }
+}
+
+/// A button on a notification and what clicking it does. Only XDG
+/// notification servers (Linux, BSD) show buttons.
+#[derive(Clone)]
+pub struct NotificationAction {
+    label: String,
+    on_invoke: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl NotificationAction {
+    /// Identifier of the button at `index` among the notification's actions
+    fn id(index: usize) -> String {
+        format!("stop_it_{}", index)
+    }
+
+    pub fn new(label: impl Into<String>, on_invoke: impl Fn() + Send + Sync + 'static) -> Self {
+        Self {
+            label: label.into(),
+            on_invoke: Arc::new(on_invoke),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Do what clicking the button does
+    pub fn invoke(&self) {
+        (self.on_invoke)();
+    }
+}
+
+/// Where notifications are shown: the desktop's notification server over
+/// D-Bus, unless replaced (e.g. in tests)
+pub trait NotificationServer: Send {
+    /// `None` when shown without a handle to update it by
+    fn show(&mut self, notification: &Notification) -> Result<Option<NotificationHandle>, String>;
+}
+
+/// Sends desktop notifications without flooding the notification daemon:
+/// each event has a cooldown, repeating the same text is a no-op and a new
+/// notification for an event replaces the previous one by ID. When the
+/// notification server keeps failing, notifications go to a `Fallback` and
+/// the server is retried every few minutes.
+#[derive(Default)]
+pub struct NotificationManager {
+    shown: HashMap<Event, Shown>,
+    silent: bool,
+    sent: Vec<(Event, String)>,
+    routing: Option<Routing>,
+    held: Option<Vec<(Event, String, Urgency)>>, // Some while notifications are held back
+    server: Option<Box<dyn NotificationServer>>, // None: D-Bus
+    fallback: Fallback,
+    failures: u32,                 // in a row
+    falling_back: Option<Instant>, // since the server was last tried
+}
+
+struct Shown {
+    handles: Vec<NotificationHandle>, // one per output, empty when silent
+    outputs: Vec<String>,
+    actions: Vec<NotificationAction>,
+    body: String,
+    at: Instant,
+}
+
+impl NotificationManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Manager that applies the same rules but never talks to the
+    /// notification server, for headless runs and tests
+    pub fn silent() -> Self {
+        Self {
+            silent: true,
+            ..Self::default()
+        }
+    }
+
+    /// Show notifications through `server` instead of D-Bus
+    pub fn with_server(mut self, server: Box<dyn NotificationServer>) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// Where notifications go while the notification server is unavailable
+    pub fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Whether notifications currently go to the fallback
+    pub fn is_falling_back(&self) -> bool {
+        self.falling_back.is_some()
+    }
+
+    /// Pick the outputs (monitors) notifications are shown on
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = Some(routing);
+        self
+    }
+
+    /// Every notification shown or updated so far, oldest first
+    pub fn sent(&self) -> &[(Event, String)] {
+        &self.sent
+    }
+
+    /// Outputs the notification for `event` was sent to, empty when the
+    /// notification daemon picked one
+    pub fn outputs(&self, event: &Event) -> &[String] {
+        self.shown
+            .get(event)
+            .map(|shown| shown.outputs.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Show `body` for `event` unless it is cooling down. Returns whether the
+    /// notification was shown or updated.
+    pub fn notify(&mut self, event: Event, body: &str) -> bool {
+        self.notify_with_urgency(event, body, Urgency::Normal)
+    }
+
+    pub fn notify_with_urgency(&mut self, event: Event, body: &str, urgency: Urgency) -> bool {
+        self.show(event, body, urgency, Vec::new())
+    }
+
+    /// Like `notify`, with a button per action, in order. A notification
+    /// replacing one for the same event is shown anew to carry them.
+    pub fn notify_with_actions(
+        &mut self,
+        event: Event,
+        body: &str,
+        actions: Vec<NotificationAction>,
+    ) -> bool {
+        self.show(event, body, Urgency::Normal, actions)
+    }
+
+    /// The buttons on the notification for `event`, empty without one
+    pub fn actions(&self, event: &Event) -> &[NotificationAction] {
+        self.shown
+            .get(event)
+            .map(|shown| shown.actions.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn show(
+        &mut self,
+        event: Event,
+        body: &str,
+        urgency: Urgency,
+        actions: Vec<NotificationAction>,
+    ) -> bool {
+        if let Some(held) = self.held.as_mut() {
+            // Only the latest text of each event is worth showing later
+            held.retain(|(held, _, _)| *held != event);
+            held.push((event, body.to_string(), urgency));
+            return false;
+        }
+        if let Some(shown) = self.shown.get_mut(&event) {
+            if shown.body == body || shown.at.elapsed() < event.cooldown() {
+                return false;
+            }
+            if !actions.is_empty() && !shown.handles.is_empty() {
+                // Buttons can't be added in place
+                if let Some(shown) = self.shown.remove(&event) {
+                    shown.handles.into_iter().for_each(close);
+                }
+            } else if self.silent || !shown.handles.is_empty() {
+                // Shown by the fallback: nothing to update, show it again below
+                // Replace the stale notification in place instead of stacking a new one
+                for handle in shown.handles.iter_mut() {
+                    if let Err(e) = update(handle, body, urgency) {
+                        eprintln!("Failed to update notification: {}", e);
+                        return false;
+                    }
+                }
+                shown.body = body.to_string();
+                shown.at = Instant::now();
+                shown.actions = actions;
+                self.sent.push((event, body.to_string()));
+                return true;
+            }
+        }
+
+        let outputs = match self.routing.as_mut() {
+            Some(routing) => routing.targets(urgency == Urgency::Critical),
+            None => Vec::new(),
+        };
+        let mut handles = Vec::new();
+        let retry = self
+            .falling_back
+            .is_none_or(|since| since.elapsed() >= RETRY_SERVER_AFTER);
+        if !self.silent && retry {
+            // One copy per output, since a notification carries a single output hint
+            let targets: Vec<Option<&String>> = if outputs.is_empty() {
+                vec![None]
+            } else {
+                outputs.iter().map(Some).collect()
+            };
+            let mut delivered = false;
+            let mut error = None;
+            for output in targets {
+                let mut notification = Notification::new();
+                notification.summary(event.summary()).body(body).timeout(0); // No auto-dismiss
+                set_urgency(&mut notification, urgency);
+                if let (Some(output), Some(routing)) = (output, &self.routing) {
+                    set_output(&mut notification, routing.hint(), output);
+                }
+                for (index, action) in actions.iter().enumerate() {
+                    notification.action(&NotificationAction::id(index), &action.label);
+                }
+                let shown = match self.server.as_mut() {
+                    Some(server) => server.show(&notification),
+                    None => notification.show().map(Some).map_err(|e| e.to_string()),
+                };
+                match shown {
+                    Ok(handle) => {
+                        delivered = true;
+                        if let Some(handle) = &handle
+                            && !actions.is_empty()
+                        {
+                            watch_actions(handle, actions.clone());
+                        }
+                        // Without updates, a changed text is shown as a new notification
+                        handles.extend(handle.filter(|_| CAN_UPDATE));
+                    }
+                    Err(e) => error = Some(e),
+                }
+            }
+            if delivered {
+                if self.falling_back.take().is_some() {
+                    eprintln!("Notification server is back");
+                }
+                self.failures = 0;
+            }
+            match error {
+                Some(e) if delivered => eprintln!("Failed to send notification: {}", e),
+                Some(e) => {
+                    self.failures += 1;
+                    if self.falling_back.is_some() {
+                        self.falling_back = Some(Instant::now());
+                    } else if self.failures >= FALLBACK_AFTER_FAILURES {
+                        eprintln!(
+                            "Notification server unavailable ({}): showing notifications in \
+                             the terminal, retrying every {} minutes",
+                            e,
+                            RETRY_SERVER_AFTER.as_secs() / 60
+                        );
+                        self.falling_back = Some(Instant::now());
+                    } else {
+                        eprintln!("Failed to send notification: {}", e);
+                        return false;
+                    }
+                }
+                None => {}
+            }
+        }
+        if self.falling_back.is_some() && handles.is_empty() {
+            self.fallback.show(event.summary(), body, urgency);
+        }
+        let shown = Shown {
+            handles,
+            outputs,
+            actions,
+            body: body.to_string(),
+            at: Instant::now(),
+        };
+        self.shown.insert(event.clone(), shown);
+        self.sent.push((event, body.to_string()));
+        true
+    }
+
+    /// Keep notifications to show them once `release` is called, e.g. while
+    /// an exempt application has focus
+    pub fn hold(&mut self) {
+        self.held.get_or_insert_with(Vec::new);
+    }
+
+    /// Show what was held back, oldest first. Returns how many were shown.
+    pub fn release(&mut self) -> usize {
+        let held = self.held.take().unwrap_or_default();
+        held.into_iter()
+            .filter(|(event, body, urgency)| {
+                self.notify_with_urgency(event.clone(), body, *urgency)
+            })
+            .count()
+    }
+
+    /// Dismiss the notification for `event`, if any
+    pub fn close(&mut self, event: &Event) {
+        if let Some(held) = self.held.as_mut() {
+            held.retain(|(held, _, _)| held != event);
+        }
+        if let Some(shown) = self.shown.remove(event) {
+            shown.handles.into_iter().for_each(close);
+        }
+    }
+}
+
+/// Whether shown notifications can be updated and closed. Only XDG
+/// notification servers (Linux, BSD) can; on macOS and Windows the
+/// notification is shown again instead and left for the user to dismiss.
+const CAN_UPDATE: bool = cfg!(all(unix, not(target_os = "macos")));
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn update(handle: &mut NotificationHandle, body: &str, urgency: Urgency) -> Result<(), String> {
+    // A button was about the previous text
+    handle.actions.clear();
+    handle.body(body).urgency(urgency);
+    handle.update().map_err(|e| e.to_string())
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn update(_handle: &mut NotificationHandle, _body: &str, _urgency: Urgency) -> Result<(), String> {
+    Err("notifications can't be updated on this platform".to_string())
+}
+
+/// Run the action whose button on the notification `handle` is clicked,
+/// waiting in a thread until one is or the notification is closed
+#[cfg(all(unix, not(target_os = "macos")))]
+fn watch_actions(handle: &NotificationHandle, actions: Vec<NotificationAction>) {
+    let id = handle.id();
+    std::thread::spawn(move || {
+        let _ = notify_rust::handle_action(id, |response| {
+            if let notify_rust::ActionResponse::Custom(clicked) = response
+                && let Some((_, action)) = actions
+                    .iter()
+                    .enumerate()
+                    .find(|(index, _)| NotificationAction::id(*index) == *clicked)
+            {
+                action.invoke();
+            }
+        });
+    });
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn watch_actions(_handle: &NotificationHandle, _actions: Vec<NotificationAction>) {}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn close(handle: NotificationHandle) {
+    handle.close();
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn close(_handle: NotificationHandle) {}
+
+/// The urgency, where the platform has one: XDG servers and Windows (as
+/// the toast scenario)
+#[cfg(any(all(unix, not(target_os = "macos")), windows))]
+fn set_urgency(notification: &mut Notification, urgency: Urgency) {
+    notification.urgency(urgency);
+}
+
+#[cfg(not(any(all(unix, not(target_os = "macos")), windows)))]
+fn set_urgency(_notification: &mut Notification, _urgency: Urgency) {}
+
+/// Name the output in the `hint` notification hint; XDG servers only
+#[cfg(all(unix, not(target_os = "macos")))]
+fn set_output(notification: &mut Notification, hint: &str, output: &str) {
+    notification.hint(notify_rust::Hint::Custom(
+        hint.to_string(),
+        output.to_string(),
+    ));
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn set_output(_notification: &mut Notification, _hint: &str, _output: &str) {}
@@ -0,0 +1,5 @@
+pub mod fallback;
+pub mod manager;
+pub mod routing;
+pub mod sounds;
+pub mod webhooks;
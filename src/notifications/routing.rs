@@ -0,0 +1,107 @@
+use serde::Deserialize;
+use std::process::Command;
+
+/// Tells the notification manager which monitors (outputs) there are and
+/// which one holds the focused window
+pub trait Outputs: Send {
+    /// `None` when the focused output can't be determined
+    fn focused(&mut self) -> Option<String>;
+    fn all(&mut self) -> Vec<String>;
+}
+
+/// The subset of `hyprctl -j monitors` we use
+#[derive(Debug, Deserialize)]
+struct Monitor {
+    name: String,
+    focused: bool,
+}
+
+/// Reads the monitors with `hyprctl -j monitors`, only when a notification
+/// is about to be shown
+#[derive(Debug, Default)]
+pub struct HyprlandOutputs;
+
+impl HyprlandOutputs {
+    fn monitors(&self) -> Vec<Monitor> {
+        let result = Command::new("hyprctl")
+            .args(["-j", "monitors"])
+            .output()
+            .map_err(|e| e.to_string())
+            .and_then(|output| {
+                serde_json::from_slice::<Vec<Monitor>>(&output.stdout).map_err(|e| e.to_string())
+            });
+        result.unwrap_or_else(|e| {
+            eprintln!("Hyprland: cannot read the monitors: {}", e);
+            Vec::new()
+        })
+    }
+}
+
+impl Outputs for HyprlandOutputs {
+    fn focused(&mut self) -> Option<String> {
+        self.monitors()
+            .into_iter()
+            .find(|monitor| monitor.focused)
+            .map(|monitor| monitor.name)
+    }
+
+    fn all(&mut self) -> Vec<String> {
+        self.monitors()
+            .into_iter()
+            .map(|monitor| monitor.name)
+            .collect()
+    }
+}
+
+/// Which outputs a notification goes to. The output name is passed in the
+/// `hint` notification hint, for notification daemons configured to read it.
+pub struct Routing {
+    outputs: Box<dyn Outputs>,
+    hint: String,
+    follow_focus: bool,
+    critical_everywhere: bool,
+}
+
+impl Routing {
+    pub fn new(outputs: Box<dyn Outputs>, hint: &str) -> Self {
+        Self {
+            outputs,
+            hint: hint.to_string(),
+            follow_focus: false,
+            critical_everywhere: false,
+        }
+    }
+
+    /// Show notifications on the output of the focused window
+    pub fn with_follow_focus(mut self, follow_focus: bool) -> Self {
+        self.follow_focus = follow_focus;
+        self
+    }
+
+    /// Show one copy of critical notifications on every output
+    pub fn with_critical_everywhere(mut self, critical_everywhere: bool) -> Self {
+        self.critical_everywhere = critical_everywhere;
+        self
+    }
+
+    pub fn hint(&self) -> &str {
+        &self.hint
+    }
+
+    /// Outputs to show a notification on; empty to leave the choice to the
+    /// notification daemon
+    pub fn targets(&mut self, critical: bool) -> Vec<String> {
+        if critical && self.critical_everywhere {
+            let all = self.outputs.all();
+            if !all.is_empty() {
+                return all;
+            }
+        }
+        if self.follow_focus
+            && let Some(focused) = self.outputs.focused()
+        {
+            return vec![focused];
+        }
+        Vec::new()
+    }
+}
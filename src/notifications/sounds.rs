@@ -0,0 +1,62 @@
+use crate::config::settings::SoundsConfig;
+use crate::pomodoro::pomodoro::PomodoroMode;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Plays the `[sounds]` file of a session when it starts, through the
+/// configured player (`paplay`, `pw-play`, `canberra-gtk-play -f`, ...)
+#[derive(Debug, Clone)]
+pub struct Sounds {
+    player: String,
+    work: Option<PathBuf>,
+    break_: Option<PathBuf>,
+    long_break: Option<PathBuf>,
+}
+
+impl Sounds {
+    /// `None` when muted or without any sound file
+    pub fn new(config: &SoundsConfig) -> Option<Self> {
+        if config.mute
+            || (config.work.is_none() && config.break_.is_none() && config.long_break.is_none())
+        {
+            return None;
+        }
+        Some(Self {
+            player: config.player.clone(),
+            work: config.work.clone(),
+            break_: config.break_.clone(),
+            long_break: config.long_break.clone(),
+        })
+    }
+
+    /// The file for a session of `mode`, if any
+    pub fn file(&self, mode: PomodoroMode) -> Option<&Path> {
+        match mode {
+            PomodoroMode::Work => self.work.as_deref(),
+            PomodoroMode::Break => self.break_.as_deref(),
+            PomodoroMode::LongBreak => self.long_break.as_deref().or(self.break_.as_deref()),
+        }
+    }
+
+    /// Play the file for a session of `mode` that just started, without
+    /// waiting for it to end
+    pub fn play(&self, mode: PomodoroMode) {
+        let Some(file) = self.file(mode) else {
+            return;
+        };
+        // The file goes in as `$1`, so paths with spaces need no quoting
+        let child = Command::new("sh")
+            .args(["-c", &format!("{} \"$1\"", self.player), "sh"])
+            .arg(file)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+        match child {
+            // Reaped in the background, a long sound doesn't hold up the daemon
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => eprintln!("Failed to play {}: {}", file.display(), e),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::config::settings::WebhookConfig;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::ops::Range;
+#[cfg(feature = "webhooks")]
+use std::time::Duration;
+
+/// Values templates can refer to; each event fills in the ones that apply
+pub const PLACEHOLDERS: &[&str] = &[
+    "event",
+    "time",
+    "message",
+    "mode",
+    "pomodoros_today",
+    "daily_goal",
+    "duration_minutes",
+    "domain",
+    "category",
+    "over_by_minutes",
+    "limit_minutes",
+    "distraction_percent",
+];
+
+/// Daemon events webhooks can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookKind {
+    WorkCompleted,
+    BreakCompleted,
+    BudgetExceeded,
+    LimitReached,
+    FocusAlert,
+}
+
+impl WebhookKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookKind::WorkCompleted => "work_completed",
+            WebhookKind::BreakCompleted => "break_completed",
+            WebhookKind::BudgetExceeded => "budget_exceeded",
+            WebhookKind::LimitReached => "limit_reached",
+            WebhookKind::FocusAlert => "focus_alert",
+        }
+    }
+}
+
+/// One occurrence of an event with the values it carries. Without a
+/// template, webhooks receive these values as a flat JSON object.
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    kind: WebhookKind,
+    values: Map<String, Value>,
+}
+
+impl WebhookEvent {
+    /// `message` is the text of the matching desktop notification
+    pub fn new(kind: WebhookKind, time: DateTime<Local>, message: &str) -> Self {
+        let mut values = Map::new();
+        values.insert("event".to_string(), kind.as_str().into());
+        values.insert("time".to_string(), time.to_rfc3339().into());
+        values.insert("message".to_string(), message.into());
+        Self { kind, values }
+    }
+
+    /// Add a value; `name` should be one of `PLACEHOLDERS`
+    pub fn with(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.values.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn kind(&self) -> WebhookKind {
+        self.kind
+    }
+
+    /// Payload sent to webhooks without a template
+    pub fn to_json(&self) -> String {
+        Value::Object(self.values.clone()).to_string()
+    }
+
+    /// Fill `template` in. Strings are JSON-escaped without their quotes,
+    /// so they go inside a quoted template string; numbers work either way.
+    /// Values this event doesn't carry are left empty.
+    pub fn render(&self, template: &str) -> Result<String, String> {
+        let rendered = replace_placeholders(template, |name| match self.values.get(name) {
+            Some(Value::String(text)) => {
+                let quoted = Value::String(text.clone()).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Some(value) => value.to_string(),
+            None => String::new(),
+        });
+        serde_json::from_str::<Value>(&rendered).map_err(|e| {
+            format!(
+                "template for {} is not valid JSON: {}",
+                self.kind.as_str(),
+                e
+            )
+        })?;
+        Ok(rendered)
+    }
+}
+
+/// The `{{name}}` placeholders in a payload template: where they are and
+/// the name, which is lowercase letters and underscores
+fn placeholders(template: &str) -> Vec<(Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(open) = template[from..].find("{{").map(|at| from + at) {
+        let Some(close) = template[open + 2..].find("}}").map(|at| open + 2 + at) else {
+            break;
+        };
+        let name = template[open + 2..close].trim();
+        if !name.is_empty() && name.bytes().all(|b| b.is_ascii_lowercase() || b == b'_') {
+            found.push((open..close + 2, name));
+            from = close + 2;
+        } else {
+            from = open + 1;
+        }
+    }
+    found
+}
+
+fn replace_placeholders(template: &str, value: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut last = 0;
+    for (range, name) in placeholders(template) {
+        rendered.push_str(&template[last..range.start]);
+        rendered.push_str(&value(name));
+        last = range.end;
+    }
+    rendered.push_str(&template[last..]);
+    rendered
+}
+
+/// Reject templates using unknown placeholders or that can't produce JSON
+pub fn check_template(template: &str) -> Result<(), String> {
+    for (_, name) in placeholders(template) {
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "unknown placeholder {{{{{}}}}}, expected one of: {}",
+                name,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+    }
+    let sample = replace_placeholders(template, |_| "0".to_string());
+    serde_json::from_str::<Value>(&sample)
+        .map(|_| ())
+        .map_err(|e| format!("template is not valid JSON: {}", e))
+}
+
+/// Posts events to the `[[webhooks]]` subscribed to them, each request on
+/// its own thread so a slow endpoint never holds up the timer
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    hooks: Vec<WebhookConfig>,
+}
+
+impl Webhooks {
+    pub fn new(hooks: &[WebhookConfig]) -> Self {
+        Self {
+            hooks: hooks.to_vec(),
+        }
+    }
+
+    pub fn send(&self, event: &WebhookEvent) {
+        let subscribed = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.events.is_empty() || hook.events.contains(&event.kind()));
+        for hook in subscribed {
+            let body = match &hook.template {
+                Some(template) => match event.render(template) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        eprintln!("Webhook {}: {}", hook.url, e);
+                        continue;
+                    }
+                },
+                None => event.to_json(),
+            };
+            let hook = hook.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = post(&hook, body) {
+                    eprintln!("Webhook {} failed: {}", hook.url, e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(feature = "webhooks")]
+fn post(hook: &WebhookConfig, body: String) -> Result<(), Box<dyn std::error::Error>> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .into();
+    let mut request = agent
+        .post(&hook.url)
+        .header("Content-Type", "application/json");
+    for (name, value) in &hook.headers {
+        request = request.header(name, value);
+    }
+    request.send(body)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "webhooks"))]
+fn post(_hook: &WebhookConfig, _body: String) -> Result<(), Box<dyn std::error::Error>> {
+    Err("webhooks require building with `--features webhooks`".into())
+}
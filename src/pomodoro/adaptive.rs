@@ -0,0 +1,16 @@
+/// Longest suggested break, as a multiple of the configured one
+const MAX_FACTOR: f64 = 3.0;
+
+/// Break length earned by a work session: the configured `base` scaled by
+/// how long the session ran against the planned length and by its focus
+/// (0.0 - 1.0). A full session at 50% focus earns the configured break, a
+/// longer or more focused one up to three times as much; never less than
+/// `base`. Rounded to whole minutes.
+pub fn suggest_break(base: i64, planned_work: i64, worked: i64, focus: f64) -> i64 {
+    if planned_work <= 0 {
+        return base;
+    }
+    let factor = (worked as f64 / planned_work as f64) * (0.5 + focus);
+    let seconds = base as f64 * factor.clamp(1.0, MAX_FACTOR);
+    ((seconds / 60.0).round() as i64 * 60).max(base)
+}
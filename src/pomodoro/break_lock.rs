@@ -0,0 +1,29 @@
+use chrono::{DateTime, Local};
+use tokio::process::Command;
+
+/// How a locked break ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakLockOutcome {
+    Completed,
+    SkippedByUnlock { remaining_seconds: i64 },
+}
+
+/// Run the screen locker for a break and report whether it was unlocked
+/// before `break_end`. `locker` is a shell command like "hyprlock" or
+/// "swaylock -f"; it must stay in the foreground until unlocked.
+pub async fn lock_for_break(
+    locker: &str,
+    break_end: DateTime<Local>,
+) -> Result<BreakLockOutcome, Box<dyn std::error::Error>> {
+    let status = Command::new("sh").args(["-c", locker]).status().await?;
+    if !status.success() {
+        return Err(format!("'{}' exited with {}", locker, status).into());
+    }
+
+    let remaining_seconds = (break_end - Local::now()).num_seconds();
+    if remaining_seconds > 0 {
+        Ok(BreakLockOutcome::SkippedByUnlock { remaining_seconds })
+    } else {
+        Ok(BreakLockOutcome::Completed)
+    }
+}
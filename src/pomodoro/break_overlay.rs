@@ -0,0 +1,112 @@
+use crate::ws::protocol::ServerMessage;
+use chrono::{DateTime, Local};
+
+/// Seconds the extension gets to confirm a `break_start` or `break_end`
+pub const ACK_TIMEOUT: i64 = 10;
+/// Times an unconfirmed command is sent again before giving up
+pub const RETRIES: u32 = 2;
+
+/// What a break overlay command that went unconfirmed leads to
+#[derive(Debug, Clone)]
+pub enum OverlayTimeout {
+    Resend(ServerMessage),
+    GaveUp { starting: bool }, // `break_start` rather than `break_end`
+}
+
+#[derive(Debug)]
+struct Pending {
+    message: ServerMessage,
+    starting: bool,
+    sent_at: DateTime<Local>,
+    retries: u32,
+}
+
+/// The `break_start`/`break_end` handshake with the browser extension, which
+/// pauses media and greys out pages during breaks (`[breaks] overlay`). Each
+/// command carries an id the extension sends back in a `break_ack`; one left
+/// unconfirmed is sent again, then given up on.
+#[derive(Debug, Default)]
+pub struct BreakOverlay {
+    next_id: u64,
+    shown: bool, // a `break_start` went out after the last `break_end`
+    pending: Option<Pending>,
+}
+
+impl BreakOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the overlay was asked for, confirmed or not
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    /// `break_start` for a break planned to end at `ends_at`, replacing an
+    /// unconfirmed command
+    pub fn start(&mut self, now: DateTime<Local>, ends_at: DateTime<Local>) -> ServerMessage {
+        self.shown = true;
+        let id = self.next_id();
+        self.send(ServerMessage::BreakStart { id, ends_at }, true, now)
+    }
+
+    /// `break_end`, replacing an unconfirmed command
+    pub fn end(&mut self, now: DateTime<Local>) -> ServerMessage {
+        self.shown = false;
+        let id = self.next_id();
+        self.send(ServerMessage::BreakEnd { id }, false, now)
+    }
+
+    /// Take the extension's `break_ack`. Returns `false` for an id that
+    /// isn't waiting, e.g. a command already superseded.
+    pub fn acknowledge(&mut self, id: u64) -> bool {
+        let waiting = self.pending.as_ref().is_some_and(|pending| {
+            matches!(
+                pending.message,
+                ServerMessage::BreakStart { id: sent, .. } | ServerMessage::BreakEnd { id: sent }
+                    if sent == id
+            )
+        });
+        if waiting {
+            self.pending = None;
+        }
+        waiting
+    }
+
+    /// The command to send again, or the one given up on, once `ACK_TIMEOUT`
+    /// passed without a `break_ack`
+    pub fn check(&mut self, now: DateTime<Local>) -> Option<OverlayTimeout> {
+        let pending = self.pending.as_mut()?;
+        if (now - pending.sent_at).num_seconds() < ACK_TIMEOUT {
+            return None;
+        }
+        if pending.retries < RETRIES {
+            pending.retries += 1;
+            pending.sent_at = now;
+            return Some(OverlayTimeout::Resend(pending.message.clone()));
+        }
+        let starting = pending.starting;
+        self.pending = None;
+        Some(OverlayTimeout::GaveUp { starting })
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn send(
+        &mut self,
+        message: ServerMessage,
+        starting: bool,
+        now: DateTime<Local>,
+    ) -> ServerMessage {
+        self.pending = Some(Pending {
+            message: message.clone(),
+            starting,
+            sent_at: now,
+            retries: 0,
+        });
+        message
+    }
+}
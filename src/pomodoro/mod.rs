@@ -1 +1,7 @@
+pub mod adaptive;
+pub mod break_lock;
+pub mod break_overlay;
+pub mod phase;
+pub mod plan;
+#[allow(clippy::module_inception)]
 pub mod pomodoro;
@@ -0,0 +1,52 @@
+use crate::config::paths;
+use crate::pomodoro::pomodoro::PomodoroMode;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The running Pomodoro phase and cycle position, saved on every switch so a
+/// restarted daemon carries on instead of starting over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPhase {
+    pub mode: PomodoroMode,
+    pub start: DateTime<Local>,
+    pub day: NaiveDate, // logical day the phase belongs to
+    pub cycle: u32,     // work sessions completed in the current cycle
+    #[serde(default)]
+    pub paused_since: Option<DateTime<Local>>, // the phase's clock is stopped
+    #[serde(default)]
+    pub length: Option<i64>, // an adapted break length, see `[breaks] adaptive`
+    #[serde(default)]
+    pub continuous: bool, // breaks were turned off for the day
+    #[serde(default)]
+    pub snoozed: i64, // seconds a work session was extended by snoozing its break
+}
+
+impl SavedPhase {
+    pub fn default_path() -> PathBuf {
+        paths::session_file("phase", "json")
+    }
+
+    /// Load the saved phase; `None` when the daemon never saved one
+    pub fn load_from(path: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Invalid phase file {}: {}", path.display(), e).into())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so a crash never leaves a half-written file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
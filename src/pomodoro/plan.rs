@@ -0,0 +1,254 @@
+use crate::config::paths;
+use crate::config::settings::parse_time;
+use crate::pomodoro::pomodoro::PomodoroMode;
+use crate::stats::aggregator::{Aggregator, TimeRange};
+use crate::stats::calendar::Calendar;
+use crate::stats::format::format_duration;
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Label used for blocks planned without one
+const DEFAULT_LABEL: &str = "focus";
+
+/// A labeled stretch of work planned for a day, e.g. "09:00-12:00 deep"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedBlock {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub label: String,
+}
+
+impl PlannedBlock {
+    /// When the block happens on the logical day `day`. Times before the
+    /// configured day start belong to the following calendar date.
+    pub fn range(&self, calendar: &Calendar, day: NaiveDate) -> TimeRange {
+        let begin = calendar.day_begin(day);
+        let at = |time: NaiveTime| {
+            let mut at = begin + (time - begin.time());
+            if at < begin {
+                at += Duration::days(1);
+            }
+            at
+        };
+        TimeRange::new(at(self.start), at(self.end))
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{}-{} {}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.label
+        )
+    }
+}
+
+/// Parse a plan like "09:00-12:00 deep, 13:00-15:00 admin"
+pub fn parse_plan(spec: &str) -> Result<Vec<PlannedBlock>, String> {
+    let mut blocks = Vec::new();
+    for part in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (span, label) = part.split_once(char::is_whitespace).unwrap_or((part, ""));
+        let (start, end) = span.split_once('-').ok_or_else(|| {
+            format!(
+                "invalid block '{}', expected e.g. \"09:00-12:00 deep\"",
+                part
+            )
+        })?;
+        let start = parse_time(start).ok_or_else(|| format!("invalid time '{}'", start))?;
+        let end = parse_time(end).ok_or_else(|| format!("invalid time '{}'", end))?;
+        if end <= start {
+            return Err(format!("block '{}' ends before it starts", part));
+        }
+        let label = label.trim();
+        blocks.push(PlannedBlock {
+            start,
+            end,
+            label: if label.is_empty() {
+                DEFAULT_LABEL.to_string()
+            } else {
+                label.to_string()
+            },
+        });
+    }
+
+    if blocks.is_empty() {
+        return Err("the plan has no blocks".to_string());
+    }
+    blocks.sort_by_key(|block| block.start);
+    if let Some(pair) = blocks.windows(2).find(|pair| pair[1].start < pair[0].end) {
+        return Err(format!(
+            "blocks '{}' and '{}' overlap",
+            pair[0].describe(),
+            pair[1].describe()
+        ));
+    }
+    Ok(blocks)
+}
+
+/// Planned blocks by logical day, kept in `plans.json` in the data directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Plans {
+    days: BTreeMap<NaiveDate, Vec<PlannedBlock>>,
+}
+
+impl Plans {
+    pub fn default_path() -> PathBuf {
+        paths::data_dir().join("plans.json")
+    }
+
+    /// Load plans from `path`; a missing file means nothing is planned
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid plan file {}: {}", path.display(), e).into())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so the daemon never reads a half-written file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    pub fn blocks(&self, day: NaiveDate) -> &[PlannedBlock] {
+        self.days.get(&day).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replace the plan for `day`; an empty plan removes it
+    pub fn set(&mut self, day: NaiveDate, blocks: Vec<PlannedBlock>) {
+        if blocks.is_empty() {
+            self.days.remove(&day);
+        } else {
+            self.days.insert(day, blocks);
+        }
+    }
+}
+
+/// Plans file followed by the daemon, re-read whenever it changes on disk
+#[derive(Debug)]
+pub struct PlanWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    plans: Plans,
+}
+
+impl PlanWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let mut watcher = Self {
+            path,
+            modified: None,
+            plans: Plans::default(),
+        };
+        watcher.refresh();
+        watcher
+    }
+
+    fn refresh(&mut self) {
+        let modified = fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        self.plans = Plans::load_from(&self.path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Plans::default()
+        });
+    }
+
+    pub fn plans(&mut self) -> &Plans {
+        self.refresh();
+        &self.plans
+    }
+
+    /// Block planned for `now` on the logical day `day`, if any
+    pub fn block_at(
+        &mut self,
+        calendar: &Calendar,
+        day: NaiveDate,
+        now: DateTime<Local>,
+    ) -> Option<PlannedBlock> {
+        self.plans()
+            .blocks(day)
+            .iter()
+            .find(|block| block.range(calendar, day).contains(now))
+            .cloned()
+    }
+}
+
+/// How a planned block went
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockReport {
+    pub block: PlannedBlock,
+    pub planned_seconds: i64,
+    pub worked_seconds: i64,  // time inside the block spent in work sessions
+    pub tracked_seconds: i64, // activity tracked inside the block
+}
+
+impl BlockReport {
+    /// One line for reports, e.g. "09:00-12:00 deep: worked 2h 30m of 3h (83%)"
+    pub fn describe(&self) -> String {
+        let percent = if self.planned_seconds > 0 {
+            self.worked_seconds * 100 / self.planned_seconds
+        } else {
+            0
+        };
+        format!(
+            "{}: worked {} of {} ({}%), tracked {}",
+            self.block.describe(),
+            format_duration(self.worked_seconds),
+            format_duration(self.planned_seconds),
+            percent,
+            format_duration(self.tracked_seconds)
+        )
+    }
+}
+
+/// Compare the plan for `day` with the recorded work sessions and activity
+pub fn compare(
+    blocks: &[PlannedBlock],
+    calendar: &Calendar,
+    day: NaiveDate,
+    store: &ActivityStore,
+) -> Vec<BlockReport> {
+    let aggregator = Aggregator::new(store);
+    blocks
+        .iter()
+        .map(|block| {
+            let range = block.range(calendar, day);
+            let worked_seconds = store
+                .pomodoros()
+                .iter()
+                .filter(|record| record.mode == PomodoroMode::Work && !record.abandoned)
+                .map(|record| {
+                    let start = record.start.max(range.start);
+                    let end = record.end.min(range.end);
+                    (end - start).num_seconds().max(0)
+                })
+                .sum();
+            BlockReport {
+                block: block.clone(),
+                planned_seconds: (range.end - range.start).num_seconds(),
+                worked_seconds,
+                tracked_seconds: aggregator.total_seconds(&range),
+            }
+        })
+        .collect()
+}
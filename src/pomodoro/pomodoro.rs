@@ -1,24 +1,76 @@
 pub const POMODORO_WORK_MINUTES: i64 = 25; // Default Pomodoro work time
 pub const POMODORO_BREAK_MINUTES: i64 = 5; // Default Pomodoro break time
+pub const POMODORO_LONG_BREAK_MINUTES: i64 = 15; // Default break closing a cycle
+pub const POMODORO_CYCLE_LENGTH: u32 = 4; // Default work sessions per cycle
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PomodoroMode {
     Work,
     Break,
+    LongBreak, // after the last work session of a cycle
 }
 
 impl PomodoroMode {
-    pub(crate) fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             PomodoroMode::Work => "WORK",
             PomodoroMode::Break => "BREAK",
+            PomodoroMode::LongBreak => "LONG_BREAK",
         }
     }
 
-    pub(crate) fn emoji(&self) -> &str {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "WORK" => Some(PomodoroMode::Work),
+            "BREAK" => Some(PomodoroMode::Break),
+            "LONG_BREAK" => Some(PomodoroMode::LongBreak),
+            _ => None,
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
         match self {
             PomodoroMode::Work => "💼",
             PomodoroMode::Break => "☕",
+            PomodoroMode::LongBreak => "🛋️",
         }
     }
+
+    pub fn is_break(&self) -> bool {
+        matches!(self, PomodoroMode::Break | PomodoroMode::LongBreak)
+    }
+}
+
+/// Whether work sessions are followed by breaks, switched at runtime with
+/// `stop_it mode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerMode {
+    #[default]
+    Pomodoro,
+    Continuous, // back-to-back work blocks, for deep-work days
+}
+
+impl TimerMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimerMode::Pomodoro => "pomodoro",
+            TimerMode::Continuous => "continuous",
+        }
+    }
+}
+
+impl FromStr for TimerMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        [TimerMode::Pomodoro, TimerMode::Continuous]
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(value))
+            .ok_or_else(|| "expected pomodoro or continuous".to_string())
+    }
 }
@@ -0,0 +1,286 @@
+use crate::pomodoro::pomodoro::PomodoroMode;
+use crate::stats::filter::Filter;
+use crate::stats::groups::GroupRules;
+use crate::storage::activity_store::{ActivitySegment, ActivityStore, PomodoroRecord};
+use chrono::{DateTime, Duration, Local};
+use std::collections::HashMap;
+
+pub const UNCATEGORIZED: &str = "uncategorized";
+pub const UNGROUPED: &str = "ungrouped"; // domains no `[groups]` entry claims
+
+/// Half-open time range `[start, end)` used by all stat queries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+impl TimeRange {
+    pub fn new(start: DateTime<Local>, end: DateTime<Local>) -> Self {
+        Self { start, end }
+    }
+
+    /// Range covering the last `seconds` up to now
+    pub fn last_seconds(seconds: i64) -> Self {
+        let end = Local::now();
+        Self::new(end - Duration::seconds(seconds), end)
+    }
+
+    /// Range covering any stored history, e.g. to copy a whole database
+    pub fn all() -> Self {
+        Self::new(
+            DateTime::<Local>::from(DateTime::UNIX_EPOCH),
+            Local::now() + Duration::days(365 * 100),
+        )
+    }
+
+    pub fn contains(&self, at: DateTime<Local>) -> bool {
+        at >= self.start && at < self.end
+    }
+
+    /// Seconds of `segment` that fall inside this range
+    pub fn overlap_seconds(&self, segment: &ActivitySegment) -> i64 {
+        let start = segment.start.max(self.start);
+        let end = segment.end().min(self.end);
+        (end - start).num_seconds().max(0)
+    }
+}
+
+/// Shared stat queries over the storage layer, used by every frontend
+/// (CLI, reports, extension) so they all report the same numbers
+pub struct Aggregator<'a> {
+    store: &'a ActivityStore,
+    filter: Option<&'a Filter>,
+}
+
+impl<'a> Aggregator<'a> {
+    pub fn new(store: &'a ActivityStore) -> Self {
+        Self {
+            store,
+            filter: None,
+        }
+    }
+
+    /// Restrict activity queries to segments matching `filter`
+    pub fn with_filter(mut self, filter: &'a Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Total tracked seconds within `range`
+    pub fn total_seconds(&self, range: &TimeRange) -> i64 {
+        self.matching_segments()
+            .map(|segment| range.overlap_seconds(segment))
+            .sum()
+    }
+
+    /// Seconds per category within `range`, sorted by time spent (descending)
+    pub fn total_by_category(&self, range: &TimeRange) -> Vec<(String, i64)> {
+        self.sum_by(range, |segment| {
+            segment
+                .category
+                .clone()
+                .unwrap_or_else(|| UNCATEGORIZED.to_string())
+        })
+    }
+
+    /// Seconds per `[groups]` entry within `range`, sorted by time spent
+    /// (descending); other domains are summed up as "ungrouped"
+    pub fn total_by_group(&self, range: &TimeRange, groups: &GroupRules) -> Vec<(String, i64)> {
+        self.sum_by(range, |segment| {
+            groups
+                .group_of(&segment.domain)
+                .unwrap_or(UNGROUPED)
+                .to_string()
+        })
+    }
+
+    /// The `n` domains with the most time within `range`
+    pub fn top_domains(&self, range: &TimeRange, n: usize) -> Vec<(String, i64)> {
+        let mut totals = self.sum_by(range, |segment| segment.domain.clone());
+        totals.truncate(n);
+        totals
+    }
+
+    /// Completed work sessions that ended within `range`
+    pub fn pomodoros(&self, range: &TimeRange) -> Vec<&'a PomodoroRecord> {
+        self.work_sessions(range, false)
+    }
+
+    /// Work sessions that ended within `range` after being spent mostly idle
+    pub fn abandoned(&self, range: &TimeRange) -> Vec<&'a PomodoroRecord> {
+        self.work_sessions(range, true)
+    }
+
+    fn work_sessions(&self, range: &TimeRange, abandoned: bool) -> Vec<&'a PomodoroRecord> {
+        self.store
+            .pomodoros()
+            .iter()
+            .filter(|record| {
+                record.mode == PomodoroMode::Work
+                    && record.abandoned == abandoned
+                    && range.contains(record.end)
+            })
+            .collect()
+    }
+
+    /// Cycles closed by a long break that started within `range`
+    pub fn cycles(&self, range: &TimeRange) -> usize {
+        self.store
+            .pomodoros()
+            .iter()
+            .filter(|record| record.mode == PomodoroMode::LongBreak && range.contains(record.start))
+            .count()
+    }
+
+    fn matching_segments(&self) -> impl Iterator<Item = &'a ActivitySegment> + '_ {
+        self.store
+            .segments()
+            .iter()
+            .filter(|segment| self.filter.is_none_or(|filter| filter.matches(segment)))
+    }
+
+    fn sum_by<F>(&self, range: &TimeRange, key: F) -> Vec<(String, i64)>
+    where
+        F: Fn(&ActivitySegment) -> String,
+    {
+        let mut totals: HashMap<String, i64> = HashMap::new();
+        for segment in self.matching_segments() {
+            let seconds = range.overlap_seconds(segment);
+            if seconds > 0 {
+                *totals.entry(key(segment)).or_insert(0) += seconds;
+            }
+        }
+
+        let mut sorted: Vec<_> = totals.into_iter().collect();
+        sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sorted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 3, 12, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn segment(
+        domain: &str,
+        category: Option<&str>,
+        start: DateTime<Local>,
+        minutes: i64,
+    ) -> ActivitySegment {
+        ActivitySegment {
+            domain: domain.to_string(),
+            category: category.map(str::to_string),
+            category_source: None,
+            profile: None,
+            on_break: false,
+            start,
+            seconds: minutes * 60,
+        }
+    }
+
+    fn work(end: DateTime<Local>, abandoned: bool) -> PomodoroRecord {
+        PomodoroRecord {
+            mode: PomodoroMode::Work,
+            start: end - Duration::minutes(25),
+            end,
+            abandoned,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn an_empty_store_has_nothing_to_report() {
+        let store = ActivityStore::new();
+        let aggregator = Aggregator::new(&store);
+        let range = TimeRange::new(at(0, 0), at(23, 59));
+        assert_eq!(aggregator.total_seconds(&range), 0);
+        assert!(aggregator.total_by_category(&range).is_empty());
+        assert!(aggregator.top_domains(&range, 5).is_empty());
+        assert!(aggregator.pomodoros(&range).is_empty());
+        assert!(aggregator.abandoned(&range).is_empty());
+        assert_eq!(aggregator.cycles(&range), 0);
+    }
+
+    #[test]
+    fn segments_crossing_the_range_count_only_inside_it() {
+        let mut store = ActivityStore::new();
+        store.insert_segment(segment("a.com", None, at(9, 50), 20)); // 10 minutes inside
+        store.insert_segment(segment("b.com", None, at(10, 20), 10)); // all inside
+        store.insert_segment(segment("c.com", None, at(10, 55), 10)); // 5 minutes inside
+        store.insert_segment(segment("d.com", None, at(11, 0), 5)); // starts at the end
+        store.insert_segment(segment("e.com", None, at(9, 0), 60)); // ends at the start
+        store.insert_segment(segment("f.com", None, at(9, 30), 120)); // covers it all
+        let range = TimeRange::new(at(10, 0), at(11, 0));
+        let aggregator = Aggregator::new(&store);
+
+        assert_eq!(aggregator.total_seconds(&range), (10 + 10 + 5 + 60) * 60);
+        assert_eq!(
+            aggregator.top_domains(&range, 10),
+            vec![
+                ("f.com".to_string(), 3600),
+                ("a.com".to_string(), 600),
+                ("b.com".to_string(), 600),
+                ("c.com".to_string(), 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn totals_add_up_per_category_and_domain() {
+        let mut store = ActivityStore::new();
+        store.insert_segment(segment("github.com", Some("work"), at(9, 0), 30));
+        store.insert_segment(segment("docs.rs", Some("work"), at(9, 30), 15));
+        store.insert_segment(segment("github.com", Some("work"), at(10, 0), 10));
+        store.insert_segment(segment("reddit.com", Some("social"), at(11, 0), 20));
+        store.insert_segment(segment("example.com", None, at(12, 0), 20));
+        let range = TimeRange::new(at(0, 0), at(23, 0));
+        let aggregator = Aggregator::new(&store);
+
+        // Ties are sorted by name
+        assert_eq!(
+            aggregator.total_by_category(&range),
+            vec![
+                ("work".to_string(), 55 * 60),
+                ("social".to_string(), 20 * 60),
+                (UNCATEGORIZED.to_string(), 20 * 60),
+            ]
+        );
+        assert_eq!(
+            aggregator.top_domains(&range, 2),
+            vec![
+                ("github.com".to_string(), 40 * 60),
+                ("example.com".to_string(), 20 * 60),
+            ]
+        );
+        assert_eq!(aggregator.total_seconds(&range), 95 * 60);
+    }
+
+    #[test]
+    fn work_sessions_count_in_the_range_they_end_in() {
+        let mut store = ActivityStore::new();
+        store.record_pomodoro(work(at(10, 10), false)); // started before the range
+        store.record_pomodoro(work(at(10, 40), true));
+        store.record_pomodoro(work(at(11, 0), false)); // ends at the end
+        store.record_pomodoro(PomodoroRecord {
+            mode: PomodoroMode::LongBreak,
+            start: at(10, 45),
+            end: at(11, 0),
+            abandoned: false,
+            label: None,
+        });
+        let range = TimeRange::new(at(10, 0), at(11, 0));
+        let aggregator = Aggregator::new(&store);
+
+        assert_eq!(aggregator.pomodoros(&range).len(), 1);
+        assert_eq!(aggregator.abandoned(&range).len(), 1);
+        assert_eq!(aggregator.cycles(&range), 1);
+    }
+}
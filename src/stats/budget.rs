@@ -0,0 +1,169 @@
+use crate::config::settings::{BudgetConfig, Config, OffDaysConfig};
+use crate::stats::aggregator::Aggregator;
+use crate::stats::calendar::Calendar;
+use crate::stats::filter::Filter;
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Current standing of a single budget, as shown by `status` and broadcast
+/// to the extension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BudgetStatus {
+    pub target: String,
+    pub used_today: i64,
+    pub daily_allowance: Option<i64>, // daily budget plus carried-over time
+    pub used_week: i64,
+    pub weekly_limit: Option<i64>,
+    pub remaining: Option<i64>, // tightest of daily/weekly, negative when over
+    pub off_day: bool,          // budgets don't apply on off-days
+    #[serde(default)]
+    pub category: bool, // `target` is a category from `[category_budgets]`
+}
+
+/// How far past a category budget each further, harsher notification comes
+pub const CATEGORY_BUDGET_STEP: i64 = 15 * 60;
+
+/// A category budget crossed, or crossed by another `CATEGORY_BUDGET_STEP`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryBudgetAlert {
+    pub category: String,
+    pub over_by: i64,
+    pub level: u32, // 1 when the budget is used up, one more per step past it
+}
+
+/// Evaluates configured daily/weekly budgets against tracked activity
+#[derive(Debug)]
+pub struct BudgetTracker {
+    budgets: Vec<(String, BudgetConfig, Filter, bool)>, // target, budget, filter, category
+    calendar: Calendar,
+    off_days: OffDaysConfig,
+}
+
+impl BudgetTracker {
+    pub fn new(config: &Config) -> Self {
+        let domains = config
+            .budgets
+            .iter()
+            .map(|(domain, budget)| (domain, budget, Filter::domain_or_subdomain(domain), false));
+        let categories = config.category_budgets.iter().map(|(category, budget)| {
+            (category, budget, Filter::InCategory(category.clone()), true)
+        });
+        Self {
+            budgets: domains
+                .chain(categories)
+                .map(|(target, budget, mut filter, category)| {
+                    if budget.work_only {
+                        filter = Filter::And(Box::new(filter), Box::new(Filter::work_time()));
+                    }
+                    (target.clone(), budget.clone(), filter, category)
+                })
+                .collect(),
+            calendar: Calendar::new(&config.day),
+            off_days: config.off_days.clone(),
+        }
+    }
+
+    pub fn statuses(&self, store: &ActivityStore, now: DateTime<Local>) -> Vec<BudgetStatus> {
+        let today = self.calendar.day_of(now);
+        let off_day = self.off_days.is_off_day(today);
+        let today_range = self.calendar.day_range(today);
+        let week_start = self.calendar.week_of(today);
+
+        self.budgets
+            .iter()
+            .map(|(target, budget, filter, category)| {
+                let aggregator = Aggregator::new(store).with_filter(filter);
+                let used_today = aggregator.total_seconds(&today_range);
+
+                // Earlier days of the week, each aggregated once. Off-days
+                // neither consume budget nor contribute carryover.
+                let earlier: Vec<i64> = week_start
+                    .iter_days()
+                    .take_while(|day| *day < today)
+                    .filter(|day| !self.off_days.is_off_day(*day))
+                    .map(|day| aggregator.total_seconds(&self.calendar.day_range(day)))
+                    .collect();
+                let used_week = earlier.iter().sum::<i64>() + if off_day { 0 } else { used_today };
+
+                let daily_allowance = budget.daily.map(|daily| {
+                    let carried: i64 = if budget.carryover {
+                        earlier.iter().map(|used| (daily - used).max(0)).sum()
+                    } else {
+                        0
+                    };
+                    daily + carried
+                });
+
+                let remaining = [
+                    daily_allowance.map(|allowance| allowance - used_today),
+                    budget.weekly.map(|weekly| weekly - used_week),
+                ]
+                .into_iter()
+                .flatten()
+                .min();
+
+                BudgetStatus {
+                    target: target.clone(),
+                    used_today,
+                    daily_allowance,
+                    used_week,
+                    weekly_limit: budget.weekly,
+                    remaining,
+                    off_day,
+                    category: *category,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::activity_store::ActivitySegment;
+    use chrono::TimeZone;
+
+    fn visit(day: u32, minutes: i64) -> ActivitySegment {
+        ActivitySegment {
+            domain: "old.reddit.com".to_string(),
+            category: None,
+            category_source: None,
+            profile: None,
+            on_break: false,
+            start: Local.with_ymd_and_hms(2025, 3, day, 10, 0, 0).unwrap(),
+            seconds: minutes * 60,
+        }
+    }
+
+    #[test]
+    fn earlier_days_carry_over_and_off_days_are_left_out() {
+        let config: Config = toml::from_str(
+            "[budgets.\"reddit.com\"]\ndaily = \"15m\"\nweekly = \"1h\"\ncarryover = true\n\n\
+             [off_days]\ndates = [\"2025-03-11\"]\n",
+        )
+        .unwrap();
+        let mut store = ActivityStore::new();
+        store.insert_segment(visit(7, 40)); // the week before
+        store.insert_segment(visit(10, 10)); // Monday: 5 minutes carried over
+        store.insert_segment(visit(11, 30)); // off-day
+        store.insert_segment(visit(12, 5));
+        let now = Local.with_ymd_and_hms(2025, 3, 12, 18, 0, 0).unwrap();
+
+        let statuses = BudgetTracker::new(&config).statuses(&store, now);
+        assert_eq!(
+            statuses,
+            vec![BudgetStatus {
+                target: "reddit.com".to_string(),
+                used_today: 5 * 60,
+                daily_allowance: Some(20 * 60),
+                used_week: 15 * 60,
+                weekly_limit: Some(60 * 60),
+                remaining: Some(15 * 60),
+                off_day: false,
+                category: false,
+            }]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::config::settings::DayConfig;
+use crate::stats::aggregator::TimeRange;
+use chrono::{
+    DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, NaiveTime, TimeZone, Weekday,
+};
+
+/// Maps instants to logical days/weeks using the configured day start and
+/// first day of the week, in the local time zone
+#[derive(Debug, Clone, Copy)]
+pub struct Calendar {
+    day_start: NaiveTime,
+    week_start: Weekday,
+}
+
+impl Calendar {
+    pub fn new(config: &DayConfig) -> Self {
+        Self {
+            day_start: config.start,
+            week_start: config.week_start,
+        }
+    }
+
+    /// The logical day `at` belongs to (before `day_start` counts as the previous day)
+    pub fn day_of(&self, at: DateTime<Local>) -> NaiveDate {
+        self.day_of_in(at)
+    }
+
+    /// Instant at which the logical day `date` begins
+    pub fn day_begin(&self, date: NaiveDate) -> DateTime<Local> {
+        self.day_begin_in(&Local, date)
+    }
+
+    /// Compares instants rather than wall-clock times, so a time in the
+    /// repeated hour after `day_begin` stays in the day that just began
+    fn day_of_in<Tz: TimeZone>(&self, at: DateTime<Tz>) -> NaiveDate {
+        let date = at.naive_local().date();
+        if at < self.day_begin_in(&at.timezone(), date) {
+            date - Duration::days(1)
+        } else {
+            date
+        }
+    }
+
+    fn day_begin_in<Tz: TimeZone>(&self, tz: &Tz, date: NaiveDate) -> DateTime<Tz> {
+        resolve_local(tz, date, self.day_start)
+    }
+
+    /// Range covering the logical day `date`. Days spanning a DST change
+    /// are 23 or 25 hours long.
+    pub fn day_range(&self, date: NaiveDate) -> TimeRange {
+        TimeRange::new(
+            self.day_begin(date),
+            self.day_begin(date + Duration::days(1)),
+        )
+    }
+
+    pub fn today(&self) -> TimeRange {
+        self.day_range(self.day_of(Local::now()))
+    }
+
+    /// First logical day of the week containing `date`
+    pub fn week_of(&self, date: NaiveDate) -> NaiveDate {
+        let offset = (7 + date.weekday().num_days_from_monday()
+            - self.week_start.num_days_from_monday())
+            % 7;
+        date - Duration::days(offset as i64)
+    }
+
+    pub fn week_range(&self, date: NaiveDate) -> TimeRange {
+        let first = self.week_of(date);
+        TimeRange::new(
+            self.day_begin(first),
+            self.day_begin(first + Duration::days(7)),
+        )
+    }
+
+    pub fn this_week(&self) -> TimeRange {
+        self.week_range(self.day_of(Local::now()))
+    }
+}
+
+/// Resolve a local wall-clock time to an instant. Ambiguous times (DST
+/// fall-back) use the earlier instant; skipped times (DST spring-forward)
+/// move to the first valid minute after the gap.
+fn resolve_local<Tz: TimeZone>(tz: &Tz, date: NaiveDate, time: NaiveTime) -> DateTime<Tz> {
+    let mut naive = date.and_time(time);
+    loop {
+        match tz.from_local_datetime(&naive) {
+            LocalResult::Single(at) => return at,
+            LocalResult::Ambiguous(earliest, _) => return earliest,
+            LocalResult::None => naive += Duration::minutes(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+    use chrono_tz::Tz;
+
+    // Berlin springs forward from 02:00 to 03:00 on 2025-03-30 and falls
+    // back from 03:00 to 02:00 on 2025-10-26
+
+    fn calendar(start: &str) -> Calendar {
+        Calendar::new(&DayConfig {
+            start: start.parse().unwrap(),
+            week_start: Weekday::Mon,
+        })
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    fn hours_in(calendar: &Calendar, day: NaiveDate) -> f64 {
+        let begin = calendar.day_begin_in(&Berlin, day);
+        let end = calendar.day_begin_in(&Berlin, day + Duration::days(1));
+        (end - begin).num_minutes() as f64 / 60.0
+    }
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        chrono::Utc
+            .with_ymd_and_hms(2025, month, day, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Berlin)
+    }
+
+    #[test]
+    fn days_spanning_a_change_are_23_or_25_hours_long() {
+        let calendar = calendar("04:00");
+        assert_eq!(hours_in(&calendar, date(3, 28)), 24.0);
+        assert_eq!(hours_in(&calendar, date(3, 29)), 23.0);
+        assert_eq!(hours_in(&calendar, date(3, 30)), 24.0);
+        assert_eq!(hours_in(&calendar, date(10, 25)), 25.0);
+        assert_eq!(hours_in(&calendar, date(10, 26)), 24.0);
+
+        // 04:00 CEST is 02:00 UTC, 04:00 CET is 03:00 UTC
+        assert_eq!(
+            calendar.day_begin_in(&Berlin, date(3, 30)),
+            utc(3, 30, 2, 0)
+        );
+        assert_eq!(
+            calendar.day_begin_in(&Berlin, date(10, 26)),
+            utc(10, 26, 3, 0)
+        );
+    }
+
+    #[test]
+    fn a_start_in_the_skipped_hour_moves_to_the_end_of_the_gap() {
+        let calendar = calendar("02:30");
+        // 03:00 CEST, the first valid minute after 02:00 CET
+        assert_eq!(
+            calendar.day_begin_in(&Berlin, date(3, 30)),
+            utc(3, 30, 1, 0)
+        );
+        assert_eq!(hours_in(&calendar, date(3, 29)), 23.5);
+        assert_eq!(hours_in(&calendar, date(3, 30)), 23.5);
+
+        assert_eq!(calendar.day_of_in(utc(3, 30, 0, 59)), date(3, 29));
+        assert_eq!(calendar.day_of_in(utc(3, 30, 1, 0)), date(3, 30));
+    }
+
+    #[test]
+    fn a_start_in_the_repeated_hour_uses_its_first_occurrence() {
+        let calendar = calendar("02:30");
+        // the first 02:30, still CEST
+        assert_eq!(
+            calendar.day_begin_in(&Berlin, date(10, 26)),
+            utc(10, 26, 0, 30)
+        );
+        assert_eq!(hours_in(&calendar, date(10, 25)), 24.0);
+        assert_eq!(hours_in(&calendar, date(10, 26)), 25.0);
+
+        assert_eq!(calendar.day_of_in(utc(10, 26, 0, 29)), date(10, 25));
+        assert_eq!(calendar.day_of_in(utc(10, 26, 0, 30)), date(10, 26));
+        // the second 02:15, an hour after the day began
+        assert_eq!(calendar.day_of_in(utc(10, 26, 1, 15)), date(10, 26));
+    }
+
+    #[test]
+    fn every_instant_falls_in_the_day_it_is_assigned_to() {
+        for start in ["00:00", "02:00", "02:30", "03:00", "04:00"] {
+            let calendar = calendar(start);
+            for (month, day) in [(3, 30), (10, 26)] {
+                let mut at = utc(month, day - 1, 20, 0);
+                while at < utc(month, day + 1, 4, 0) {
+                    let logical = calendar.day_of_in(at);
+                    let begin = calendar.day_begin_in(&Berlin, logical);
+                    let end = calendar.day_begin_in(&Berlin, logical + Duration::days(1));
+                    assert!(begin <= at && at < end, "{} with day start {}", at, start);
+                    at += Duration::minutes(15);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::stats::aggregator::TimeRange;
+use crate::stats::filter::is_domain_or_subdomain;
+use crate::stats::sites::builtin_category;
+use crate::storage::activity_store::{ActivitySegment, ActivityStore, CategorySource};
+use std::collections::BTreeMap;
+
+/// Server-side category rules from the `[categories]` config section. A
+/// matching rule overrides the hint sent by the extension; the most specific
+/// domain wins.
+#[derive(Debug, Clone, Default)]
+pub struct CategoryRules {
+    rules: Vec<(String, String)>, // domain -> category, longest domain first
+}
+
+impl CategoryRules {
+    pub fn new(rules: &BTreeMap<String, String>) -> Self {
+        let mut rules: Vec<(String, String)> = rules
+            .iter()
+            .map(|(domain, category)| (domain.to_lowercase(), category.clone()))
+            .collect();
+        rules.sort_by_key(|(domain, _)| std::cmp::Reverse(domain.len()));
+        Self { rules }
+    }
+
+    /// Add the rule `domain` -> `category`, replacing one for the same domain
+    pub fn set(&mut self, domain: &str, category: &str) {
+        let domain = domain.to_lowercase();
+        self.rules.retain(|(parent, _)| *parent != domain);
+        self.rules.push((domain, category.to_string()));
+        self.rules
+            .sort_by_key(|(domain, _)| std::cmp::Reverse(domain.len()));
+    }
+
+    /// Apply the rules again to the segments of `domain` (subdomains
+    /// included) overlapping `range`, after its rule changed. Returns the
+    /// segments whose category changed, to be saved again.
+    pub fn reapply(
+        &self,
+        store: &mut ActivityStore,
+        range: &TimeRange,
+        domain: &str,
+    ) -> Vec<ActivitySegment> {
+        let mut changed = Vec::new();
+        for segment in store.segments_mut() {
+            if segment.end() <= range.start
+                || segment.start >= range.end
+                || !is_domain_or_subdomain(&segment.domain, domain)
+            {
+                continue;
+            }
+            // A more specific rule may still decide
+            if let Some((category, CategorySource::Server)) = self.categorize(&segment.domain, None)
+                && (segment.category.as_ref() != Some(&category)
+                    || segment.category_source != Some(CategorySource::Server))
+            {
+                segment.category = Some(category);
+                segment.category_source = Some(CategorySource::Server);
+                changed.push(segment.clone());
+            }
+        }
+        changed
+    }
+
+    /// Category for `domain` and who decided it: a rule, else the client
+    /// hint, else the built-in site database
+    pub fn categorize(&self, domain: &str, hint: Option<&str>) -> Option<(String, CategorySource)> {
+        if let Some((_, category)) = self
+            .rules
+            .iter()
+            .find(|(parent, _)| is_domain_or_subdomain(domain, parent))
+        {
+            return Some((category.clone(), CategorySource::Server));
+        }
+        if let Some(hint) = hint.filter(|hint| !hint.is_empty()) {
+            return Some((hint.to_string(), CategorySource::Client));
+        }
+        builtin_category(domain).map(|category| (category.to_string(), CategorySource::Builtin))
+    }
+}
@@ -0,0 +1,216 @@
+use crate::stats::aggregator::TimeRange;
+use crate::stats::calendar::Calendar;
+use crate::stats::filter::Filter;
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A table `stop_it export` can write as CSV, one record per row
+pub trait Row: Serialize {
+    const HEADER: &'static [&'static str];
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Time on one domain during one day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DomainRow {
+    pub date: NaiveDate,
+    pub domain: String,
+    pub category: Option<String>,
+    pub seconds: i64,       // work sessions and breaks
+    pub break_seconds: i64, // the part tracked on breaks
+}
+
+impl Row for DomainRow {
+    const HEADER: &'static [&'static str] =
+        &["date", "domain", "category", "seconds", "break_seconds"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.date.to_string(),
+            self.domain.clone(),
+            self.category.clone().unwrap_or_default(),
+            self.seconds.to_string(),
+            self.break_seconds.to_string(),
+        ]
+    }
+}
+
+/// One finished Pomodoro phase
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PomodoroRow {
+    pub mode: &'static str,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub abandoned: bool,
+}
+
+impl Row for PomodoroRow {
+    const HEADER: &'static [&'static str] = &["mode", "start", "end", "abandoned"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.mode.to_string(),
+            self.start.to_rfc3339(),
+            self.end.to_rfc3339(),
+            self.abandoned.to_string(),
+        ]
+    }
+}
+
+/// Seconds per day, domain and category within `range`, by date and then
+/// most time first. Segments crossing the day boundary are split.
+pub fn domain_rows(
+    store: &ActivityStore,
+    calendar: &Calendar,
+    range: &TimeRange,
+    filter: Option<&Filter>,
+) -> Vec<DomainRow> {
+    let mut totals: BTreeMap<(NaiveDate, String, Option<String>), (i64, i64)> = BTreeMap::new();
+    let segments = store
+        .segments()
+        .iter()
+        .filter(|segment| filter.is_none_or(|filter| filter.matches(segment)));
+    for segment in segments {
+        let end = segment.end().min(range.end);
+        let mut day = calendar.day_of(segment.start.max(range.start));
+        while calendar.day_begin(day) < end {
+            let day_range = calendar.day_range(day);
+            let within = TimeRange::new(
+                day_range.start.max(range.start),
+                day_range.end.min(range.end),
+            );
+            let seconds = within.overlap_seconds(segment);
+            if seconds > 0 {
+                let key = (day, segment.domain.clone(), segment.category.clone());
+                let (total, on_breaks) = totals.entry(key).or_default();
+                *total += seconds;
+                if segment.on_break {
+                    *on_breaks += seconds;
+                }
+            }
+            day += Duration::days(1);
+        }
+    }
+
+    let mut rows: Vec<DomainRow> = totals
+        .into_iter()
+        .map(
+            |((date, domain, category), (seconds, break_seconds))| DomainRow {
+                date,
+                domain,
+                category,
+                seconds,
+                break_seconds,
+            },
+        )
+        .collect();
+    rows.sort_by(|a, b| a.date.cmp(&b.date).then(b.seconds.cmp(&a.seconds)));
+    rows
+}
+
+/// Pomodoro phases that started within `range`, oldest first
+pub fn pomodoro_rows(store: &ActivityStore, range: &TimeRange) -> Vec<PomodoroRow> {
+    store
+        .pomodoros()
+        .iter()
+        .filter(|record| range.contains(record.start))
+        .map(|record| PomodoroRow {
+            mode: record.mode.as_str(),
+            start: record.start,
+            end: record.end,
+            abandoned: record.abandoned,
+        })
+        .collect()
+}
+
+/// RFC 4180 CSV with a header line
+pub fn to_csv<R: Row>(rows: &[R]) -> String {
+    let mut csv = R::HEADER.join(",");
+    csv.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<String> = row.fields().iter().map(|field| quote(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::Config;
+    use crate::pomodoro::pomodoro::PomodoroMode;
+    use crate::storage::activity_store::ActivitySegment;
+    use crate::storage::activity_store::PomodoroRecord;
+    use chrono::TimeZone;
+
+    #[test]
+    fn exports_split_time_by_day() {
+        let at = |day, hour, minute| {
+            Local
+                .with_ymd_and_hms(2025, 3, day, hour, minute, 0)
+                .unwrap()
+        };
+        let segment =
+            |domain: &str, category: Option<&str>, start, seconds, on_break| ActivitySegment {
+                domain: domain.to_string(),
+                category: category.map(String::from),
+                category_source: None,
+                profile: None,
+                on_break,
+                start,
+                seconds,
+            };
+        let mut store = ActivityStore::new();
+        store.insert_segment(segment("github.com", None, at(11, 23, 50), 20 * 60, false));
+        store.insert_segment(segment(
+            "news.ycombinator.com",
+            Some("news, tech"),
+            at(12, 9, 0),
+            300,
+            true,
+        ));
+        store.insert_segment(segment("github.com", None, at(12, 9, 5), 60, false));
+        store.record_pomodoro(PomodoroRecord {
+            mode: PomodoroMode::Work,
+            start: at(12, 8, 35),
+            end: at(12, 9, 0),
+            abandoned: false,
+            label: None,
+        });
+
+        let calendar = Calendar::new(&Config::default().day);
+        let range = TimeRange::new(at(11, 0, 0), at(13, 0, 0));
+        let rows = domain_rows(&store, &calendar, &range, None);
+        assert_eq!(
+            to_csv(&rows),
+            "date,domain,category,seconds,break_seconds\r\n\
+             2025-03-11,github.com,,600,0\r\n\
+             2025-03-12,github.com,,660,0\r\n\
+             2025-03-12,news.ycombinator.com,\"news, tech\",300,300\r\n"
+        );
+        let filter = Filter::parse("mode=break").unwrap();
+        assert_eq!(
+            domain_rows(&store, &calendar, &range, Some(&filter)).len(),
+            1
+        );
+
+        let pomodoros = pomodoro_rows(&store, &range);
+        let json: serde_json::Value = serde_json::to_value(&pomodoros).unwrap();
+        assert_eq!(json[0]["mode"], "WORK");
+        assert_eq!(json[0]["abandoned"], false);
+        assert!(
+            to_csv(&pomodoros).starts_with("mode,start,end,abandoned\r\nWORK,2025-03-12T08:35:00")
+        );
+    }
+}
@@ -0,0 +1,612 @@
+use crate::stats::aggregator::UNCATEGORIZED;
+use crate::storage::activity_store::ActivitySegment;
+use chrono::{Datelike, Timelike, Weekday};
+use std::fmt;
+
+/// Filter expression for `stats`/`export`, e.g.
+/// `category=social AND weekday=sat..sun` or `domain~"*.google.com"`
+///
+/// Grammar (keywords are case-insensitive):
+///   expr       := and ("OR" and)*
+///   and        := unary ("AND" unary)*
+///   unary      := "NOT" unary | "(" expr ")" | comparison
+///   comparison := field ("=" | "!=" | "~") value
+///   field      := category | domain | profile | mode | weekday | hour
+///
+/// `weekday` and `hour` look at when a segment started: one that runs past
+/// midnight or the full hour counts entirely towards its starting slot.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Category(Match),
+    Domain(Match),
+    Profile(Match),     // browser profile, see `[server.tokens]`
+    Mode(Match),        // "work" or "break", the Pomodoro phase it was tracked in
+    Weekday(u32, u32),  // inclusive range, 0 = Monday, of the segment's start
+    Hour(u32, u32),     // inclusive range, 0..23, of the segment's start
+    InCategory(String), // one of a segment's comma-separated categories
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Match {
+    Exact(String),
+    Glob(String),
+}
+
+impl Match {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Match::Exact(expected) => expected.eq_ignore_ascii_case(value),
+            Match::Glob(pattern) => {
+                glob_match(&pattern.to_ascii_lowercase(), &value.to_ascii_lowercase())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            input_len: input.len(),
+        };
+        let filter = parser.parse_or()?;
+        if let Some((token, position)) = parser.tokens.get(parser.pos) {
+            return Err(FilterError {
+                message: format!("unexpected '{}'", token),
+                position: *position,
+            });
+        }
+        Ok(filter)
+    }
+
+    /// Matches time tracked during work sessions
+    pub fn work_time() -> Self {
+        Filter::Mode(Match::Exact("work".to_string()))
+    }
+
+    /// Matches time tracked during breaks
+    pub fn break_time() -> Self {
+        Filter::Mode(Match::Exact("break".to_string()))
+    }
+
+    /// Matches `domain` itself and any of its subdomains
+    pub fn domain_or_subdomain(domain: &str) -> Self {
+        Filter::Or(
+            Box::new(Filter::Domain(Match::Exact(domain.to_string()))),
+            Box::new(Filter::Domain(Match::Glob(format!("*.{}", domain)))),
+        )
+    }
+
+    pub fn matches(&self, segment: &ActivitySegment) -> bool {
+        match self {
+            Filter::And(a, b) => a.matches(segment) && b.matches(segment),
+            Filter::Or(a, b) => a.matches(segment) || b.matches(segment),
+            Filter::Not(inner) => !inner.matches(segment),
+            Filter::Category(m) => m.matches(segment.category.as_deref().unwrap_or(UNCATEGORIZED)),
+            Filter::Domain(m) => m.matches(&segment.domain),
+            Filter::Profile(m) => m.matches(segment.profile.as_deref().unwrap_or("")),
+            Filter::Mode(m) => m.matches(if segment.on_break { "break" } else { "work" }),
+            Filter::Weekday(from, to) => {
+                in_cyclic_range(segment.start.weekday().num_days_from_monday(), *from, *to)
+            }
+            Filter::Hour(from, to) => in_cyclic_range(segment.start.hour(), *from, *to),
+            Filter::InCategory(category) => segment.category.as_deref().is_some_and(|categories| {
+                categories
+                    .split(',')
+                    .any(|name| name.trim().eq_ignore_ascii_case(category))
+            }),
+        }
+    }
+}
+
+/// Whether `domain` is `parent` itself or one of its subdomains
+pub fn is_domain_or_subdomain(domain: &str, parent: &str) -> bool {
+    domain == parent
+        || domain
+            .strip_suffix(parent)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Inclusive range check that wraps around, so `fri..mon` and `22..2` work
+fn in_cyclic_range(value: u32, from: u32, to: u32) -> bool {
+    if from <= to {
+        value >= from && value <= to
+    } else {
+        value >= from || value <= to
+    }
+}
+
+/// Minimal glob matching supporting `*` and `?`
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = backtrack {
+            p = star_p + 1;
+            v = star_v + 1;
+            backtrack = Some((star_p, star_v + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "{}", w),
+            Token::Quoted(q) => write!(f, "\"{}\"", q),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(pos, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push((Token::LParen, pos));
+            }
+            ')' => {
+                chars.next();
+                tokens.push((Token::RParen, pos));
+            }
+            '=' | '~' => {
+                chars.next();
+                tokens.push((Token::Op(if c == '=' { "=" } else { "~" }), pos));
+            }
+            '!' => {
+                chars.next();
+                match chars.next() {
+                    Some((_, '=')) => tokens.push((Token::Op("!="), pos)),
+                    _ => {
+                        return Err(FilterError {
+                            message: "expected '=' after '!'".to_string(),
+                            position: pos,
+                        });
+                    }
+                }
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, ch)) => value.push(ch),
+                        None => {
+                            return Err(FilterError {
+                                message: "unterminated string".to_string(),
+                                position: pos,
+                            });
+                        }
+                    }
+                }
+                tokens.push((Token::Quoted(value), pos));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch.is_whitespace() || matches!(ch, '(' | ')' | '=' | '~' | '!' | '"') {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), pos));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some((Token::Word(w), _)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next(&mut self) -> Result<(Token, usize), FilterError> {
+        let token = self.tokens.get(self.pos).cloned().ok_or(FilterError {
+            message: "unexpected end of expression".to_string(),
+            position: self.input_len,
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, FilterError> {
+        let mut left = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Filter::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, FilterError> {
+        let mut left = self.parse_unary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Filter::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, FilterError> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if let Some((Token::LParen, _)) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            return match self.next()? {
+                (Token::RParen, _) => Ok(inner),
+                (token, position) => Err(FilterError {
+                    message: format!("expected ')' but found '{}'", token),
+                    position,
+                }),
+            };
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Filter, FilterError> {
+        let (field, field_pos) = match self.next()? {
+            (Token::Word(w), pos) => (w.to_ascii_lowercase(), pos),
+            (token, position) => {
+                return Err(FilterError {
+                    message: format!("expected a field name but found '{}'", token),
+                    position,
+                });
+            }
+        };
+
+        let (op, op_pos) = match self.next()? {
+            (Token::Op(op), pos) => (op, pos),
+            (token, position) => {
+                return Err(FilterError {
+                    message: format!("expected '=', '!=' or '~' but found '{}'", token),
+                    position,
+                });
+            }
+        };
+
+        let (value, value_pos) = match self.next()? {
+            (Token::Word(v), pos) | (Token::Quoted(v), pos) => (v, pos),
+            (token, position) => {
+                return Err(FilterError {
+                    message: format!("expected a value but found '{}'", token),
+                    position,
+                });
+            }
+        };
+
+        let to_match = |value: String| {
+            if op == "~" {
+                Match::Glob(value)
+            } else {
+                Match::Exact(value)
+            }
+        };
+
+        let filter = match field.as_str() {
+            "category" => Filter::Category(to_match(value)),
+            "domain" => Filter::Domain(to_match(value)),
+            "profile" => Filter::Profile(to_match(value)),
+            "mode" => Filter::Mode(to_match(value)),
+            "weekday" | "hour" if op == "~" => {
+                return Err(FilterError {
+                    message: format!("'~' is not supported for '{}'", field),
+                    position: op_pos,
+                });
+            }
+            "weekday" => {
+                let (from, to) = parse_range(&value, value_pos, parse_weekday)?;
+                Filter::Weekday(from, to)
+            }
+            "hour" => {
+                let (from, to) = parse_range(&value, value_pos, parse_hour)?;
+                Filter::Hour(from, to)
+            }
+            _ => {
+                return Err(FilterError {
+                    message: format!(
+                        "unknown field '{}' (expected category, domain, profile, mode, weekday or hour)",
+                        field
+                    ),
+                    position: field_pos,
+                });
+            }
+        };
+
+        Ok(if op == "!=" {
+            Filter::Not(Box::new(filter))
+        } else {
+            filter
+        })
+    }
+}
+
+fn parse_range(
+    value: &str,
+    position: usize,
+    parse_one: fn(&str) -> Option<u32>,
+) -> Result<(u32, u32), FilterError> {
+    let (from, to) = value.split_once("..").unwrap_or((value, value));
+    match (parse_one(from), parse_one(to)) {
+        (Some(from), Some(to)) => Ok((from, to)),
+        _ => Err(FilterError {
+            message: format!("invalid value or range '{}'", value),
+            position,
+        }),
+    }
+}
+
+fn parse_weekday(value: &str) -> Option<u32> {
+    value
+        .parse::<Weekday>()
+        .ok()
+        .map(|day| day.num_days_from_monday())
+}
+
+fn parse_hour(value: &str) -> Option<u32> {
+    value.parse::<u32>().ok().filter(|hour| *hour < 24)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Local, TimeZone};
+
+    fn field(name: &str, value: &str) -> Filter {
+        Filter::parse(&format!("{}={}", name, value)).unwrap()
+    }
+
+    // 2025-03-15 is a Saturday
+    fn segment_at(day: u32, hour: u32, minute: u32, minutes: i64) -> ActivitySegment {
+        let start: DateTime<Local> = Local
+            .with_ymd_and_hms(2025, 3, day, hour, minute, 0)
+            .unwrap();
+        ActivitySegment {
+            domain: "mail.google.com".to_string(),
+            category: Some("work, email".to_string()),
+            category_source: None,
+            profile: None,
+            on_break: false,
+            start,
+            seconds: minutes * 60,
+        }
+    }
+
+    fn error(input: &str) -> FilterError {
+        Filter::parse(input).unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tightest() {
+        let a = field("domain", "a");
+        let b = field("domain", "b");
+        let c = field("domain", "c");
+        assert_eq!(
+            Filter::parse("domain=a OR domain=b AND domain=c").unwrap(),
+            Filter::Or(
+                Box::new(a.clone()),
+                Box::new(Filter::And(Box::new(b.clone()), Box::new(c.clone())))
+            )
+        );
+        assert_eq!(
+            Filter::parse("not domain=a and domain=b").unwrap(),
+            Filter::And(
+                Box::new(Filter::Not(Box::new(a.clone()))),
+                Box::new(b.clone())
+            )
+        );
+        assert_eq!(
+            Filter::parse("(domain=a OR domain=b) AND domain=c").unwrap(),
+            Filter::And(
+                Box::new(Filter::Or(Box::new(a.clone()), Box::new(b.clone()))),
+                Box::new(c)
+            )
+        );
+        assert_eq!(
+            Filter::parse("domain!=a").unwrap(),
+            Filter::Not(Box::new(a))
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        let e = error("domain=a AND bogus=x");
+        assert_eq!(e.position, 13);
+        assert!(e.message.starts_with("unknown field 'bogus'"), "{}", e);
+        assert!(e.to_string().ends_with("(at position 13)"), "{}", e);
+
+        assert_eq!(
+            error("domain ! a"),
+            FilterError {
+                message: "expected '=' after '!'".to_string(),
+                position: 7
+            }
+        );
+        assert_eq!(
+            error("domain=\"abc"),
+            FilterError {
+                message: "unterminated string".to_string(),
+                position: 7
+            }
+        );
+        assert_eq!(
+            error("(domain=a"),
+            FilterError {
+                message: "unexpected end of expression".to_string(),
+                position: 9
+            }
+        );
+        assert_eq!(
+            error("domain=a)"),
+            FilterError {
+                message: "unexpected ')'".to_string(),
+                position: 8
+            }
+        );
+        assert_eq!(
+            error("domain a"),
+            FilterError {
+                message: "expected '=', '!=' or '~' but found 'a'".to_string(),
+                position: 7
+            }
+        );
+        assert_eq!(
+            error("hour~1"),
+            FilterError {
+                message: "'~' is not supported for 'hour'".to_string(),
+                position: 4
+            }
+        );
+        assert_eq!(
+            error("hour=20..24"),
+            FilterError {
+                message: "invalid value or range '20..24'".to_string(),
+                position: 5
+            }
+        );
+        assert_eq!(error("weekday=someday").position, 8);
+    }
+
+    #[test]
+    fn weekday_ranges_cover_the_weekend_and_wrap_around() {
+        let weekend = Filter::parse("weekday=sat..sun").unwrap();
+        assert_eq!(weekend, Filter::Weekday(5, 6));
+        assert!(!weekend.matches(&segment_at(14, 12, 0, 10)));
+        assert!(weekend.matches(&segment_at(15, 12, 0, 10)));
+        assert!(weekend.matches(&segment_at(16, 12, 0, 10)));
+        assert!(!weekend.matches(&segment_at(17, 12, 0, 10)));
+
+        let long_weekend = Filter::parse("weekday=Fri..Mon").unwrap();
+        assert!(long_weekend.matches(&segment_at(14, 12, 0, 10)));
+        assert!(long_weekend.matches(&segment_at(17, 12, 0, 10)));
+        assert!(!long_weekend.matches(&segment_at(18, 12, 0, 10)));
+    }
+
+    #[test]
+    fn hour_ranges_wrap_around_midnight() {
+        let night = Filter::parse("hour=22..2").unwrap();
+        assert_eq!(night, Filter::Hour(22, 2));
+        for hour in [22, 23, 0, 1, 2] {
+            assert!(night.matches(&segment_at(12, hour, 0, 10)), "{}", hour);
+        }
+        for hour in [3, 12, 21] {
+            assert!(!night.matches(&segment_at(12, hour, 0, 10)), "{}", hour);
+        }
+        assert_eq!(Filter::parse("hour=9").unwrap(), Filter::Hour(9, 9));
+    }
+
+    #[test]
+    fn segments_count_towards_the_slot_they_start_in() {
+        // Saturday 23:30 to Sunday 00:30
+        let crossing = segment_at(15, 23, 30, 60);
+        assert!(Filter::parse("weekday=sat").unwrap().matches(&crossing));
+        assert!(!Filter::parse("weekday=sun").unwrap().matches(&crossing));
+        assert!(Filter::parse("hour=23").unwrap().matches(&crossing));
+        assert!(!Filter::parse("hour=0").unwrap().matches(&crossing));
+    }
+
+    #[test]
+    fn glob_matches_stars_and_question_marks() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+        assert!(glob_match("*.google.com", "mail.google.com"));
+        assert!(!glob_match("*.google.com", "google.com"));
+        assert!(!glob_match("*.google.com", "google.com.evil.net"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("abc**", "abc"));
+        assert!(glob_match("?.io", "x.io"));
+        assert!(!glob_match("?.io", ".io"));
+        assert!(!glob_match("?.io", "xy.io"));
+        assert!(glob_match("*ü*", "grün.de"));
+    }
+
+    #[test]
+    fn values_match_without_regard_to_case() {
+        let segment = segment_at(12, 9, 0, 10);
+        assert!(
+            Filter::parse("domain~\"*.GOOGLE.com\"")
+                .unwrap()
+                .matches(&segment)
+        );
+        assert!(
+            Filter::parse("DOMAIN=Mail.Google.Com")
+                .unwrap()
+                .matches(&segment)
+        );
+        assert!(Filter::InCategory("EMAIL".to_string()).matches(&segment));
+        assert!(!Filter::InCategory("mail".to_string()).matches(&segment));
+        assert!(
+            Filter::parse("category=\"work, email\"")
+                .unwrap()
+                .matches(&segment)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config::settings::FocusAlertsConfig;
+use crate::stats::aggregator::{Aggregator, TimeRange};
+use crate::stats::filter::{Filter, Match};
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Duration, Local};
+
+/// Watches the rolling distraction ratio and decides when to send a
+/// "you've been drifting" alert
+#[derive(Debug)]
+pub struct FocusMonitor {
+    config: FocusAlertsConfig,
+    distracting: Option<Filter>, // during work sessions
+    work_time: Filter,
+    last_alert: Option<DateTime<Local>>,
+}
+
+impl FocusMonitor {
+    pub fn new(config: &FocusAlertsConfig) -> Self {
+        Self {
+            config: config.clone(),
+            distracting: distracting_filter(config).map(|distracting| {
+                Filter::And(Box::new(distracting), Box::new(Filter::work_time()))
+            }),
+            work_time: Filter::work_time(),
+            last_alert: None,
+        }
+    }
+
+    /// Matches focused time: tracked during work sessions, distractions
+    /// left out
+    pub fn focused(&self) -> Filter {
+        match &self.distracting {
+            Some(distracting) => Filter::And(
+                Box::new(self.work_time.clone()),
+                Box::new(Filter::Not(Box::new(distracting.clone()))),
+            ),
+            None => self.work_time.clone(),
+        }
+    }
+
+    /// Distraction ratio (0.0 - 1.0) over the configured window ending at `now`
+    pub fn distraction_ratio(&self, store: &ActivityStore, now: DateTime<Local>) -> f64 {
+        let window = TimeRange::new(now - Duration::minutes(self.config.window_minutes), now);
+        self.distraction_ratio_in(store, &window)
+    }
+
+    /// Share (0.0 - 1.0) of the work time tracked in `range` spent on
+    /// distractions; browsing on breaks is not a distraction
+    pub fn distraction_ratio_in(&self, store: &ActivityStore, range: &TimeRange) -> f64 {
+        let Some(ref distracting) = self.distracting else {
+            return 0.0;
+        };
+        let total = Aggregator::new(store)
+            .with_filter(&self.work_time)
+            .total_seconds(range);
+        if total == 0 {
+            return 0.0;
+        }
+        let distracted = Aggregator::new(store)
+            .with_filter(distracting)
+            .total_seconds(range);
+        distracted as f64 / total as f64
+    }
+
+    /// Returns the ratio when an alert should fire. Alerts need at least half of
+    /// the window to be tracked and fire at most once per window.
+    pub fn check(&mut self, store: &ActivityStore, now: DateTime<Local>) -> Option<f64> {
+        if !self.config.enabled || !self.in_work_hours(now) {
+            return None;
+        }
+        let window = Duration::minutes(self.config.window_minutes);
+        if self.last_alert.is_some_and(|last| now - last < window) {
+            return None;
+        }
+
+        let range = TimeRange::new(now - window, now);
+        let worked = Aggregator::new(store)
+            .with_filter(&self.work_time)
+            .total_seconds(&range);
+        if worked < window.num_seconds() / 2 {
+            return None;
+        }
+
+        let ratio = self.distraction_ratio(store, now);
+        if ratio > self.config.threshold {
+            self.last_alert = Some(now);
+            Some(ratio)
+        } else {
+            None
+        }
+    }
+
+    fn in_work_hours(&self, now: DateTime<Local>) -> bool {
+        let (from, to) = self.config.work_hours;
+        let time = now.time();
+        if from <= to {
+            time >= from && time < to
+        } else {
+            time >= from || time < to
+        }
+    }
+}
+
+/// Build a filter matching distracting categories, domains and their subdomains
+fn distracting_filter(config: &FocusAlertsConfig) -> Option<Filter> {
+    let categories = config
+        .distracting_categories
+        .iter()
+        .map(|category| Filter::Category(Match::Exact(category.clone())));
+    let domains = config
+        .distracting_domains
+        .iter()
+        .map(|domain| Filter::domain_or_subdomain(domain));
+
+    categories
+        .chain(domains)
+        .reduce(|a, b| Filter::Or(Box::new(a), Box::new(b)))
+}
@@ -0,0 +1,10 @@
+/// Human-readable duration, e.g. "1h 05m" or "4m 30s"
+pub fn format_duration(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    if seconds >= 3600 {
+        format!("{}{}h {:02}m", sign, seconds / 3600, (seconds % 3600) / 60)
+    } else {
+        format!("{}{}m {:02}s", sign, seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,114 @@
+use crate::stats::filter::{glob_match, is_domain_or_subdomain};
+use std::collections::BTreeMap;
+
+/// Company groups from the `[groups]` config section, e.g. gmail.com and
+/// docs.google.com -> "Google", to report time per vendor apart from
+/// categories. Plain domains include subdomains, the most specific one wins;
+/// patterns like "jira.*" are tried after them.
+#[derive(Debug, Clone, Default)]
+pub struct GroupRules {
+    domains: Vec<(String, String)>, // domain -> group, longest domain first
+    patterns: Vec<(String, String)>, // pattern -> group
+}
+
+impl GroupRules {
+    pub fn new(groups: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut rules = Self::default();
+        for (group, domains) in groups {
+            for domain in domains {
+                let domain = domain.to_lowercase();
+                if domain.contains(['*', '?']) {
+                    rules.patterns.push((domain, group.clone()));
+                } else {
+                    rules.domains.push((domain, group.clone()));
+                }
+            }
+        }
+        rules
+            .domains
+            .sort_by_key(|(domain, _)| std::cmp::Reverse(domain.len()));
+        rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty() && self.patterns.is_empty()
+    }
+
+    /// The group `domain` belongs to, if any
+    pub fn group_of(&self, domain: &str) -> Option<&str> {
+        self.domains
+            .iter()
+            .find(|(parent, _)| is_domain_or_subdomain(domain, parent))
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| glob_match(pattern, domain))
+            })
+            .map(|(_, group)| group.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::aggregator::{Aggregator, TimeRange};
+    use crate::storage::activity_store::ActivitySegment;
+    use crate::storage::activity_store::ActivityStore;
+    use chrono::Duration;
+    use chrono::Local;
+    use chrono::TimeZone;
+
+    #[test]
+    fn domains_are_summed_up_per_group() {
+        let at = |minute| Local.with_ymd_and_hms(2025, 3, 12, 9, minute, 0).unwrap();
+        let mut store = ActivityStore::new();
+        let sites = [
+            ("mail.google.com", 10),
+            ("gmail.com", 5),
+            ("acme.atlassian.net", 20),
+            ("jira.acme.com", 5),
+            ("maps.google.com", 5), // claimed by a more specific entry
+            ("github.com", 15),
+        ];
+        let mut start = at(0);
+        for (domain, minutes) in sites {
+            store.insert_segment(ActivitySegment {
+                domain: domain.to_string(),
+                category: None,
+                category_source: None,
+                profile: None,
+                on_break: false,
+                start,
+                seconds: minutes * 60,
+            });
+            start += Duration::minutes(minutes);
+        }
+        let groups: BTreeMap<String, Vec<String>> = [
+            ("Google", vec!["google.com", "gmail.com"]),
+            ("Atlassian", vec!["atlassian.net", "jira.*"]),
+            ("Maps", vec!["maps.google.com"]),
+        ]
+        .into_iter()
+        .map(|(group, domains)| {
+            (
+                group.to_string(),
+                domains.into_iter().map(String::from).collect(),
+            )
+        })
+        .collect();
+        let groups = GroupRules::new(&groups);
+
+        assert_eq!(groups.group_of("docs.google.com"), Some("Google"));
+        assert_eq!(groups.group_of("maps.google.com"), Some("Maps"));
+        assert_eq!(groups.group_of("notgoogle.com"), None);
+        assert_eq!(
+            Aggregator::new(&store).total_by_group(&TimeRange::all(), &groups),
+            [
+                ("Atlassian".to_string(), 25 * 60),
+                ("Google".to_string(), 15 * 60),
+                ("ungrouped".to_string(), 15 * 60),
+                ("Maps".to_string(), 5 * 60),
+            ]
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::{Duration, NaiveDate};
+
+/// Largest page clients may ask for, in days
+pub const MAX_PAGE_SIZE: u32 = 31;
+
+/// Days from `from` to `to` (both included), split into pages of
+/// `page_size` days so long ranges are sent in several small replies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayPages {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub page_size: u32,
+}
+
+impl DayPages {
+    pub fn new(from: NaiveDate, to: NaiveDate, page_size: u32) -> Result<Self, String> {
+        if from > to {
+            return Err(format!(
+                "History range starts after it ends ({} > {})",
+                from, to
+            ));
+        }
+        if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+            return Err(format!(
+                "Invalid page_size {} (expected 1 to {})",
+                page_size, MAX_PAGE_SIZE
+            ));
+        }
+        Ok(Self {
+            from,
+            to,
+            page_size,
+        })
+    }
+
+    pub fn pages(&self) -> u32 {
+        let days = (self.to - self.from).num_days() as u64 + 1;
+        days.div_ceil(u64::from(self.page_size)) as u32
+    }
+
+    /// Days on `page` (from 0) in chronological order, empty past the last page
+    pub fn days(&self, page: u32) -> Vec<NaiveDate> {
+        let first = self.from + Duration::days(i64::from(page) * i64::from(self.page_size));
+        first
+            .iter_days()
+            .take(self.page_size as usize)
+            .take_while(|day| *day <= self.to)
+            .collect()
+    }
+}
@@ -0,0 +1,40 @@
+use crate::pomodoro::pomodoro::PomodoroMode;
+use crate::stats::aggregator::TimeRange;
+use crate::stats::focus::FocusMonitor;
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// One finished phase of the day, work or break
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Lap {
+    pub mode: PomodoroMode,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub label: Option<String>,      // planned block the work session ran in
+    pub focus_percent: Option<u32>, // share not spent on distractions, work sessions only
+    pub abandoned: bool,
+}
+
+/// The phases that ended within `range`, oldest first. Focus is measured
+/// the way the session recap does, with the `[focus_alerts]` distractions.
+pub fn laps(store: &ActivityStore, range: &TimeRange, focus: &FocusMonitor) -> Vec<Lap> {
+    let mut laps: Vec<Lap> = store
+        .pomodoros()
+        .iter()
+        .filter(|record| range.contains(record.end))
+        .map(|record| Lap {
+            mode: record.mode,
+            start: record.start,
+            end: record.end,
+            label: record.label.clone(),
+            focus_percent: (record.mode == PomodoroMode::Work).then(|| {
+                let phase = TimeRange::new(record.start, record.end);
+                ((1.0 - focus.distraction_ratio_in(store, &phase)) * 100.0).round() as u32
+            }),
+            abandoned: record.abandoned,
+        })
+        .collect();
+    laps.sort_by_key(|lap| lap.start);
+    laps
+}
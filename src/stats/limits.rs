@@ -0,0 +1,126 @@
+use crate::config::settings::Config;
+use crate::stats::aggregator::Aggregator;
+use crate::stats::calendar::Calendar;
+use crate::stats::filter::{Filter, is_domain_or_subdomain};
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+
+/// Share of a daily limit after which a warning comes, in percent
+pub const LIMIT_WARNING_PERCENT: i64 = 80;
+
+/// How close a domain is to its daily limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LimitLevel {
+    Warning, // `LIMIT_WARNING_PERCENT` used
+    Reached,
+}
+
+impl LimitLevel {
+    fn of(used: i64, limit: i64) -> Option<Self> {
+        if used >= limit {
+            Some(LimitLevel::Reached)
+        } else if used * 100 >= limit * LIMIT_WARNING_PERCENT {
+            Some(LimitLevel::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// A domain that just got close to or used up its daily limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitAlert {
+    pub domain: String, // the domain in `limits`, subdomains included
+    pub used: i64,
+    pub limit: i64,
+    pub level: LimitLevel,
+}
+
+/// Daily time per domain from `limits`. Time comes from the stored history,
+/// so a restarted daemon picks up where the previous one left off.
+#[derive(Debug)]
+pub struct DomainLimits {
+    limits: Vec<(String, i64, Filter)>, // domain, seconds per day, filter
+    calendar: Calendar,
+    alerted: HashMap<String, LimitLevel>, // level reached today, per domain
+}
+
+impl DomainLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            limits: config
+                .limits
+                .iter()
+                .map(|(domain, limit)| {
+                    (domain.clone(), *limit, Filter::domain_or_subdomain(domain))
+                })
+                .collect(),
+            calendar: Calendar::new(&config.day),
+            alerted: HashMap::new(),
+        }
+    }
+
+    /// Take the levels today's history already reached as alerted, so a
+    /// restart doesn't repeat them
+    pub fn with_history(mut self, store: &ActivityStore, now: DateTime<Local>) -> Self {
+        for (domain, used, limit) in self.usage(store, now) {
+            if let Some(level) = LimitLevel::of(used, limit) {
+                self.alerted.insert(domain, level);
+            }
+        }
+        self
+    }
+
+    /// Time used today and the limit of every limited domain
+    pub fn usage(&self, store: &ActivityStore, now: DateTime<Local>) -> Vec<(String, i64, i64)> {
+        self.usage_of(store, now, |_| true)
+    }
+
+    fn usage_of(
+        &self,
+        store: &ActivityStore,
+        now: DateTime<Local>,
+        wanted: impl Fn(&str) -> bool,
+    ) -> Vec<(String, i64, i64)> {
+        let today = self.calendar.day_range(self.calendar.day_of(now));
+        self.limits
+            .iter()
+            .filter(|(domain, _, _)| wanted(domain))
+            .map(|(domain, limit, filter)| {
+                let used = Aggregator::new(store)
+                    .with_filter(filter)
+                    .total_seconds(&today);
+                (domain.clone(), used, *limit)
+            })
+            .collect()
+    }
+
+    /// The limit covering `current` that reached a new level, once a day
+    pub fn check(
+        &mut self,
+        store: &ActivityStore,
+        now: DateTime<Local>,
+        current: &str,
+    ) -> Option<LimitAlert> {
+        let alert = self
+            .usage_of(store, now, |domain| is_domain_or_subdomain(current, domain))
+            .into_iter()
+            .find_map(|(domain, used, limit)| {
+                let level = LimitLevel::of(used, limit)?;
+                (self.alerted.get(&domain) < Some(&level)).then_some(LimitAlert {
+                    domain,
+                    used,
+                    limit,
+                    level,
+                })
+            })?;
+        self.alerted.insert(alert.domain.clone(), alert.level);
+        Some(alert)
+    }
+
+    /// A new day starts without alerts
+    pub fn reset(&mut self) {
+        self.alerted.clear();
+    }
+}
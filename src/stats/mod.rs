@@ -0,0 +1,18 @@
+pub mod aggregator;
+pub mod budget;
+pub mod calendar;
+pub mod categories;
+pub mod export;
+pub mod filter;
+pub mod focus;
+pub mod format;
+pub mod groups;
+pub mod history;
+pub mod laps;
+pub mod limits;
+pub mod pace;
+#[cfg(feature = "share")]
+pub mod share;
+pub mod sites;
+pub mod triage;
+pub mod visits;
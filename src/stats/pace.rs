@@ -0,0 +1,194 @@
+use crate::config::settings::{Config, OffDaysConfig};
+use crate::stats::aggregator::{Aggregator, TimeRange};
+use crate::stats::calendar::Calendar;
+use crate::stats::filter::Filter;
+use crate::stats::focus::FocusMonitor;
+use crate::storage::activity_store::ActivityStore;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+/// Days before today the typical day is drawn from
+pub const BASELINE_DAYS: i64 = 14;
+/// Days with focus time needed before a pace is given
+pub const MIN_BASELINE_DAYS: usize = 3;
+
+const HOUR: i64 = 3600;
+
+/// Compares today's focus time with a typical day at the same hour: the
+/// median of the recent days that had any, off-days left out
+#[derive(Debug)]
+pub struct PaceTracker {
+    focused: Filter,
+    calendar: Calendar,
+    off_days: OffDaysConfig,
+    typical: Option<Vec<i64>>, // focus time reached by each hour of the day, 0h first
+}
+
+impl PaceTracker {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            focused: FocusMonitor::new(&config.focus_alerts).focused(),
+            calendar: Calendar::new(&config.day),
+            off_days: config.off_days.clone(),
+            typical: None,
+        }
+    }
+
+    /// History `learn` needs for the typical day before `today`
+    pub fn history_range(&self, today: NaiveDate) -> TimeRange {
+        TimeRange::new(
+            self.calendar
+                .day_begin(today - Duration::days(BASELINE_DAYS)),
+            self.calendar.day_begin(today),
+        )
+    }
+
+    /// Work out the typical day from the `history_range` before `today`
+    pub fn learn(&mut self, history: &ActivityStore, today: NaiveDate) {
+        let days: Vec<Vec<i64>> = (1..=BASELINE_DAYS)
+            .map(|ago| today - Duration::days(ago))
+            .filter(|day| !self.off_days.is_off_day(*day))
+            .map(|day| self.curve(history, day))
+            .filter(|curve| curve.last().is_some_and(|total| *total > 0))
+            .collect();
+        if days.len() < MIN_BASELINE_DAYS {
+            self.typical = None;
+            return;
+        }
+        let hours = days.iter().map(Vec::len).min().unwrap_or(0);
+        self.typical = Some(
+            (0..hours)
+                .map(|hour| median(days.iter().map(|curve| curve[hour]).collect()))
+                .collect(),
+        );
+    }
+
+    /// Focus time by each hour of `day`, up to its end
+    fn curve(&self, store: &ActivityStore, day: NaiveDate) -> Vec<i64> {
+        let range = self.calendar.day_range(day);
+        let aggregator = Aggregator::new(store).with_filter(&self.focused);
+        let hours = (range.end - range.start).num_seconds() / HOUR;
+        (0..=hours)
+            .map(|hour| {
+                let end = (range.start + Duration::hours(hour)).min(range.end);
+                aggregator.total_seconds(&TimeRange::new(range.start, end))
+            })
+            .collect()
+    }
+
+    /// Focus time ahead of a typical day by `now` (negative when behind),
+    /// `None` on off-days or without enough history
+    pub fn pace(&self, store: &ActivityStore, now: DateTime<Local>) -> Option<i64> {
+        let typical = self.typical.as_ref()?;
+        let today = self.calendar.day_of(now);
+        if self.off_days.is_off_day(today) {
+            return None;
+        }
+        let begin = self.calendar.day_begin(today);
+        let focused = Aggregator::new(store)
+            .with_filter(&self.focused)
+            .total_seconds(&TimeRange::new(begin, now));
+        Some(focused - expected(typical, (now - begin).num_seconds()))
+    }
+}
+
+/// Focus time of the typical day `elapsed` seconds into it, in a straight
+/// line between the hours
+fn expected(typical: &[i64], elapsed: i64) -> i64 {
+    let hour = (elapsed / HOUR) as usize;
+    match (typical.get(hour), typical.get(hour + 1)) {
+        (Some(from), Some(to)) => from + (to - from) * (elapsed % HOUR) / HOUR,
+        _ => typical.last().copied().unwrap_or(0),
+    }
+}
+
+fn median(mut values: Vec<i64>) -> i64 {
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2
+    } else {
+        values[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::clock::ManualClock;
+    use crate::daemon::log::Log;
+    use crate::daemon::tracker::DomainTracker;
+    use crate::storage::activity_store::ActivitySegment;
+    use crate::storage::backend::Storage;
+    use crate::storage::jsonl_backend::JsonlStorage;
+    use chrono::TimeZone;
+    use std::sync::Arc;
+
+    #[test]
+    fn pace_compares_today_with_a_typical_day() {
+        let mut config = Config::default();
+        config.storage.journal = false;
+        config.focus_alerts.distracting_domains = vec!["youtube.com".to_string()];
+        let dir = std::env::temp_dir().join(format!("stop_it-test-pace-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage_path = dir.join("activity.jsonl");
+        let at = |day, hour, minute| {
+            Local
+                .with_ymd_and_hms(2025, 3, day, hour, minute, 0)
+                .unwrap()
+        };
+        let segment = |domain: &str, start, seconds| ActivitySegment {
+            domain: domain.to_string(),
+            category: None,
+            category_source: None,
+            profile: None,
+            on_break: false,
+            start,
+            seconds,
+        };
+
+        // An hour of focus from 9 on recent days; distractions and breaks don't count
+        let mut storage = JsonlStorage::open(&storage_path).unwrap();
+        for day in 7..=11 {
+            storage
+                .save_segment(&segment("github.com", at(day, 9, 0), 3600))
+                .unwrap();
+            storage
+                .save_segment(&segment("youtube.com", at(day, 10, 0), 1800))
+                .unwrap();
+            storage
+                .save_segment(&ActivitySegment {
+                    on_break: true,
+                    ..segment("docs.rs", at(day, 10, 30), 600)
+                })
+                .unwrap();
+        }
+        // Today: half an hour of focus, then distractions
+        storage
+            .save_segment(&segment("github.com", at(12, 9, 0), 1800))
+            .unwrap();
+        storage
+            .save_segment(&segment("youtube.com", at(12, 9, 30), 1800))
+            .unwrap();
+        drop(storage);
+
+        let pace_at = |config: &Config, now| {
+            let clock = Arc::new(ManualClock::new(now));
+            let storage = Box::new(JsonlStorage::open(&storage_path).unwrap());
+            DomainTracker::new(config, storage, clock, Log::capturing())
+                .unwrap()
+                .timer_state()
+                .pace_seconds
+        };
+        assert_eq!(pace_at(&config, at(12, 8, 0)), Some(0));
+        // Halfway through the typical hour, with as much done
+        assert_eq!(pace_at(&config, at(12, 9, 30)), Some(0));
+        assert_eq!(pace_at(&config, at(12, 10, 0)), Some(-1800));
+        assert_eq!(pace_at(&config, at(12, 18, 0)), Some(-1800));
+        // Not enough history yet: only the 7th and 8th came before
+        assert_eq!(pace_at(&config, at(9, 12, 0)), None);
+
+        // Off-days have no pace
+        config.off_days.weekdays = vec![chrono::Weekday::Wed];
+        assert_eq!(pace_at(&config, at(12, 10, 0)), None);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::stats::aggregator::{Aggregator, TimeRange};
+use crate::stats::filter::Filter;
+use crate::stats::format::format_duration;
+use crate::storage::activity_store::ActivityStore;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::FontStyle;
+use std::path::Path;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 360;
+/// Bars on the card; the rest of the time is summed up as "other"
+const TOP_CATEGORIES: usize = 4;
+
+const BACKGROUND: RGBColor = RGBColor(0xfd, 0xf6, 0xec);
+const TOMATO: RGBColor = RGBColor(0xe2, 0x4a, 0x33);
+const INK: RGBColor = RGBColor(0x2b, 0x2b, 0x2b);
+const MUTED: RGBColor = RGBColor(0x8a, 0x80, 0x78);
+const TRACK: RGBColor = RGBColor(0xee, 0xe4, 0xd8);
+
+/// What `stop_it share` puts on a card: totals and categories, domains only
+/// when asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareCard {
+    pub title: String,                  // e.g. "Today · Wed 12 Mar"
+    pub pomodoros: usize,               // completed work sessions
+    pub focus_seconds: i64,             // spent in them
+    pub categories: Vec<(String, i64)>, // work time, most first
+    pub domains: Vec<(String, i64)>,    // empty unless opted in
+}
+
+impl ShareCard {
+    /// Sum up `range`; breaks don't count towards the categories
+    pub fn new(store: &ActivityStore, range: &TimeRange, title: String, domains: usize) -> Self {
+        let work = Filter::Not(Box::new(Filter::break_time()));
+        let aggregator = Aggregator::new(store).with_filter(&work);
+        let pomodoros = aggregator.pomodoros(range);
+        let mut categories = aggregator.total_by_category(range);
+        if categories.len() > TOP_CATEGORIES {
+            let other = categories.split_off(TOP_CATEGORIES - 1);
+            categories.push(("other".to_string(), other.iter().map(|(_, s)| s).sum()));
+        }
+        Self {
+            title,
+            pomodoros: pomodoros.len(),
+            focus_seconds: pomodoros
+                .iter()
+                .map(|record| (record.end - record.start).num_seconds())
+                .sum(),
+            categories,
+            domains: aggregator.top_domains(range, domains),
+        }
+    }
+
+    pub fn to_svg(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut svg = String::new();
+        {
+            let root = SVGBackend::with_string(&mut svg, (WIDTH, HEIGHT)).into_drawing_area();
+            self.draw(&root).map_err(|e| e.to_string())?;
+            root.present().map_err(|e| e.to_string())?;
+        }
+        Ok(svg)
+    }
+
+    /// Bitmaps need a font to draw text with, e.g. DejaVu Sans
+    pub fn write_png(&self, path: &Path, font: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        // Fonts are registered for the life of the process
+        let bytes: &'static [u8] = Vec::leak(std::fs::read(font)?);
+        plotters::style::register_font("sans-serif", FontStyle::Normal, bytes)
+            .map_err(|_| format!("{} is not a usable font", font.display()))?;
+        let root = BitMapBackend::new(path, (WIDTH, HEIGHT)).into_drawing_area();
+        self.draw(&root).map_err(|e| e.to_string())?;
+        root.present().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        let text = |size: u32, color: &RGBColor| ("sans-serif", size).into_font().color(color);
+        root.fill(&BACKGROUND)?;
+        root.draw(&Rectangle::new(
+            [(0, 0), (WIDTH as i32, 8)],
+            TOMATO.filled(),
+        ))?;
+        root.draw(&Text::new(self.title.clone(), (32, 32), text(20, &MUTED)))?;
+
+        // The headline numbers
+        let pomodoros = match self.pomodoros {
+            1 => "1 pomodoro".to_string(),
+            n => format!("{} pomodoros", n),
+        };
+        root.draw(&Text::new(pomodoros, (32, 66), text(40, &TOMATO)))?;
+        let focus = format!("{} focused", format_duration(self.focus_seconds));
+        root.draw(&Text::new(focus, (32, 116), text(24, &INK)))?;
+
+        // One bar per category, as a share of the work time
+        let total: i64 = self.categories.iter().map(|(_, seconds)| seconds).sum();
+        let (bar_x, bar_width) = (200, WIDTH as i32 - 200 - 120);
+        let mut y = 170;
+        for (category, seconds) in &self.categories {
+            let filled = (bar_width as i64 * seconds / total.max(1)) as i32;
+            root.draw(&Text::new(category.clone(), (32, y), text(18, &INK)))?;
+            root.draw(&Rectangle::new(
+                [(bar_x, y + 2), (bar_x + bar_width, y + 18)],
+                TRACK.filled(),
+            ))?;
+            root.draw(&Rectangle::new(
+                [(bar_x, y + 2), (bar_x + filled.max(2), y + 18)],
+                TOMATO.filled(),
+            ))?;
+            let share = format!("{}%", seconds * 100 / total.max(1));
+            root.draw(&Text::new(
+                share,
+                (bar_x + bar_width + 16, y),
+                text(18, &MUTED),
+            ))?;
+            y += 30;
+        }
+        if self.categories.is_empty() {
+            root.draw(&Text::new("Nothing tracked yet", (32, y), text(18, &MUTED)))?;
+        }
+
+        if !self.domains.is_empty() {
+            let domains: Vec<&str> = self
+                .domains
+                .iter()
+                .map(|(domain, _)| domain.as_str())
+                .collect();
+            root.draw(&Text::new(
+                format!("Top sites: {}", domains.join(", ")),
+                (32, HEIGHT as i32 - 64),
+                text(16, &MUTED),
+            ))?;
+        }
+        root.draw(&Text::new(
+            "stop_it",
+            (WIDTH as i32 - 96, HEIGHT as i32 - 36),
+            text(16, &MUTED),
+        ))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::pomodoro::PomodoroMode;
+    use crate::storage::activity_store::ActivitySegment;
+    use crate::storage::activity_store::PomodoroRecord;
+    use chrono::Duration;
+    use chrono::Local;
+    use chrono::TimeZone;
+
+    #[test]
+    fn share_cards_leave_out_domains_unless_asked() {
+        let at = |hour, minute| {
+            Local
+                .with_ymd_and_hms(2025, 3, 12, hour, minute, 0)
+                .unwrap()
+        };
+        let segment = |domain: &str, category: &str, start, seconds, on_break| ActivitySegment {
+            domain: domain.to_string(),
+            category: Some(category.to_string()),
+            category_source: None,
+            profile: None,
+            on_break,
+            start,
+            seconds,
+        };
+        let mut store = ActivityStore::new();
+        let sites = [
+            ("github.com", "dev", 40),
+            ("docs.rs", "docs", 20),
+            ("mail.example.com", "mail", 10),
+            ("calendar.example.com", "planning", 5),
+            ("music.example.com", "music", 5),
+        ];
+        let mut start = at(9, 0);
+        for (domain, category, minutes) in sites {
+            store.insert_segment(segment(domain, category, start, minutes * 60, false));
+            start += Duration::minutes(minutes);
+        }
+        store.insert_segment(segment("reddit.com", "social", start, 300, true));
+        for (from, to) in [(at(9, 0), at(9, 25)), (at(9, 30), at(9, 55))] {
+            store.record_pomodoro(PomodoroRecord {
+                mode: PomodoroMode::Work,
+                start: from,
+                end: to,
+                abandoned: false,
+                label: None,
+            });
+        }
+
+        let range = TimeRange::new(at(0, 0), at(23, 0));
+        let card = ShareCard::new(&store, &range, "Today".to_string(), 0);
+        assert_eq!(card.pomodoros, 2);
+        assert_eq!(card.focus_seconds, 50 * 60);
+        // Breaks don't count, the smallest categories are summed up
+        assert_eq!(
+            card.categories,
+            [
+                ("dev".to_string(), 2400),
+                ("docs".to_string(), 1200),
+                ("mail".to_string(), 600),
+                ("other".to_string(), 600),
+            ]
+        );
+        let svg = card.to_svg().unwrap();
+        assert!(svg.contains("2 pomodoros") && svg.contains("50m 00s focused"));
+        assert!(!svg.contains("github.com"), "{}", svg);
+
+        let card = ShareCard::new(&store, &range, "Today".to_string(), 2);
+        let svg = card.to_svg().unwrap();
+        assert!(svg.contains("Top sites: github.com, docs.rs"), "{}", svg);
+    }
+}
@@ -0,0 +1,21 @@
+//! Built-in site database compiled from `data/sites.txt`: a category for
+//! popular sites and aliases such as youtu.be -> youtube.com, so reports are
+//! meaningful before any `[categories]` rule is written.
+
+include!(concat!(env!("OUT_DIR"), "/sites.rs"));
+
+/// Built-in category of `domain`, or of the closest parent domain listed
+pub fn builtin_category(domain: &str) -> Option<&'static str> {
+    let mut candidate = domain;
+    loop {
+        if let Some(category) = CATEGORIES.get(candidate) {
+            return Some(category);
+        }
+        candidate = candidate.split_once('.')?.1;
+    }
+}
+
+/// The site `host` is an alias of, e.g. "youtu.be" -> "youtube.com"
+pub fn canonical_site(host: &str) -> Option<&'static str> {
+    ALIASES.get(host).copied()
+}
@@ -0,0 +1,24 @@
+use crate::stats::aggregator::{Aggregator, TimeRange, UNCATEGORIZED};
+use crate::stats::categories::CategoryRules;
+use crate::stats::filter::{Filter, Match};
+use crate::storage::activity_store::ActivityStore;
+
+/// Domains that took at least `min_seconds` within `range` without a
+/// category: no hint from the extension, no built-in entry and no
+/// `[categories]` rule, including rules written since. Most time first.
+pub fn uncategorized(
+    store: &ActivityStore,
+    range: &TimeRange,
+    rules: &CategoryRules,
+    min_seconds: i64,
+) -> Vec<(String, i64)> {
+    let filter = Filter::Category(Match::Exact(UNCATEGORIZED.to_string()));
+    Aggregator::new(store)
+        .with_filter(&filter)
+        .top_domains(range, usize::MAX)
+        .into_iter()
+        .filter(|(domain, seconds)| {
+            *seconds >= min_seconds && rules.categorize(domain, None).is_none()
+        })
+        .collect()
+}
@@ -0,0 +1,152 @@
+use crate::config::settings::{FocusAlertsConfig, StrictConfig};
+use crate::stats::filter::{glob_match, is_domain_or_subdomain};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// How a switch to a category is answered during work sessions, from the
+/// mildest to the strictest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnforcementAction {
+    Log,   // a line in the log, nothing else
+    Nag,   // a reminder at normal urgency, at most every few minutes
+    Block, // escalating warnings, critical from the third visit
+}
+
+/// What `Nag` and `Log` name instead of a category for sites `[strict]`
+/// doesn't list
+pub const OFF_ALLOWLIST: &str = "off the allowlist";
+
+/// What a switch to a site during a work session triggers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Enforcement {
+    Block { target: String, visits: u32 }, // visits this phase
+    Nag { category: String },
+    Log { category: String },
+    Allowed { pattern: String }, // an `allowed_urls` exception, checked first
+}
+
+/// Counts visits to blocked domains and categories during the current work
+/// phase, so repeated relapses can be answered with increasingly urgent
+/// notifications, and looks up what other categories call for
+#[derive(Debug)]
+pub struct VisitTracker {
+    blocked: Vec<String>,                         // also matches subdomains
+    allowed: Vec<String>,                         // URL patterns, case-insensitive
+    actions: BTreeMap<String, EnforcementAction>, // category -> action
+    strict: StrictConfig,
+    visits: HashMap<String, u32>,
+    excepted: bool, // the last visit matched an exception
+}
+
+impl VisitTracker {
+    pub fn new(
+        config: &FocusAlertsConfig,
+        actions: &BTreeMap<String, EnforcementAction>,
+        strict: &StrictConfig,
+    ) -> Self {
+        Self {
+            blocked: config.distracting_domains.clone(),
+            allowed: config.allowed_urls.clone(),
+            actions: actions.clone(),
+            strict: strict.clone(),
+            visits: HashMap::new(),
+            excepted: false,
+        }
+    }
+
+    /// Record a switch to `domain` and return the blocked entry it falls under
+    /// with the number of visits this phase. `over_budget` lists targets whose
+    /// budget is used up, which count as blocked too.
+    fn record(&mut self, domain: &str, over_budget: &[String]) -> Option<(String, u32)> {
+        let target = self
+            .blocked
+            .iter()
+            .chain(over_budget)
+            .find(|target| is_domain_or_subdomain(domain, target))?
+            .clone();
+        Some((target.clone(), self.count(target)))
+    }
+
+    /// Like `record`, falling back to the action for `category` (e.g.
+    /// "news, tech", the strictest action wins) and, in strict mode, the
+    /// one for sites off the allowlist. Blocked categories count visits
+    /// across their sites. A `url` matching an exception is let through
+    /// before anything else.
+    pub fn enforce(
+        &mut self,
+        domain: &str,
+        url: &str,
+        category: Option<&str>,
+        over_budget: &[String],
+    ) -> Option<Enforcement> {
+        self.excepted = false;
+        if let Some(pattern) = self.exception(url) {
+            self.excepted = true;
+            return Some(Enforcement::Allowed { pattern });
+        }
+        if let Some((target, visits)) = self.record(domain, over_budget) {
+            return Some(Enforcement::Block { target, visits });
+        }
+        let off_allowlist = !self.strict.domains.is_empty()
+            && !self
+                .strict
+                .domains
+                .iter()
+                .any(|allowed| is_domain_or_subdomain(domain, allowed));
+        // On a tie the site's own category is named, being the later one
+        let (category, action) = off_allowlist
+            .then_some((OFF_ALLOWLIST, self.strict.action))
+            .into_iter()
+            .chain(
+                category
+                    .into_iter()
+                    .flat_map(|categories| categories.split(','))
+                    .map(str::trim)
+                    .filter_map(|category| Some((category, *self.actions.get(category)?))),
+            )
+            .max_by_key(|(_, action)| *action)?;
+        let category = category.to_string();
+        Some(match action {
+            // Each site off the allowlist counts its own visits
+            EnforcementAction::Block if category == OFF_ALLOWLIST => Enforcement::Block {
+                visits: self.count(domain.to_string()),
+                target: domain.to_string(),
+            },
+            EnforcementAction::Block => Enforcement::Block {
+                visits: self.count(category.clone()),
+                target: category,
+            },
+            EnforcementAction::Nag => Enforcement::Nag { category },
+            EnforcementAction::Log => Enforcement::Log { category },
+        })
+    }
+
+    /// The pattern `url` matches, compared without its scheme and `www.`
+    fn exception(&self, url: &str) -> Option<String> {
+        let url = url.to_lowercase();
+        let url = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+        let url = url.strip_prefix("www.").unwrap_or(url);
+        self.allowed
+            .iter()
+            .find(|pattern| glob_match(&pattern.to_lowercase(), url))
+            .cloned()
+    }
+
+    /// Whether the last visit was let through by an exception, so moving to
+    /// another page of the same site has to be checked again
+    pub fn is_excepted(&self) -> bool {
+        self.excepted
+    }
+
+    fn count(&mut self, target: String) -> u32 {
+        let count = self.visits.entry(target).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    /// Start counting from zero for a new work phase
+    pub fn reset(&mut self) {
+        self.visits.clear();
+    }
+}
@@ -0,0 +1,146 @@
+use crate::pomodoro::pomodoro::PomodoroMode;
+use chrono::{DateTime, Duration, Local};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A contiguous stretch of time spent on a single domain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivitySegment {
+    pub domain: String,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub category_source: Option<CategorySource>, // None for history from before sources were recorded
+    #[serde(default)]
+    pub profile: Option<String>, // browser profile that reported it, when extensions use tokens
+    #[serde(default)]
+    pub on_break: bool, // tracked during a Pomodoro break rather than a work session
+    pub start: DateTime<Local>,
+    pub seconds: i64,
+}
+
+impl ActivitySegment {
+    pub fn end(&self) -> DateTime<Local> {
+        self.start + Duration::seconds(self.seconds)
+    }
+}
+
+/// Who decided the category of an activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CategorySource {
+    Client,  // hint sent by the browser extension
+    Server,  // [categories] rule in the config
+    Builtin, // built-in site database
+}
+
+impl CategorySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CategorySource::Client => "client",
+            CategorySource::Server => "server",
+            CategorySource::Builtin => "builtin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "client" => Some(CategorySource::Client),
+            "server" => Some(CategorySource::Server),
+            "builtin" => Some(CategorySource::Builtin),
+            _ => None,
+        }
+    }
+}
+
+/// A finished Pomodoro phase (work or break)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PomodoroRecord {
+    pub mode: PomodoroMode,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    #[serde(default)]
+    pub abandoned: bool, // a work session spent mostly idle, not counted as completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>, // planned block the work session ran in
+}
+
+/// In-memory storage layer for tracked activity and Pomodoro history
+#[derive(Debug, Default)]
+pub struct ActivityStore {
+    segments: Vec<ActivitySegment>,
+    pomodoros: Vec<PomodoroRecord>,
+}
+
+impl ActivityStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one second spent on `domain` at `at`, extending the last segment
+    /// when it is contiguous and for the same domain/category/profile/phase
+    pub fn record_second(
+        &mut self,
+        domain: &str,
+        category: Option<(&str, CategorySource)>,
+        profile: Option<&str>,
+        on_break: bool,
+        at: DateTime<Local>,
+    ) {
+        if let Some(last) = self.segments.last_mut()
+            && last.domain == domain
+            && last.category.as_deref() == category.map(|(name, _)| name)
+            && last.category_source == category.map(|(_, source)| source)
+            && last.profile.as_deref() == profile
+            && last.on_break == on_break
+            && (at - last.end()).num_seconds().abs() <= 1
+        {
+            last.seconds += 1;
+            return;
+        }
+
+        self.segments.push(ActivitySegment {
+            domain: domain.to_string(),
+            category: category.map(|(name, _)| name.to_string()),
+            category_source: category.map(|(_, source)| source),
+            profile: profile.map(str::to_string),
+            on_break,
+            start: at,
+            seconds: 1,
+        });
+    }
+
+    pub fn record_pomodoro(&mut self, record: PomodoroRecord) {
+        self.pomodoros.push(record);
+    }
+
+    /// Take back the latest Pomodoro record, e.g. a work session that goes
+    /// on after all. Saving it again replaces the stored copy.
+    pub fn pop_pomodoro(&mut self) -> Option<PomodoroRecord> {
+        self.pomodoros.pop()
+    }
+
+    /// Add a segment loaded from persistent storage
+    pub fn insert_segment(&mut self, segment: ActivitySegment) {
+        self.segments.push(segment);
+    }
+
+    /// Drop segments and Pomodoro records that ended before `cutoff`
+    pub fn prune_before(&mut self, cutoff: DateTime<Local>) {
+        self.segments.retain(|segment| segment.end() >= cutoff);
+        self.pomodoros.retain(|record| record.end >= cutoff);
+    }
+
+    pub fn segments(&self) -> &[ActivitySegment] {
+        &self.segments
+    }
+
+    /// Segments to correct in place, e.g. when a category rule changes.
+    /// Changed segments need saving again.
+    pub fn segments_mut(&mut self) -> &mut [ActivitySegment] {
+        &mut self.segments
+    }
+
+    pub fn pomodoros(&self) -> &[PomodoroRecord] {
+        &self.pomodoros
+    }
+}
@@ -0,0 +1,381 @@
+use crate::daemon::log;
+use crate::stats::aggregator::TimeRange;
+use crate::storage::activity_store::{ActivitySegment, ActivityStore, PomodoroRecord};
+use crate::storage::backend::{Storage, StorageResult};
+use crate::storage::jsonl_backend::Record;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Truncate the journal once this many records have been folded
+const COMPACT_AFTER_RECORDS: usize = 1000;
+/// Wait before retrying records the inner storage refused, doubled per
+/// failure up to `MAX_BACKOFF`
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+enum Job {
+    Fold(Record),
+    Sync(Sender<()>),
+}
+
+/// Write-ahead journal in front of another backend. Every save is appended to
+/// a JSONL file immediately and folded into the inner storage on a background
+/// thread; unfolded records are replayed on the next start after a crash.
+#[derive(Debug)]
+pub struct JournaledStorage {
+    file: Arc<Mutex<File>>,
+    inner: Arc<Mutex<Box<dyn Storage>>>,
+    pending: Arc<AtomicUsize>,
+    jobs: Sender<Job>,
+}
+
+impl JournaledStorage {
+    pub fn open(path: &Path, mut inner: Box<dyn Storage>) -> StorageResult<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let replayed = replay(path, inner.as_mut())?;
+        if replayed > 0 {
+            log::print(format!(
+                "Recovered {} journal records from {}",
+                replayed,
+                path.display()
+            ));
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.set_len(0)?;
+
+        let file = Arc::new(Mutex::new(file));
+        let inner = Arc::new(Mutex::new(inner));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let (jobs, rx) = mpsc::channel();
+
+        let folder = Folder {
+            file: Arc::clone(&file),
+            inner: Arc::clone(&inner),
+            pending: Arc::clone(&pending),
+            folded_since_compaction: 0,
+            unfolded: Vec::new(),
+            retry_at: None,
+            backoff: MIN_BACKOFF,
+        };
+        thread::spawn(move || folder.run(rx));
+
+        Ok(Self {
+            file,
+            inner,
+            pending,
+            jobs,
+        })
+    }
+
+    fn append(&mut self, record: Record) -> StorageResult<()> {
+        let line = serde_json::to_string(&record)?;
+        {
+            // Count the record as pending before compaction can see the line
+            let mut file = self.file.lock().map_err(|_| "journal lock poisoned")?;
+            self.pending.fetch_add(1, Ordering::SeqCst);
+            writeln!(file, "{}", line)?;
+        }
+        self.jobs
+            .send(Job::Fold(record))
+            .map_err(|_| "journal folder stopped")?;
+        Ok(())
+    }
+
+    /// Block until every journaled record has been folded into the inner
+    /// storage, or it refused them
+    fn sync(&self) -> StorageResult<()> {
+        let (done_tx, done_rx) = mpsc::channel();
+        self.jobs
+            .send(Job::Sync(done_tx))
+            .map_err(|_| "journal folder stopped")?;
+        done_rx.recv()?;
+        Ok(())
+    }
+}
+
+impl Storage for JournaledStorage {
+    fn save_segment(&mut self, segment: &ActivitySegment) -> StorageResult<()> {
+        self.append(Record::Segment(segment.clone()))
+    }
+
+    fn save_pomodoro(&mut self, record: &PomodoroRecord) -> StorageResult<()> {
+        self.append(Record::Pomodoro(record.clone()))
+    }
+
+    fn load(&mut self, range: &TimeRange) -> StorageResult<ActivityStore> {
+        self.sync()?;
+        let mut inner = self.inner.lock().map_err(|_| "storage lock poisoned")?;
+        inner.load(range)
+    }
+}
+
+/// Background worker folding journal records into the inner storage
+struct Folder {
+    file: Arc<Mutex<File>>,
+    inner: Arc<Mutex<Box<dyn Storage>>>,
+    pending: Arc<AtomicUsize>,
+    folded_since_compaction: usize,
+    unfolded: Vec<Record>,     // journaled, not yet in the inner storage
+    retry_at: Option<Instant>, // after the inner storage refused records
+    backoff: Duration,         // wait before the next retry
+}
+
+impl Folder {
+    fn run(mut self, rx: Receiver<Job>) {
+        loop {
+            let job = match self.retry_at {
+                None => match rx.recv() {
+                    Ok(job) => Some(job),
+                    Err(_) => break,
+                },
+                Some(at) => match rx.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                    Ok(job) => Some(job),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    // Still in the journal, replayed on the next start
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+            };
+            // Drain everything queued so repeated saves of the growing open
+            // segment collapse into a single write
+            let mut waiters = Vec::new();
+            for job in job.into_iter().chain(rx.try_iter()) {
+                match job {
+                    Job::Fold(record) => self.unfolded.push(record),
+                    Job::Sync(done) => waiters.push(done),
+                }
+            }
+            // After a failure, new records wait with the refused ones
+            if self.retry_at.is_none_or(|at| Instant::now() >= at) {
+                self.fold_unfolded();
+            }
+
+            for done in waiters {
+                let _ = done.send(());
+            }
+        }
+    }
+
+    fn fold_unfolded(&mut self) {
+        let records = std::mem::take(&mut self.unfolded);
+        let count = records.len();
+        let folded = match self.fold(records) {
+            Ok(()) => {
+                if self.retry_at.take().is_some() {
+                    log::print("Journal folded into storage again");
+                }
+                self.backoff = MIN_BACKOFF;
+                count
+            }
+            Err((unfolded, e)) => {
+                if self.retry_at.is_none() {
+                    eprintln!(
+                        "Failed to fold journal into storage: {} (keeping {} records, retrying)",
+                        e,
+                        unfolded.len()
+                    );
+                }
+                self.retry_at = Some(Instant::now() + self.backoff);
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                let folded = count - unfolded.len();
+                self.unfolded = unfolded;
+                folded
+            }
+        };
+        // Refused records stay pending, so compaction leaves them in the journal
+        self.pending.fetch_sub(folded, Ordering::SeqCst);
+        self.folded_since_compaction += folded;
+        self.compact();
+    }
+
+    /// Save `records`, giving back those not saved when the inner storage
+    /// fails. Segments are saved last: saving one again only replaces it.
+    fn fold(&self, records: Vec<Record>) -> Result<(), (Vec<Record>, Box<dyn std::error::Error>)> {
+        let mut latest_segments: HashMap<(i64, String), ActivitySegment> = HashMap::new();
+        let mut pomodoros = Vec::new();
+        for record in records {
+            match record {
+                Record::Segment(segment) => {
+                    latest_segments
+                        .insert((segment.start.timestamp(), segment.domain.clone()), segment);
+                }
+                Record::Pomodoro(record) => pomodoros.push(record),
+            }
+        }
+        let segments: Vec<ActivitySegment> = latest_segments.into_values().collect();
+        let unfolded = |pomodoros: &[PomodoroRecord], segments: &[ActivitySegment]| {
+            pomodoros
+                .iter()
+                .cloned()
+                .map(Record::Pomodoro)
+                .chain(segments.iter().cloned().map(Record::Segment))
+                .collect::<Vec<_>>()
+        };
+
+        let Ok(mut inner) = self.inner.lock() else {
+            return Err((
+                unfolded(&pomodoros, &segments),
+                "storage lock poisoned".into(),
+            ));
+        };
+        for (i, record) in pomodoros.iter().enumerate() {
+            if let Err(e) = inner.save_pomodoro(record) {
+                return Err((unfolded(&pomodoros[i..], &segments), e));
+            }
+        }
+        for (i, segment) in segments.iter().enumerate() {
+            if let Err(e) = inner.save_segment(segment) {
+                return Err((unfolded(&[], &segments[i..]), e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Truncate the journal when everything written so far has been folded
+    fn compact(&mut self) {
+        if self.folded_since_compaction < COMPACT_AFTER_RECORDS {
+            return;
+        }
+        let Ok(file) = self.file.lock() else {
+            return;
+        };
+        // New records may have been appended after this batch was drained
+        if self.pending.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        match file.set_len(0) {
+            Ok(()) => self.folded_since_compaction = 0,
+            Err(e) => eprintln!("Failed to compact journal: {}", e),
+        }
+    }
+}
+
+/// Re-apply records left over from a previous run
+fn replay(path: &Path, storage: &mut dyn Storage) -> StorageResult<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        // A crash can leave a partially written last line
+        let Ok(record) = serde_json::from_str::<Record>(&line) else {
+            continue;
+        };
+        match record {
+            Record::Segment(segment) => storage.save_segment(&segment)?,
+            Record::Pomodoro(record) => storage.save_pomodoro(&record)?,
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::pomodoro::PomodoroMode;
+    use crate::storage::jsonl_backend::JsonlStorage;
+    use chrono::Local;
+    use chrono::TimeZone;
+
+    #[test]
+    fn the_journal_keeps_what_storage_refused() {
+        use crate::storage::backend::StorageResult;
+        use crate::storage::journal::JournaledStorage;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        /// Storage that refuses writes while `failing`, e.g. a database server
+        /// that went away
+        #[derive(Debug)]
+        struct Flaky {
+            inner: JsonlStorage,
+            failing: Arc<AtomicBool>,
+        }
+        impl Storage for Flaky {
+            fn save_segment(&mut self, segment: &ActivitySegment) -> StorageResult<()> {
+                if self.failing.load(Ordering::SeqCst) {
+                    return Err("connection lost".into());
+                }
+                self.inner.save_segment(segment)
+            }
+            fn save_pomodoro(&mut self, record: &PomodoroRecord) -> StorageResult<()> {
+                if self.failing.load(Ordering::SeqCst) {
+                    return Err("connection lost".into());
+                }
+                self.inner.save_pomodoro(record)
+            }
+            fn load(&mut self, range: &TimeRange) -> StorageResult<ActivityStore> {
+                self.inner.load(range)
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("stop_it-test-journal-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let failing = Arc::new(AtomicBool::new(true));
+        let flaky = Flaky {
+            inner: JsonlStorage::open(&dir.join("activity.jsonl")).unwrap(),
+            failing: Arc::clone(&failing),
+        };
+        let mut storage =
+            JournaledStorage::open(&dir.join("journal.jsonl"), Box::new(flaky)).unwrap();
+        let start = Local.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let range = TimeRange::new(start, start + chrono::Duration::hours(24));
+        let pomodoro = PomodoroRecord {
+            mode: PomodoroMode::Work,
+            start,
+            end: start + chrono::Duration::minutes(25),
+            abandoned: false,
+            label: None,
+        };
+        storage.save_pomodoro(&pomodoro).unwrap();
+        assert!(storage.load(&range).unwrap().pomodoros().is_empty());
+
+        // Storage is back: enough records follow to compact the journal, which
+        // must not take the refused one along before it's stored
+        failing.store(false, Ordering::SeqCst);
+        for minute in 0..1200 {
+            storage
+                .save_segment(&ActivitySegment {
+                    domain: format!("site{}.example", minute),
+                    category: None,
+                    category_source: None,
+                    profile: None,
+                    on_break: false,
+                    start: start + chrono::Duration::minutes(minute),
+                    seconds: 60,
+                })
+                .unwrap();
+        }
+        let journal = std::fs::read_to_string(dir.join("journal.jsonl")).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        loop {
+            let store = storage.load(&range).unwrap();
+            if store.pomodoros().len() == 1 {
+                assert_eq!(store.segments().len(), 1200);
+                break;
+            }
+            assert!(
+                journal.contains("\"kind\":\"pomodoro\""),
+                "the refused record left the journal"
+            );
+            assert!(
+                std::time::Instant::now() < deadline,
+                "the refused record was never retried"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::stats::aggregator::TimeRange;
+use crate::storage::activity_store::{ActivitySegment, ActivityStore, PomodoroRecord};
+use crate::storage::backend::{Storage, StorageResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// One line of a JSONL file, shared with the write-ahead journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum Record {
+    Segment(ActivitySegment),
+    Pomodoro(PomodoroRecord),
+}
+
+/// Append-only flat file backend: one JSON record per line. Re-saved segments
+/// are appended again and the latest copy wins when loading.
+#[derive(Debug)]
+pub struct JsonlStorage {
+    path: PathBuf,
+    file: Option<File>, // None when opened read-only
+}
+
+impl JsonlStorage {
+    pub fn open(path: &Path) -> StorageResult<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Some(file),
+        })
+    }
+
+    pub fn open_read_only(path: &Path) -> StorageResult<Self> {
+        if !path.exists() {
+            return Err(format!("{} does not exist", path.display()).into());
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: None,
+        })
+    }
+
+    fn append(&mut self, record: &Record) -> StorageResult<()> {
+        let file = self.file.as_mut().ok_or("storage opened read-only")?;
+        let line = serde_json::to_string(record)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+impl Storage for JsonlStorage {
+    fn save_segment(&mut self, segment: &ActivitySegment) -> StorageResult<()> {
+        self.append(&Record::Segment(segment.clone()))
+    }
+
+    fn save_pomodoro(&mut self, record: &PomodoroRecord) -> StorageResult<()> {
+        self.append(&Record::Pomodoro(record.clone()))
+    }
+
+    fn load(&mut self, range: &TimeRange) -> StorageResult<ActivityStore> {
+        let mut segments = BTreeMap::new();
+        let mut pomodoros = BTreeMap::new();
+
+        for (number, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(Record::Segment(segment)) => {
+                    segments.insert((segment.start, segment.domain.clone()), segment);
+                }
+                Ok(Record::Pomodoro(record)) => {
+                    pomodoros.insert((record.start, record.mode.as_str()), record);
+                }
+                Err(e) => eprintln!(
+                    "Skipping invalid line {} in {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+
+        let mut store = ActivityStore::new();
+        for segment in segments.into_values() {
+            if segment.start < range.end && segment.end() > range.start {
+                store.insert_segment(segment);
+            }
+        }
+        for record in pomodoros.into_values() {
+            if record.start < range.end && record.end >= range.start {
+                store.record_pomodoro(record);
+            }
+        }
+        Ok(store)
+    }
+}
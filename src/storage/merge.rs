@@ -0,0 +1,291 @@
+use crate::storage::activity_store::{ActivitySegment, ActivityStore, PomodoroRecord};
+use chrono::{DateTime, Local};
+
+/// Which of the two merged databases a record came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    First,
+    Second,
+    Both, // the same record, kept once
+}
+
+/// Overlapping records the two databases disagree on, e.g. from two
+/// daemons tracking the same time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub pomodoros: bool, // Pomodoro phases rather than activity segments
+    pub kept: Side,      // `First` or `Second`
+    pub kept_records: usize,
+    pub dropped_records: usize,
+    pub dropped_seconds: i64,
+}
+
+/// What `merge_stores` did besides copying
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    pub duplicates: usize, // records found in both databases, kept once
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A stored record as far as merging goes
+trait Record: Clone {
+    fn start(&self) -> DateTime<Local>;
+    fn end(&self) -> DateTime<Local>;
+    /// What the database's primary key holds besides `start`
+    fn key(&self) -> String;
+    /// Optional fields filled in, more for records of newer versions
+    fn detail(&self) -> usize;
+}
+
+impl Record for ActivitySegment {
+    fn start(&self) -> DateTime<Local> {
+        self.start
+    }
+
+    fn end(&self) -> DateTime<Local> {
+        ActivitySegment::end(self)
+    }
+
+    fn key(&self) -> String {
+        self.domain.clone()
+    }
+
+    fn detail(&self) -> usize {
+        [
+            self.category.is_some(),
+            self.category_source.is_some(),
+            self.profile.is_some(),
+        ]
+        .into_iter()
+        .filter(|filled| *filled)
+        .count()
+    }
+}
+
+impl Record for PomodoroRecord {
+    fn start(&self) -> DateTime<Local> {
+        self.start
+    }
+
+    fn end(&self) -> DateTime<Local> {
+        self.end
+    }
+
+    fn key(&self) -> String {
+        self.mode.as_str().to_string()
+    }
+
+    fn detail(&self) -> usize {
+        usize::from(self.label.is_some())
+    }
+}
+
+/// Combine two databases' worth of records. A record found in both is kept
+/// once, in its longest copy (an old backup holds a shorter one). Records of
+/// one database overlapping different ones of the other are conflicts: the
+/// side with more records over that stretch wins, being the finer
+/// resolution, then the one tracking more time.
+pub fn merge_stores(first: &ActivityStore, second: &ActivityStore) -> (ActivityStore, MergeReport) {
+    let mut report = MergeReport::default();
+    let mut merged = ActivityStore::new();
+    for segment in reconcile(first.segments(), second.segments(), false, &mut report) {
+        merged.insert_segment(segment);
+    }
+    for record in reconcile(first.pomodoros(), second.pomodoros(), true, &mut report) {
+        merged.record_pomodoro(record);
+    }
+    (merged, report)
+}
+
+fn reconcile<T: Record>(
+    first: &[T],
+    second: &[T],
+    pomodoros: bool,
+    report: &mut MergeReport,
+) -> Vec<T> {
+    let mut records: Vec<(Side, T)> = first
+        .iter()
+        .map(|record| (Side::First, record.clone()))
+        .chain(second.iter().map(|record| (Side::Second, record.clone())))
+        .collect();
+    records.sort_by_key(|(_, record)| (record.start(), record.key()));
+    records.dedup_by(|(side, record), (kept_side, kept)| {
+        if record.start() != kept.start() || record.key() != kept.key() || side == kept_side {
+            return false;
+        }
+        if (record.end(), record.detail()) > (kept.end(), kept.detail()) {
+            *kept = record.clone();
+        }
+        *kept_side = Side::Both;
+        report.duplicates += 1;
+        true
+    });
+
+    let mut merged = Vec::new();
+    let mut group: Vec<(Side, T)> = Vec::new();
+    let mut group_end = None;
+    for (side, record) in records {
+        if group_end.is_some_and(|end| record.start() >= end) {
+            merged.extend(resolve(std::mem::take(&mut group), pomodoros, report));
+        }
+        group_end = Some(match group_end {
+            Some(end) if !group.is_empty() => record.end().max(end),
+            _ => record.end(),
+        });
+        group.push((side, record));
+    }
+    merged.extend(resolve(group, pomodoros, report));
+    merged
+}
+
+/// Keep one side of a run of overlapping records when both have records of
+/// their own in it
+fn resolve<T: Record>(group: Vec<(Side, T)>, pomodoros: bool, report: &mut MergeReport) -> Vec<T> {
+    let standing = |wanted: Side| {
+        let records: Vec<&T> = group
+            .iter()
+            .filter(|(side, _)| *side == wanted)
+            .map(|(_, record)| record)
+            .collect();
+        let seconds: i64 = records
+            .iter()
+            .map(|record| (record.end() - record.start()).num_seconds())
+            .sum();
+        (records.len(), seconds)
+    };
+    let (first, second) = (standing(Side::First), standing(Side::Second));
+    if first.0 == 0 || second.0 == 0 {
+        return group.into_iter().map(|(_, record)| record).collect();
+    }
+
+    let (kept, winner, loser) = if second > first {
+        (Side::Second, second, first)
+    } else {
+        (Side::First, first, second)
+    };
+    report.conflicts.push(Conflict {
+        start: group
+            .iter()
+            .map(|(_, record)| record.start())
+            .min()
+            .unwrap_or_default(),
+        end: group
+            .iter()
+            .map(|(_, record)| record.end())
+            .max()
+            .unwrap_or_default(),
+        pomodoros,
+        kept,
+        kept_records: winner.0,
+        dropped_records: loser.0,
+        dropped_seconds: loser.1,
+    });
+    group
+        .into_iter()
+        .filter(|(side, _)| *side == kept || *side == Side::Both)
+        .map(|(_, record)| record)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::pomodoro::PomodoroMode;
+    use crate::stats::aggregator::TimeRange;
+    use crate::storage::activity_store::CategorySource;
+    use crate::storage::backend::Storage;
+    use crate::storage::sqlite_backend::SqliteStorage;
+    use chrono::TimeZone;
+
+    #[test]
+    fn merged_databases_keep_the_finer_records() {
+        let at = |hour, minute| {
+            Local
+                .with_ymd_and_hms(2025, 3, 10, hour, minute, 0)
+                .unwrap()
+        };
+        let segment = |domain: &str, start, seconds, category: Option<&str>| ActivitySegment {
+            domain: domain.to_string(),
+            category: category.map(str::to_string),
+            category_source: category.map(|_| CategorySource::Server),
+            profile: None,
+            on_break: false,
+            start,
+            seconds,
+        };
+        let work = PomodoroRecord {
+            mode: PomodoroMode::Work,
+            start: at(9, 0),
+            end: at(9, 25),
+            abandoned: false,
+            label: None,
+        };
+
+        let dir = std::env::temp_dir().join(format!("stop_it-test-merge-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut first = SqliteStorage::open(&dir.join("a.sqlite")).unwrap();
+        // An old backup: the same segment before it grew, and one coarse record
+        // where the other daemon saw three sites
+        first
+            .save_segment(&segment("github.com", at(9, 0), 600, None))
+            .unwrap();
+        first
+            .save_segment(&segment("youtube.com", at(10, 0), 1800, None))
+            .unwrap();
+        first
+            .save_segment(&segment("news.ycombinator.com", at(12, 0), 300, None))
+            .unwrap();
+        first.save_pomodoro(&work).unwrap();
+        let mut second = SqliteStorage::open(&dir.join("b.sqlite")).unwrap();
+        second
+            .save_segment(&segment("github.com", at(9, 0), 900, Some("work")))
+            .unwrap();
+        for (domain, minute) in [("docs.rs", 0), ("github.com", 10), ("docs.rs", 20)] {
+            second
+                .save_segment(&segment(domain, at(10, minute), 600, None))
+                .unwrap();
+        }
+        second.save_pomodoro(&work).unwrap();
+        drop((first, second));
+
+        let load = |name: &str| {
+            SqliteStorage::open_read_only(&dir.join(name))
+                .unwrap()
+                .load(&TimeRange::all())
+                .unwrap()
+        };
+        let (merged, report) = merge_stores(&load("a.sqlite"), &load("b.sqlite"));
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(report.conflicts.len(), 1);
+        let conflict = &report.conflicts[0];
+        assert_eq!((conflict.start, conflict.end), (at(10, 0), at(10, 30)));
+        assert!(!conflict.pomodoros);
+        assert_eq!(conflict.kept, Side::Second);
+        assert_eq!((conflict.kept_records, conflict.dropped_records), (3, 1));
+        assert_eq!(conflict.dropped_seconds, 1800);
+
+        let mut out = SqliteStorage::open(&dir.join("merged.sqlite")).unwrap();
+        out.import(&merged).unwrap();
+        let stored = load("merged.sqlite");
+        let domains: Vec<(&str, i64)> = stored
+            .segments()
+            .iter()
+            .map(|segment| (segment.domain.as_str(), segment.seconds))
+            .collect();
+        assert_eq!(
+            domains,
+            [
+                ("github.com", 900),
+                ("docs.rs", 600),
+                ("github.com", 600),
+                ("docs.rs", 600),
+                ("news.ycombinator.com", 300),
+            ]
+        );
+        // The longer copy keeps its category
+        assert_eq!(stored.segments()[0].category.as_deref(), Some("work"));
+        assert_eq!(stored.pomodoros(), [work]);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod activity_store;
+pub mod backend;
+pub mod journal;
+pub mod jsonl_backend;
+pub mod merge;
+#[cfg(feature = "postgres")]
+pub mod postgres_backend;
+pub mod sqlite_backend;
@@ -0,0 +1,145 @@
+use crate::pomodoro::pomodoro::PomodoroMode;
+use crate::stats::aggregator::TimeRange;
+use crate::storage::activity_store::{
+    ActivitySegment, ActivityStore, CategorySource, PomodoroRecord,
+};
+use crate::storage::backend::{Storage, StorageResult};
+use crate::storage::sqlite_backend::from_timestamp;
+use std::future::Future;
+use tokio::runtime::Handle;
+use tokio_postgres::{Client, NoTls};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS segments (
+    start    BIGINT NOT NULL, -- unix seconds
+    domain   TEXT   NOT NULL,
+    category TEXT,
+    seconds  BIGINT NOT NULL,
+    PRIMARY KEY (start, domain)
+);
+CREATE TABLE IF NOT EXISTS pomodoros (
+    start BIGINT NOT NULL,
+    \"end\" BIGINT NOT NULL,
+    mode  TEXT   NOT NULL,
+    PRIMARY KEY (start, mode)
+);
+ALTER TABLE segments ADD COLUMN IF NOT EXISTS category_source TEXT;
+ALTER TABLE segments ADD COLUMN IF NOT EXISTS profile TEXT;
+ALTER TABLE segments ADD COLUMN IF NOT EXISTS on_break BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE pomodoros ADD COLUMN IF NOT EXISTS abandoned BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE pomodoros ADD COLUMN IF NOT EXISTS label TEXT;
+";
+
+/// PostgreSQL backend, for centralizing several machines in one database.
+/// Calls block the current worker thread, like the other backends.
+#[derive(Debug)]
+pub struct PostgresStorage {
+    client: Client,
+    handle: Handle, // runtime driving the connection, for calls from plain threads
+}
+
+impl PostgresStorage {
+    pub fn connect(url: &str) -> StorageResult<Self> {
+        let handle = Handle::current();
+        let (client, connection) =
+            tokio::task::block_in_place(|| handle.block_on(tokio_postgres::connect(url, NoTls)))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("PostgreSQL connection error: {}", e);
+            }
+        });
+        let storage = Self { client, handle };
+        storage.block_on(storage.client.batch_execute(SCHEMA))?;
+        Ok(storage)
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match Handle::try_current() {
+            Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+            Err(_) => self.handle.block_on(future),
+        }
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn save_segment(&mut self, segment: &ActivitySegment) -> StorageResult<()> {
+        self.block_on(self.client.execute(
+            "INSERT INTO segments (start, domain, category, seconds, category_source, profile, on_break)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (start, domain)
+             DO UPDATE SET category = $3, seconds = $4, category_source = $5, profile = $6,
+                           on_break = $7",
+            &[
+                &segment.start.timestamp(),
+                &segment.domain,
+                &segment.category,
+                &segment.seconds,
+                &segment.category_source.map(|source| source.as_str()),
+                &segment.profile,
+                &segment.on_break,
+            ],
+        ))?;
+        Ok(())
+    }
+
+    fn save_pomodoro(&mut self, record: &PomodoroRecord) -> StorageResult<()> {
+        self.block_on(self.client.execute(
+            "INSERT INTO pomodoros (start, \"end\", mode, abandoned, label)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (start, mode) DO UPDATE SET \"end\" = $2, abandoned = $4, label = $5",
+            &[
+                &record.start.timestamp(),
+                &record.end.timestamp(),
+                &record.mode.as_str(),
+                &record.abandoned,
+                &record.label,
+            ],
+        ))?;
+        Ok(())
+    }
+
+    fn load(&mut self, range: &TimeRange) -> StorageResult<ActivityStore> {
+        let (from, to) = (range.start.timestamp(), range.end.timestamp());
+        let mut store = ActivityStore::new();
+
+        let rows = self.block_on(self.client.query(
+            "SELECT start, domain, category, seconds, category_source, profile, on_break
+             FROM segments
+             WHERE start < $2 AND start + seconds > $1 ORDER BY start",
+            &[&from, &to],
+        ))?;
+        for row in rows {
+            store.insert_segment(ActivitySegment {
+                start: from_timestamp(row.get(0)),
+                domain: row.get(1),
+                category: row.get(2),
+                category_source: row
+                    .get::<_, Option<String>>(4)
+                    .and_then(|source| CategorySource::parse(&source)),
+                profile: row.get(5),
+                on_break: row.get(6),
+                seconds: row.get(3),
+            });
+        }
+
+        let rows = self.block_on(self.client.query(
+            "SELECT start, \"end\", mode, abandoned, label FROM pomodoros
+             WHERE start < $2 AND \"end\" >= $1 ORDER BY start",
+            &[&from, &to],
+        ))?;
+        for row in rows {
+            let mode: String = row.get(2);
+            if let Some(mode) = PomodoroMode::parse(&mode) {
+                store.record_pomodoro(PomodoroRecord {
+                    mode,
+                    start: from_timestamp(row.get(0)),
+                    end: from_timestamp(row.get(1)),
+                    abandoned: row.get(3),
+                    label: row.get(4),
+                });
+            }
+        }
+
+        Ok(store)
+    }
+}
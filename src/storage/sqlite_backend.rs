@@ -0,0 +1,233 @@
+use crate::pomodoro::pomodoro::PomodoroMode;
+use crate::stats::aggregator::TimeRange;
+use crate::storage::activity_store::{
+    ActivitySegment, ActivityStore, CategorySource, PomodoroRecord,
+};
+use crate::storage::backend::{Storage, StorageResult};
+use chrono::{DateTime, Local, TimeZone};
+use rusqlite::{Connection, OpenFlags, params};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long a connection waits for a lock held by another process
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS segments (
+    start    INTEGER NOT NULL, -- unix seconds
+    domain   TEXT    NOT NULL,
+    category TEXT,
+    seconds  INTEGER NOT NULL,
+    PRIMARY KEY (start, domain)
+);
+CREATE TABLE IF NOT EXISTS pomodoros (
+    start INTEGER NOT NULL,
+    \"end\" INTEGER NOT NULL,
+    mode  TEXT    NOT NULL,
+    PRIMARY KEY (start, mode)
+);
+";
+
+/// Default storage backend: a single SQLite database file
+#[derive(Debug)]
+pub struct SqliteStorage {
+    conn: Connection,
+    has_category_source: bool, // false for read-only databases not migrated yet
+    has_profile: bool,
+    has_on_break: bool,
+    has_abandoned: bool,
+    has_label: bool,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> StorageResult<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // WAL lets readers (e.g. `stop_it stats`) run while the daemon writes
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        // Added after the first release
+        for (table, column, kind) in [
+            ("segments", "category_source", "TEXT"),
+            ("segments", "profile", "TEXT"),
+            ("segments", "on_break", "INTEGER NOT NULL DEFAULT 0"),
+            ("pomodoros", "abandoned", "INTEGER NOT NULL DEFAULT 0"),
+            ("pomodoros", "label", "TEXT"),
+        ] {
+            if !has_column(&conn, table, column)? {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, kind
+                ))?;
+            }
+        }
+        Ok(Self {
+            conn,
+            has_category_source: true,
+            has_profile: true,
+            has_on_break: true,
+            has_abandoned: true,
+            has_label: true,
+        })
+    }
+
+    /// Open an existing database without taking write locks, safe to use
+    /// while the daemon is running
+    pub fn open_read_only(path: &Path) -> StorageResult<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let has_category_source = has_column(&conn, "segments", "category_source")?;
+        let has_profile = has_column(&conn, "segments", "profile")?;
+        let has_on_break = has_column(&conn, "segments", "on_break")?;
+        let has_abandoned = has_column(&conn, "pomodoros", "abandoned")?;
+        let has_label = has_column(&conn, "pomodoros", "label")?;
+        Ok(Self {
+            conn,
+            has_category_source,
+            has_profile,
+            has_on_break,
+            has_abandoned,
+            has_label,
+        })
+    }
+
+    /// Save every record of `store` in a single transaction, e.g. to write a
+    /// merged database
+    pub fn import(&mut self, store: &ActivityStore) -> StorageResult<()> {
+        self.conn.execute_batch("BEGIN")?;
+        let saved = store
+            .segments()
+            .iter()
+            .try_for_each(|segment| self.save_segment(segment))
+            .and_then(|()| {
+                store
+                    .pomodoros()
+                    .iter()
+                    .try_for_each(|record| self.save_pomodoro(record))
+            });
+        match saved {
+            Ok(()) => Ok(self.conn.execute_batch("COMMIT")?),
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+pub(crate) fn from_timestamp(seconds: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(seconds, 0)
+        .single()
+        .unwrap_or_else(Local::now)
+}
+
+impl Storage for SqliteStorage {
+    fn save_segment(&mut self, segment: &ActivitySegment) -> StorageResult<()> {
+        self.conn.execute(
+            "INSERT INTO segments (start, domain, category, seconds, category_source, profile, on_break)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (start, domain)
+             DO UPDATE SET category = ?3, seconds = ?4, category_source = ?5, profile = ?6,
+                           on_break = ?7",
+            params![
+                segment.start.timestamp(),
+                segment.domain,
+                segment.category,
+                segment.seconds,
+                segment.category_source.map(|source| source.as_str()),
+                segment.profile,
+                segment.on_break
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn save_pomodoro(&mut self, record: &PomodoroRecord) -> StorageResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO pomodoros (start, \"end\", mode, abandoned, label)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                record.start.timestamp(),
+                record.end.timestamp(),
+                record.mode.as_str(),
+                record.abandoned,
+                record.label
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn load(&mut self, range: &TimeRange) -> StorageResult<ActivityStore> {
+        let (from, to) = (range.start.timestamp(), range.end.timestamp());
+        let mut store = ActivityStore::new();
+
+        let optional = |present: bool, column: &'static str| if present { column } else { "NULL" };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT start, domain, category, seconds, {}, {}, {} FROM segments
+             WHERE start < ?2 AND start + seconds > ?1 ORDER BY start",
+            optional(self.has_category_source, "category_source"),
+            optional(self.has_profile, "profile"),
+            optional(self.has_on_break, "on_break")
+        ))?;
+        let segments = stmt.query_map(params![from, to], |row| {
+            Ok(ActivitySegment {
+                start: from_timestamp(row.get(0)?),
+                domain: row.get(1)?,
+                category: row.get(2)?,
+                category_source: row
+                    .get::<_, Option<String>>(4)?
+                    .and_then(|source| CategorySource::parse(&source)),
+                profile: row.get(5)?,
+                on_break: row.get::<_, Option<bool>>(6)?.unwrap_or(false),
+                seconds: row.get(3)?,
+            })
+        })?;
+        for segment in segments {
+            store.insert_segment(segment?);
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT start, \"end\", mode, {}, {} FROM pomodoros
+             WHERE start < ?2 AND \"end\" >= ?1 ORDER BY start",
+            optional(self.has_abandoned, "abandoned"),
+            optional(self.has_label, "label")
+        ))?;
+        let records = stmt.query_map(params![from, to], |row| {
+            let mode: String = row.get(2)?;
+            let abandoned = row.get::<_, Option<bool>>(3)?.unwrap_or(false);
+            Ok((row.get(0)?, row.get(1)?, mode, abandoned, row.get(4)?))
+        })?;
+        for record in records {
+            let (start, end, mode, abandoned, label) = record?;
+            if let Some(mode) = PomodoroMode::parse(&mode) {
+                store.record_pomodoro(PomodoroRecord {
+                    mode,
+                    start: from_timestamp(start),
+                    end: from_timestamp(end),
+                    abandoned,
+                    label,
+                });
+            }
+        }
+
+        Ok(store)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::window::provider::ActiveWindow;
+use serde::Deserialize;
+
+/// Whether we are running inside a Sway session
+pub fn is_available() -> bool {
+    std::env::var_os("SWAYSOCK").is_some()
+}
+
+/// The subset of a `swaymsg -t get_tree` node we use
+#[derive(Debug, Deserialize)]
+pub struct Node {
+    #[serde(rename = "type")]
+    pub kind: String, // "root", "output", "workspace", "con" or "floating_con"
+    #[serde(default)]
+    pub name: Option<String>, // the title, for windows
+    #[serde(default)]
+    pub focused: bool,
+    #[serde(default)]
+    pub app_id: Option<String>, // native Wayland windows
+    #[serde(default)]
+    pub window_properties: Option<WindowProperties>, // XWayland windows
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub floating_nodes: Vec<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WindowProperties {
+    #[serde(default)]
+    pub class: Option<String>,
+}
+
+impl Node {
+    /// The focused node in this tree: a window, or a workspace without one
+    pub fn focused(&self) -> Option<&Node> {
+        if self.focused {
+            return Some(self);
+        }
+        self.nodes
+            .iter()
+            .chain(&self.floating_nodes)
+            .find_map(Node::focused)
+    }
+
+    /// The focused window, empty when an empty workspace has focus
+    pub fn active_window(&self) -> Option<ActiveWindow> {
+        let node = self.focused()?;
+        if !matches!(node.kind.as_str(), "con" | "floating_con") {
+            return Some(ActiveWindow {
+                class: String::new(),
+                title: String::new(),
+            });
+        }
+        let class = node
+            .app_id
+            .clone()
+            .or_else(|| node.window_properties.as_ref()?.class.clone())
+            .unwrap_or_default();
+        Some(ActiveWindow {
+            class,
+            title: node.name.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sway_tree_yields_the_focused_window() {
+        let tree = |focused: &str| {
+            let node = |id: &str, kind: &str, extra: &str| {
+                format!(
+                    r#"{{"type": "{}", "focused": {}, {} "nodes": [], "floating_nodes": []}}"#,
+                    kind,
+                    id == focused,
+                    extra
+                )
+            };
+            format!(
+                r#"{{"type": "root", "name": "root", "focused": false, "nodes": [
+                    {{"type": "output", "name": "DP-1", "focused": false, "nodes": [
+                        {{"type": "workspace", "name": "1", "focused": {}, "nodes": [{}, {}],
+                          "floating_nodes": [{}]}}
+                    ]}}
+                ]}}"#,
+                focused == "workspace",
+                node(
+                    "kitty",
+                    "con",
+                    r#""name": "cargo test", "app_id": "kitty","#
+                ),
+                node(
+                    "steam",
+                    "con",
+                    r#""name": "Steam", "app_id": null, "window_properties": {"class": "steam"},"#
+                ),
+                node(
+                    "pavucontrol",
+                    "floating_con",
+                    r#""name": "Volume Control", "app_id": "pavucontrol","#
+                ),
+            )
+        };
+        let active = |focused| {
+            let window = serde_json::from_str::<Node>(&tree(focused))
+                .unwrap()
+                .active_window()
+                .unwrap();
+            (window.class, window.title)
+        };
+
+        assert_eq!(active("kitty"), ("kitty".into(), "cargo test".into()));
+        // XWayland windows have a class instead of an app ID
+        assert_eq!(active("steam"), ("steam".into(), "Steam".into()));
+        assert_eq!(
+            active("pavucontrol"),
+            ("pavucontrol".into(), "Volume Control".into())
+        );
+        // An empty workspace, like Hyprland's `{}`
+        assert_eq!(active("workspace"), (String::new(), String::new()));
+    }
+}
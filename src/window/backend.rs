@@ -0,0 +1,214 @@
+use crate::config::settings::Config;
+use crate::window::provider::{
+    GnomeWindowProvider, HyprlandWindowProvider, KdeWindowProvider, NoWindowProvider,
+    SwayWindowProvider, WindowProvider, WindowsWindowProvider, X11WindowProvider,
+};
+use crate::{gnome, hypr, kde, sway, windows, x11};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where the focused window comes from, one per compositor or OS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Hyprland,
+    Sway,
+    Gnome,
+    Kde,
+    X11,
+    Windows,
+    None, // no focus tracking
+}
+
+impl Backend {
+    /// In the order sessions are detected: compositors before X11, since
+    /// XWayland sets DISPLAY too
+    pub const ALL: [Backend; 7] = [
+        Backend::Hyprland,
+        Backend::Sway,
+        Backend::Gnome,
+        Backend::Kde,
+        Backend::Windows,
+        Backend::X11,
+        Backend::None,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Hyprland => "hyprland",
+            Backend::Sway => "sway",
+            Backend::Gnome => "gnome",
+            Backend::Kde => "kde",
+            Backend::X11 => "x11",
+            Backend::Windows => "windows",
+            Backend::None => "none",
+        }
+    }
+
+    /// Whether this build can read the focused window this way at all: the
+    /// Wayland compositors and desktops run on Linux and the BSDs, X11 on
+    /// any Unix, Windows needs the `windows-focus` feature
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Hyprland | Backend::Sway | Backend::Gnome | Backend::Kde => {
+                cfg!(all(unix, not(target_os = "macos")))
+            }
+            Backend::X11 => cfg!(unix),
+            Backend::Windows => windows::foreground::is_available(),
+            Backend::None => true,
+        }
+    }
+
+    /// The backends `is_supported`, to tell what this build can track
+    pub fn supported() -> Vec<Backend> {
+        Backend::ALL
+            .into_iter()
+            .filter(|backend| *backend != Backend::None && backend.is_supported())
+            .collect()
+    }
+
+    /// Whether the environment points to this session, e.g. SWAYSOCK for Sway
+    pub fn is_available(self) -> bool {
+        match self {
+            Backend::Hyprland => hypr::hyprctl::is_available(),
+            Backend::Sway => sway::swaymsg::is_available(),
+            Backend::Gnome => gnome::gdbus::is_available(),
+            Backend::Kde => kde::kdotool::is_available(),
+            Backend::X11 => x11::xprop::is_available(),
+            Backend::Windows => windows::foreground::is_available(),
+            Backend::None => true,
+        }
+    }
+
+    /// Whether `config` asks for focus tracking with this backend. Any
+    /// `track_focus` applies to X11, so one config serves both.
+    pub fn is_enabled(self, config: &Config) -> bool {
+        match self {
+            Backend::Hyprland => config.hyprland.track_focus,
+            Backend::Sway => config.sway.track_focus,
+            Backend::Gnome => config.gnome.track_focus,
+            Backend::Kde => config.kde.track_focus,
+            Backend::Windows => config.windows.track_focus,
+            Backend::X11 => {
+                config.hyprland.track_focus
+                    || config.sway.track_focus
+                    || config.x11.track_focus
+                    || config.gnome.track_focus
+                    || config.kde.track_focus
+                    || config.windows.track_focus
+            }
+            Backend::None => true,
+        }
+    }
+
+    /// The first enabled backend `is_available` accepts, `None` when focus
+    /// tracking is off or no session was found
+    pub fn detect(config: &Config, is_available: impl Fn(Backend) -> bool) -> Option<Backend> {
+        Backend::supported()
+            .into_iter()
+            .find(|backend| backend.is_enabled(config) && is_available(*backend))
+    }
+
+    pub fn provider(self) -> Box<dyn WindowProvider> {
+        match self {
+            Backend::Hyprland => Box::new(HyprlandWindowProvider::default()),
+            Backend::Sway => Box::new(SwayWindowProvider::default()),
+            Backend::Gnome => Box::new(GnomeWindowProvider::default()),
+            Backend::Kde => Box::new(KdeWindowProvider::default()),
+            Backend::X11 => Box::new(X11WindowProvider::default()),
+            Backend::Windows => Box::new(WindowsWindowProvider::default()),
+            Backend::None => Box::new(NoWindowProvider),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .into_iter()
+            .find(|backend| backend.name().eq_ignore_ascii_case(value))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Backend::ALL.iter().map(|backend| backend.name()).collect();
+                format!("expected one of {}", names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::activity::InputSource;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn backends_this_build_cannot_use_are_left_out() {
+        let mut config = Config::default();
+        config.windows.track_focus = true;
+        // Without Windows and the windows-focus feature only X11 is left
+        assert!(!Backend::Windows.is_supported());
+        assert_eq!(Backend::detect(&config, |_| true), Some(Backend::X11));
+        assert_eq!(
+            Backend::supported(),
+            vec![
+                Backend::Hyprland,
+                Backend::Sway,
+                Backend::Gnome,
+                Backend::Kde,
+                Backend::X11
+            ]
+        );
+        assert!(InputSource::Evdev.is_supported());
+        assert!(InputSource::Logind.is_supported());
+    }
+
+    #[test]
+    fn window_backends_are_detected_from_the_session() {
+        let mut config = Config::default();
+        // Nothing detected while focus tracking is off, whatever the session
+        assert_eq!(Backend::detect(&config, |_| true), None);
+
+        config.sway.track_focus = true;
+        config.kde.track_focus = true;
+        let session = |available: Backend| move |backend: Backend| backend == available;
+        assert_eq!(
+            Backend::detect(&config, session(Backend::Sway)),
+            Some(Backend::Sway)
+        );
+        assert_eq!(
+            Backend::detect(&config, session(Backend::Kde)),
+            Some(Backend::Kde)
+        );
+        // Any track_focus carries over to X11
+        assert_eq!(
+            Backend::detect(&config, session(Backend::X11)),
+            Some(Backend::X11)
+        );
+        // Hyprland isn't enabled, so its session doesn't count
+        assert_eq!(Backend::detect(&config, session(Backend::Hyprland)), None);
+        // Compositors come before X11, which XWayland makes look available
+        assert_eq!(
+            Backend::detect(&config, |backend| matches!(
+                backend,
+                Backend::X11 | Backend::Kde
+            )),
+            Some(Backend::Kde)
+        );
+
+        // The names `--backend` takes
+        assert_eq!("gnome".parse::<Backend>(), Ok(Backend::Gnome));
+        assert_eq!("X11".parse::<Backend>(), Ok(Backend::X11));
+        assert_eq!("none".parse::<Backend>(), Ok(Backend::None));
+        for backend in Backend::ALL {
+            assert_eq!(backend.to_string().parse::<Backend>(), Ok(backend));
+        }
+        let error = "wayland".parse::<Backend>().unwrap_err();
+        assert!(error.contains("hyprland, sway, gnome, kde, windows, x11, none"));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::config::settings::TitlesConfig;
+use crate::window::provider::ActiveWindow;
+use chrono::{DateTime, Duration, Local};
+use std::collections::{HashMap, VecDeque};
+
+/// Freezes the title of window classes whose title changes faster than
+/// configured, so a ticking clock or a playlist doesn't look like a stream
+/// of new activities
+#[derive(Debug)]
+pub struct TitleChurn {
+    config: TitlesConfig,
+    classes: HashMap<String, ClassTitles>,
+}
+
+#[derive(Debug)]
+struct ClassTitles {
+    last: String,                                 // title as last reported
+    stable: String,                               // title passed on to the tracker
+    changes: VecDeque<(DateTime<Local>, String)>, // recent changes and the title they replaced
+    noisy: bool,
+}
+
+/// What happened to a window class while filtering
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChurnChange {
+    Frozen(String),
+    Calmed(String),
+}
+
+impl TitleChurn {
+    pub fn new(config: &TitlesConfig) -> Self {
+        Self {
+            config: config.clone(),
+            classes: HashMap::new(),
+        }
+    }
+
+    /// Title changes allowed for `class` within the configured window
+    fn max_changes(&self, class: &str) -> usize {
+        self.config
+            .classes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(class))
+            .map_or(self.config.max_changes, |(_, max)| *max)
+    }
+
+    /// Replace the title of `window` with its last stable title while its
+    /// class is churning. Also reports when a class starts or stops churning.
+    pub fn filter(
+        &mut self,
+        mut window: ActiveWindow,
+        now: DateTime<Local>,
+    ) -> (ActiveWindow, Option<ChurnChange>) {
+        let max_changes = self.max_changes(&window.class);
+        let span = Duration::seconds(self.config.window);
+        let entry = self
+            .classes
+            .entry(window.class.clone())
+            .or_insert_with(|| ClassTitles {
+                last: window.title.clone(),
+                stable: window.title.clone(),
+                changes: VecDeque::new(),
+                noisy: false,
+            });
+
+        if entry.last != window.title {
+            let previous = std::mem::replace(&mut entry.last, window.title.clone());
+            entry.changes.push_back((now, previous));
+        }
+        while entry
+            .changes
+            .front()
+            .is_some_and(|(at, _)| now - *at > span)
+        {
+            entry.changes.pop_front();
+        }
+
+        let mut change = None;
+        if !entry.noisy && entry.changes.len() > max_changes {
+            entry.noisy = true;
+            // Keep the title from before the burst started
+            if let Some((_, before)) = entry.changes.front() {
+                entry.stable = before.clone();
+            }
+            change = Some(ChurnChange::Frozen(window.class.clone()));
+        } else if entry.noisy && entry.changes.is_empty() {
+            entry.noisy = false;
+            change = Some(ChurnChange::Calmed(window.class.clone()));
+        }
+
+        if entry.noisy {
+            window.title = entry.stable.clone();
+        } else {
+            entry.stable = window.title.clone();
+        }
+        (window, change)
+    }
+}
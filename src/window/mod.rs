@@ -0,0 +1,5 @@
+pub mod backend;
+pub mod churn;
+pub mod provider;
+pub mod services;
+pub mod title;
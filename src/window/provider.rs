@@ -0,0 +1,498 @@
+use crate::gnome::gdbus;
+use crate::hypr::socket2::{self, EventListener};
+use crate::kde::kdotool;
+use crate::sway::swaymsg::Node;
+use crate::windows::foreground;
+use crate::x11::xprop;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// The focused window as reported by the compositor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ActiveWindow {
+    pub class: String,
+    pub title: String,
+}
+
+impl ActiveWindow {
+    /// Whether this window belongs to a web browser
+    pub fn is_browser(&self) -> bool {
+        const BROWSERS: &[&str] = &[
+            "firefox",
+            "librewolf",
+            "floorp",
+            "waterfox",
+            "zen",
+            "chromium",
+            "chrome",
+            "brave",
+            "vivaldi",
+            "opera",
+            "microsoft-edge",
+            "msedge",
+            "qutebrowser",
+            "epiphany",
+        ];
+        let class = self.class.to_lowercase();
+        BROWSERS.iter().any(|browser| class.contains(browser))
+    }
+}
+
+/// What is done to the focused browser window once a blocked site outstays
+/// `[focus_alerts] grace` during a work session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowAction {
+    #[default]
+    None, // notifications only
+    Away,  // focus the previous workspace
+    Close, // close the window
+}
+
+/// Tells the daemon which window has focus, so time isn't credited to the
+/// last visited site while another application is in front
+pub trait WindowProvider: Send {
+    /// `None` when the focused window can't be determined
+    fn active_window(&mut self) -> Option<ActiveWindow>;
+
+    /// Apply `action` to the focused window, where the compositor allows it
+    fn act_on_focused(&mut self, action: WindowAction) -> Result<(), String> {
+        match action {
+            WindowAction::None => Ok(()),
+            _ => Err("not supported with this focus backend".to_string()),
+        }
+    }
+}
+
+/// Provider for sessions without compositor integration
+#[derive(Debug, Default)]
+pub struct NoWindowProvider;
+
+impl WindowProvider for NoWindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        None
+    }
+}
+
+/// How long the daemon waits for the focus backend each step
+pub const PROVIDER_TIMEOUT: Duration = Duration::from_millis(500);
+
+enum ProviderRequest {
+    ActiveWindow,
+    Act(WindowAction),
+}
+
+enum ProviderReply {
+    Window(Option<ActiveWindow>),
+    Acted(Result<(), String>),
+}
+
+/// Runs a provider on its own thread, so a compositor call that is slow or
+/// hangs holds up the caller for `timeout` at most. Until it answers again
+/// the last known window stands and no further calls pile up behind it.
+pub struct ProviderThread {
+    requests: mpsc::Sender<ProviderRequest>,
+    replies: mpsc::Receiver<ProviderReply>,
+    timeout: Duration,
+    outstanding: usize, // requests the provider hasn't answered yet
+    last: Option<ActiveWindow>,
+}
+
+impl ProviderThread {
+    pub fn spawn(mut provider: Box<dyn WindowProvider>, timeout: Duration) -> Self {
+        let (requests, request_rx) = mpsc::channel();
+        let (reply_tx, replies) = mpsc::channel();
+        let _ = thread::Builder::new()
+            .name("window-provider".to_string())
+            .spawn(move || {
+                for request in request_rx {
+                    let reply = match request {
+                        ProviderRequest::ActiveWindow => {
+                            ProviderReply::Window(provider.active_window())
+                        }
+                        ProviderRequest::Act(action) => {
+                            ProviderReply::Acted(provider.act_on_focused(action))
+                        }
+                    };
+                    if reply_tx.send(reply).is_err() {
+                        break;
+                    }
+                }
+            });
+        Self {
+            requests,
+            replies,
+            timeout,
+            outstanding: 0,
+            last: None,
+        }
+    }
+
+    /// Whether the provider answered the last request in time
+    pub fn is_answering(&self) -> bool {
+        self.outstanding == 0
+    }
+
+    /// Send `request` once earlier ones are answered, and wait for its reply
+    fn request(&mut self, request: ProviderRequest) -> Option<ProviderReply> {
+        while self.outstanding > 0 {
+            match self.replies.try_recv() {
+                Ok(ProviderReply::Window(window)) => self.last = window,
+                Ok(ProviderReply::Acted(_)) => {}
+                Err(_) => return None,
+            }
+            self.outstanding -= 1;
+        }
+        self.requests.send(request).ok()?;
+        self.outstanding += 1;
+        let reply = self.replies.recv_timeout(self.timeout).ok()?;
+        self.outstanding -= 1;
+        Some(reply)
+    }
+}
+
+impl WindowProvider for ProviderThread {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        if let Some(ProviderReply::Window(window)) = self.request(ProviderRequest::ActiveWindow) {
+            self.last = window;
+        }
+        self.last.clone()
+    }
+
+    fn act_on_focused(&mut self, action: WindowAction) -> Result<(), String> {
+        match self.request(ProviderRequest::Act(action)) {
+            Some(ProviderReply::Acted(result)) => result,
+            _ => Err("the focus backend isn't answering".to_string()),
+        }
+    }
+}
+
+/// Follows the focused window through Hyprland's event socket, running
+/// `hyprctl -j activewindow` only until the first event and while the socket
+/// is unreachable
+#[derive(Debug)]
+pub struct HyprlandWindowProvider {
+    events: EventListener,
+    failing: bool, // report a broken hyprctl once, not every second
+}
+
+impl HyprlandWindowProvider {
+    pub fn new() -> Self {
+        Self {
+            events: EventListener::spawn(socket2::socket_path),
+            failing: false,
+        }
+    }
+
+    fn hyprctl(&mut self) -> Option<ActiveWindow> {
+        let result = Command::new("hyprctl")
+            .args(["-j", "activewindow"])
+            .output()
+            .map_err(|e| e.to_string())
+            .and_then(|output| {
+                // An empty object when no window has focus, e.g. on an empty workspace
+                if output.stdout.trim_ascii() == b"{}" {
+                    return Ok(ActiveWindow {
+                        class: String::new(),
+                        title: String::new(),
+                    });
+                }
+                serde_json::from_slice::<ActiveWindow>(&output.stdout).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(window) => {
+                self.failing = false;
+                Some(window)
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!("Hyprland: cannot read the active window: {}", e);
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Default for HyprlandWindowProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowProvider for HyprlandWindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        if let Some(window) = self.events.latest() {
+            return Some(window);
+        }
+        let window = self.hyprctl()?;
+        self.events.seed(&window);
+        Some(window)
+    }
+
+    /// `hyprctl dispatch workspace previous` or `hyprctl dispatch killactive`
+    fn act_on_focused(&mut self, action: WindowAction) -> Result<(), String> {
+        let args: &[&str] = match action {
+            WindowAction::None => return Ok(()),
+            WindowAction::Away => &["dispatch", "workspace", "previous"],
+            WindowAction::Close => &["dispatch", "killactive"],
+        };
+        let output = Command::new("hyprctl")
+            .args(args)
+            .output()
+            .map_err(|e| e.to_string())?;
+        // hyprctl exits 0 even when the dispatcher fails, answering with the error
+        let reply = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || reply.trim() != "ok" {
+            return Err(format!(
+                "hyprctl {} failed: {}",
+                args.join(" "),
+                reply.trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Reads the focused window with `swaymsg -t get_tree`
+#[derive(Debug, Default)]
+pub struct SwayWindowProvider {
+    failing: bool, // report a broken swaymsg once, not every second
+}
+
+impl WindowProvider for SwayWindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        let result = Command::new("swaymsg")
+            .args(["-r", "-t", "get_tree"])
+            .output()
+            .map_err(|e| e.to_string())
+            .and_then(|output| {
+                serde_json::from_slice::<Node>(&output.stdout).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(tree) => {
+                self.failing = false;
+                tree.active_window()
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!("Sway: cannot read the active window: {}", e);
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Asks the companion GNOME Shell extension for the focused window with
+/// `gdbus call`, for GNOME Wayland sessions
+#[derive(Debug, Default)]
+pub struct GnomeWindowProvider {
+    failing: bool, // report a broken gdbus or missing extension once, not every second
+}
+
+impl WindowProvider for GnomeWindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        let result = Command::new("gdbus")
+            .args(["call", "--session", "--dest", "org.gnome.Shell"])
+            .args([
+                "--object-path",
+                gdbus::OBJECT_PATH,
+                "--method",
+                gdbus::METHOD,
+            ])
+            .output()
+            .map_err(|e| e.to_string())
+            .and_then(|output| {
+                if !output.status.success() {
+                    return Err(format!(
+                        "{} (is the extension in gnome-extension/ enabled?)",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                gdbus::parse_focused_window(&String::from_utf8_lossy(&output.stdout))
+                    .ok_or_else(|| "unexpected gdbus output".to_string())
+            });
+
+        match result {
+            Ok(window) => {
+                self.failing = false;
+                Some(window)
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!("GNOME: cannot read the active window: {}", e);
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Asks KWin for the focused window with `kdotool`, which runs a KWin script
+/// over D-Bus, for KDE Plasma sessions (Wayland or X11)
+#[derive(Debug, Default)]
+pub struct KdeWindowProvider {
+    failing: bool, // report a missing kdotool once, not every second
+}
+
+impl WindowProvider for KdeWindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        let result = Command::new("kdotool")
+            .args(["getactivewindow", "getwindowclassname", "getwindowname"])
+            .output()
+            .map_err(|e| format!("{} (is kdotool installed?)", e))
+            .and_then(|output| {
+                if !output.status.success() {
+                    return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+                }
+                Ok(kdotool::parse_window(&String::from_utf8_lossy(
+                    &output.stdout,
+                )))
+            });
+
+        match result {
+            Ok(window) => {
+                self.failing = false;
+                Some(window)
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!("KDE: cannot read the active window: {}", e);
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Reads the focused window with `xprop`, for X11 sessions (i3, bspwm, XFCE)
+#[derive(Debug, Default)]
+pub struct X11WindowProvider {
+    failing: bool, // report a broken xprop once, not every second
+}
+
+impl X11WindowProvider {
+    fn xprop(args: &[&str]) -> Result<String, String> {
+        let output = Command::new("xprop")
+            .args(args)
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl WindowProvider for X11WindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        let result = Self::xprop(&["-root", "_NET_ACTIVE_WINDOW"]).and_then(|output| {
+            match xprop::parse_active_window_id(&output) {
+                Some(id) => Self::xprop(&["-id", &id, "WM_CLASS", "_NET_WM_NAME", "WM_NAME"])
+                    .map(|output| xprop::parse_window(&output)),
+                // No window has focus, e.g. on an empty desktop
+                None => Ok(ActiveWindow {
+                    class: String::new(),
+                    title: String::new(),
+                }),
+            }
+        });
+
+        match result {
+            Ok(window) => {
+                self.failing = false;
+                Some(window)
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!("X11: cannot read the active window: {}", e);
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Reads the foreground window through the Win32 API, on Windows builds with
+/// the `windows-focus` feature
+#[derive(Debug, Default)]
+pub struct WindowsWindowProvider {
+    failing: bool, // report a failing call once, not every second
+}
+
+impl WindowProvider for WindowsWindowProvider {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        match foreground::foreground_window() {
+            Ok(window) => {
+                self.failing = false;
+                Some(window)
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!("Windows: cannot read the foreground window: {}", e);
+                    self.failing = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Answers with the class in `class`, waiting while the test holds it
+    struct Gated {
+        class: Arc<Mutex<&'static str>>,
+    }
+
+    impl WindowProvider for Gated {
+        fn active_window(&mut self) -> Option<ActiveWindow> {
+            Some(ActiveWindow {
+                class: self.class.lock().unwrap().to_string(),
+                title: String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn a_hung_provider_keeps_the_last_window_until_it_answers() {
+        let class = Arc::new(Mutex::new("kitty"));
+        let provider = Gated {
+            class: class.clone(),
+        };
+        let mut thread = ProviderThread::spawn(Box::new(provider), Duration::from_millis(50));
+        assert_eq!(thread.active_window().unwrap().class, "kitty");
+        assert!(thread.is_answering());
+
+        let mut hung = class.lock().unwrap();
+        *hung = "firefox";
+        assert_eq!(thread.active_window().unwrap().class, "kitty");
+        assert!(!thread.is_answering());
+        // Nothing piles up behind the call that hangs
+        assert_eq!(thread.active_window().unwrap().class, "kitty");
+        assert_eq!(
+            thread.act_on_focused(WindowAction::Close),
+            Err("the focus backend isn't answering".to_string())
+        );
+
+        drop(hung);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(thread.active_window().unwrap().class, "firefox");
+        assert!(thread.is_answering());
+    }
+}
@@ -0,0 +1,37 @@
+//! Service names shown in browser titles, for sites that don't put their
+//! hostname in the title ("Some video - YouTube"). Built-in names, localized
+//! ones included, come from `data/services.txt`; `[titles] services` adds more.
+
+use std::collections::{BTreeMap, HashMap};
+
+include!(concat!(env!("OUT_DIR"), "/services.rs"));
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceNames {
+    user: HashMap<String, String>, // lowercase name -> domain
+}
+
+impl ServiceNames {
+    /// `services` maps domains to their names, as in `[titles] services`
+    pub fn new(services: &BTreeMap<String, Vec<String>>) -> Self {
+        Self {
+            user: services
+                .iter()
+                .flat_map(|(domain, names)| {
+                    names
+                        .iter()
+                        .map(move |name| (name.trim().to_lowercase(), domain.to_lowercase()))
+                })
+                .collect(),
+        }
+    }
+
+    /// Domain of the service called `name`; configured names win over built-in ones
+    pub fn lookup(&self, name: &str) -> Option<String> {
+        let name = name.trim().to_lowercase();
+        self.user
+            .get(&name)
+            .cloned()
+            .or_else(|| SERVICES.get(name.as_str()).map(|domain| domain.to_string()))
+    }
+}
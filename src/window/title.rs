@@ -0,0 +1,151 @@
+use crate::stats::sites::canonical_site;
+use crate::window::services::ServiceNames;
+#[cfg(feature = "title-fallback")]
+use regex::Regex;
+#[cfg(feature = "title-fallback")]
+use std::sync::LazyLock;
+
+/// Hostname-looking tokens: dot-separated labels ending in a letter-only TLD
+#[cfg(feature = "title-fallback")]
+static HOSTNAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|[^a-z0-9.@-])((?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+([a-z]{2,24}))(?::\d+)?(?:$|[^a-z0-9@-])")
+        .expect("valid hostname regex")
+});
+
+/// Top-level domains accepted in titles. Deliberately short: ambiguous TLDs
+/// that are also common file extensions (rs, md, py, sh, pl, ...) are left
+/// out so "main.rs - VSCodium" isn't mistaken for a site.
+#[cfg(feature = "title-fallback")]
+const KNOWN_TLDS: &[&str] = &[
+    "com", "org", "net", "io", "dev", "app", "co", "ai", "gg", "tv", "me", "so", "xyz", "info",
+    "edu", "gov", "eu", "uk", "de", "fr", "it", "es", "nl", "be", "at", "ch", "se", "no", "fi",
+    "dk", "ie", "pt", "cz", "ru", "jp", "cn", "kr", "in", "br", "ca", "au", "nz", "us", "mx", "ar",
+    "fm", "ly", "to", "tech", "news", "blog", "cloud", "page", "site", "online", "wiki",
+];
+
+/// Separators between the parts of a page title, e.g. "Video - YouTube"
+#[cfg(feature = "title-fallback")]
+const TITLE_SEPARATORS: &[&str] = &[" - ", " – ", " — ", " | ", " · "];
+
+/// Unread counters sites put around their title, e.g. "(3) YouTube"
+#[cfg(feature = "title-fallback")]
+static COUNTER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\(\d+\+?\)\s*|\s*\(\d+\+?\)$").expect("valid counter regex"));
+
+/// Browser names appended to window titles, stripped before matching
+#[cfg(feature = "title-fallback")]
+const BROWSER_SUFFIXES: &[&str] = &[
+    " — Mozilla Firefox",
+    " - Mozilla Firefox",
+    " — LibreWolf",
+    " - Google Chrome",
+    " - Chromium",
+    " - Brave",
+    " - Vivaldi",
+    " - Opera",
+    " - Microsoft Edge",
+    " - qutebrowser",
+];
+
+/// Extract the site shown in a browser window title, e.g.
+/// "github.com/rust-lang/rust: Rust — Mozilla Firefox" -> "github.com".
+/// Returns `None` when the title contains no recognizable hostname or
+/// built-in service name.
+pub fn domain_from_title(title: &str) -> Option<String> {
+    domain_from_title_with(title, &ServiceNames::default())
+}
+
+/// Like `domain_from_title`, with the service names configured in
+/// `[titles] services`. Service names ("Video - YouTube", "ютуб") are only
+/// matched in titles ending with a known browser name, so desktop apps
+/// called like a site (Spotify, Discord) don't count as browsing.
+#[cfg(feature = "title-fallback")]
+pub fn domain_from_title_with(title: &str, services: &ServiceNames) -> Option<String> {
+    let mut title = title.trim();
+    let mut browser = false;
+    for suffix in BROWSER_SUFFIXES {
+        if let Some(stripped) = title.strip_suffix(suffix) {
+            title = stripped;
+            browser = true;
+            break;
+        }
+    }
+
+    let host = HOSTNAME.captures_iter(title).find_map(|captures| {
+        let tld = captures[2].to_lowercase();
+        if !KNOWN_TLDS.contains(&tld.as_str()) {
+            return None;
+        }
+        Some(normalize_host(&captures[1]))
+    });
+    if host.is_some() || !browser {
+        return host;
+    }
+    service_from_title(title, services)
+}
+
+/// Built without `title-fallback`: sites only come from the extension
+#[cfg(not(feature = "title-fallback"))]
+pub fn domain_from_title_with(_title: &str, _services: &ServiceNames) -> Option<String> {
+    None
+}
+
+/// The service named in a page title: the whole title or one of its parts,
+/// the last part first since sites usually append their name
+#[cfg(feature = "title-fallback")]
+fn service_from_title(title: &str, services: &ServiceNames) -> Option<String> {
+    let title = COUNTER.replace_all(title, "");
+    if let Some(domain) = services.lookup(&title) {
+        return Some(domain);
+    }
+    let mut parts = vec![title.as_ref()];
+    for separator in TITLE_SEPARATORS {
+        parts = parts
+            .into_iter()
+            .flat_map(|part| part.split(separator))
+            .collect();
+    }
+    parts
+        .into_iter()
+        .rev()
+        .find_map(|part| services.lookup(&COUNTER.replace_all(part, "")))
+}
+
+/// Extract the site from a page URL, e.g.
+/// "https://user@www.GitHub.com:443/rust-lang" -> "github.com".
+/// Only web pages count: other schemes (about:, file://, chrome://) give `None`.
+pub fn domain_from_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = if let Some(bracketed) = host_port.strip_prefix('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        bracketed.split(']').next()?
+    } else {
+        host_port.split(':').next()?
+    };
+
+    let host = normalize_host(host);
+    (!host.is_empty()).then_some(host)
+}
+
+/// Lowercase a hostname, drop the "www." prefix and the trailing root dot
+/// and resolve built-in aliases (youtu.be -> youtube.com), so every source
+/// reports a site under the same name
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim().trim_end_matches('.').to_lowercase();
+    let host = match host.strip_prefix("www.") {
+        Some(stripped) => stripped.to_string(),
+        None => host,
+    };
+    match canonical_site(&host) {
+        Some(site) => site.to_string(),
+        None => host,
+    }
+}
@@ -0,0 +1,100 @@
+use crate::window::provider::ActiveWindow;
+
+/// Whether this build can read the foreground window: on Windows with the
+/// `windows-focus` feature
+pub fn is_available() -> bool {
+    cfg!(all(windows, feature = "windows-focus"))
+}
+
+/// The class for a window owned by the executable at `path`, e.g.
+/// `C:\Program Files\Mozilla Firefox\firefox.exe` -> `firefox`. Windows has
+/// no application IDs, and window classes (`MozillaWindowClass`) don't name
+/// the application.
+pub fn class_from_image_path(path: &str) -> String {
+    let file = path.rsplit(['\\', '/']).next().unwrap_or(path);
+    let name = match file.rsplit_once('.') {
+        Some((name, extension)) if extension.eq_ignore_ascii_case("exe") => name,
+        _ => file,
+    };
+    name.to_lowercase()
+}
+
+/// The foreground window, with an empty class and title when none has
+/// focus (e.g. while the desktop is shown)
+#[cfg(all(windows, feature = "windows-focus"))]
+pub fn foreground_window() -> Result<ActiveWindow, String> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+        QueryFullProcessImageNameW,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId,
+    };
+
+    // SAFETY: plain Win32 calls on a window handle that may go away at any
+    // time; they then fail and return 0, which is handled
+    unsafe {
+        let window = GetForegroundWindow();
+        if window.is_null() {
+            return Ok(ActiveWindow {
+                class: String::new(),
+                title: String::new(),
+            });
+        }
+
+        let length = GetWindowTextLengthW(window);
+        let mut title = vec![0u16; length.max(0) as usize + 1];
+        let copied = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+        let title = String::from_utf16_lossy(&title[..copied.max(0) as usize]);
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(window, &mut pid);
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return Err(format!("cannot open process {}", pid));
+        }
+        let mut path = vec![0u16; 1024];
+        let mut size = path.len() as u32;
+        let ok =
+            QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, path.as_mut_ptr(), &mut size);
+        CloseHandle(process);
+        if ok == 0 {
+            return Err(format!("cannot read the executable of process {}", pid));
+        }
+        let path = String::from_utf16_lossy(&path[..size as usize]);
+
+        Ok(ActiveWindow {
+            class: class_from_image_path(&path),
+            title,
+        })
+    }
+}
+
+#[cfg(not(all(windows, feature = "windows-focus")))]
+pub fn foreground_window() -> Result<ActiveWindow, String> {
+    Err("built without the `windows-focus` feature, or not on Windows".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_executables_name_the_window_class() {
+        let class = class_from_image_path(
+            "C:\\Program Files (x86)\\Microsoft\\Edge\\Application\\msedge.exe",
+        );
+        assert_eq!(class, "msedge");
+        let window = ActiveWindow {
+            class,
+            title: "Rust - Microsoft Edge".to_string(),
+        };
+        assert!(window.is_browser());
+        assert_eq!(
+            class_from_image_path("C:\\Program Files\\Mozilla Firefox\\Firefox.EXE"),
+            "firefox"
+        );
+        assert_eq!(class_from_image_path("Code"), "code");
+    }
+}
@@ -0,0 +1,114 @@
+use crate::config::paths;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Profile name of the token local `stop_it` commands connect with
+pub const CLI_PROFILE: &str = "cli";
+
+/// Tokens WebSocket clients present when connecting (`?token=...`), one per
+/// browser profile, so each can be revoked on its own and activity is
+/// attributed to the profile the daemon knows instead of one a client claims
+#[derive(Debug, Clone, Default)]
+pub struct Tokens {
+    profiles: HashMap<String, String>, // token -> profile
+}
+
+impl Tokens {
+    /// `profiles` maps profile names to tokens, as in `[server.tokens]`
+    pub fn new<'a>(profiles: impl IntoIterator<Item = (&'a String, &'a String)>) -> Self {
+        Self {
+            profiles: profiles
+                .into_iter()
+                .map(|(profile, token)| (token.clone(), profile.clone()))
+                .collect(),
+        }
+    }
+
+    /// Without tokens every connection is accepted
+    pub fn is_enabled(&self) -> bool {
+        !self.profiles.is_empty()
+    }
+
+    pub fn add(&mut self, profile: &str, token: String) {
+        self.profiles.insert(token, profile.to_string());
+    }
+
+    /// Profile of the token in a handshake's query string, `None` when
+    /// tokens are disabled
+    pub fn authenticate(&self, query: Option<&str>) -> Result<Option<String>, &'static str> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+        let token = query
+            .unwrap_or("")
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .ok_or("missing token")?;
+        match self.profiles.get(token) {
+            Some(profile) => Ok(Some(profile.clone())),
+            None => Err("unknown token"),
+        }
+    }
+}
+
+/// Origins of browser extension pages, the only ones a browser may connect
+/// from
+const EXTENSION_ORIGINS: [&str; 3] = [
+    "chrome-extension://",
+    "moz-extension://",
+    "safari-web-extension://",
+];
+
+/// Browsers send the page's `Origin` with every WebSocket handshake, so a web
+/// site the user visits can't pass for the extension. Local tools (`stop_it
+/// top`, the GNOME extension) send none.
+pub fn check_origin(origin: Option<&str>) -> Result<(), String> {
+    match origin {
+        None => Ok(()),
+        Some(origin)
+            if EXTENSION_ORIGINS
+                .iter()
+                .any(|scheme| origin.starts_with(scheme)) =>
+        {
+            Ok(())
+        }
+        Some(origin) => Err(format!("origin {} is not a browser extension", origin)),
+    }
+}
+
+/// Random token for a profile, 32 hex characters
+pub fn generate_token() -> io::Result<String> {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| io::Error::other("no random source"))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Token of the running daemon for local commands, readable by its owner only
+pub fn cli_token_file() -> PathBuf {
+    paths::runtime_dir().join("cli.token")
+}
+
+pub fn write_cli_token(token: &str) -> io::Result<()> {
+    let path = cli_token_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _ = fs::remove_file(&path);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // Elsewhere the file inherits the access of the user's profile directory
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(token.as_bytes())
+}
+
+pub fn cli_token() -> Option<String> {
+    fs::read_to_string(cli_token_file())
+        .ok()
+        .map(|token| token.trim().to_string())
+}
@@ -0,0 +1,72 @@
+use crate::ws::protocol::{
+    Badge, ClientMessage, HeartbeatMessage, ServerMessage, TabClosedMessage, TabUpdateMessage,
+    WindowClosedMessage,
+};
+use std::net::SocketAddr;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+/// What the browser extension reports, kept in one channel so closes are
+/// applied in order with the tab updates around them
+#[derive(Debug, Clone)]
+pub enum BrowserEvent {
+    TabUpdate(TabUpdateMessage),
+    TabClosed(TabClosedMessage),
+    WindowClosed(WindowClosedMessage),
+    Heartbeat(HeartbeatMessage),
+    BreakAck { id: u64 },
+}
+
+pub type ActivitySender = mpsc::UnboundedSender<BrowserEvent>;
+pub type ActivityReceiver = mpsc::UnboundedReceiver<BrowserEvent>;
+
+pub fn create_activity_channel() -> (ActivitySender, ActivityReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Daemon -> clients broadcast channel (timer state etc.)
+pub type StateSender = broadcast::Sender<ServerMessage>;
+
+pub fn create_state_channel() -> StateSender {
+    broadcast::channel(16).0
+}
+
+/// Latest toolbar badge; new clients get the current value right away
+pub type BadgeSender = watch::Sender<Option<Badge>>;
+
+pub fn create_badge_channel() -> BadgeSender {
+    watch::channel(None).0
+}
+
+/// Client requests the daemon answers (stats queries etc.), with a reply channel
+pub type RequestSender = mpsc::UnboundedSender<(ClientMessage, oneshot::Sender<ServerMessage>)>;
+pub type RequestReceiver = mpsc::UnboundedReceiver<(ClientMessage, oneshot::Sender<ServerMessage>)>;
+
+pub fn create_request_channel() -> (RequestSender, RequestReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Browser extensions connecting and going away, so the daemon can report
+/// setup problems. A connection counts as an extension once it sends a tab
+/// update or a heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionEvent {
+    ExtensionConnected(SocketAddr),
+    ExtensionDisconnected(SocketAddr),
+}
+
+pub type ConnectionSender = mpsc::UnboundedSender<ConnectionEvent>;
+pub type ConnectionReceiver = mpsc::UnboundedReceiver<ConnectionEvent>;
+
+pub fn create_connection_channel() -> (ConnectionSender, ConnectionReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Channels connecting the WebSocket server to the daemon
+#[derive(Debug, Clone)]
+pub struct DaemonChannels {
+    pub activity_tx: ActivitySender,
+    pub state_tx: StateSender,
+    pub badge_tx: BadgeSender,
+    pub request_tx: RequestSender,
+    pub connection_tx: ConnectionSender,
+}
@@ -0,0 +1,86 @@
+use crate::ws::protocol::{ClientMessage, ServerMessage, TimerState};
+use crate::ws::{auth, discovery};
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub const DEFAULT_DAEMON_URL: &str = "ws://127.0.0.1:8765";
+
+/// URL of the running daemon, following the port it advertised and with
+/// the token it left for local commands
+pub fn daemon_url() -> String {
+    let url = match discovery::advertised_port() {
+        Some(port) => format!("ws://127.0.0.1:{}", port),
+        None => DEFAULT_DAEMON_URL.to_string(),
+    };
+    match auth::cli_token() {
+        Some(token) => format!("{}/?token={}", url, token),
+        None => url,
+    }
+}
+
+/// Connection to the running daemon's WebSocket API
+pub struct DaemonClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl DaemonClient {
+    pub async fn connect(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (stream, _) = tokio_tungstenite::connect_async(url).await.map_err(|e| {
+            format!(
+                "Could not connect to daemon at {} ({}). Is it running?",
+                url.split('?').next().unwrap_or(url), // without the token
+                e
+            )
+        })?;
+        Ok(Self { stream })
+    }
+
+    pub async fn send(
+        &mut self,
+        message: &ClientMessage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string(message)?;
+        self.stream.send(Message::Text(json)).await?;
+        Ok(())
+    }
+
+    /// Wait for the next server message accepted by `pick`, skipping others
+    /// (e.g. timer state broadcasts interleaved with replies)
+    pub async fn recv_matching<T, F>(&mut self, pick: F) -> Result<T, Box<dyn std::error::Error>>
+    where
+        F: Fn(ServerMessage) -> Option<T>,
+    {
+        while let Some(msg) = self.stream.next().await {
+            if let Message::Text(text) = msg?
+                && let Ok(message) = serde_json::from_str::<ServerMessage>(&text)
+            {
+                if let ServerMessage::Error { message } = message {
+                    return Err(message.into());
+                }
+                if let Some(value) = pick(message) {
+                    return Ok(value);
+                }
+            }
+        }
+        Err("Daemon closed the connection".into())
+    }
+
+    pub async fn close(mut self) {
+        let _ = self.stream.close(None).await;
+    }
+}
+
+/// Connect to the running daemon and wait for the next timer state broadcast
+pub async fn fetch_timer_state(url: &str) -> Result<TimerState, Box<dyn std::error::Error>> {
+    let mut client = DaemonClient::connect(url).await?;
+    let state = client
+        .recv_matching(|message| match message {
+            ServerMessage::TimerState(state) => Some(state),
+            _ => None,
+        })
+        .await?;
+    client.close().await;
+    Ok(state)
+}
@@ -0,0 +1,22 @@
+use crate::config::paths;
+use std::fs;
+use std::path::PathBuf;
+
+/// File advertising the port the running daemon listens on, for when it had
+/// to fall back from the configured one
+pub fn port_file() -> PathBuf {
+    paths::runtime_dir().join("port")
+}
+
+pub fn advertise_port(port: u16) -> std::io::Result<()> {
+    let path = port_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, port.to_string())
+}
+
+/// Port advertised by the daemon, if any
+pub fn advertised_port() -> Option<u16> {
+    fs::read_to_string(port_file()).ok()?.trim().parse().ok()
+}
@@ -1 +1,10 @@
+pub mod auth;
+pub mod channels;
+#[cfg(feature = "websocket")]
+pub mod client;
+pub mod discovery;
+pub mod peer;
+pub mod protocol;
+pub mod schema;
+#[cfg(feature = "websocket")]
 pub mod websocket_server;
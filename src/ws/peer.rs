@@ -0,0 +1,63 @@
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// User the daemon runs as, where there are uids and /proc
+#[cfg(unix)]
+pub fn current_uid() -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata("/proc/self").ok().map(|meta| meta.uid())
+}
+
+#[cfg(not(unix))]
+pub fn current_uid() -> Option<u32> {
+    None
+}
+
+/// User owning the client end of a localhost TCP connection, looked up in
+/// `/proc/net/tcp{,6}`. `peer` and `local` are the addresses as seen by the
+/// server; `None` when the connection can't be found.
+pub fn peer_uid(peer: SocketAddr, local: SocketAddr) -> Option<u32> {
+    ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .find_map(|table| {
+            let content = fs::read_to_string(table).ok()?;
+            content.lines().skip(1).find_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                // sl local_address rem_address st tx:rx tr:when retrnsmt uid ...
+                let (from, to, uid) = (fields.get(1)?, fields.get(2)?, fields.get(7)?);
+                let matches = same_address(parse_address(from)?, peer)
+                    && same_address(parse_address(to)?, local);
+                matches.then(|| uid.parse().ok()).flatten()
+            })
+        })
+}
+
+/// Owner of a connection coming from another user, which must be refused.
+/// Connections whose owner can't be determined are let through.
+pub fn foreign_uid(peer: SocketAddr, local: SocketAddr) -> Option<u32> {
+    let uid = peer_uid(peer, local)?;
+    (Some(uid) != current_uid()).then_some(uid)
+}
+
+/// Parse "0100007F:1F90": the address words are in host byte order, the
+/// port is plain hex
+fn parse_address(field: &str) -> Option<SocketAddr> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let words = (0..address.len() / 8)
+        .map(|i| u32::from_str_radix(address.get(i * 8..i * 8 + 8)?, 16).ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    let ip = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Compare addresses, treating IPv4-mapped IPv6 addresses as IPv4
+fn same_address(a: SocketAddr, b: SocketAddr) -> bool {
+    let canonical = |addr: SocketAddr| (addr.ip().to_canonical(), addr.port());
+    canonical(a) == canonical(b)
+}
@@ -0,0 +1,253 @@
+use crate::daemon::trace::TraceEvent;
+use crate::pomodoro::pomodoro::TimerMode;
+use crate::stats::budget::BudgetStatus;
+use crate::storage::activity_store::CategorySource;
+use chrono::{DateTime, Local, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON protocol spoken over the WebSocket and the control
+/// socket, raised whenever a change breaks existing clients
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Messages sent by clients (browser extension, CLI)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    TabUpdate(TabUpdateMessage),
+    TabClosed(TabClosedMessage),
+    WindowClosed(WindowClosedMessage),
+    Heartbeat(HeartbeatMessage),
+    // the extension carried out the `break_start` or `break_end` with this id
+    BreakAck {
+        id: u64,
+    },
+    GetTodayStats,
+    GetHistory(HistoryRequest),
+    GetStatus, // answered with the current `TimerState`
+    Pause,     // stop the timer and tracking until `resume`
+    Resume,
+    Skip,  // end the current phase now, as if its time were up
+    Reset, // drop the current phase and start a new cycle with a work session
+    Start, // end the break and start the next work session, see `[pomodoro] manual`
+    // put the break off: the work session goes on for `minutes`, `[breaks] snooze` by default
+    Snooze {
+        #[serde(default)]
+        minutes: Option<u32>,
+    },
+    // breaks or back-to-back work blocks from now on
+    SetTimerMode {
+        mode: TimerMode,
+    },
+    // a `[categories]` rule the client just saved: apply it and re-tag today's activity
+    Categorize {
+        domain: String,
+        category: String,
+    },
+    Attach,  // control socket: stream the daemon's log lines
+    Handoff, // control socket: save state and exit so a daemon can take over
+    // control socket: stream trace events for `stop_it record`, scrubbed unless `raw`
+    Record {
+        #[serde(default)]
+        raw: bool,
+    },
+    // control socket: the latest events kept for `stop_it inspect`
+    Inspect {
+        events: usize,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TabUpdateMessage {
+    pub url: String,
+    pub title: String,
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>, // e.g., "productivity", "social", "entertainment"
+    #[serde(skip)]
+    pub profile: Option<String>, // set by the daemon from the connection's token, never by clients
+}
+
+/// Per-day history for charts, one page of days per request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryRequest {
+    #[serde(default)]
+    pub from: Option<NaiveDate>, // default: 29 days before `to`
+    #[serde(default)]
+    pub to: Option<NaiveDate>, // default: today
+    #[serde(default)]
+    pub page: u32, // from 0
+    #[serde(default = "default_page_size")]
+    pub page_size: u32, // days per page, at most 31
+    #[serde(default = "default_top_domains")]
+    pub top_domains: usize, // per day
+}
+
+fn default_page_size() -> u32 {
+    7
+}
+
+fn default_top_domains() -> usize {
+    10
+}
+
+/// The tab last reported in a `tab_update` was closed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TabClosedMessage {
+    pub domain: Option<String>,
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// The window holding the tab last reported was closed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WindowClosedMessage {
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub browser_exiting: bool, // it was the browser's last window
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// Sent by the extension every few seconds and on focus or idle changes,
+/// which is all the daemon goes by with `[extension] only`. Not answered.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HeartbeatMessage {
+    pub focused: bool, // a browser window has focus
+    #[serde(default)]
+    pub idle: bool, // the browser saw no input for a while, or the screen is locked
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// Acknowledgement for a `tab_update`, `tab_closed` or `window_closed`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WebSocketResponse {
+    pub success: bool,
+    pub message: Option<String>,
+}
+
+/// Messages pushed from the daemon to clients
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    TimerState(TimerState),
+    TodayStats(TodayStats),
+    History(HistoryPage),
+    Badge(Badge),
+    SessionSummary(SessionSummary),
+    // after a `categorize` request, so badges and colors follow at once
+    CategoryChanged { domain: String, category: String },
+    // `[breaks] overlay`: pause media and grey out pages until `break_end`;
+    // both are sent again until a `break_ack` comes
+    BreakStart { id: u64, ends_at: DateTime<Local> },
+    BreakEnd { id: u64 },
+    LogLine { line: String },
+    Trace { event: TraceEvent },
+    Events { events: Vec<TraceEvent> },
+    Ack { message: String },
+    Error { message: String },
+}
+
+/// Full timer state, broadcast every second
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TimerState {
+    pub mode: String, // "WORK", "BREAK" or "LONG_BREAK"
+    pub remaining_seconds: i64,
+    pub duration_seconds: i64, // full length of the current session
+    pub pomodoros_today: u32,
+    #[serde(default)]
+    pub cycle: u32, // work sessions completed in the current cycle
+    #[serde(default)]
+    pub cycle_length: u32, // work sessions before a long break, 0 without long breaks
+    pub daily_goal: u32, // 0 on off-days
+    pub current_domain: Option<String>,
+    pub current_category: Option<String>,
+    pub category_source: Option<CategorySource>,
+    pub block: Option<String>, // label of the planned block in progress
+    pub budgets: Vec<BudgetStatus>,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub waiting: bool, // the break ran out and waits for `start`
+    #[serde(default)]
+    pub continuous: bool, // no breaks, see `stop_it mode`
+    #[serde(default)]
+    pub pace_seconds: Option<i64>, // focus time ahead of a typical day by now, negative when behind
+    #[serde(default)]
+    pub extensions: u32, // browser extensions connected to the WebSocket server
+}
+
+impl TimerState {
+    /// Minute-resolution summary for toolbar badges
+    pub fn badge(&self) -> Badge {
+        Badge {
+            text: format!("{}m", (self.remaining_seconds + 59) / 60),
+            color: if self.paused {
+                "#888"
+            } else if self.mode == "WORK" {
+                "#d33"
+            } else {
+                "#2a2"
+            }
+            .to_string(),
+        }
+    }
+}
+
+/// Compact toolbar badge, only sent when it changes (about once a minute)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Badge {
+    pub text: String,
+    pub color: String,
+}
+
+/// Reply to `get_today_stats`: time per domain for the current day
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TodayStats {
+    pub total_seconds: i64,
+    pub domains: Vec<DomainTotal>,
+}
+
+/// Reply to `get_history`: one page of days, oldest first. Ask for
+/// `page + 1` while it is below `pages`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryPage {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub page: u32,
+    pub pages: u32,
+    pub days: Vec<DayHistory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DayHistory {
+    pub date: NaiveDate,
+    pub total_seconds: i64,
+    pub pomodoros: u32,
+    pub categories: Vec<CategoryTotal>,
+    pub domains: Vec<DomainTotal>, // the day's top domains
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CategoryTotal {
+    pub category: String,
+    pub seconds: i64,
+}
+
+/// Recap of a finished work session, pushed when the break starts
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionSummary {
+    pub duration_seconds: i64,
+    pub top_domains: Vec<DomainTotal>,
+    pub focus_percent: u32, // share of tracked time not spent on distractions
+    pub idle_seconds: i64,  // untracked because there was no input (with [input] sample)
+    pub pomodoros_today: u32,
+    pub daily_goal: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DomainTotal {
+    pub domain: String,
+    pub seconds: i64,
+}
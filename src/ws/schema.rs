@@ -0,0 +1,24 @@
+use crate::ws::protocol::{ClientMessage, PROTOCOL_VERSION, ServerMessage, WebSocketResponse};
+use schemars::JsonSchema;
+use schemars::generate::SchemaSettings;
+use serde_json::{Value, json};
+
+/// JSON Schemas of the protocol this build speaks, for `stop_it schema` and
+/// `GET /schema`: what clients may send (as the daemon reads it) and what
+/// they receive (as the daemon writes it), with the protocol version
+pub fn protocol_schema() -> Value {
+    json!({
+        "protocol_version": PROTOCOL_VERSION,
+        "daemon_version": env!("CARGO_PKG_VERSION"),
+        "client_message": schema_for::<ClientMessage>(SchemaSettings::default().for_deserialize()),
+        "server_message": schema_for::<ServerMessage>(SchemaSettings::default().for_serialize()),
+        "response": schema_for::<WebSocketResponse>(SchemaSettings::default().for_serialize()),
+    })
+}
+
+fn schema_for<T: JsonSchema>(settings: SchemaSettings) -> Value {
+    settings
+        .into_generator()
+        .into_root_schema_for::<T>()
+        .to_value()
+}
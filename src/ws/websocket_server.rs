@@ -1,53 +1,129 @@
+use crate::daemon::{log, telemetry};
+use crate::ws::auth::{Tokens, check_origin};
+use crate::ws::channels::{ActivitySender, BrowserEvent, ConnectionEvent, DaemonChannels};
+use crate::ws::peer;
+use crate::ws::protocol::{ClientMessage, ServerMessage, TabUpdateMessage, WebSocketResponse};
+use crate::ws::schema::protocol_schema;
+use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, oneshot};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::http::header::ORIGIN;
 use tokio_tungstenite::tungstenite::protocol::Message;
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct TabUpdateMessage {
-    #[serde(rename = "type")]
-    // pub msg_type: String,
-    pub url: String,
-    pub title: String,
-    pub domain: Option<String>,
-    //TODO: add category by user choice, future impl with ui? by default impl base fields
-    #[serde(default)]
-    pub category: Option<String>, // e.g., "productivity", "social", "entertainment"
-}
-
-#[derive(Debug, Serialize)]
-pub struct WebSocketResponse {
-    pub success: bool,
-    pub message: Option<String>,
-}
+type WsSender = SplitSink<WebSocketStream<TcpStream>, Message>;
 
-pub type ActivitySender = mpsc::UnboundedSender<TabUpdateMessage>;
-pub type ActivityReceiver = mpsc::UnboundedReceiver<TabUpdateMessage>;
+/// Bind the WebSocket server to `port` on localhost, trying up to
+/// `fallback_ports` following ports when it is taken
+pub async fn bind_websocket_server(
+    port: u16,
+    fallback_ports: u16,
+) -> Result<TcpListener, Box<dyn std::error::Error>> {
+    let last = port.saturating_add(fallback_ports);
+    for candidate in port..=last {
+        match TcpListener::bind((Ipv4Addr::LOCALHOST, candidate)).await {
+            Ok(listener) => {
+                if candidate != port {
+                    log::print(format!(
+                        "Port {} is in use, using {} instead",
+                        port, candidate
+                    ));
+                }
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == ErrorKind::AddrInUse => continue,
+            Err(e) => return Err(format!("Cannot listen on 127.0.0.1:{}: {}", candidate, e).into()),
+        }
+    }
 
-pub fn create_activity_channel() -> (ActivitySender, ActivityReceiver) {
-    mpsc::unbounded_channel()
+    let ports = if last == port {
+        format!("Port {} is", port)
+    } else {
+        format!("Ports {}-{} are", port, last)
+    };
+    Err(format!(
+        "{} already in use. Is another stop_it daemon running? \
+         Check with `ss -tlnp | grep {}`, or set [server] port / fallback_ports in the config.",
+        ports, port
+    )
+    .into())
 }
 
 pub async fn start_websocket_server(
-    addr: SocketAddr,
-    activity_tx: ActivitySender,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let listener = TcpListener::bind(&addr).await?;
-    println!("WebSocket server listening on: {}", addr);
+    listener: TcpListener,
+    channels: DaemonChannels,
+    tokens: Tokens,
+) {
+    if let Ok(addr) = listener.local_addr() {
+        log::print(format!("WebSocket server listening on: {}", addr));
+    }
 
     while let Ok((stream, peer_addr)) = listener.accept().await {
-        println!("New WebSocket connection from: {}", peer_addr);
-        let tx = activity_tx.clone();
-        tokio::spawn(handle_connection(stream, peer_addr, tx));
+        // Another user's extension may scan our port on a shared machine
+        if let Ok(local) = stream.local_addr()
+            && let Some(uid) = peer::foreign_uid(peer_addr, local)
+        {
+            eprintln!(
+                "Refused connection from {}: it belongs to uid {}",
+                peer_addr, uid
+            );
+            continue;
+        }
+        log::print(format!("New WebSocket connection from: {}", peer_addr));
+        tokio::spawn(handle_connection(
+            stream,
+            peer_addr,
+            channels.clone(),
+            tokens.clone(),
+        ));
     }
-
-    Ok(())
 }
 
-async fn handle_connection(stream: TcpStream, peer_addr: SocketAddr, activity_tx: ActivitySender) {
-    let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+async fn handle_connection(
+    mut stream: TcpStream,
+    peer_addr: SocketAddr,
+    channels: DaemonChannels,
+    tokens: Tokens,
+) {
+    if is_schema_request(&stream).await {
+        if let Err(e) = serve_schema(&mut stream).await {
+            eprintln!("Failed to send the schema to {}: {}", peer_addr, e);
+        }
+        return;
+    }
+    let mut state_rx = channels.state_tx.subscribe();
+    let mut badge_rx = channels.badge_tx.subscribe();
+    badge_rx.mark_changed(); // send the current badge right after the handshake
+    let mut profile = None;
+    #[allow(clippy::result_large_err)] // the signature tungstenite expects
+    let authenticate = |request: &Request, response: Response| {
+        let origin = request
+            .headers()
+            .get(ORIGIN)
+            .map(|origin| origin.to_str().unwrap_or("(not text)"));
+        let (reason, status) = match check_origin(origin) {
+            Err(reason) => (reason, StatusCode::FORBIDDEN),
+            Ok(()) => match tokens.authenticate(request.uri().query()) {
+                Ok(authenticated) => {
+                    profile = authenticated;
+                    return Ok(response);
+                }
+                Err(reason) => (reason.to_string(), StatusCode::UNAUTHORIZED),
+            },
+        };
+        eprintln!("Rejected WebSocket client {}: {}", peer_addr, reason);
+        let mut rejection = ErrorResponse::new(Some(format!("Rejected: {}", reason)));
+        *rejection.status_mut() = status;
+        Err(rejection)
+    };
+    let ws_stream = match tokio_tungstenite::accept_hdr_async(stream, authenticate).await {
         Ok(ws) => ws,
         Err(e) => {
             eprintln!("WebSocket handshake failed with {}: {}", peer_addr, e);
@@ -55,55 +131,161 @@ async fn handle_connection(stream: TcpStream, peer_addr: SocketAddr, activity_tx
         }
     };
 
-    println!("WebSocket handshake completed with {}", peer_addr);
+    match &profile {
+        Some(profile) => log::print(format!(
+            "WebSocket handshake completed with {} (profile {})",
+            peer_addr, profile
+        )),
+        None => log::print(format!("WebSocket handshake completed with {}", peer_addr)),
+    }
 
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    let mut is_extension = false;
 
-    while let Some(msg) = ws_receiver.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                match serde_json::from_str::<TabUpdateMessage>(&text) {
-                    Ok(tab_message) => {
-                        println!(
-                            "[WebSocket] Received: url={}, title={}, domain={:?}, category={:?}",
-                            tab_message.url,
-                            tab_message.title,
-                            tab_message.domain,
-                            tab_message.category
-                        );
-
-                        // Send to activity tracker
-                        if let Err(e) = activity_tx.send(tab_message) {
-                            eprintln!("Failed to send activity message: {}", e);
+    loop {
+        let msg = tokio::select! {
+            msg = ws_receiver.next() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            state = state_rx.recv() => {
+                match state {
+                    Ok(state) => {
+                        if let Err(e) = send_json(&mut ws_sender, &state).await {
+                            eprintln!("Failed to send state to {}: {}", peer_addr, e);
+                            break;
                         }
+                    }
+                    // Slow client: skip stale states, the next one supersedes them
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+                continue;
+            }
+            changed = badge_rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let badge = badge_rx.borrow_and_update().clone();
+                if let Some(badge) = badge
+                    && let Err(e) = send_json(&mut ws_sender, &ServerMessage::Badge(badge)).await
+                {
+                    eprintln!("Failed to send badge to {}: {}", peer_addr, e);
+                    break;
+                }
+                continue;
+            }
+        };
 
-                        // Send success response
-                        let response = WebSocketResponse {
-                            success: true,
-                            message: Some("Message received".to_string()),
-                        };
-
-                        if let Ok(response_json) = serde_json::to_string(&response) {
-                            if let Err(e) = ws_sender.send(Message::Text(response_json)).await {
-                                eprintln!("Failed to send WebSocket response: {}", e);
-                                break;
-                            }
-                        }
+        match msg {
+            Ok(Message::Text(text)) => match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::TabUpdate(mut tab_message)) => {
+                    tab_message.profile = profile.clone();
+                    if !is_extension {
+                        is_extension = true;
+                        let _ = channels
+                            .connection_tx
+                            .send(ConnectionEvent::ExtensionConnected(peer_addr));
                     }
-                    Err(e) => {
-                        eprintln!("Failed to parse message: {}", e);
-                        let response = WebSocketResponse {
-                            success: false,
-                            message: Some(format!("Parse error: {}", e)),
-                        };
-                        if let Ok(response_json) = serde_json::to_string(&response) {
-                            let _ = ws_sender.send(Message::Text(response_json)).await;
-                        }
+                    if let Err(e) =
+                        handle_tab_update(tab_message, &channels.activity_tx, &mut ws_sender).await
+                    {
+                        eprintln!("Failed to send WebSocket response: {}", e);
+                        break;
                     }
                 }
-            }
+                Ok(ClientMessage::Heartbeat(mut heartbeat)) => {
+                    heartbeat.profile = profile.clone();
+                    if !is_extension {
+                        is_extension = true;
+                        let _ = channels
+                            .connection_tx
+                            .send(ConnectionEvent::ExtensionConnected(peer_addr));
+                    }
+                    // Every few seconds: neither logged nor answered
+                    if let Err(e) = channels
+                        .activity_tx
+                        .send(BrowserEvent::Heartbeat(heartbeat))
+                    {
+                        eprintln!("Failed to send activity message: {}", e);
+                    }
+                }
+                Ok(ClientMessage::BreakAck { id }) => {
+                    if let Err(e) = channels.activity_tx.send(BrowserEvent::BreakAck { id }) {
+                        eprintln!("Failed to send activity message: {}", e);
+                    }
+                }
+                Ok(ClientMessage::TabClosed(mut closed)) => {
+                    closed.profile = profile.clone();
+                    if let Err(e) = forward_close(
+                        BrowserEvent::TabClosed(closed),
+                        &channels.activity_tx,
+                        &mut ws_sender,
+                    )
+                    .await
+                    {
+                        eprintln!("Failed to send WebSocket response: {}", e);
+                        break;
+                    }
+                }
+                Ok(ClientMessage::WindowClosed(mut closed)) => {
+                    closed.profile = profile.clone();
+                    if let Err(e) = forward_close(
+                        BrowserEvent::WindowClosed(closed),
+                        &channels.activity_tx,
+                        &mut ws_sender,
+                    )
+                    .await
+                    {
+                        eprintln!("Failed to send WebSocket response: {}", e);
+                        break;
+                    }
+                }
+                Ok(
+                    ClientMessage::Attach
+                    | ClientMessage::Handoff
+                    | ClientMessage::Record { .. }
+                    | ClientMessage::Inspect { .. },
+                ) => {
+                    let reply = ServerMessage::Error {
+                        message: "Only accepted on the control socket".to_string(),
+                    };
+                    if let Err(e) = send_json(&mut ws_sender, &reply).await {
+                        eprintln!("Failed to send WebSocket response: {}", e);
+                        break;
+                    }
+                }
+                // Queries and commands reach the daemon only from clients the
+                // handshake let in: extensions by their origin (and token with
+                // `[server.tokens]`) and local tools
+                Ok(request) => {
+                    let mut span = telemetry::async_span("ws.request");
+                    let (reply_tx, reply_rx) = oneshot::channel();
+                    let reply = match channels.request_tx.send((request, reply_tx)) {
+                        Ok(()) => reply_rx.await.unwrap_or_else(|_| ServerMessage::Error {
+                            message: "Daemon did not answer".to_string(),
+                        }),
+                        Err(_) => ServerMessage::Error {
+                            message: "Daemon is shutting down".to_string(),
+                        },
+                    };
+                    if let Err(e) = send_json(&mut ws_sender, &reply).await {
+                        eprintln!("Failed to send WebSocket response: {}", e);
+                        span.fail("response not delivered");
+                        break;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to parse message: {}", e);
+                    let response = WebSocketResponse {
+                        success: false,
+                        message: Some(format!("Parse error: {}", e)),
+                    };
+                    let _ = send_json(&mut ws_sender, &response).await;
+                }
+            },
             Ok(Message::Close(_)) => {
-                println!("WebSocket connection closed by {}", peer_addr);
+                log::print(format!("WebSocket connection closed by {}", peer_addr));
                 break;
             }
             Ok(Message::Ping(data)) => {
@@ -120,5 +302,107 @@ async fn handle_connection(stream: TcpStream, peer_addr: SocketAddr, activity_tx
         }
     }
 
-    println!("WebSocket connection with {} terminated", peer_addr);
+    log::print(format!(
+        "WebSocket connection with {} terminated",
+        peer_addr
+    ));
+    if is_extension {
+        let _ = channels
+            .connection_tx
+            .send(ConnectionEvent::ExtensionDisconnected(peer_addr));
+    }
+}
+
+async fn handle_tab_update(
+    tab_message: TabUpdateMessage,
+    activity_tx: &ActivitySender,
+    ws_sender: &mut WsSender,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::print(format!(
+        "[WebSocket] Received: url={}, title={}, domain={:?}, category={:?}",
+        tab_message.url, tab_message.title, tab_message.domain, tab_message.category
+    ));
+
+    // Send to activity tracker
+    let mut span = telemetry::async_span("ws.tab_update");
+    if let Err(e) = activity_tx.send(BrowserEvent::TabUpdate(tab_message)) {
+        eprintln!("Failed to send activity message: {}", e);
+        span.fail("activity message dropped");
+    }
+
+    // Send success response
+    let response = WebSocketResponse {
+        success: true,
+        message: Some("Message received".to_string()),
+    };
+    send_json(ws_sender, &response).await
+}
+
+/// Pass a tab or window close on to the tracker
+async fn forward_close(
+    event: BrowserEvent,
+    activity_tx: &ActivitySender,
+    ws_sender: &mut WsSender,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log::print(format!("[WebSocket] Received: {:?}", event));
+    if let Err(e) = activity_tx.send(event) {
+        eprintln!("Failed to send activity message: {}", e);
+    }
+
+    let response = WebSocketResponse {
+        success: true,
+        message: Some("Message received".to_string()),
+    };
+    send_json(ws_sender, &response).await
+}
+
+/// Whether the connection asks for `GET /schema` over plain HTTP rather
+/// than a WebSocket handshake. Only peeks, so the handshake still sees the
+/// whole request otherwise.
+async fn is_schema_request(stream: &TcpStream) -> bool {
+    const REQUEST: &[u8] = b"GET /schema";
+    let mut start = [0u8; REQUEST.len() + 1];
+    match stream.peek(&mut start).await {
+        Ok(read) if read == start.len() => {
+            start.starts_with(REQUEST) && matches!(start[REQUEST.len()], b' ' | b'?')
+        }
+        _ => false,
+    }
+}
+
+/// Answer with the protocol's JSON Schemas and close. No token needed: they
+/// describe messages, not activity.
+async fn serve_schema(stream: &mut TcpStream) -> Result<(), Box<dyn std::error::Error>> {
+    // Read the request head, bounded, so the client sees a complete exchange
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") && head.len() < 16 * 1024 {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let body = serde_json::to_string(&protocol_schema())?;
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn send_json<T: Serialize>(
+    ws_sender: &mut WsSender,
+    value: &T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string(value)?;
+    ws_sender.send(Message::Text(json)).await?;
+    Ok(())
 }
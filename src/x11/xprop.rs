@@ -0,0 +1,104 @@
+use crate::window::provider::ActiveWindow;
+
+/// Whether we are running inside an X11 session rather than a Wayland one
+/// (where `DISPLAY` may point at XWayland)
+pub fn is_available() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_none() && std::env::var_os("DISPLAY").is_some()
+}
+
+/// The window ID in `xprop -root _NET_ACTIVE_WINDOW` output, e.g.
+/// `_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007`; `None` when no window
+/// has focus
+pub fn parse_active_window_id(output: &str) -> Option<String> {
+    let id = output.rsplit("# ").next()?.split(',').next()?.trim();
+    if !id.starts_with("0x") || u64::from_str_radix(&id[2..], 16).ok()? == 0 {
+        return None;
+    }
+    Some(id.to_string())
+}
+
+/// The window in `xprop -id <id> WM_CLASS _NET_WM_NAME WM_NAME` output. The
+/// class is the second `WM_CLASS` string; `_NET_WM_NAME` (UTF-8) wins over the
+/// legacy `WM_NAME`.
+pub fn parse_window(output: &str) -> ActiveWindow {
+    let mut class = String::new();
+    let mut net_title = None;
+    let mut title = String::new();
+    for line in output.lines() {
+        let Some((property, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let strings = quoted_strings(value);
+        match property.split('(').next() {
+            Some("WM_CLASS") => class = strings.last().cloned().unwrap_or_default(),
+            Some("_NET_WM_NAME") => net_title = strings.into_iter().next(),
+            Some("WM_NAME") => title = strings.into_iter().next().unwrap_or_default(),
+            _ => {}
+        }
+    }
+    ActiveWindow {
+        class,
+        title: net_title.unwrap_or(title),
+    }
+}
+
+/// `"kitty", "kitty"` -> `["kitty", "kitty"]`, with `\"` and `\\` unescaped
+fn quoted_strings(value: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (&mut current, c) {
+            (None, '"') => current = Some(String::new()),
+            (None, _) => {}
+            (Some(string), '\\') => string.extend(chars.next()),
+            (Some(_), '"') => strings.extend(current.take()),
+            (Some(string), c) => string.push(c),
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xprop_output_yields_the_focused_window() {
+        assert_eq!(
+            parse_active_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007\n"),
+            Some("0x3a00007".to_string())
+        );
+        // Some window managers report a second ID, or none while the desktop has focus
+        assert_eq!(
+            parse_active_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x1c00003, 0x0"),
+            Some("0x1c00003".to_string())
+        );
+        assert_eq!(
+            parse_active_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0"),
+            None
+        );
+        assert_eq!(
+            parse_active_window_id("_NET_ACTIVE_WINDOW:  not found."),
+            None
+        );
+
+        let window = parse_window(
+            "WM_CLASS(STRING) = \"Navigator\", \"firefox\"\n\
+             _NET_WM_NAME(UTF8_STRING) = \"\\\"Rust\\\" — Mozilla Firefox\"\n\
+             WM_NAME(STRING) = \"Rust - Mozilla Firefox\"\n",
+        );
+        assert_eq!(window.class, "firefox");
+        assert_eq!(window.title, "\"Rust\" — Mozilla Firefox");
+        // Older clients only set the legacy name
+        let window = parse_window(
+            "WM_CLASS(STRING) = \"xterm\", \"XTerm\"\n\
+             _NET_WM_NAME:  not found.\n\
+             WM_NAME(STRING) = \"vim notes.txt\"\n",
+        );
+        assert_eq!(
+            (window.class.as_str(), window.title.as_str()),
+            ("XTerm", "vim notes.txt")
+        );
+    }
+}
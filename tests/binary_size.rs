@@ -0,0 +1,48 @@
+//! Keeps the minimal build lean. Only a release build with nothing but the
+//! `minimal` marker feature is measured:
+//! `cargo test --release --no-default-features --features minimal --test binary_size`
+
+/// Stripped release binary with the tracker, Pomodoro timer and
+/// notifications only: 8.2 MiB when the budget was set
+const MINIMAL_BUDGET: u64 = 17 * 1024 * 1024 / 2;
+
+#[test]
+#[cfg_attr(
+    not(feature = "minimal"),
+    ignore = "run with `--release --no-default-features --features minimal`"
+)]
+fn minimal_release_build_fits_its_budget() {
+    if cfg!(debug_assertions) {
+        panic!("the budget is for release builds, add `--release`");
+    }
+    let enabled: Vec<&str> = [
+        ("websocket", cfg!(feature = "websocket")),
+        ("title-fallback", cfg!(feature = "title-fallback")),
+        ("dbus", cfg!(feature = "dbus")),
+        ("webhooks", cfg!(feature = "webhooks")),
+        ("remote-config", cfg!(feature = "remote-config")),
+        ("share", cfg!(feature = "share")),
+        ("gzip", cfg!(feature = "gzip")),
+        ("postgres", cfg!(feature = "postgres")),
+        ("otel", cfg!(feature = "otel")),
+        ("windows-focus", cfg!(feature = "windows-focus")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature)
+    .collect();
+    assert!(
+        enabled.is_empty(),
+        "the minimal build leaves out {}, add `--no-default-features`",
+        enabled.join(", ")
+    );
+    let size = std::fs::metadata(env!("CARGO_BIN_EXE_stop_it"))
+        .unwrap()
+        .len();
+    assert!(
+        size <= MINIMAL_BUDGET,
+        "minimal build is {:.1} MiB, over its {:.1} MiB budget",
+        size as f64 / 1048576.0,
+        MINIMAL_BUDGET as f64 / 1048576.0
+    );
+}
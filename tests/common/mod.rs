@@ -0,0 +1,281 @@
+//! Test harness shared by the daemon tests: a daemon on a simulated clock
+//! with mock windows and input, and helpers to drive it.
+#![allow(dead_code)]
+
+use chrono::{Duration, Local, TimeZone};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use stop_it::config::settings::Config;
+use stop_it::control::socket::{bind_control_socket, start_control_socket};
+use stop_it::daemon::clock::ManualClock;
+use stop_it::daemon::engine::StopItEngine;
+use stop_it::daemon::events::EventStream;
+use stop_it::daemon::log::Log;
+use stop_it::daemon::status_file::StatusFile;
+use stop_it::daemon::trace::Trace;
+use stop_it::daemon::tracker::DomainTracker;
+use stop_it::input::activity::InputActivity;
+use stop_it::notifications::manager::NotificationManager;
+use stop_it::pomodoro::plan::PlanWatcher;
+use stop_it::pomodoro::pomodoro::PomodoroMode;
+use stop_it::stats::aggregator::{Aggregator, TimeRange};
+use stop_it::storage::activity_store::ActivitySegment;
+use stop_it::storage::backend::Storage;
+use stop_it::storage::jsonl_backend::JsonlStorage;
+use stop_it::window::provider::{ActiveWindow, WindowAction, WindowProvider};
+#[cfg(feature = "websocket")]
+use stop_it::ws::auth::Tokens;
+use stop_it::ws::protocol::{ClientMessage, TabUpdateMessage};
+#[cfg(feature = "websocket")]
+use stop_it::ws::websocket_server;
+
+/// Window provider whose focused window is set by the test, recording the
+/// actions applied to it
+#[derive(Clone, Default)]
+pub struct MockWindows(
+    Arc<Mutex<Option<ActiveWindow>>>,
+    Arc<Mutex<Vec<WindowAction>>>,
+);
+
+impl MockWindows {
+    pub fn focus(&self, class: &str, title: &str) {
+        *self.0.lock().unwrap() = Some(ActiveWindow {
+            class: class.to_string(),
+            title: title.to_string(),
+        });
+    }
+
+    pub fn actions(&self) -> Vec<WindowAction> {
+        self.1.lock().unwrap().clone()
+    }
+}
+impl WindowProvider for MockWindows {
+    fn active_window(&mut self) -> Option<ActiveWindow> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn act_on_focused(&mut self, action: WindowAction) -> Result<(), String> {
+        self.1.lock().unwrap().push(action);
+        Ok(())
+    }
+}
+
+/// Input sampler whose event counts are set by the test; `None` until then
+#[derive(Clone, Default)]
+pub struct MockInput(Arc<Mutex<Option<u64>>>);
+
+impl MockInput {
+    pub fn set(&self, events: u64) {
+        *self.0.lock().unwrap() = Some(events);
+    }
+}
+impl InputActivity for MockInput {
+    fn events(&mut self) -> Option<u64> {
+        *self.0.lock().unwrap()
+    }
+}
+
+pub struct Harness {
+    pub daemon: StopItEngine,
+    pub clock: Arc<ManualClock>,
+    pub notifier: Arc<Mutex<NotificationManager>>,
+    pub log: Log,
+    pub events: EventStream,
+    pub windows: MockWindows,
+    pub input: MockInput,
+    pub storage_path: PathBuf,
+    pub plans_path: PathBuf,
+    pub control_path: PathBuf,
+    pub phase_path: PathBuf,
+    pub status_path: PathBuf,
+    #[cfg(feature = "websocket")]
+    pub url: String, // the browser extension's server
+}
+
+impl Harness {
+    pub async fn start(name: &str) -> Self {
+        Self::with_config(name, Config::default()).await
+    }
+
+    pub async fn with_config(name: &str, mut config: Config) -> Self {
+        let dir = std::env::temp_dir().join(format!("stop_it-e2e-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage_path = dir.join("activity.jsonl");
+        let plans_path = dir.join("plans.json");
+        let control_path = dir.join("run").join("control.sock");
+        let phase_path = dir.join("phase.json");
+        let status_path = dir.join("run").join("status.json");
+
+        config.storage.journal = false;
+        config.hyprland.track_focus = true;
+
+        // A Wednesday morning, far from the day boundary
+        let start = Local.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        let log = Log::capturing();
+        let storage = Box::new(JsonlStorage::open(&storage_path).unwrap());
+        let trace = Trace::new().with_history(config.log.recent_events);
+        let tracker = DomainTracker::new(&config, storage, clock.clone(), log.clone())
+            .unwrap()
+            .with_trace(trace.clone())
+            .with_plan(PlanWatcher::new(plans_path.clone()))
+            .with_saved_phase(phase_path.clone());
+
+        #[cfg(feature = "websocket")]
+        let tokens = Tokens::new(&config.server.tokens);
+        let notifier = Arc::new(Mutex::new(NotificationManager::silent()));
+        let windows = MockWindows::default();
+        let input = MockInput::default();
+        let events = EventStream::capturing();
+        let mut daemon = StopItEngine::new(
+            config,
+            tracker,
+            Arc::clone(&notifier),
+            Box::new(windows.clone()),
+            log.clone(),
+        )
+        .with_input(Box::new(input.clone()))
+        .with_events(events.clone())
+        .with_trace(trace.clone())
+        .with_status_file(StatusFile::new(status_path.clone()))
+        .with_handoff();
+        daemon.spawn_handlers();
+        let channels = daemon.channels().clone();
+
+        let control = bind_control_socket(&control_path).await.unwrap();
+        tokio::spawn(start_control_socket(
+            control,
+            channels.request_tx.clone(),
+            log.clone(),
+            trace.clone(),
+        ));
+        #[cfg(feature = "websocket")]
+        let url = {
+            let listener = websocket_server::bind_websocket_server(0, 0).await.unwrap();
+            let url = format!("ws://{}", listener.local_addr().unwrap());
+            tokio::spawn(websocket_server::start_websocket_server(
+                listener, channels, tokens,
+            ));
+            url
+        };
+
+        Self {
+            daemon,
+            clock,
+            notifier,
+            log,
+            events,
+            windows,
+            input,
+            storage_path,
+            plans_path,
+            control_path,
+            phase_path,
+            status_path,
+            #[cfg(feature = "websocket")]
+            url,
+        }
+    }
+
+    /// Advance the simulated clock by `seconds`, one daemon step per second
+    pub fn run_for(&mut self, seconds: i64) {
+        for _ in 0..seconds {
+            self.clock.advance(Duration::seconds(1));
+            self.daemon.step();
+        }
+    }
+
+    pub fn mode(&self) -> PomodoroMode {
+        self.daemon.tracker().lock().unwrap().mode()
+    }
+
+    pub async fn wait_for_domain(&self, domain: &str) {
+        for _ in 0..200 {
+            if self.daemon.tracker().lock().unwrap().current_domain() == Some(domain) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("daemon never switched to {}", domain);
+    }
+
+    pub async fn wait_for_no_domain(&self) {
+        for _ in 0..200 {
+            if self
+                .daemon
+                .tracker()
+                .lock()
+                .unwrap()
+                .current_domain()
+                .is_none()
+            {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("daemon kept crediting a domain");
+    }
+
+    pub fn stored_seconds(&self, domain: &str) -> i64 {
+        let store = JsonlStorage::open_read_only(&self.storage_path)
+            .unwrap()
+            .load(&everything())
+            .unwrap();
+        Aggregator::new(&store)
+            .top_domains(&everything(), usize::MAX)
+            .into_iter()
+            .find(|(name, _)| name == domain)
+            .map_or(0, |(_, seconds)| seconds)
+    }
+
+    pub fn stored_segments(&self) -> Vec<ActivitySegment> {
+        let store = JsonlStorage::open_read_only(&self.storage_path)
+            .unwrap()
+            .load(&everything())
+            .unwrap();
+        store.segments().to_vec()
+    }
+
+    pub fn stored_pomodoros(&self) -> Vec<PomodoroMode> {
+        let store = JsonlStorage::open_read_only(&self.storage_path)
+            .unwrap()
+            .load(&everything())
+            .unwrap();
+        store.pomodoros().iter().map(|record| record.mode).collect()
+    }
+}
+
+pub fn everything() -> TimeRange {
+    TimeRange::new(
+        Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+        Local.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+    )
+}
+
+pub fn tab(domain: &str) -> ClientMessage {
+    categorized_tab(domain, None)
+}
+
+pub fn categorized_tab(domain: &str, category: Option<&str>) -> ClientMessage {
+    ClientMessage::TabUpdate(TabUpdateMessage {
+        url: format!("https://{}/", domain),
+        title: domain.to_string(),
+        domain: Some(domain.to_string()),
+        category: category.map(str::to_string),
+        profile: None,
+    })
+}
+
+/// Step the clock second by second until the timer is in `mode`, for
+/// requests handled on the next step
+pub async fn run_until_mode(harness: &mut Harness, mode: PomodoroMode) {
+    for _ in 0..100 {
+        if harness.mode() == mode {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        harness.run_for(1);
+    }
+    panic!("still in {:?}, expected {:?}", harness.mode(), mode);
+}
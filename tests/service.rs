@@ -0,0 +1,177 @@
+//! The `stop_it` binary run as a service: readiness, the watchdog and
+//! per-session runtime files.
+
+use chrono::TimeZone;
+use chrono::{Duration, Local};
+use std::path::PathBuf;
+use stop_it::daemon::systemd::{ServiceNotifier, Watchdog};
+use stop_it::pomodoro::pomodoro::PomodoroMode;
+use stop_it::stats::aggregator::TimeRange;
+use stop_it::storage::activity_store::PomodoroRecord;
+use stop_it::storage::backend::Storage;
+use stop_it::storage::sqlite_backend::SqliteStorage;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn wayland_sessions_share_the_history_but_not_their_sockets() {
+    use std::os::unix::net::UnixDatagram;
+
+    let dir = std::env::temp_dir().join(format!("stop_it-e2e-session-db-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("config/stop_it")).unwrap();
+    std::fs::write(
+        dir.join("config/stop_it/config.toml"),
+        "[server]\nport = 0\n\n[log]\nfiles = false\n",
+    )
+    .unwrap();
+    let data = dir.join("data/stop_it");
+    // History recorded before either session started
+    let start = Local.with_ymd_and_hms(2025, 3, 12, 9, 0, 0).unwrap();
+    let mut shared = SqliteStorage::open(&data.join("activity.db")).unwrap();
+    shared
+        .save_pomodoro(&PomodoroRecord {
+            mode: PomodoroMode::Work,
+            start,
+            end: start + Duration::minutes(25),
+            abandoned: false,
+            label: None,
+        })
+        .unwrap();
+    drop(shared);
+
+    // Started once ready, which is after the database is open
+    let start_daemon = |session: &str| {
+        let notify_path = dir.join(format!("notify-{}.sock", session));
+        let notify = UnixDatagram::bind(&notify_path).unwrap();
+        notify
+            .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+            .unwrap();
+        let daemon = std::process::Command::new(env!("CARGO_BIN_EXE_stop_it"))
+            .arg("daemon")
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", &dir)
+            .env("XDG_CONFIG_HOME", dir.join("config"))
+            .env("XDG_DATA_HOME", dir.join("data"))
+            .env("XDG_RUNTIME_DIR", dir.join("run"))
+            .env("WAYLAND_DISPLAY", session)
+            .env("NOTIFY_SOCKET", &notify_path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut buffer = [0; 256];
+        let n = notify
+            .recv(&mut buffer)
+            .expect("the daemon never got ready");
+        assert_eq!(&buffer[..n], b"READY=1");
+        daemon
+    };
+    let pomodoros = |path: &PathBuf| {
+        SqliteStorage::open_read_only(path)
+            .unwrap()
+            .load(&TimeRange::all())
+            .unwrap()
+            .pomodoros()
+            .len()
+    };
+
+    // Each session's daemon has its own control socket, for the CLI running
+    // in that session, and both keep the one database
+    let mut first = start_daemon("wayland-1");
+    let mut second = start_daemon("wayland-2");
+    for session in ["wayland-1", "wayland-2"] {
+        let socket = dir.join("run/stop_it").join(session).join("control.sock");
+        assert!(socket.exists(), "no {}", socket.display());
+    }
+    for daemon in [&mut first, &mut second] {
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+    }
+    assert_eq!(pomodoros(&data.join("activity.db")), 1);
+    let per_session = std::fs::read_dir(&data)
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("activity-"))
+        .collect::<Vec<_>>();
+    assert!(per_session.is_empty(), "{:?}", per_session);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn the_service_tells_systemd_it_is_ready_and_alive() {
+    use std::os::unix::net::UnixDatagram;
+
+    let dir = std::env::temp_dir().join(format!("stop_it-e2e-sd-notify-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("config/stop_it")).unwrap();
+    std::fs::write(
+        dir.join("config/stop_it/config.toml"),
+        "[server]\nport = 0\n\n[log]\nfiles = false\n",
+    )
+    .unwrap();
+    let socket_path = dir.join("notify.sock");
+    let socket = UnixDatagram::bind(&socket_path).unwrap();
+    socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+        .unwrap();
+    let receive = || {
+        let mut buffer = [0; 256];
+        let n = socket.recv(&mut buffer).expect("nothing from the daemon");
+        String::from_utf8_lossy(&buffer[..n]).into_owned()
+    };
+
+    // The daemon as the unit runs it: ready once serving, then fed the
+    // watchdog every half timeout
+    let mut daemon = std::process::Command::new(env!("CARGO_BIN_EXE_stop_it"))
+        .arg("daemon")
+        .env_clear()
+        .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+        .env("HOME", &dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("XDG_RUNTIME_DIR", dir.join("run"))
+        .env("NOTIFY_SOCKET", &socket_path)
+        .env("WATCHDOG_USEC", "1000000")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let first = receive();
+    let second = receive();
+    let third = receive();
+    let _ = daemon.kill();
+    let _ = daemon.wait();
+    // The first step may come before or after the sockets are served
+    let mut messages = [first.as_str(), second.as_str()];
+    messages.sort();
+    assert_eq!(messages, ["READY=1", "WATCHDOG=1"]);
+    assert_eq!(third, "WATCHDOG=1");
+
+    // Half the timeout between pings, however often the daemon steps
+    let notifier = ServiceNotifier::new(socket_path.clone());
+    let mut watchdog = Watchdog::new(notifier, std::time::Duration::from_millis(400));
+    assert!(watchdog.ping());
+    assert!(!watchdog.ping());
+    std::thread::sleep(std::time::Duration::from_millis(250));
+    assert!(watchdog.ping());
+    assert_eq!(receive(), "WATCHDOG=1");
+    assert_eq!(receive(), "WATCHDOG=1");
+
+    // Abstract socket names start with '@'
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+
+        let name = format!("stop_it-e2e-{}", std::process::id());
+        let abstract_socket =
+            UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(&name).unwrap()).unwrap();
+        ServiceNotifier::new(PathBuf::from(format!("@{}", name)))
+            .notify("READY=1")
+            .unwrap();
+        let mut buffer = [0; 16];
+        let n = abstract_socket.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..n], b"READY=1");
+    }
+}
@@ -0,0 +1,950 @@
+//! The Pomodoro timer driven through the daemon with a simulated clock:
+//! phases, breaks, plans and restarts.
+
+mod common;
+
+use chrono::Duration;
+use common::{Harness, everything};
+use std::sync::Arc;
+use stop_it::config::settings::Config;
+use stop_it::control::client::ControlClient;
+use stop_it::daemon::clock::{Clock, ManualClock};
+use stop_it::daemon::log::Log;
+use stop_it::daemon::tracker::DomainTracker;
+use stop_it::input::session;
+use stop_it::notifications::manager::Event;
+use stop_it::pomodoro::pomodoro::{PomodoroMode, TimerMode};
+use stop_it::stats::aggregator::Aggregator;
+use stop_it::storage::backend::Storage;
+use stop_it::storage::jsonl_backend::JsonlStorage;
+use stop_it::ws::protocol::{ClientMessage, ServerMessage};
+
+#[cfg(feature = "websocket")]
+use common::tab;
+#[cfg(feature = "websocket")]
+use stop_it::pomodoro::adaptive;
+#[cfg(feature = "title-fallback")]
+use stop_it::pomodoro::plan;
+#[cfg(any(feature = "websocket", feature = "title-fallback"))]
+use stop_it::pomodoro::plan::{Plans, parse_plan};
+#[cfg(feature = "title-fallback")]
+use stop_it::stats::calendar::Calendar;
+#[cfg(feature = "websocket")]
+use stop_it::stats::{focus::FocusMonitor, laps};
+#[cfg(feature = "websocket")]
+use stop_it::ws::client::DaemonClient;
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn full_work_and_break_cycle() {
+    let mut harness = Harness::start("cycle").await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+
+    // Default 25 minute work session
+    harness.run_for(25 * 60 - 1);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    harness.run_for(1);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+
+    let badge = client
+        .recv_matching(|message| match message {
+            ServerMessage::Badge(badge) => Some(badge),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert!(badge.text.ends_with('m'));
+    let state = client
+        .recv_matching(|message| match message {
+            ServerMessage::TimerState(state) => Some(state),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(state.current_domain.as_deref(), Some("github.com"));
+    let summary = client
+        .recv_matching(|message| match message {
+            ServerMessage::SessionSummary(summary) => Some(summary),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(summary.duration_seconds, 25 * 60);
+    assert_eq!(summary.top_domains[0].domain, "github.com");
+    assert_eq!(summary.focus_percent, 100);
+    assert_eq!(summary.pomodoros_today, 1);
+
+    // Focus moves to a terminal during the break: nothing is credited
+    harness.windows.focus("kitty", "~/src");
+    harness.run_for(4 * 60);
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+    harness.run_for(60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+
+    let github = harness.stored_seconds("github.com");
+    assert!(
+        (25 * 60..=25 * 60 + 60).contains(&github),
+        "github.com stored {}s",
+        github
+    );
+    assert_eq!(
+        harness.stored_pomodoros(),
+        vec![PomodoroMode::Work, PomodoroMode::Break]
+    );
+
+    let lines = harness.log.lines();
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Switched to BREAK mode"))
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Switched to WORK mode"))
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Browser switched to: github.com"))
+    );
+    // Session statistics are read back from the store
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.starts_with("  github.com - 24m")),
+        "{:?}",
+        lines
+    );
+
+    let sent = harness.notifier.lock().unwrap().sent().to_vec();
+    let switches = sent
+        .iter()
+        .filter(|(event, _)| *event == Event::ModeSwitch)
+        .count();
+    assert_eq!(switches, 2);
+
+    client.close().await;
+}
+
+#[tokio::test]
+async fn session_lengths_come_from_the_config() {
+    let mut config = Config::default();
+    config.pomodoro.work = 50 * 60;
+    config.pomodoro.break_ = 10 * 60;
+    let mut harness = Harness::with_config("lengths", config).await;
+    harness.windows.focus("kitty", "cargo test");
+
+    harness.run_for(50 * 60 - 1);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    harness.run_for(1);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    harness.run_for(10 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+
+    let lines = harness.log.lines();
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Time for a 10-minute break")),
+        "{:?}",
+        lines
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Starting 50-minute work session")),
+        "{:?}",
+        lines
+    );
+}
+
+#[tokio::test]
+async fn cycles_end_with_a_long_break() {
+    let mut config = Config::default();
+    config.pomodoro.long_break = 20 * 60;
+    config.pomodoro.long_break_after = 2;
+    let mut harness = Harness::with_config("long-break", config).await;
+    harness.windows.focus("kitty", "cargo test");
+
+    harness.run_for(25 * 60 + 5 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::LongBreak);
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!((state.mode.as_str(), state.cycle), ("LONG_BREAK", 2));
+    assert_eq!((state.cycle_length, state.duration_seconds), (2, 20 * 60));
+
+    harness.run_for(20 * 60 - 1);
+    assert_eq!(harness.mode(), PomodoroMode::LongBreak);
+    harness.run_for(1);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    assert_eq!(harness.daemon.tracker().lock().unwrap().cycle(), 0);
+
+    let lines = harness.log.lines();
+    assert!(
+        lines.iter().any(|line| line
+            .contains("Cycle complete: 2 work sessions done! Time for a 20-minute long break.")),
+        "{:?}",
+        lines
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Long break is over! Starting a new cycle")),
+        "{:?}",
+        lines
+    );
+    assert_eq!(
+        harness.stored_pomodoros(),
+        vec![
+            PomodoroMode::Work,
+            PomodoroMode::Break,
+            PomodoroMode::Work,
+            PomodoroMode::LongBreak
+        ]
+    );
+}
+
+#[tokio::test]
+async fn the_pomodoro_can_pause_while_away() {
+    let mut config = Config::default();
+    config.input.idle_after = 60;
+    config.input.pause_when_idle = true;
+    let mut harness = Harness::with_config("idle_pause", config).await;
+    harness
+        .windows
+        .focus("firefox", "github.com — Mozilla Firefox");
+    let remaining =
+        |harness: &Harness| harness.daemon.tracker().lock().unwrap().remaining_seconds();
+
+    harness.input.set(12);
+    harness.run_for(5 * 60);
+    harness.input.set(0);
+    harness.run_for(10 * 60);
+    // The clock stopped once idle_after passed without input
+    assert!(harness.daemon.tracker().lock().unwrap().is_paused());
+    assert_eq!(remaining(&harness), 25 * 60 - 6 * 60);
+    harness.input.set(3);
+    harness.run_for(60);
+    assert!(!harness.daemon.tracker().lock().unwrap().is_paused());
+    // The clock restarts with the step that saw input again
+    assert_eq!(remaining(&harness), 25 * 60 - 6 * 60 - 59);
+
+    // A pause of the user's own outlasts coming back
+    assert!(harness.daemon.tracker().lock().unwrap().pause());
+    harness.input.set(0);
+    harness.run_for(2 * 60);
+    harness.input.set(3);
+    harness.run_for(10);
+    assert!(harness.daemon.tracker().lock().unwrap().is_paused());
+
+    // What `loginctl show-session -p IdleHint --value` answers
+    assert_eq!(session::parse_idle_hint("yes\n"), Some(true));
+    assert_eq!(session::parse_idle_hint("no\n"), Some(false));
+    assert_eq!(session::parse_idle_hint(""), None);
+}
+
+#[tokio::test]
+async fn sessions_left_idle_are_abandoned() {
+    let mut harness = Harness::start("abandoned").await;
+    harness.windows.focus("kitty", "cargo test");
+
+    // Gone for the whole session after five minutes of work
+    harness.input.set(8);
+    harness.run_for(5 * 60);
+    harness.input.set(0);
+    harness.run_for(20 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!((state.pomodoros_today, state.cycle), (0, 0));
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("Work session abandoned: idle for 18m")),
+        "{:?}",
+        harness.log.lines()
+    );
+
+    // Idle for less than half of it: completed
+    harness.run_for(5 * 60);
+    harness.input.set(8);
+    harness.run_for(15 * 60);
+    harness.input.set(0);
+    harness.run_for(10 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!((state.pomodoros_today, state.cycle), (1, 1));
+
+    let store = JsonlStorage::open_read_only(&harness.storage_path)
+        .unwrap()
+        .load(&everything())
+        .unwrap();
+    let work: Vec<bool> = store
+        .pomodoros()
+        .iter()
+        .filter(|record| record.mode == PomodoroMode::Work)
+        .map(|record| record.abandoned)
+        .collect();
+    assert_eq!(work, vec![true, false]);
+    assert_eq!(Aggregator::new(&store).abandoned(&everything()).len(), 1);
+}
+
+#[tokio::test]
+async fn sessions_can_be_skipped_and_reset() {
+    let mut harness = Harness::start("skip").await;
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(5 * 60);
+
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    match cli.request(&ClientMessage::Skip).await.unwrap() {
+        ServerMessage::Ack { message } => assert_eq!(
+            message, "Skipped WORK session, BREAK starts now",
+            "{}",
+            message
+        ),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    let again = cli.request(&ClientMessage::Skip).await.unwrap_err();
+    assert!(again.to_string().contains("Already skipping"));
+    harness.run_for(1);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    assert_eq!(harness.daemon.tracker().lock().unwrap().cycle(), 1);
+    assert!(
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .any(|(event, message)| *event == Event::ModeSwitch
+                && message.contains("5-minute break"))
+    );
+
+    // Skipping a paused break resumes with the work session
+    cli.request(&ClientMessage::Pause).await.unwrap();
+    cli.request(&ClientMessage::Skip).await.unwrap();
+    harness.run_for(1);
+    match cli.request(&ClientMessage::GetStatus).await.unwrap() {
+        ServerMessage::TimerState(state) => {
+            assert_eq!((state.mode.as_str(), state.paused), ("WORK", false));
+            assert_eq!(state.remaining_seconds, 25 * 60);
+        }
+        other => panic!("unexpected reply {:?}", other),
+    }
+
+    harness.run_for(10 * 60);
+    match cli.request(&ClientMessage::Reset).await.unwrap() {
+        ServerMessage::Ack { message } => assert!(message.contains("25m 00s left"), "{}", message),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    let tracker = harness.daemon.tracker();
+    let tracker = tracker.lock().unwrap();
+    assert_eq!((tracker.mode(), tracker.cycle()), (PomodoroMode::Work, 0));
+    assert_eq!(tracker.remaining_seconds(), 25 * 60);
+    drop(tracker);
+    assert_eq!(
+        harness.stored_pomodoros(),
+        vec![PomodoroMode::Work, PomodoroMode::Break]
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn continuous_mode_skips_the_breaks() {
+    let mut harness = Harness::start("continuous").await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+
+    // Turning breaks off ends the running one
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    let continuous = ClientMessage::SetTimerMode {
+        mode: TimerMode::Continuous,
+    };
+    match cli.request(&continuous).await.unwrap() {
+        ServerMessage::Ack { message } => {
+            assert!(message.starts_with("Continuous mode"), "{}", message)
+        }
+        other => panic!("unexpected reply {:?}", other),
+    }
+    let again = cli.request(&continuous).await.unwrap_err();
+    assert!(again.to_string().contains("Already in continuous mode"));
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+
+    // Work blocks follow each other, each with its summary
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    let summary = client
+        .recv_matching(|message| match message {
+            ServerMessage::SessionSummary(summary) if summary.pomodoros_today == 2 => Some(summary),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(summary.top_domains[0].domain, "github.com");
+    assert!(
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .any(|(event, message)| *event == Event::ModeSwitch
+                && message.starts_with("Work block complete!"))
+    );
+    match cli.request(&ClientMessage::GetStatus).await.unwrap() {
+        ServerMessage::TimerState(state) => {
+            assert!(state.continuous);
+            assert_eq!((state.cycle, state.remaining_seconds), (1, 25 * 60));
+        }
+        other => panic!("unexpected reply {:?}", other),
+    }
+
+    // Back to Pomodoro cycles: a break follows the running block
+    cli.request(&ClientMessage::SetTimerMode {
+        mode: TimerMode::Pomodoro,
+    })
+    .await
+    .unwrap();
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    assert_eq!(
+        harness.stored_pomodoros(),
+        vec![
+            PomodoroMode::Work,
+            PomodoroMode::Break,
+            PomodoroMode::Work,
+            PomodoroMode::Work
+        ]
+    );
+}
+
+#[tokio::test]
+async fn breaks_can_be_snoozed() {
+    let mut harness = Harness::start("snooze").await;
+    harness.windows.focus("kitty", "cargo test");
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    harness.run_for(10 * 60);
+    let early = cli
+        .request(&ClientMessage::Snooze { minutes: None })
+        .await
+        .unwrap_err();
+    assert!(
+        early.to_string().contains("The break is still"),
+        "{}",
+        early
+    );
+
+    // The button on the break notification puts the break off
+    harness.run_for(15 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    let snooze = {
+        let notifier = harness.notifier.lock().unwrap();
+        let action = notifier
+            .actions(&Event::ModeSwitch)
+            .iter()
+            .find(|action| action.label() == "Snooze 5 min")
+            .unwrap();
+        action.clone()
+    };
+    snooze.invoke();
+    for _ in 0..100 {
+        if harness.mode() == PomodoroMode::Work {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    {
+        let tracker = harness.daemon.tracker();
+        let tracker = tracker.lock().unwrap();
+        assert_eq!((tracker.mode(), tracker.cycle()), (PomodoroMode::Work, 0));
+        assert_eq!(tracker.remaining_seconds(), 5 * 60);
+    }
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("💤 Break snoozed: 5m 00s more of WORK"))
+    );
+
+    // Snoozing again near the end counts from now, in the same work session
+    harness.run_for(4 * 60);
+    match cli
+        .request(&ClientMessage::Snooze { minutes: Some(10) })
+        .await
+        .unwrap()
+    {
+        ServerMessage::Ack { message } => {
+            assert_eq!(message, "Break snoozed: 10m 00s more of WORK")
+        }
+        other => panic!("unexpected reply {:?}", other),
+    }
+    harness.run_for(10 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    assert_eq!(harness.daemon.tracker().lock().unwrap().cycle(), 1);
+    let work: Vec<_> = JsonlStorage::open(&harness.storage_path)
+        .unwrap()
+        .load(&everything())
+        .unwrap()
+        .pomodoros()
+        .to_vec();
+    assert_eq!(work.len(), 1);
+    assert_eq!((work[0].end - work[0].start).num_minutes(), 39);
+
+    // Without breaks there is nothing to snooze
+    cli.request(&ClientMessage::SetTimerMode {
+        mode: TimerMode::Continuous,
+    })
+    .await
+    .unwrap();
+    let continuous = cli
+        .request(&ClientMessage::Snooze { minutes: None })
+        .await
+        .unwrap_err();
+    assert!(continuous.to_string().contains("continuous mode"));
+}
+
+#[tokio::test]
+async fn manual_mode_waits_for_start_after_breaks() {
+    let mut config = Config::default();
+    config.pomodoro.manual = true;
+    let mut harness = Harness::with_config("manual", config).await;
+    harness.windows.focus("kitty", "cargo test");
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    let early = cli.request(&ClientMessage::Start).await.unwrap_err();
+    assert!(early.to_string().contains("Already in a WORK session"));
+
+    // The break runs out and stays
+    harness.run_for(30 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    assert!(harness.log.lines().iter().any(|line| {
+        line.contains("🔔 Break is over! Start the next 25-minute work session when you're back.")
+    }));
+    assert_eq!(
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .actions(&Event::ModeSwitch)
+            .iter()
+            .map(|action| action.label())
+            .collect::<Vec<_>>(),
+        vec!["Start"]
+    );
+    harness.run_for(10 * 60);
+    match cli.request(&ClientMessage::GetStatus).await.unwrap() {
+        ServerMessage::TimerState(state) => {
+            assert_eq!(state.mode, "BREAK");
+            assert!(state.waiting);
+            assert_eq!(state.remaining_seconds, 0);
+        }
+        other => panic!("unexpected reply {:?}", other),
+    }
+    assert_eq!(harness.stored_pomodoros(), vec![PomodoroMode::Work]);
+
+    // The break lasts until the start
+    match cli.request(&ClientMessage::Start).await.unwrap() {
+        ServerMessage::Ack { message } => assert_eq!(message, "WORK session starts now"),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    harness.run_for(1);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    let store = JsonlStorage::open_read_only(&harness.storage_path)
+        .unwrap()
+        .load(&everything())
+        .unwrap();
+    let pomodoros = store.pomodoros();
+    assert_eq!(pomodoros.len(), 2);
+    assert_eq!((pomodoros[1].end - pomodoros[1].start).num_minutes(), 15);
+
+    // Work sessions still end on time
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+}
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn planned_blocks_start_work_sessions() {
+    let mut harness = Harness::start("plan").await;
+    let day = harness.clock.now().date_naive();
+    let mut plans = Plans::default();
+    plans.set(day, parse_plan("09:10-09:40 deep").unwrap());
+    plans.save_to(&harness.plans_path).unwrap();
+
+    harness
+        .windows
+        .focus("firefox", "github.com — Mozilla Firefox");
+    harness.run_for(10 * 60);
+    {
+        let tracker = harness.daemon.tracker();
+        let tracker = tracker.lock().unwrap();
+        assert_eq!(
+            tracker.active_block().map(|block| block.label.as_str()),
+            Some("deep")
+        );
+        assert_eq!(tracker.mode_start(), harness.clock.now());
+        assert_eq!(tracker.timer_state().block.as_deref(), Some("deep"));
+    }
+
+    harness.run_for(30 * 60);
+    let lines = harness.log.lines();
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Planned block 'deep' starts now"))
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("09:10-09:40 deep ended"))
+    );
+
+    // The 25 minute session inside the block counts as worked time
+    let store = JsonlStorage::open_read_only(&harness.storage_path)
+        .unwrap()
+        .load(&everything())
+        .unwrap();
+    let report = plan::compare(
+        plans.blocks(day),
+        &Calendar::new(&Config::default().day),
+        day,
+        &store,
+    );
+    assert_eq!(report[0].planned_seconds, 30 * 60);
+    assert_eq!(report[0].worked_seconds, 25 * 60);
+    assert_eq!(report[0].tracked_seconds, 30 * 60);
+}
+
+#[tokio::test]
+async fn restarts_resume_the_running_phase() {
+    let mut harness = Harness::start("restart").await;
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(25 * 60 + 1);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    harness.run_for(120);
+
+    // A second daemon over the same files, as after a crash
+    let restarted = |clock: Arc<ManualClock>| {
+        let mut config = Config::default();
+        config.storage.journal = false;
+        let storage = Box::new(JsonlStorage::open(&harness.storage_path).unwrap());
+        DomainTracker::new(&config, storage, clock, Log::capturing())
+            .unwrap()
+            .with_saved_phase(harness.phase_path.clone())
+    };
+    let tracker = restarted(harness.clock.clone());
+    assert_eq!(tracker.mode(), PomodoroMode::Break);
+    assert_eq!(
+        tracker.mode_start(),
+        harness.daemon.tracker().lock().unwrap().mode_start()
+    );
+    assert_eq!(tracker.cycle(), 1);
+    assert_eq!(tracker.timer_state().pomodoros_today, 1);
+
+    // The break ran out while the daemon was down: a new work session starts,
+    // still counting the finished one
+    harness.clock.advance(Duration::minutes(10));
+    let tracker = restarted(harness.clock.clone());
+    assert_eq!(tracker.mode(), PomodoroMode::Work);
+    assert_eq!(tracker.mode_start(), harness.clock.now());
+    assert_eq!(tracker.cycle(), 1);
+
+    // A new day starts a new cycle
+    harness.clock.advance(Duration::days(1));
+    assert_eq!(restarted(harness.clock.clone()).cycle(), 0);
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn breaks_are_confirmed_by_the_extension() {
+    let mut config = Config::default();
+    config.breaks.overlay = true;
+    let mut harness = Harness::with_config("break-overlay", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(25 * 60);
+
+    let (id, ends_at) = client
+        .recv_matching(|message| match message {
+            ServerMessage::BreakStart { id, ends_at } => Some((id, ends_at)),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(ends_at, harness.clock.now() + chrono::Duration::minutes(5));
+    client.send(&ClientMessage::BreakAck { id }).await.unwrap();
+    // Messages from the extension are handled in order
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(30);
+    assert!(
+        !harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("No browser confirmed"))
+    );
+
+    // An unconfirmed `break_end` is sent again, then given up on
+    harness.run_for(5 * 60 - 30);
+    let end = client
+        .recv_matching(|message| match message {
+            ServerMessage::BreakEnd { id } => Some(id),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert!(end > id);
+    harness.run_for(10);
+    let resent = client
+        .recv_matching(|message| match message {
+            ServerMessage::BreakEnd { id } => Some(id),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(resent, end);
+    harness.run_for(30);
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("No browser confirmed the end of the break"))
+    );
+}
+
+#[tokio::test]
+async fn the_engine_runs_on_its_own_clock() {
+    let mut harness = Harness::start("engine").await;
+    let engine = &mut harness.daemon;
+    let mut updates = engine.subscribe();
+    engine.start();
+    assert!(engine.is_running());
+    let state = loop {
+        if let ServerMessage::TimerState(state) = updates.recv().await.unwrap() {
+            break state;
+        }
+    };
+    assert_eq!(state.mode, "WORK");
+
+    // Commands and queries without a socket
+    match engine.request(ClientMessage::Pause).await.unwrap() {
+        ServerMessage::Ack { .. } => {}
+        other => panic!("unexpected reply {:?}", other),
+    }
+    assert!(engine.status().await.unwrap().paused);
+    let already = engine.request(ClientMessage::Pause).await.unwrap_err();
+    assert!(already.to_string().contains("paused"), "{}", already);
+    assert_eq!(engine.today_stats().await.unwrap().total_seconds, 0);
+
+    // Stopped, the engine can be stepped by hand and started again
+    engine.stop().await;
+    assert!(!engine.is_running());
+    engine.request(ClientMessage::Resume).await.unwrap();
+    harness.run_for(60);
+    assert!(!harness.daemon.status().await.unwrap().paused);
+    harness.daemon.start();
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    cli.request(&ClientMessage::Handoff).await.unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(2), harness.daemon.finished())
+        .await
+        .expect("handoff didn't stop the engine");
+    assert!(!harness.daemon.is_running());
+}
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn forgotten_pauses_resume_on_their_own() {
+    let mut config = Config::default();
+    config.pause.auto_resume = Some(30 * 60);
+    let mut harness = Harness::with_config("pause", config).await;
+    harness
+        .windows
+        .focus("firefox", "github.com — Mozilla Firefox");
+    harness.run_for(10 * 60);
+
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    match cli.request(&ClientMessage::Pause).await.unwrap() {
+        ServerMessage::Ack { message } => assert!(message.contains("15m 00s left"), "{}", message),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    let again = cli.request(&ClientMessage::Pause).await.unwrap_err();
+    assert!(again.to_string().contains("Already paused"));
+
+    // The clock and tracking stand still, past the end of the work session
+    harness.run_for(20 * 60);
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert!(state.paused);
+    assert_eq!(state.remaining_seconds, 15 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    assert_eq!(harness.stored_seconds("github.com"), 10 * 60);
+
+    // Warned 5 minutes ahead, then resumed where the session stopped
+    let pauses = |harness: &Harness| {
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .filter(|(event, _)| *event == Event::Pause)
+            .map(|(_, message)| message.clone())
+            .collect::<Vec<_>>()
+    };
+    assert!(pauses(&harness).is_empty());
+    harness.run_for(5 * 60);
+    assert_eq!(pauses(&harness).len(), 1);
+    assert!(
+        pauses(&harness)[0].contains("5m 00s"),
+        "{:?}",
+        pauses(&harness)
+    );
+    harness.run_for(5 * 60);
+    assert_eq!(pauses(&harness).len(), 2);
+    assert!(!harness.daemon.tracker().lock().unwrap().is_paused());
+    harness.run_for(60);
+    assert_eq!(
+        harness.daemon.tracker().lock().unwrap().remaining_seconds(),
+        14 * 60
+    );
+    assert_eq!(harness.stored_seconds("github.com"), 11 * 60);
+
+    // A manual pause and resume shifts the session by the pause
+    assert!(matches!(
+        cli.request(&ClientMessage::Pause).await.unwrap(),
+        ServerMessage::Ack { .. }
+    ));
+    harness.run_for(120);
+    match cli.request(&ClientMessage::Resume).await.unwrap() {
+        ServerMessage::Ack { message } => assert!(message.contains("after 2m 00s"), "{}", message),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    assert_eq!(
+        harness.daemon.tracker().lock().unwrap().remaining_seconds(),
+        14 * 60
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn focused_sessions_earn_longer_breaks() {
+    let mut config = Config::default();
+    config.breaks.adaptive = true;
+    config.focus_alerts.distracting_domains = vec!["reddit.com".to_string()];
+    let mut harness = Harness::with_config("adaptive", config.clone()).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(25 * 60);
+
+    // Only suggested: the break keeps its configured length
+    let switch = |harness: &Harness| {
+        let notifier = harness.notifier.lock().unwrap();
+        let sent = notifier.sent();
+        sent.iter()
+            .rev()
+            .find(|(event, _)| *event == Event::ModeSwitch)
+            .map(|(_, message)| message.clone())
+            .unwrap()
+    };
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    assert_eq!(
+        switch(&harness),
+        "Work session complete! Time for a 5-minute break. \
+         This session earned 8 minutes if you can spare them."
+    );
+    assert_eq!(
+        harness
+            .daemon
+            .tracker()
+            .lock()
+            .unwrap()
+            .timer_state()
+            .duration_seconds,
+        5 * 60
+    );
+
+    // Accepted: a focused session gets the longer break, a drifting one doesn't
+    config.breaks.auto_accept = true;
+    let mut harness = Harness::with_config("adaptive-accept", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(25 * 60);
+    assert_eq!(
+        switch(&harness),
+        "Work session complete! Time for a 8-minute break."
+    );
+    harness.run_for(8 * 60 - 1);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+    harness.run_for(1);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+
+    client.send(&tab("reddit.com")).await.unwrap();
+    harness.wait_for_domain("reddit.com").await;
+    harness.run_for(25 * 60);
+    assert_eq!(
+        switch(&harness),
+        "Work session complete! Time for a 5-minute break."
+    );
+    assert_eq!(
+        adaptive::suggest_break(5 * 60, 25 * 60, 50 * 60, 0.9),
+        14 * 60
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn laps_list_the_phases_of_the_day() {
+    let mut config = Config::default();
+    config.focus_alerts.distracting_domains = vec!["reddit.com".to_string()];
+    let focus = FocusMonitor::new(&config.focus_alerts);
+    let mut harness = Harness::with_config("laps", config).await;
+    let day = harness.clock.now().date_naive();
+    let mut plans = Plans::default();
+    plans.set(day, parse_plan("09:00-10:00 deep").unwrap());
+    plans.save_to(&harness.plans_path).unwrap();
+
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(20 * 60);
+    client.send(&tab("reddit.com")).await.unwrap();
+    harness.wait_for_domain("reddit.com").await;
+    // The block started the session a second in
+    harness.run_for(10 * 60 + 1);
+
+    let store = JsonlStorage::open_read_only(&harness.storage_path)
+        .unwrap()
+        .load(&everything())
+        .unwrap();
+    let laps = laps::laps(&store, &everything(), &focus);
+    assert_eq!(laps.len(), 2, "{:?}", laps);
+    let (work, rest) = (&laps[0], &laps[1]);
+    assert_eq!(work.mode, PomodoroMode::Work);
+    assert_eq!((work.end - work.start).num_minutes(), 25);
+    assert_eq!(work.label.as_deref(), Some("deep"));
+    assert_eq!(work.focus_percent, Some(80));
+    // Breaks follow on, without a focus score or the block's label
+    assert_eq!(rest.mode, PomodoroMode::Break);
+    assert_eq!(rest.start, work.end);
+    assert_eq!((rest.end - rest.start).num_minutes(), 5);
+    assert_eq!((rest.label.as_deref(), rest.focus_percent), (None, None));
+}
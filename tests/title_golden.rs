@@ -0,0 +1,45 @@
+//! Golden corpus for domain extraction from window titles. Each line of
+//! `data/window_titles.tsv` is a real-world title and the domain expected
+//! from it; change the corpus deliberately when the extraction rules change.
+#![cfg(feature = "title-fallback")]
+
+use stop_it::window::title::domain_from_title;
+
+const CORPUS: &str = include_str!("data/window_titles.tsv");
+
+#[test]
+fn window_titles_match_golden_corpus() {
+    let mut cases = 0;
+    let mut failures = Vec::new();
+
+    for (index, line) in CORPUS.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (expected, title) = line
+            .split_once('\t')
+            .unwrap_or_else(|| panic!("line {}: missing tab separator", index + 1));
+        let expected = (expected != "-").then(|| expected.to_string());
+
+        cases += 1;
+        let actual = domain_from_title(title);
+        if actual != expected {
+            failures.push(format!(
+                "line {}: {:?} -> {:?}, expected {:?}",
+                index + 1,
+                title,
+                actual,
+                expected
+            ));
+        }
+    }
+
+    assert!(cases >= 300, "corpus shrank to {} titles", cases);
+    assert!(
+        failures.is_empty(),
+        "{} of {} titles changed:\n{}",
+        failures.len(),
+        cases,
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,500 @@
+//! Time tracking through the daemon: sites from the browser extension and
+//! window titles, idle time and categories.
+
+mod common;
+
+use common::Harness;
+#[cfg(any(feature = "websocket", feature = "title-fallback"))]
+use stop_it::config::settings::Config;
+
+#[cfg(feature = "websocket")]
+use chrono::Duration;
+#[cfg(feature = "websocket")]
+use common::{categorized_tab, tab};
+#[cfg(feature = "websocket")]
+use stop_it::config::edit::{add_category_rules, set_category_rule};
+#[cfg(feature = "title-fallback")]
+use stop_it::config::settings::BudgetConfig;
+#[cfg(feature = "websocket")]
+use stop_it::control::client::ControlClient;
+#[cfg(feature = "websocket")]
+use stop_it::daemon::clock::Clock;
+#[cfg(feature = "websocket")]
+use stop_it::notifications::manager::Event;
+#[cfg(feature = "title-fallback")]
+use stop_it::pomodoro::pomodoro::PomodoroMode;
+#[cfg(feature = "websocket")]
+use stop_it::storage::activity_store::CategorySource;
+#[cfg(feature = "websocket")]
+use stop_it::ws::client::DaemonClient;
+#[cfg(feature = "websocket")]
+use stop_it::ws::protocol::{
+    ClientMessage, HeartbeatMessage, ServerMessage, TabClosedMessage, TabUpdateMessage,
+    WindowClosedMessage,
+};
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn break_browsing_is_kept_apart() {
+    let mut config = Config::default();
+    config.budgets.insert(
+        "reddit.com".to_string(),
+        BudgetConfig {
+            daily: Some(10 * 60),
+            work_only: true,
+            ..BudgetConfig::default()
+        },
+    );
+    let mut harness = Harness::with_config("break-browsing", config).await;
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(25 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Break);
+
+    harness
+        .windows
+        .focus("firefox", "reddit.com — Mozilla Firefox");
+    harness.run_for(5 * 60);
+    assert_eq!(harness.mode(), PomodoroMode::Work);
+    harness.run_for(60);
+    harness.daemon.tracker().lock().unwrap().flush();
+
+    let reddit: Vec<(bool, i64)> = harness
+        .stored_segments()
+        .into_iter()
+        .filter(|segment| segment.domain == "reddit.com")
+        .map(|segment| (segment.on_break, segment.seconds))
+        .collect();
+    assert_eq!(reddit, vec![(true, 5 * 60), (false, 60)]);
+
+    // Only the minute of work time counts against the budget
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!(state.budgets[0].used_today, 60);
+    assert_eq!(state.budgets[0].remaining, Some(9 * 60));
+}
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn window_title_is_used_without_extension() {
+    let mut harness = Harness::start("title").await;
+
+    harness.windows.focus(
+        "firefox",
+        "github.com/rust-lang/rust: Rust — Mozilla Firefox",
+    );
+    harness.run_for(30);
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(30);
+
+    let github = harness.stored_seconds("github.com");
+    assert!((29..=30).contains(&github), "github.com stored {}s", github);
+}
+
+#[cfg(feature = "title-fallback")]
+#[tokio::test]
+async fn idle_time_is_not_tracked() {
+    let mut config = Config::default();
+    config.input.idle_after = 60;
+    let mut harness = Harness::with_config("idle", config).await;
+
+    harness
+        .windows
+        .focus("firefox", "github.com — Mozilla Firefox");
+    harness.input.set(12);
+    harness.run_for(30);
+    harness.input.set(0);
+    harness.run_for(300);
+    harness.input.set(3);
+    harness.run_for(30);
+
+    // 30s of input, 60s before going idle, then 30s after coming back
+    let github = harness.stored_seconds("github.com");
+    assert!(
+        (119..=121).contains(&github),
+        "github.com stored {}s",
+        github
+    );
+    let lines = harness.log.lines();
+    assert!(lines.iter().any(|line| line.contains("tracking paused")));
+    assert!(lines.iter().any(|line| line.contains("Input resumed")));
+}
+
+#[tokio::test]
+async fn churning_titles_are_merged() {
+    let mut harness = Harness::start("churn").await;
+
+    for second in 0..60 {
+        let site = if second % 2 == 0 {
+            "github.com"
+        } else {
+            "youtube.com"
+        };
+        harness
+            .windows
+            .focus("firefox", &format!("{} — Mozilla Firefox", site));
+        harness.run_for(1);
+    }
+    harness.windows.focus("kitty", "cargo test");
+    harness.run_for(60);
+
+    // Five changes are let through before the title is frozen
+    let youtube = harness.stored_seconds("youtube.com");
+    assert!(youtube <= 3, "youtube.com stored {}s", youtube);
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("firefox changes too often"))
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn url_fallback_is_normalized() {
+    let harness = Harness::start("url").await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+
+    client
+        .send(&ClientMessage::TabUpdate(TabUpdateMessage {
+            url: "https://me@www.GitHub.com:443/rust-lang/rust?tab=readme".to_string(),
+            title: "rust-lang/rust".to_string(),
+            domain: None,
+            category: None,
+            profile: None,
+        }))
+        .await
+        .unwrap();
+    harness.wait_for_domain("github.com").await;
+
+    client.close().await;
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn config_rules_override_extension_categories() {
+    let mut config = Config::default();
+    config
+        .categories
+        .insert("youtube.com".to_string(), "entertainment".to_string());
+    let mut harness = Harness::with_config("categories", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+
+    client
+        .send(&categorized_tab("music.youtube.com", Some("productivity")))
+        .await
+        .unwrap();
+    harness.wait_for_domain("music.youtube.com").await;
+    harness.run_for(10);
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!(state.current_category.as_deref(), Some("entertainment"));
+    assert_eq!(state.category_source, Some(CategorySource::Server));
+
+    client
+        .send(&categorized_tab("github.com", Some("productivity")))
+        .await
+        .unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(60);
+
+    let sources: Vec<_> = harness
+        .stored_segments()
+        .into_iter()
+        .map(|segment| (segment.domain, segment.category, segment.category_source))
+        .collect();
+    assert_eq!(
+        sources,
+        vec![
+            (
+                "music.youtube.com".to_string(),
+                Some("entertainment".to_string()),
+                Some(CategorySource::Server)
+            ),
+            (
+                "github.com".to_string(),
+                Some("productivity".to_string()),
+                Some(CategorySource::Client)
+            ),
+        ]
+    );
+
+    // Without a rule or hint the built-in database decides, after aliases
+    client.send(&tab("redd.it")).await.unwrap();
+    harness.wait_for_domain("reddit.com").await;
+    harness.run_for(1);
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!(state.current_category.as_deref(), Some("social"));
+    assert_eq!(state.category_source, Some(CategorySource::Builtin));
+
+    client.close().await;
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn corrected_categories_apply_to_today() {
+    let mut config = Config::default();
+    config
+        .categories
+        .insert("music.youtube.com".to_string(), "music".to_string());
+    let mut harness = Harness::with_config("categorize", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+    let visits = [
+        ("youtube.com", Some("education")),
+        ("music.youtube.com", None),
+        ("github.com", Some("productivity")),
+        ("m.youtube.com", Some("education")),
+    ];
+    for (domain, hint) in visits {
+        client.send(&categorized_tab(domain, hint)).await.unwrap();
+        harness.wait_for_domain(domain).await;
+        harness.run_for(30);
+    }
+
+    let mut cli = ControlClient::connect(&harness.control_path).await.unwrap();
+    let request = ClientMessage::Categorize {
+        domain: "YouTube.com".to_string(),
+        category: "entertainment".to_string(),
+    };
+    match cli.request(&request).await.unwrap() {
+        ServerMessage::Ack { message } => assert_eq!(
+            message,
+            "youtube.com is now entertainment, 1m 00s of today re-tagged"
+        ),
+        other => panic!("unexpected reply {:?}", other),
+    }
+    let changed = client
+        .recv_matching(|message| match message {
+            ServerMessage::CategoryChanged { domain, category } => Some((domain, category)),
+            _ => None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        changed,
+        ("youtube.com".to_string(), "entertainment".to_string())
+    );
+    let state = harness.daemon.tracker().lock().unwrap().timer_state();
+    assert_eq!(state.current_category.as_deref(), Some("entertainment"));
+
+    // The more specific rule still holds; the open segment carries on
+    harness.run_for(30);
+    harness.daemon.tracker().lock().unwrap().flush();
+    let categories: Vec<_> = harness
+        .stored_segments()
+        .into_iter()
+        .map(|segment| (segment.domain, segment.category, segment.seconds))
+        .collect();
+    let category = |name: &str| Some(name.to_string());
+    assert_eq!(
+        categories,
+        vec![
+            ("youtube.com".to_string(), category("entertainment"), 30),
+            ("music.youtube.com".to_string(), category("music"), 30),
+            ("github.com".to_string(), category("productivity"), 30),
+            ("m.youtube.com".to_string(), category("entertainment"), 60),
+        ]
+    );
+
+    // The rule is rewritten in place when the config already has one
+    let edited = set_category_rule(
+        "[categories]\n\"youtube.com\" = \"education\"  # hint was wrong\n\"docs.rs\" = \"docs\"\n",
+        "youtube.com",
+        "entertainment",
+    );
+    assert_eq!(
+        edited,
+        "[categories]\n\"youtube.com\" = \"entertainment\"\n\"docs.rs\" = \"docs\"\n"
+    );
+    assert_eq!(
+        set_category_rule("[pomodoro]\nwork = \"50m\"\n", "lwn.net", "news"),
+        "[pomodoro]\nwork = \"50m\"\n\n[categories]\n\"lwn.net\" = \"news\"\n"
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn closed_tabs_stop_being_credited_at_once() {
+    let mut harness = Harness::start("closed").await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "GitHub — Mozilla Firefox");
+
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(30);
+
+    // A close for another site is stale and changes nothing
+    let closed = |domain: &str| {
+        ClientMessage::TabClosed(TabClosedMessage {
+            domain: Some(domain.to_string()),
+            profile: None,
+        })
+    };
+    let closed_at = harness.clock.now();
+    client.send(&closed("example.org")).await.unwrap();
+    client.send(&closed("github.com")).await.unwrap();
+    harness.wait_for_no_domain().await;
+    harness.run_for(30);
+
+    // The segment ends at the close, not when the next update comes
+    assert_eq!(harness.stored_seconds("github.com"), 30);
+    let segment = harness.stored_segments().pop().unwrap();
+    assert!((segment.end() - closed_at).num_seconds().abs() <= 1);
+
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    harness.run_for(10);
+    client
+        .send(&ClientMessage::WindowClosed(WindowClosedMessage {
+            domain: Some("docs.rs".to_string()),
+            browser_exiting: true,
+            profile: None,
+        }))
+        .await
+        .unwrap();
+    harness.wait_for_no_domain().await;
+    harness.run_for(10);
+    assert_eq!(harness.stored_seconds("docs.rs"), 10);
+
+    let lines = harness.log.lines();
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Browser tab closed: github.com"))
+    );
+    assert!(!lines.iter().any(|line| line.contains("example.org")));
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("Browser shut down: stopped tracking docs.rs"))
+    );
+}
+
+#[cfg(feature = "websocket")]
+fn heartbeat(focused: bool, idle: bool) -> ClientMessage {
+    ClientMessage::Heartbeat(HeartbeatMessage {
+        focused,
+        idle,
+        profile: None,
+    })
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn browser_only_mode_follows_heartbeats() {
+    let mut config = Config::default();
+    config.extension.only = true;
+    let mut harness = Harness::with_config("browser-only", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    client.send(&heartbeat(true, false)).await.unwrap();
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(30);
+
+    // Another application in front
+    client.send(&heartbeat(false, false)).await.unwrap();
+    harness.wait_for_no_domain().await;
+    harness.run_for(30);
+
+    // Back, but away from the keyboard
+    client.send(&heartbeat(true, true)).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    assert!(harness.daemon.tracker().lock().unwrap().is_idle());
+    harness.run_for(30);
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("💤 The browser reports no input, tracking paused"))
+    );
+
+    // A browser gone quiet, e.g. on a suspended machine, stops being credited
+    client.send(&heartbeat(true, false)).await.unwrap();
+    client.send(&tab("docs.rs")).await.unwrap();
+    harness.wait_for_domain("docs.rs").await;
+    assert!(!harness.daemon.tracker().lock().unwrap().is_idle());
+    harness.run_for(80);
+    assert_eq!(
+        harness.daemon.tracker().lock().unwrap().current_domain(),
+        None
+    );
+    assert!(
+        harness
+            .log
+            .lines()
+            .iter()
+            .any(|line| line.contains("No heartbeat from the browser for 1m 00s"))
+    );
+    assert_eq!(harness.stored_seconds("github.com"), 30);
+    assert_eq!(harness.stored_seconds("docs.rs"), 59);
+
+    // Closing the browser stops tracking at once
+    client.send(&tab("github.com")).await.unwrap();
+    harness.wait_for_domain("github.com").await;
+    harness.run_for(10);
+    client.close().await;
+    harness.wait_for_no_domain().await;
+    harness.run_for(10);
+    assert_eq!(harness.stored_seconds("github.com"), 40);
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn uncategorized_sites_are_brought_up_weekly() {
+    let mut config = Config::default();
+    config.triage.min_time = 10 * 60;
+    config
+        .categories
+        .insert("wiki.acme.test".to_string(), "docs".to_string());
+    let mut harness = Harness::with_config("triage", config).await;
+    let mut client = DaemonClient::connect(&harness.url).await.unwrap();
+    harness.windows.focus("firefox", "Mozilla Firefox");
+
+    let sites = [
+        ("tracker.acme.test", 15),
+        ("wiki.acme.test", 15),    // has a rule
+        ("intranet.acme.test", 5), // too little time
+        ("crates.io", 1),          // in the built-in site list
+    ];
+    for (domain, minutes) in sites {
+        client.send(&tab(domain)).await.unwrap();
+        harness.wait_for_domain(domain).await;
+        harness.run_for(minutes * 60);
+    }
+    harness.run_for(60);
+    let triage = |harness: &Harness| -> Vec<String> {
+        harness
+            .notifier
+            .lock()
+            .unwrap()
+            .sent()
+            .iter()
+            .filter(|(event, _)| *event == Event::Triage)
+            .map(|(_, message)| message.clone())
+            .collect()
+    };
+    assert!(triage(&harness).is_empty());
+
+    // Wednesday to Monday: a new week
+    harness.clock.advance(Duration::days(5));
+    harness.run_for(1);
+    assert_eq!(
+        triage(&harness),
+        ["Uncategorized last week: tracker.acme.test 15m 00s - categorize with `stop_it triage`?"]
+    );
+    harness.run_for(60);
+    assert_eq!(triage(&harness).len(), 1);
+
+    // `stop_it triage` adds its answers to the config, comments kept
+    let content = "[pomodoro]\nwork = \"50m\"\n\n[categories]  # by hand\n\"docs.rs\" = \"docs\"\n";
+    let rules = [("tracker.acme.test".to_string(), "work".to_string())];
+    let edited = add_category_rules(content, &rules);
+    assert_eq!(
+        edited,
+        "[pomodoro]\nwork = \"50m\"\n\n[categories]  # by hand\n\"tracker.acme.test\" = \"work\"\n\"docs.rs\" = \"docs\"\n"
+    );
+    let config: Config =
+        toml::from_str(&add_category_rules("[pomodoro]\nwork = \"50m\"", &rules)).unwrap();
+    assert_eq!(config.categories["tracker.acme.test"], "work");
+    assert_eq!(config.pomodoro.work, 50 * 60);
+}